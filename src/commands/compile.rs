@@ -2,11 +2,13 @@
 
 use anyhow::Result;
 use colored::Colorize;
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 use std::time::Instant;
 
-use crate::core::codemap::{CodeMap, FileSummary};
+use crate::core::codemap::{assign_symbol_ids, CodeMap, FileSummary, UnparsedFile};
+use crate::core::config::Config;
 use crate::core::parser::SymbolParser;
 use crate::core::scanner::FileScanner;
 
@@ -28,6 +30,56 @@ pub async fn run(options: CompileOptions) -> Result<()> {
     println!("{}", "Compiling codebase map...".cyan());
     println!();
 
+    let _lock = CodeMap::lock(&root)?;
+    let map = build_map(&root, |file_count, symbol_count| {
+        // Progress indicator
+        if file_count % 10 == 0 {
+            print!("\r  {} files, {} symbols...", file_count, symbol_count);
+        }
+    })?;
+
+    println!("\r                                          ");
+
+    // Save map
+    map.save(&root)?;
+
+    let elapsed = start.elapsed();
+    let stats = map.stats();
+
+    println!("{} Compiled codebase map", "✓".green());
+    println!();
+    println!("  {} {} files", "•".cyan(), stats.files);
+    println!("  {} {} symbols", "•".cyan(), stats.symbols);
+    println!("    {} {} functions", "├".dimmed(), stats.functions);
+    println!("    {} {} structs/classes", "├".dimmed(), stats.structs);
+    println!("    {} {} other", "└".dimmed(), stats.other);
+    println!();
+    println!("  {} {:.1}s", "Time:".dimmed(), elapsed.as_secs_f32());
+    println!(
+        "  {} {}",
+        "Saved:".dimmed(),
+        CodeMap::map_path(&root).display()
+    );
+    println!();
+
+    // Show compact overview
+    let overview = map.to_minimal_overview();
+    let token_estimate = overview.len() / 4; // Rough estimate
+    println!(
+        "  {} ~{} tokens (vs ~{}K reading all files)",
+        "LLM cost:".dimmed(),
+        token_estimate,
+        (stats.files * 500) / 1000 // Assume 500 tokens per file average
+    );
+
+    Ok(())
+}
+
+/// Parse every file under `root` into a codebase map with resolved call edges
+///
+/// `progress` gets the running file and symbol counts after each parsed file.
+/// The caller holds the map lock and saves the result.
+pub fn build_map(root: &Path, mut progress: impl FnMut(usize, usize)) -> Result<CodeMap> {
     let parser = SymbolParser::new()?;
     let scanner = FileScanner::new(&root.to_string_lossy());
     let files = scanner.scan()?;
@@ -35,27 +87,35 @@ pub async fn run(options: CompileOptions) -> Result<()> {
     let mut map = CodeMap::new(&root.to_string_lossy());
     let mut file_count = 0;
     let mut symbol_count = 0;
+    let mut sources = HashMap::new();
 
     for file in &files {
         let file_path = Path::new(&file.path);
         let content = fs::read_to_string(file_path)?;
 
-        let parsed = parser.parse_file(file_path, &content)?;
+        let mut parsed = parser.parse_file(file_path, &content)?;
+
+        let relative_path = file_path
+            .strip_prefix(root)
+            .unwrap_or(file_path)
+            .to_string_lossy()
+            .to_string();
 
         if parsed.symbols.is_empty() {
+            // Keep a name-only entry so the map still reflects the tree
+            map.add_unparsed_file(UnparsedFile {
+                path: relative_path,
+                language: parsed.language,
+                size: content.len(),
+            });
             continue;
         }
 
         file_count += 1;
         symbol_count += parsed.symbols.len();
+        assign_symbol_ids(&relative_path, &mut parsed.symbols);
 
         // Add file summary
-        let relative_path = file_path
-            .strip_prefix(&root)
-            .unwrap_or(file_path)
-            .to_string_lossy()
-            .to_string();
-
         let symbol_ids: Vec<String> = parsed.symbols.iter().map(|s| s.id.clone()).collect();
 
         let exports: Vec<String> = parsed
@@ -71,63 +131,30 @@ pub async fn run(options: CompileOptions) -> Result<()> {
             symbols: symbol_ids,
             imports: parsed.imports,
             exports,
-            summary: String::new(), // TODO: Generate with LLM
+            summary: parsed.summary, // TODO: Generate with LLM when no doc comment
             lines: parsed.lines,
         });
 
         // Add symbols
-        for mut symbol in parsed.symbols {
-            // Update path to relative
-            symbol.file = relative_path.clone();
-            symbol.id = format!("{}:{}", relative_path, symbol.name);
+        for symbol in parsed.symbols {
             map.add_symbol(symbol);
         }
-
-        // Progress indicator
-        if file_count % 10 == 0 {
-            print!("\r  {} files, {} symbols...", file_count, symbol_count);
+        for mut imp in parsed.trait_impls {
+            imp.file = relative_path.clone();
+            map.add_trait_impl(imp);
         }
+        sources.insert(relative_path, content);
+        progress(file_count, symbol_count);
     }
 
-    println!("\r                                          ");
-
-    // Save map
-    map.save(&root)?;
-
-    let elapsed = start.elapsed();
-    let stats = map.stats();
-
-    println!("{} Compiled codebase map", "✓".green());
-    println!();
-    println!("  {} {} files", "•".cyan(), stats.files);
-    println!("  {} {} symbols", "•".cyan(), stats.symbols);
-    println!("    {} {} functions", "├".dimmed(), stats.functions);
-    println!("    {} {} structs/classes", "├".dimmed(), stats.structs);
-    println!("    {} {} other", "└".dimmed(), stats.other);
-    println!();
-    println!("  {} {:.1}s", "Time:".dimmed(), elapsed.as_secs_f32());
-    println!(
-        "  {} {}",
-        "Saved:".dimmed(),
-        CodeMap::map_path(&root).display()
-    );
-    println!();
-
-    // Show compact overview
-    let overview = map.to_minimal_overview();
-    let token_estimate = overview.len() / 4; // Rough estimate
-    println!(
-        "  {} ~{} tokens (vs ~{}K reading all files)",
-        "LLM cost:".dimmed(),
-        token_estimate,
-        (stats.files * 500) / 1000 // Assume 500 tokens per file average
-    );
-
-    Ok(())
+    // Resolve calls between symbols once every file is in the map
+    map.build_edges(&sources);
+    Ok(map)
 }
 
 fn show_map(root: &Path, minimal: bool) -> Result<()> {
-    let map = CodeMap::load(root)?;
+    let labels = Config::load().map(|c| c.kind_labels).unwrap_or_default();
+    let map = CodeMap::load(root)?.map(|m| m.with_kind_labels(labels));
 
     match map {
         Some(m) => {
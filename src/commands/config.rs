@@ -38,6 +38,7 @@ pub async fn run(options: ConfigOptions) -> Result<()> {
     }
 
     if options.clear {
+        let _lock = VectorStore::lock(None)?;
         let mut store = VectorStore::load(None)?;
         store.clear();
         store.save(None)?;
@@ -9,7 +9,7 @@ use crate::core::graph::make_file_id;
 use crate::core::hybrid_embedder::HybridEmbedder;
 use crate::core::local_embeddings::{LocalEmbedder, SpeedMode};
 use crate::core::reranker::{simple_rerank, Reranker};
-use crate::core::search::{HybridSearcher, SearchResult};
+use crate::core::search::{check_query_dim, HybridSearcher, SearchResult};
 use crate::core::store::VectorStore;
 use crate::ui::progress::SearchAnimation;
 use crate::ui::search_display;
@@ -105,6 +105,7 @@ pub async fn run(options: SearchOptions) -> Result<()> {
     if let Some(ref anim) = animation {
         anim.update_stage("Searching index...");
     }
+    check_query_dim(&store, &query_embedding)?;
     let searcher = HybridSearcher::default();
     let file_types = options.file_types.as_ref().map(|v| v.as_slice());
 
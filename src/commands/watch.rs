@@ -1,18 +1,21 @@
 use anyhow::Result;
 use colored::Colorize;
+use ignore::gitignore::Gitignore;
 use indicatif::{ProgressBar, ProgressStyle};
 use notify::{Config as NotifyConfig, RecommendedWatcher, RecursiveMode, Watcher};
-use std::path::Path;
+use rayon::prelude::*;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::channel;
 use std::time::Duration;
 
-use crate::core::chunker::CodeChunker;
+use crate::core::chunker::{path_context_text, Chunk, CodeChunker};
 use crate::core::config::Config;
 use crate::core::embeddings::EmbeddingProvider;
 use crate::core::local_embeddings::SpeedMode;
-use crate::core::scanner::FileScanner;
+use crate::core::scanner::{ignore_matcher, FileScanner, ScannedFile, SkippedFile};
 use crate::core::store::{
-    compute_file_hash, generate_chunk_id, FileChunk, IndexedFile, VectorStore,
+    compute_file_hash, generate_chunk_id, FileChunk, IndexedFile, Quantization, VectorStore,
 };
 
 pub struct WatchOptions {
@@ -66,19 +69,32 @@ pub async fn run(options: WatchOptions) -> Result<()> {
     watcher.watch(Path::new(&path_str), RecursiveMode::Recursive)?;
 
     let store_name = options.store.clone();
+    // Loaded once; each change re-embeds only the touched files
+    let embeddings = EmbeddingProvider::with_speed_mode(Config::load()?, speed_mode);
+    let gitignore = ignore_matcher(&abs_path);
     loop {
         match rx.recv_timeout(Duration::from_secs(1)) {
             Ok(event) => {
                 use notify::EventKind;
                 match event.kind {
                     EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) => {
-                        for path in event.paths {
-                            let path_str = path.to_string_lossy().to_string();
-                            println!("{} {}", "Changed:".yellow(), path_str.dimmed());
+                        let paths: Vec<PathBuf> = event
+                            .paths
+                            .into_iter()
+                            .filter(|p| !is_ignored(&abs_path, &gitignore, p))
+                            .collect();
+                        if paths.is_empty() {
+                            continue;
+                        }
+                        for path in &paths {
+                            println!(
+                                "{} {}",
+                                "Changed:".yellow(),
+                                path.display().to_string().dimmed()
+                            );
                         }
-                        // Re-sync
                         if let Err(e) =
-                            sync_files(&path_str, store_name.as_deref(), speed_mode).await
+                            sync_paths(&path_str, store_name.as_deref(), &embeddings, &paths).await
                         {
                             eprintln!("{} {}", "Error syncing:".red(), e);
                         }
@@ -94,18 +110,223 @@ pub async fn run(options: WatchOptions) -> Result<()> {
     Ok(())
 }
 
-pub async fn sync_files(path: &str, store_name: Option<&str>, speed_mode: SpeedMode) -> Result<()> {
-    let config = Config::load()?;
+/// Re-index just `paths` after a change, reusing an already-loaded embedder
+///
+/// Paths that are gone or no longer indexable are dropped from the index;
+/// unchanged files are left alone.
+pub async fn sync_paths(
+    root: &str,
+    store_name: Option<&str>,
+    embeddings: &EmbeddingProvider,
+    paths: &[PathBuf],
+) -> Result<()> {
+    let _lock = VectorStore::lock(store_name)?;
     let mut store = VectorStore::load(store_name)?;
-    let embeddings = EmbeddingProvider::with_speed_mode(config, speed_mode);
+    adopt_model(&mut store, embeddings.model_id())?;
+
+    let scanner = FileScanner::new(root);
     let chunker = CodeChunker::default();
-    let scanner = FileScanner::new(path);
+    let mut changed = false;
+
+    for path in paths {
+        let key = path.to_string_lossy().to_string();
+        let file = match scanner.scan_single(path)? {
+            Some(file) => file,
+            None => {
+                // Deleted (a removed directory takes its files along) or no
+                // longer indexable, e.g. grown past the size limit
+                let stale: Vec<String> = if path.exists() {
+                    vec![key]
+                } else {
+                    store
+                        .list_files()
+                        .into_iter()
+                        .filter(|f| Path::new(f).starts_with(path))
+                        .collect()
+                };
+                for stale_path in stale {
+                    if store.get_file(&stale_path).is_some() {
+                        store.remove_file(&stale_path);
+                        println!("{} {}", "Removed:".yellow(), stale_path.dimmed());
+                        changed = true;
+                    }
+                }
+                continue;
+            }
+        };
+
+        let hash = compute_file_hash(&file.content);
+        if !store.file_needs_update(&file.path, &hash) {
+            continue;
+        }
+
+        let pending = prepare_file(file, hash, &chunker, root, store.embed_path_context);
+        changed = true;
+        if pending.chunks.is_empty() {
+            store.remove_file(&key);
+            continue;
+        }
+
+        let vectors = embeddings.embed(&pending.texts).await?;
+        if vectors.len() != pending.chunks.len() {
+            anyhow::bail!(
+                "expected {} embeddings for {}, got {}",
+                pending.chunks.len(),
+                key,
+                vectors.len()
+            );
+        }
+        let (file, chunks) = index_entries(pending, &mut vectors.into_iter());
+        store.upsert_file(file, chunks);
+        println!("{} {}", "Updated:".green(), key.dimmed());
+    }
 
-    let files = scanner.scan()?;
+    if changed {
+        store.update_bm25_stats();
+        store.save(store_name)?;
+    }
+    Ok(())
+}
+
+/// Record the embedding model on the index, refusing to mix models
+///
+/// Mixing embeddings from different models in one index breaks similarity.
+/// Indexes saved before the model was recorded can't be checked, so one that
+/// already holds chunks has to be rebuilt rather than adopted.
+fn adopt_model(store: &mut VectorStore, model_id: Option<String>) -> Result<()> {
+    if store.chunk_count() > 0 {
+        match (&store.model, &model_id) {
+            (Some(indexed), Some(current)) if indexed != current => anyhow::bail!(
+                "Index was built with model '{}' but '{}' was requested; clear the index before switching models",
+                indexed,
+                current
+            ),
+            (None, Some(current)) => anyhow::bail!(
+                "Index predates model tracking, so it can't be checked against '{}'; clear the index and rebuild it",
+                current
+            ),
+            _ => {}
+        }
+    }
+    if model_id.is_some() {
+        store.model = model_id;
+    }
+    Ok(())
+}
+
+/// Called with (files processed, total files) as a sync advances
+pub type ProgressFn = Box<dyn Fn(u64, u64) + Send + Sync>;
+
+/// Extra knobs for `sync_files_with_options`
+#[derive(Default)]
+pub struct SyncOptions {
+    pub chunker: CodeChunker,
+    /// Embedding model id; overrides the SpeedMode preset when set
+    pub model: Option<String>,
+    /// Prefix each chunk with its file path before embedding
+    /// (None keeps whatever the existing index uses)
+    pub embed_path_context: Option<bool>,
+    /// Progress callback, invoked after each file alongside the progress bar
+    pub progress: Option<ProgressFn>,
+    /// Worker threads for chunking (None = available parallelism)
+    pub threads: Option<usize>,
+    /// On-disk embedding format (None keeps whatever the existing index uses)
+    pub quantization: Option<Quantization>,
+    /// Index files excluded by `.gitignore` / `.sgrepignore` as well
+    pub include_ignored: bool,
+    /// Only index these languages (empty = every language)
+    pub languages: Vec<String>,
+    /// Never index these languages
+    pub exclude_languages: Vec<String>,
+}
+
+/// What a sync did
+#[derive(Debug, Default)]
+pub struct SyncSummary {
+    /// Files embedded for the first time
+    pub added: usize,
+    /// Indexed files whose content changed, re-embedded
+    pub updated: usize,
+    /// Indexed files no longer on disk, dropped from the index
+    pub removed: usize,
+    /// Files whose content hash matches the index, left alone
+    pub unchanged: usize,
+    /// Files left out as binary, too large or minified
+    pub skipped: Vec<SkippedFile>,
+}
+
+/// Skipped files listed individually in sync output; the rest are counted
+pub const MAX_LISTED_SKIPS: usize = 10;
+
+pub async fn sync_files(
+    path: &str,
+    store_name: Option<&str>,
+    speed_mode: SpeedMode,
+) -> Result<SyncSummary> {
+    sync_files_with_options(path, store_name, speed_mode, SyncOptions::default()).await
+}
+
+/// Like `sync_files`, but with a custom chunker and/or embedding model
+pub async fn sync_files_with_options(
+    path: &str,
+    store_name: Option<&str>,
+    speed_mode: SpeedMode,
+    options: SyncOptions,
+) -> Result<SyncSummary> {
+    let config = Config::load()?;
+    let batch_size = config.embed_batch_size.max(1);
+    let scanner = FileScanner::new(path)
+        .with_include_ignored(options.include_ignored)
+        .with_languages(&options.languages, &options.exclude_languages)
+        .with_limits(config.max_file_size, config.max_line_length);
+    let _lock = VectorStore::lock(store_name)?;
+    let mut store = VectorStore::load(store_name)?;
+    let embeddings = match options.model.as_deref() {
+        Some(model) => EmbeddingProvider::with_model(config, model),
+        None => EmbeddingProvider::with_speed_mode(config, speed_mode),
+    };
+    let chunker = options.chunker;
+    adopt_model(&mut store, embeddings.model_id())?;
+
+    // Self-heal: deleted or moved files stop showing up as phantom hits
+    let (removed, pruned) = store.prune_missing(Path::new(path));
+    if pruned > 0 {
+        println!(
+            "{} {} deleted files ({} chunks)",
+            "Removed".yellow(),
+            removed,
+            pruned
+        );
+    }
+
+    if let Some(requested) = options.embed_path_context {
+        if store.chunk_count() > 0 && store.embed_path_context != requested {
+            anyhow::bail!(
+                "Index was built with embed_path_context={}; clear the index before changing it",
+                store.embed_path_context
+            );
+        }
+        store.embed_path_context = requested;
+    }
+    if let Some(quantization) = options.quantization {
+        store.quantization = quantization;
+    }
+    let report = scanner.scan_report()?;
+    let files = report.files;
+    let skipped = report.skipped;
+    print_skipped(&skipped);
 
     if files.is_empty() {
         println!("{}", "No files found to index".yellow());
-        return Ok(());
+        if removed > 0 {
+            store.update_bm25_stats();
+            store.save(store_name)?;
+        }
+        return Ok(SyncSummary {
+            removed,
+            skipped,
+            ..SyncSummary::default()
+        });
     }
 
     let pb = ProgressBar::new(files.len() as u64);
@@ -115,78 +336,53 @@ pub async fn sync_files(path: &str, store_name: Option<&str>, speed_mode: SpeedM
             .unwrap()
             .progress_chars("#>-"),
     );
-
-    let mut indexed_count = 0;
-    let mut skipped_count = 0;
-
-    for file in files {
-        pb.set_message(truncate_path(&file.path, 40));
-
-        let hash = compute_file_hash(&file.content);
-
-        // Check if file needs updating
-        if !store.file_needs_update(&file.path, &hash) {
-            skipped_count += 1;
-            pb.inc(1);
-            continue;
+    let total = files.len() as u64;
+    let advance = || {
+        pb.inc(1);
+        if let Some(report) = &options.progress {
+            report(pb.position(), total);
         }
+    };
 
-        // Remove old chunks if file exists
+    // Only changed files are chunked and embedded
+    let IndexDiff { changed, unchanged } = diff_against_index(&store, files);
+    for _ in 0..unchanged {
+        advance();
+    }
+    let previously_indexed: HashSet<String> = changed
+        .iter()
+        .filter(|(file, _)| store.get_file(&file.path).is_some())
+        .map(|(file, _)| file.path.clone())
+        .collect();
+    for (file, _) in &changed {
         store.remove_file(&file.path);
+    }
+
+    let threads = options.threads.unwrap_or_else(default_threads).max(1);
+    let prepared = prepare_files(changed, &chunker, path, store.embed_path_context, threads)?;
+    let mut stored = Vec::new();
 
-        // Chunk the file
-        let chunks = chunker.chunk(&file.content, file.language.as_deref());
+    let mut batch = Vec::new();
+    let mut batch_chunks = 0;
+    for file in prepared {
+        pb.set_message(truncate_path(&file.path, 40));
 
-        if chunks.is_empty() {
-            pb.inc(1);
+        if file.chunks.is_empty() {
+            advance();
             continue;
         }
 
-        // Generate embeddings for all chunks
-        let chunk_texts: Vec<String> = chunks.iter().map(|c| c.content.clone()).collect();
-
-        let chunk_embeddings = match embeddings.embed(&chunk_texts).await {
-            Ok(emb) => emb,
-            Err(e) => {
-                eprintln!("{} {} - {}", "Error embedding".red(), file.path, e);
-                pb.inc(1);
-                continue;
-            }
-        };
-
-        // Store chunks
-        let mut chunk_ids = Vec::new();
-        for (i, chunk) in chunks.iter().enumerate() {
-            let chunk_id = generate_chunk_id(&file.path, chunk.start_line, chunk.end_line);
-            chunk_ids.push(chunk_id.clone());
-
-            store.add_chunk(FileChunk {
-                id: chunk_id,
-                file_path: file.path.clone(),
-                content: chunk.content.clone(),
-                start_line: chunk.start_line,
-                end_line: chunk.end_line,
-                chunk_type: chunk.chunk_type.as_str().to_string(),
-                language: file.language.clone(),
-                embedding: chunk_embeddings[i].clone(),
-                token_embeddings: None,
-                symbol_name: None,
-                parent_name: None,
-                hierarchy_path: None,
-            });
+        // Queue the file; embed once enough chunks are waiting
+        batch_chunks += file.chunks.len();
+        batch.push(file);
+        if batch_chunks >= batch_size {
+            stored.extend(
+                embed_batch(&embeddings, batch_size, &mut store, &mut batch, &advance).await,
+            );
+            batch_chunks = 0;
         }
-
-        // Store file metadata
-        store.add_file(IndexedFile {
-            path: file.path.clone(),
-            hash,
-            chunks: chunk_ids,
-            indexed_at: chrono::Utc::now().to_rfc3339(),
-        });
-
-        indexed_count += 1;
-        pb.inc(1);
     }
+    stored.extend(embed_batch(&embeddings, batch_size, &mut store, &mut batch, &advance).await);
 
     pb.finish_and_clear();
 
@@ -194,15 +390,246 @@ pub async fn sync_files(path: &str, store_name: Option<&str>, speed_mode: SpeedM
     store.update_bm25_stats();
     store.save(store_name)?;
 
+    let updated = stored
+        .iter()
+        .filter(|path| previously_indexed.contains(*path))
+        .count();
+    let added = stored.len() - updated;
     println!(
-        "{} {} files ({} new, {} unchanged)",
+        "{} {} files ({} new, {} updated, {} removed, {} unchanged, {} skipped)",
         "✓ Indexed".green(),
-        indexed_count + skipped_count,
-        indexed_count,
-        skipped_count
+        stored.len() + unchanged,
+        added,
+        updated,
+        removed,
+        unchanged,
+        skipped.len()
     );
 
-    Ok(())
+    Ok(SyncSummary {
+        added,
+        updated,
+        removed,
+        unchanged,
+        skipped,
+    })
+}
+
+/// A scan compared with the index by content hash
+struct IndexDiff {
+    /// New or modified files, with their new hash
+    changed: Vec<(ScannedFile, String)>,
+    /// Files indexed with the same content
+    unchanged: usize,
+}
+
+fn diff_against_index(store: &VectorStore, files: Vec<ScannedFile>) -> IndexDiff {
+    let mut changed = Vec::new();
+    let mut unchanged = 0;
+    for file in files {
+        let hash = compute_file_hash(&file.content);
+        if store.file_needs_update(&file.path, &hash) {
+            changed.push((file, hash));
+        } else {
+            unchanged += 1;
+        }
+    }
+    IndexDiff { changed, unchanged }
+}
+
+fn print_skipped(skipped: &[SkippedFile]) {
+    for file in skipped.iter().take(MAX_LISTED_SKIPS) {
+        println!(
+            "{} {} ({})",
+            "Skipped:".yellow(),
+            file.path.dimmed(),
+            file.reason
+        );
+    }
+    if skipped.len() > MAX_LISTED_SKIPS {
+        println!(
+            "{} {} more files",
+            "Skipped:".yellow(),
+            skipped.len() - MAX_LISTED_SKIPS
+        );
+    }
+}
+
+/// Number of worker threads when none is requested
+fn default_threads() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
+
+/// Chunk files on a pool of `threads` workers, keeping their scan order
+///
+/// Embedding stays on the caller: the local model sits behind one mutex
+/// (a copy per thread would multiply its memory), and its matrix math
+/// already spreads across cores.
+fn prepare_files(
+    files: Vec<(ScannedFile, String)>,
+    chunker: &CodeChunker,
+    root: &str,
+    embed_path_context: bool,
+    threads: usize,
+) -> Result<Vec<PendingFile>> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()?;
+
+    Ok(pool.install(|| {
+        files
+            .into_par_iter()
+            .map(|(file, hash)| prepare_file(file, hash, chunker, root, embed_path_context))
+            .collect()
+    }))
+}
+
+fn prepare_file(
+    file: ScannedFile,
+    hash: String,
+    chunker: &CodeChunker,
+    root: &str,
+    embed_path_context: bool,
+) -> PendingFile {
+    let chunks = chunker.chunk(&file.content, file.language.as_deref());
+
+    // Text to embed for each chunk
+    let texts = if embed_path_context {
+        let relative = Path::new(&file.path)
+            .strip_prefix(root)
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|_| file.path.clone());
+        chunks
+            .iter()
+            .map(|c| path_context_text(&relative, &c.content))
+            .collect()
+    } else {
+        chunks.iter().map(|c| c.content.clone()).collect()
+    };
+
+    PendingFile {
+        path: file.path,
+        language: file.language,
+        hash,
+        chunks,
+        texts,
+    }
+}
+
+/// A changed file whose chunks are waiting to be embedded
+struct PendingFile {
+    path: String,
+    language: Option<String>,
+    hash: String,
+    chunks: Vec<Chunk>,
+    texts: Vec<String>,
+}
+
+/// Embed every queued chunk, `batch_size` at a time, and store the files,
+/// draining `batch`
+///
+/// Returns how many files were stored. If embedding fails, each file in the
+/// batch is reported and skipped, as a single file used to be.
+async fn embed_batch(
+    embeddings: &EmbeddingProvider,
+    batch_size: usize,
+    store: &mut VectorStore,
+    batch: &mut Vec<PendingFile>,
+    advance: &dyn Fn(),
+) -> Vec<String> {
+    if batch.is_empty() {
+        return Vec::new();
+    }
+
+    let texts: Vec<String> = batch.iter().flat_map(|f| f.texts.iter().cloned()).collect();
+    let mut embedded = Vec::with_capacity(texts.len());
+    let mut failure = None;
+    for slice in texts.chunks(batch_size) {
+        match embeddings.embed(slice).await {
+            Ok(vectors) => embedded.extend(vectors),
+            Err(e) => {
+                failure = Some(e.to_string());
+                break;
+            }
+        }
+    }
+    if failure.is_none() && embedded.len() != texts.len() {
+        failure = Some(format!(
+            "expected {} embeddings, got {}",
+            texts.len(),
+            embedded.len()
+        ));
+    }
+
+    if let Some(error) = failure {
+        for file in batch.drain(..) {
+            eprintln!("{} {} - {}", "Error embedding".red(), file.path, error);
+            advance();
+        }
+        return Vec::new();
+    }
+
+    let mut vectors = embedded.into_iter();
+    let mut stored = Vec::with_capacity(batch.len());
+    for file in batch.drain(..) {
+        let (file, chunks) = index_entries(file, &mut vectors);
+        stored.push(file.path.clone());
+        store.upsert_file(file, chunks);
+        advance();
+    }
+    stored
+}
+
+/// Pair a prepared file's chunks with their embeddings, taken in order
+fn index_entries(
+    file: PendingFile,
+    vectors: &mut impl Iterator<Item = Vec<f32>>,
+) -> (IndexedFile, Vec<FileChunk>) {
+    let mut chunk_ids = Vec::new();
+    let mut chunks = Vec::new();
+    for (chunk, embedding) in file.chunks.iter().zip(vectors) {
+        let chunk_id = generate_chunk_id(&file.path, chunk.start_line, chunk.end_line);
+        chunk_ids.push(chunk_id.clone());
+
+        chunks.push(FileChunk {
+            id: chunk_id,
+            file_path: file.path.clone(),
+            content: chunk.content.clone(),
+            start_line: chunk.start_line,
+            end_line: chunk.end_line,
+            chunk_type: chunk.chunk_type.as_str().to_string(),
+            language: file.language.clone(),
+            embedding,
+            token_embeddings: None,
+            symbol_name: None,
+            parent_name: None,
+            hierarchy_path: None,
+        });
+    }
+
+    let file = IndexedFile {
+        path: file.path,
+        hash: file.hash,
+        chunks: chunk_ids,
+        indexed_at: chrono::Utc::now().to_rfc3339(),
+    };
+    (file, chunks)
+}
+
+/// Skip VCS/sgrep metadata and gitignored paths; the full scan skips them too
+fn is_ignored(root: &Path, gitignore: &Gitignore, path: &Path) -> bool {
+    let relative = match path.strip_prefix(root) {
+        Ok(relative) => relative,
+        Err(_) => return true,
+    };
+    relative
+        .components()
+        .any(|c| c.as_os_str() == ".git" || c.as_os_str() == ".sgrep")
+        || gitignore
+            .matched_path_or_any_parents(relative, path.is_dir())
+            .is_ignore()
 }
 
 fn truncate_path(path: &str, max_len: usize) -> String {
@@ -212,3 +639,123 @@ fn truncate_path(path: &str, max_len: usize) -> String {
         format!("...{}", &path[path.len() - max_len + 3..])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk_set(files: &[PendingFile]) -> Vec<(String, Vec<String>, Vec<String>)> {
+        files
+            .iter()
+            .map(|f| {
+                let ids = f
+                    .chunks
+                    .iter()
+                    .map(|c| generate_chunk_id(&f.path, c.start_line, c.end_line))
+                    .collect();
+                (f.path.clone(), ids, f.texts.clone())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parallel_chunking_matches_sequential() {
+        let files: Vec<(ScannedFile, String)> = (0..40)
+            .map(|i| {
+                let content = (0..i * 7)
+                    .map(|n| format!("fn f{}_{}() {{\n    let x = {};\n}}\n\n", i, n, n))
+                    .collect::<String>();
+                let file = ScannedFile {
+                    path: format!("/repo/src/m{}.rs", i),
+                    language: Some("rust".to_string()),
+                    content,
+                };
+                let hash = compute_file_hash(&file.content);
+                (file, hash)
+            })
+            .collect();
+
+        let chunker = CodeChunker::default();
+        let sequential = prepare_files(files.clone(), &chunker, "/repo", true, 1).unwrap();
+        let parallel = prepare_files(files, &chunker, "/repo", true, 4).unwrap();
+
+        assert_eq!(sequential.len(), 40);
+        assert!(sequential.iter().any(|f| f.chunks.len() > 1));
+        assert_eq!(chunk_set(&sequential), chunk_set(&parallel));
+    }
+
+    #[test]
+    fn test_reindex_only_processes_changed_files() {
+        let scan = |files: &[(&str, &str)]| -> Vec<ScannedFile> {
+            files
+                .iter()
+                .map(|(path, content)| ScannedFile {
+                    path: path.to_string(),
+                    content: content.to_string(),
+                    language: Some("rust".to_string()),
+                })
+                .collect()
+        };
+
+        let mut store = VectorStore::default();
+        let first = scan(&[("/repo/a.rs", "fn a() {}"), ("/repo/b.rs", "fn b() {}")]);
+        let diff = diff_against_index(&store, first);
+        assert_eq!(diff.changed.len(), 2);
+        assert_eq!(diff.unchanged, 0);
+        for (file, hash) in diff.changed {
+            store.upsert_file(
+                IndexedFile {
+                    path: file.path,
+                    hash,
+                    chunks: vec![],
+                    indexed_at: String::new(),
+                },
+                vec![],
+            );
+        }
+
+        let second = scan(&[
+            ("/repo/a.rs", "fn a() { todo!() }"),
+            ("/repo/b.rs", "fn b() {}"),
+            ("/repo/c.rs", "fn c() {}"),
+        ]);
+        let diff = diff_against_index(&store, second);
+        let changed: Vec<&str> = diff.changed.iter().map(|(f, _)| f.path.as_str()).collect();
+        assert_eq!(changed, ["/repo/a.rs", "/repo/c.rs"]);
+        assert_eq!(diff.unchanged, 1);
+    }
+
+    #[test]
+    fn test_adopt_model_rejects_unrecorded_model_with_chunks() {
+        let model = || Some("BAAI/bge-small-en-v1.5".to_string());
+
+        // Empty legacy index: nothing to mix, so the model is recorded
+        let mut store = VectorStore::default();
+        adopt_model(&mut store, model()).unwrap();
+        assert_eq!(store.model, model());
+
+        let mut store = VectorStore::default();
+        store.add_chunk(FileChunk {
+            id: generate_chunk_id("/repo/a.rs", 1, 3),
+            file_path: "/repo/a.rs".to_string(),
+            content: "fn a() {}".to_string(),
+            start_line: 1,
+            end_line: 3,
+            chunk_type: "function".to_string(),
+            language: Some("rust".to_string()),
+            embedding: vec![0.0; 4],
+            token_embeddings: None,
+            symbol_name: None,
+            parent_name: None,
+            hierarchy_path: None,
+        });
+        let err = adopt_model(&mut store, model()).unwrap_err();
+        assert!(err.to_string().contains("rebuild"));
+        assert_eq!(store.model, None);
+
+        store.model = Some("other-model".to_string());
+        assert!(adopt_model(&mut store, model()).is_err());
+        store.model = model();
+        adopt_model(&mut store, model()).unwrap();
+    }
+}
@@ -1,4 +1,9 @@
 use regex::Regex;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::core::codemap::SymbolKind;
+use crate::core::parser::SymbolParser;
 
 #[derive(Debug, Clone)]
 pub struct Chunk {
@@ -31,10 +36,35 @@ impl ChunkType {
     }
 }
 
+/// How a file is split into chunks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChunkStrategy {
+    /// Pattern-detected blocks, falling back to line windows
+    #[default]
+    Window,
+    /// One chunk per top-level symbol, using the symbol parser's line ranges
+    Symbol,
+}
+
+impl ChunkStrategy {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "window" => Some(ChunkStrategy::Window),
+            "symbol" => Some(ChunkStrategy::Symbol),
+            _ => None,
+        }
+    }
+}
+
 pub struct CodeChunker {
     max_chunk_size: usize,
     min_chunk_size: usize,
     overlap: usize,
+    /// Snap line-window cuts to the nearest preceding blank line
+    align_boundaries: bool,
+    strategy: ChunkStrategy,
+    /// Built on first use by symbol chunking (`None` if it failed to build)
+    symbol_parser: OnceLock<Option<SymbolParser>>,
 }
 
 impl Default for CodeChunker {
@@ -43,19 +73,56 @@ impl Default for CodeChunker {
             max_chunk_size: 1500,
             min_chunk_size: 100,
             overlap: 100,
+            align_boundaries: false,
+            strategy: ChunkStrategy::Window,
+            symbol_parser: OnceLock::new(),
         }
     }
 }
 
 impl CodeChunker {
+    /// How far back (in lines) to look for a blank line when aligning a cut
+    const ALIGN_TOLERANCE: usize = 10;
+
     pub fn new(max_chunk_size: usize, min_chunk_size: usize, overlap: usize) -> Self {
         Self {
             max_chunk_size,
             min_chunk_size,
             overlap,
+            align_boundaries: false,
+            strategy: ChunkStrategy::Window,
+            symbol_parser: OnceLock::new(),
         }
     }
 
+    /// Start line-window chunks at blank lines where possible
+    ///
+    /// Aligned windows don't overlap, so every line is covered exactly once.
+    pub fn with_align_boundaries(mut self, enabled: bool) -> Self {
+        self.align_boundaries = enabled;
+        self
+    }
+
+    /// Choose how files are split; `Symbol` falls back to `Window` for
+    /// languages (or files) the symbol parser finds nothing in
+    pub fn with_strategy(mut self, strategy: ChunkStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Last line (inclusive) of a window that would otherwise end at `end`
+    ///
+    /// Returns the nearest blank line within `ALIGN_TOLERANCE` lines, so the
+    /// next window starts right after it. `None` if alignment is off or no
+    /// blank line is close enough.
+    fn aligned_cut(&self, lines: &[&str], start: usize, end: usize) -> Option<usize> {
+        if !self.align_boundaries {
+            return None;
+        }
+        let floor = end.saturating_sub(Self::ALIGN_TOLERANCE).max(start + 1);
+        (floor..=end).rev().find(|&j| lines[j].trim().is_empty())
+    }
+
     pub fn chunk(&self, content: &str, language: Option<&str>) -> Vec<Chunk> {
         let lines: Vec<&str> = content.lines().collect();
 
@@ -63,6 +130,12 @@ impl CodeChunker {
             return vec![];
         }
 
+        if self.strategy == ChunkStrategy::Symbol {
+            if let Some(chunks) = self.symbol_chunk(content, &lines, language) {
+                return chunks;
+            }
+        }
+
         // Try semantic chunking first
         let semantic_chunks = self.semantic_chunk(&lines, language);
 
@@ -74,6 +147,122 @@ impl CodeChunker {
         self.simple_chunk(&lines)
     }
 
+    /// One chunk per top-level symbol (with its leading comments/attributes)
+    ///
+    /// Code between symbols gets chunks of its own, or joins the next symbol's
+    /// chunk (the last one's, at the end of the file) when it's too short to
+    /// stand alone, so imports and top-level statements aren't lost. `None`
+    /// when the parser finds no symbols, so the caller can fall back to
+    /// windowing.
+    fn symbol_chunk(
+        &self,
+        content: &str,
+        lines: &[&str],
+        language: Option<&str>,
+    ) -> Option<Vec<Chunk>> {
+        let parser = self
+            .symbol_parser
+            .get_or_init(|| SymbolParser::new().ok())
+            .as_ref()?;
+        let mut symbols = parser
+            .parse_as(Path::new(""), language?, content)
+            .ok()?
+            .symbols;
+        symbols.sort_by_key(|s| (s.line, std::cmp::Reverse(s.end_line)));
+
+        // (first line, last line, type), 1-based; nested symbols are skipped
+        let mut spans: Vec<(usize, usize, ChunkType)> = Vec::new();
+        for sym in &symbols {
+            if sym.line == 0 || sym.line > lines.len() {
+                continue;
+            }
+            if spans.last().is_some_and(|&(_, end, _)| sym.line <= end) {
+                continue;
+            }
+            let end = sym.end_line.max(sym.line).min(lines.len());
+            spans.push((sym.line, end, symbol_chunk_type(&sym.kind)));
+        }
+        if spans.is_empty() {
+            return None;
+        }
+
+        let mut chunks = Vec::new();
+        let mut next = 1; // first line not yet covered
+        for (mut start, end, chunk_type) in spans {
+            while start > next && is_leading_comment(lines[start - 2]) {
+                start -= 1;
+            }
+            if self.is_short_gap(lines, next, start) {
+                start = next;
+            } else {
+                chunks.extend(self.gap_chunks(lines, next, start));
+            }
+
+            let block = &lines[start - 1..end];
+            let content = block.join("\n");
+            if content.len() > self.max_chunk_size {
+                chunks.extend(self.split_covering(block, start - 1, chunk_type));
+            } else {
+                chunks.push(Chunk {
+                    content,
+                    start_line: start,
+                    end_line: end,
+                    chunk_type,
+                });
+            }
+            next = end + 1;
+        }
+        if !self.is_short_gap(lines, next, lines.len() + 1) {
+            chunks.extend(self.gap_chunks(lines, next, lines.len() + 1));
+        } else if let Some(last) = chunks.last_mut() {
+            last.content = lines[last.start_line - 1..].join("\n");
+            last.end_line = lines.len();
+        }
+
+        Some(chunks)
+    }
+
+    /// Whether lines `from..to` (1-based, exclusive) are blank or too small
+    /// for a chunk of their own
+    fn is_short_gap(&self, lines: &[&str], from: usize, to: usize) -> bool {
+        let gap = &lines[from - 1..to.max(from) - 1];
+        gap.iter().all(|l| l.trim().is_empty())
+            || gap.iter().map(|l| l.len() + 1).sum::<usize>() < self.min_chunk_size
+    }
+
+    /// Chunks for lines `from..to` (1-based, exclusive) between symbols
+    fn gap_chunks(&self, lines: &[&str], from: usize, to: usize) -> Vec<Chunk> {
+        let gap = &lines[from - 1..to - 1];
+        let content = gap.join("\n");
+        if content.len() > self.max_chunk_size {
+            return self.split_covering(gap, from - 1, ChunkType::Code);
+        }
+        vec![Chunk {
+            content,
+            start_line: from,
+            end_line: to - 1,
+            chunk_type: ChunkType::Code,
+        }]
+    }
+
+    /// `split_large_block`, keeping the short tail it drops in the last chunk
+    fn split_covering(
+        &self,
+        block: &[&str],
+        base_line: usize,
+        chunk_type: ChunkType,
+    ) -> Vec<Chunk> {
+        let mut chunks = self.split_large_block(block, base_line, chunk_type);
+        let end = base_line + block.len();
+        if let Some(last) = chunks.last_mut() {
+            if last.end_line < end {
+                last.content = block[last.start_line - 1 - base_line..].join("\n");
+                last.end_line = end;
+            }
+        }
+        chunks
+    }
+
     fn semantic_chunk(&self, lines: &[&str], language: Option<&str>) -> Vec<Chunk> {
         let mut chunks = Vec::new();
         let patterns = get_language_patterns(language);
@@ -250,6 +439,18 @@ impl CodeChunker {
             current_size += line.len() + 1;
 
             if current_size >= self.max_chunk_size && i > current_start {
+                if let Some(cut) = self.aligned_cut(lines, current_start, i - 1) {
+                    chunks.push(Chunk {
+                        content: lines[current_start..=cut].join("\n"),
+                        start_line: base_line + current_start + 1,
+                        end_line: base_line + cut + 1,
+                        chunk_type: chunk_type.clone(),
+                    });
+                    current_start = cut + 1;
+                    current_size = lines[current_start..=i].iter().map(|l| l.len() + 1).sum();
+                    continue;
+                }
+
                 let content: String = lines[current_start..i].join("\n");
                 if content.len() >= self.min_chunk_size {
                     chunks.push(Chunk {
@@ -291,6 +492,18 @@ impl CodeChunker {
             current_size += line.len() + 1;
 
             if current_size >= self.max_chunk_size {
+                if let Some(cut) = self.aligned_cut(lines, current_start, i) {
+                    chunks.push(Chunk {
+                        content: lines[current_start..=cut].join("\n"),
+                        start_line: current_start + 1,
+                        end_line: cut + 1,
+                        chunk_type: ChunkType::Code,
+                    });
+                    current_start = cut + 1;
+                    current_size = lines[current_start..=i].iter().map(|l| l.len() + 1).sum();
+                    continue;
+                }
+
                 let content: String = lines[current_start..=i].join("\n");
                 chunks.push(Chunk {
                     content,
@@ -314,6 +527,13 @@ impl CodeChunker {
                     end_line: lines.len(),
                     chunk_type: ChunkType::Code,
                 });
+            } else if self.align_boundaries {
+                // Fold a short tail into the previous window rather than dropping it
+                if let Some(last) = chunks.last_mut() {
+                    last.content.push('\n');
+                    last.content.push_str(&content);
+                    last.end_line = lines.len();
+                }
             }
         }
 
@@ -321,6 +541,17 @@ impl CodeChunker {
     }
 }
 
+/// Text to embed for a chunk, prefixed with its (normalized) file path
+///
+/// Gives the embedding model locational context, so queries like "auth
+/// refresh" can match code under `src/auth/` even when the code itself
+/// never says "auth". Only used for embedding; stored content is unchanged.
+pub fn path_context_text(relative_path: &str, content: &str) -> String {
+    let normalized = relative_path.replace('\\', "/");
+    let normalized = normalized.trim_start_matches("./");
+    format!("File: {}\n\n{}", normalized, content)
+}
+
 struct LanguagePatterns {
     function_pattern: Regex,
     class_pattern: Regex,
@@ -379,3 +610,187 @@ fn get_language_patterns(language: Option<&str>) -> LanguagePatterns {
 fn get_indent_level(line: &str) -> usize {
     line.chars().take_while(|c| c.is_whitespace()).count()
 }
+
+fn symbol_chunk_type(kind: &SymbolKind) -> ChunkType {
+    match kind {
+        SymbolKind::Function | SymbolKind::Method => ChunkType::Function,
+        SymbolKind::Struct
+        | SymbolKind::Enum
+        | SymbolKind::Trait
+        | SymbolKind::Interface
+        | SymbolKind::Class
+        | SymbolKind::Type => ChunkType::Class,
+        SymbolKind::Module => ChunkType::Module,
+        _ => ChunkType::Code,
+    }
+}
+
+/// Doc comment, attribute or decorator line that belongs to the next symbol
+fn is_leading_comment(line: &str) -> bool {
+    let line = line.trim_start();
+    ["//", "/*", "*", "#", "@"]
+        .iter()
+        .any(|prefix| line.starts_with(prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::embeddings::cosine_similarity;
+
+    /// Deterministic bag-of-words embedding, standing in for a real model
+    fn toy_embed(text: &str) -> Vec<f32> {
+        let mut v = vec![0.0f32; 256];
+        for token in text
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|t| !t.is_empty())
+        {
+            let hash = token.bytes().fold(0xcbf29ce484222325u64, |h, b| {
+                (h ^ b as u64).wrapping_mul(0x100000001b3)
+            });
+            v[(hash % 256) as usize] += 1.0;
+        }
+        v
+    }
+
+    #[test]
+    fn test_path_context_changes_ranking() {
+        let chunks = [
+            (
+                "src/cache/store.rs",
+                "fn refresh(entry: &str) -> Result<Entry> { evict(entry) }",
+            ),
+            (
+                "src/auth/session.rs",
+                "fn refresh(token: &str) -> Result<Token> { check(token) }",
+            ),
+        ];
+        let query = toy_embed("auth refresh");
+
+        let score = |path: &str, content: &str, with_path: bool| -> f32 {
+            let text = if with_path {
+                path_context_text(path, content)
+            } else {
+                content.to_string()
+            };
+            cosine_similarity(&query, &toy_embed(&text))
+        };
+        let [(cache_path, cache), (auth_path, auth)] = chunks;
+
+        // A: content only - nothing ties the session code to "auth"
+        let (a_cache, a_auth) = (
+            score(cache_path, cache, false),
+            score(auth_path, auth, false),
+        );
+        assert!((a_cache - a_auth).abs() < 1e-6);
+
+        // B: path header - the auth module ranks first
+        let (b_cache, b_auth) = (score(cache_path, cache, true), score(auth_path, auth, true));
+        assert!(b_auth > b_cache);
+
+        assert_eq!(
+            path_context_text("./src\\auth\\session.rs", "fn a() {}"),
+            "File: src/auth/session.rs\n\nfn a() {}"
+        );
+    }
+
+    #[test]
+    fn test_align_boundaries_starts_chunks_after_blank_lines() {
+        // Paragraphs of 7 lines separated by blank lines, no semantic blocks
+        let mut lines = Vec::new();
+        for p in 0..12 {
+            for l in 0..7 {
+                lines.push(format!("value_{}_{} = compute({}, {})", p, l, p, l));
+            }
+            lines.push(String::new());
+        }
+        let content = lines.join("\n");
+
+        let chunker = CodeChunker::new(300, 10, 0).with_align_boundaries(true);
+        let chunks = chunker.chunk(&content, Some("text"));
+        assert!(chunks.len() > 1);
+
+        // Every chunk after the first starts right after a blank line
+        for chunk in &chunks[1..] {
+            assert!(lines[chunk.start_line - 2].is_empty());
+        }
+
+        // Contiguous, complete coverage
+        assert_eq!(chunks[0].start_line, 1);
+        for pair in chunks.windows(2) {
+            assert_eq!(pair[1].start_line, pair[0].end_line + 1);
+        }
+        assert_eq!(chunks.last().unwrap().end_line, content.lines().count());
+    }
+
+    #[test]
+    fn test_symbol_chunking_one_chunk_per_function() {
+        let code = r#"use std::collections::HashMap;
+
+/// Count how often each word appears in the text, ignoring case
+pub fn word_counts(text: &str) -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+    for word in text.split_whitespace() {
+        *counts.entry(word.to_lowercase()).or_insert(0) += 1;
+    }
+    counts
+}
+
+pub fn most_common(counts: &HashMap<String, usize>) -> Option<(&String, &usize)> {
+    counts.iter().max_by_key(|(word, count)| (*count, std::cmp::Reverse(*word)))
+}
+
+fn render(counts: &HashMap<String, usize>) -> String {
+    let mut lines: Vec<String> = counts.iter().map(|(w, c)| format!("{}: {}", w, c)).collect();
+    lines.sort();
+    lines.join("\n")
+}
+"#;
+        let chunker = CodeChunker::default().with_strategy(ChunkStrategy::Symbol);
+        let chunks = chunker.chunk(code, Some("rust"));
+
+        let spans: Vec<(usize, usize)> =
+            chunks.iter().map(|c| (c.start_line, c.end_line)).collect();
+        // The short `use` line and blank lines join the following function
+        assert_eq!(spans, [(1, 10), (11, 14), (15, 20)]);
+        assert!(chunks.iter().all(|c| c.chunk_type == ChunkType::Function));
+        assert!(chunks[0]
+            .content
+            .starts_with("use std::collections::HashMap;\n\n/// Count how often"));
+
+        assert!(chunks[2].content.ends_with("lines.join(\"\\n\")\n}"));
+
+        // A gap long enough to stand alone keeps a chunk of its own; every
+        // line lands in some chunk either way
+        let code = format!(
+            "{}\nfn main() {{\n    run();\n}}\n\nconst LIMIT: usize = 10;\n",
+            "use std::collections::{HashMap, HashSet, BTreeMap};\n".repeat(3)
+        );
+        let chunks = chunker.chunk(&code, Some("rust"));
+        let spans: Vec<(usize, usize, ChunkType)> = chunks
+            .iter()
+            .map(|c| (c.start_line, c.end_line, c.chunk_type.clone()))
+            .collect();
+        assert_eq!(
+            spans,
+            [
+                (1, 4, ChunkType::Code),
+                (5, 7, ChunkType::Function),
+                (8, 9, ChunkType::Code)
+            ],
+            "{:?}",
+            chunks
+        );
+
+        // No symbol parser for the language: same chunks as window mode
+        let text = "value = 1\n".repeat(300);
+        let window = CodeChunker::default().chunk(&text, Some("text"));
+        let symbol = chunker.chunk(&text, Some("text"));
+        let spans = |chunks: &[Chunk]| -> Vec<(usize, usize)> {
+            chunks.iter().map(|c| (c.start_line, c.end_line)).collect()
+        };
+        assert_eq!(spans(&symbol), spans(&window));
+        assert_eq!(ChunkStrategy::parse("lines"), None);
+    }
+}
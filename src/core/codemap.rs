@@ -9,10 +9,16 @@
 //! Result: 90%+ token reduction for LLM code understanding
 
 use anyhow::Result;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::core::config::Config;
+use crate::core::lock::{write_atomic, IndexLock};
+use crate::core::parser::{compact_signature, mask_source};
 
 /// A symbol in the codebase (function, struct, type, etc.)
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,12 +27,21 @@ pub struct Symbol {
     pub id: String,
     /// Symbol name
     pub name: String,
+    /// Owning type for methods (e.g. the `impl` target in Rust)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<String>,
     /// File path
     pub file: String,
     /// Line number
     pub line: usize,
+    /// Last line of the body (same as `line` when there is none)
+    #[serde(default)]
+    pub end_line: usize,
     /// Symbol kind
     pub kind: SymbolKind,
+    /// Declared visibility (`Unknown` in maps saved before it was tracked)
+    #[serde(default)]
+    pub visibility: Visibility,
     /// Signature (for functions: params + return type)
     pub signature: String,
     /// One-line semantic summary (auto-generated)
@@ -40,22 +55,157 @@ pub struct Symbol {
     pub embedding: Vec<f32>,
 }
 
+impl Symbol {
+    /// A declaration with id `file:name`, before body, docs, or links are known
+    pub fn new(file: &str, name: &str, line: usize, kind: SymbolKind, signature: String) -> Self {
+        Self {
+            id: format!("{}:{}", file, name),
+            name: name.to_string(),
+            container: None,
+            file: file.to_string(),
+            line,
+            end_line: line,
+            kind,
+            visibility: Visibility::Unknown,
+            signature,
+            summary: String::new(),
+            depends_on: Vec::new(),
+            depended_by: Vec::new(),
+            embedding: Vec::new(),
+        }
+    }
+
+    /// Set the owning type; the id is requalified to `file:Type::name`
+    pub fn with_container(mut self, container: Option<String>) -> Self {
+        self.container = container;
+        self.id = format!("{}:{}", self.file, self.qualified_name());
+        self
+    }
+
+    pub fn with_visibility(mut self, visibility: Visibility) -> Self {
+        self.visibility = visibility;
+        self
+    }
+
+    pub fn with_end_line(mut self, end_line: usize) -> Self {
+        self.end_line = end_line;
+        self
+    }
+
+    /// Name qualified by its container, e.g. `Type::new` (`Class.method` in
+    /// Python and TypeScript/JavaScript, `Type.Method` in Go); used in ids
+    pub fn qualified_name(&self) -> String {
+        match &self.container {
+            Some(container)
+                if [
+                    ".py", ".pyi", ".go", ".ts", ".tsx", ".mts", ".cts", ".js", ".jsx", ".mjs",
+                ]
+                .iter()
+                .any(|ext| self.file.ends_with(ext)) =>
+            {
+                format!("{}.{}", container, self.name)
+            }
+            Some(container) => format!("{}::{}", container, self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// Move a file's symbols to `file` and give them ids (`file:Type::name`)
+///
+/// A name declared more than once in the file (overloads, the same method
+/// on different receivers) gets its line appended, `file:handle@42`, so
+/// every declaration stays addressable.
+pub fn assign_symbol_ids(file: &str, symbols: &mut [Symbol]) {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for symbol in symbols.iter() {
+        *counts.entry(symbol.qualified_name()).or_default() += 1;
+    }
+
+    for symbol in symbols.iter_mut() {
+        let name = symbol.qualified_name();
+        symbol.file = file.to_string();
+        symbol.id = if counts[&name] > 1 {
+            format!("{}:{}@{}", file, name, symbol.line)
+        } else {
+            format!("{}:{}", file, name)
+        };
+    }
+}
+
+/// Serialized as its `as_str()` form; the aliases read maps saved before that
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum SymbolKind {
+    #[serde(rename = "fn", alias = "Function")]
     Function,
+    #[serde(rename = "method", alias = "Method")]
     Method,
+    #[serde(rename = "struct", alias = "Struct")]
     Struct,
+    #[serde(rename = "enum", alias = "Enum")]
     Enum,
+    #[serde(rename = "trait", alias = "Trait")]
     Trait,
+    #[serde(rename = "interface", alias = "Interface")]
     Interface,
+    #[serde(rename = "class", alias = "Class")]
     Class,
+    #[serde(rename = "type", alias = "Type")]
     Type,
+    #[serde(rename = "const", alias = "Const")]
     Const,
+    #[serde(rename = "var", alias = "Var")]
+    Var,
+    #[serde(rename = "mod", alias = "Module")]
     Module,
+    #[serde(rename = "export", alias = "Export")]
     Export,
+    #[serde(rename = "macro")]
+    Macro,
 }
 
 impl SymbolKind {
+    pub const ALL: [SymbolKind; 13] = [
+        SymbolKind::Function,
+        SymbolKind::Method,
+        SymbolKind::Struct,
+        SymbolKind::Enum,
+        SymbolKind::Trait,
+        SymbolKind::Interface,
+        SymbolKind::Class,
+        SymbolKind::Type,
+        SymbolKind::Const,
+        SymbolKind::Var,
+        SymbolKind::Module,
+        SymbolKind::Export,
+        SymbolKind::Macro,
+    ];
+
+    /// Parse an `as_str()` name or its spelled-out form (e.g. "function", "module")
+    pub fn parse(name: &str) -> Option<Self> {
+        let name = name.trim().to_lowercase();
+        let name = match name.as_str() {
+            "function" | "func" => "fn",
+            "module" => "mod",
+            "constant" => "const",
+            "variable" => "var",
+            other => other,
+        };
+        Self::ALL.into_iter().find(|k| k.as_str() == name)
+    }
+
+    /// Fill color for the node in `CodeMap::to_dot`
+    fn dot_color(&self) -> &'static str {
+        match self {
+            SymbolKind::Function | SymbolKind::Method | SymbolKind::Macro => "lightblue",
+            SymbolKind::Struct | SymbolKind::Class => "lightgoldenrod",
+            SymbolKind::Enum | SymbolKind::Type => "khaki",
+            SymbolKind::Trait | SymbolKind::Interface => "palegreen",
+            SymbolKind::Const | SymbolKind::Var => "lightgray",
+            SymbolKind::Module | SymbolKind::Export => "thistle",
+        }
+    }
+
     pub fn as_str(&self) -> &'static str {
         match self {
             SymbolKind::Function => "fn",
@@ -67,12 +217,37 @@ impl SymbolKind {
             SymbolKind::Class => "class",
             SymbolKind::Type => "type",
             SymbolKind::Const => "const",
+            SymbolKind::Var => "var",
             SymbolKind::Module => "mod",
             SymbolKind::Export => "export",
+            SymbolKind::Macro => "macro",
         }
     }
 }
 
+/// Visibility of a symbol, as far as its language's modifiers tell
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    /// Exported: `pub`, `export`, `public`, a capitalized Go name
+    Public,
+    /// Visible only within its file, type, or module
+    Private,
+    /// Visible across the crate or package but not exported (`pub(crate)`,
+    /// `internal`, Java package-private)
+    Crate,
+    /// The language has no reliable marker (e.g. Ruby)
+    #[default]
+    Unknown,
+}
+
+/// Granularity for dependency cycle detection
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CycleLevel {
+    Symbol,
+    File,
+}
+
 /// File summary in the codebase map
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileSummary {
@@ -85,6 +260,30 @@ pub struct FileSummary {
     pub lines: usize,
 }
 
+/// A file with no extracted symbols (kept as a name-only entry)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnparsedFile {
+    pub path: String,
+    pub language: String,
+    pub size: usize,
+}
+
+/// An `impl Trait for Type` block (Rust)
+///
+/// Both sides are kept by name, so impls of external traits (`Display`) and
+/// impls in a different file than the type are still recorded.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TraitImpl {
+    /// Implementing type (`Foo` in `impl fmt::Display for Foo`)
+    pub type_name: String,
+    /// Implemented trait, without path or generics (`Display`)
+    pub trait_name: String,
+    /// File containing the impl
+    pub file: String,
+    /// Line of the `impl`
+    pub line: usize,
+}
+
 /// The complete codebase map
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct CodeMap {
@@ -98,8 +297,20 @@ pub struct CodeMap {
     pub modules: HashMap<String, Vec<String>>, // module -> files
     /// Global dependency graph edges
     pub edges: Vec<(String, String)>, // (from_symbol, to_symbol)
+    /// Files scanned but without any symbols (Makefile, Dockerfile, unsupported languages)
+    #[serde(default)]
+    pub unparsed: Vec<UnparsedFile>,
+    /// Trait implementations, resolved to symbols by name when expanding
+    #[serde(default)]
+    pub trait_impls: Vec<TraitImpl>,
     /// Version for cache invalidation
     pub version: u64,
+    /// Per-language display labels for symbol kinds (from config, not persisted)
+    #[serde(skip)]
+    kind_labels: HashMap<String, HashMap<String, String>>,
+    /// Show signatures verbatim instead of compacted (not persisted)
+    #[serde(skip)]
+    full_signatures: bool,
 }
 
 impl CodeMap {
@@ -110,7 +321,45 @@ impl CodeMap {
             files: HashMap::new(),
             modules: HashMap::new(),
             edges: Vec::new(),
+            unparsed: Vec::new(),
+            trait_impls: Vec::new(),
             version: 1,
+            kind_labels: HashMap::new(),
+            full_signatures: false,
+        }
+    }
+
+    /// Override how symbol kinds are displayed, keyed by language then kind
+    ///
+    /// Purely presentational: `SymbolKind` itself is unchanged.
+    pub fn with_kind_labels(mut self, labels: HashMap<String, HashMap<String, String>>) -> Self {
+        self.kind_labels = labels;
+        self
+    }
+
+    /// Display label for a symbol's kind, honoring per-language overrides
+    pub fn kind_label<'a>(&'a self, sym: &'a Symbol) -> &'a str {
+        let kind = sym.kind.as_str();
+        self.files
+            .get(&sym.file)
+            .and_then(|f| self.kind_labels.get(&f.language))
+            .and_then(|labels| labels.get(kind))
+            .map(|label| label.as_str())
+            .unwrap_or(kind)
+    }
+
+    /// Show full signatures in listings instead of compacting long parameters
+    pub fn with_full_signatures(mut self, enabled: bool) -> Self {
+        self.full_signatures = enabled;
+        self
+    }
+
+    /// Signature as shown in listings: compacted unless full signatures are on
+    pub fn display_signature(&self, sym: &Symbol) -> String {
+        if self.full_signatures {
+            sym.signature.clone()
+        } else {
+            compact_signature(&sym.signature)
         }
     }
 
@@ -119,6 +368,14 @@ impl CodeMap {
         root.join(".sgrep").join("map.json")
     }
 
+    /// Take the writer lock for this root's map
+    pub fn lock(root: &Path) -> Result<IndexLock> {
+        let dir = root.join(".sgrep");
+        fs::create_dir_all(&dir)?;
+        let wait = Config::load().map(|c| c.lock_wait_secs).unwrap_or(0);
+        IndexLock::acquire(&dir.join(".searchgrep.lock"), Duration::from_secs(wait))
+    }
+
     /// Load existing map
     pub fn load(root: &Path) -> Result<Option<Self>> {
         let path = Self::map_path(root);
@@ -137,10 +394,21 @@ impl CodeMap {
         fs::create_dir_all(&dir)?;
         let path = Self::map_path(root);
         let content = serde_json::to_string_pretty(self)?;
-        fs::write(path, content)?;
+        write_atomic(&path, content)?;
         Ok(())
     }
 
+    /// Remove the saved map, returning whether one existed
+    pub fn delete(root: &Path) -> Result<bool> {
+        let path = Self::map_path(root);
+        if !path.exists() {
+            return Ok(false);
+        }
+        let _lock = Self::lock(root)?;
+        fs::remove_file(&path)?;
+        Ok(true)
+    }
+
     /// Add a symbol
     pub fn add_symbol(&mut self, symbol: Symbol) {
         let id = symbol.id.clone();
@@ -161,6 +429,39 @@ impl CodeMap {
         self.files.insert(file.path.clone(), file);
     }
 
+    /// Record an `impl Trait for Type` block
+    pub fn add_trait_impl(&mut self, imp: TraitImpl) {
+        self.trait_impls.push(imp);
+    }
+
+    /// Add a name-only entry for a file without symbols
+    pub fn add_unparsed_file(&mut self, file: UnparsedFile) {
+        self.unparsed.push(file);
+    }
+
+    /// List files without symbols (path, size, language)
+    pub fn to_unparsed_overview(&self) -> String {
+        let mut output = String::new();
+        if self.unparsed.is_empty() {
+            return output;
+        }
+
+        output.push_str(&format!("## Other files ({})\n", self.unparsed.len()));
+
+        let mut files: Vec<&UnparsedFile> = self.unparsed.iter().collect();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        for file in files {
+            output.push_str(&format!(
+                "  {} ({} bytes, {})\n",
+                file.path, file.size, file.language
+            ));
+        }
+        output.push('\n');
+
+        output
+    }
+
     /// Generate compact overview for LLM (minimal tokens)
     pub fn to_compact_overview(&self) -> String {
         let mut output = String::new();
@@ -183,8 +484,8 @@ impl CodeMap {
                 if let Some(sym) = self.symbols.get(sym_id) {
                     output.push_str(&format!(
                         "  {} {} {}\n",
-                        sym.kind.as_str(),
-                        sym.signature,
+                        self.kind_label(sym),
+                        self.display_signature(sym),
                         if sym.summary.is_empty() {
                             "".to_string()
                         } else {
@@ -199,6 +500,80 @@ impl CodeMap {
         output
     }
 
+    /// Symbols ordered by in-degree (most depended-on first), then by location
+    pub fn symbols_by_centrality(&self) -> Vec<&Symbol> {
+        let mut symbols: Vec<&Symbol> = self.symbols.values().collect();
+        symbols.sort_by(|a, b| {
+            b.depended_by
+                .len()
+                .cmp(&a.depended_by.len())
+                .then_with(|| (&a.file, a.line, &a.id).cmp(&(&b.file, b.line, &b.id)))
+        });
+        symbols
+    }
+
+    /// Functions and methods nothing in the map depends on
+    ///
+    /// A dead-code heuristic: edges can't see dynamic dispatch, reflection or
+    /// callers outside the map, so entry points, exported symbols, tests,
+    /// Python dunders and methods of types with trait impls are left out.
+    /// Meaningless until edges are built. Sorted by file and line.
+    pub fn orphans(&self) -> Vec<&Symbol> {
+        let mut orphans: Vec<&Symbol> = self
+            .symbols
+            .values()
+            .filter(|s| matches!(s.kind, SymbolKind::Function | SymbolKind::Method))
+            .filter(|s| s.depended_by.is_empty())
+            .filter(|s| s.visibility != Visibility::Public)
+            .filter(|s| {
+                !self
+                    .files
+                    .get(&s.file)
+                    .is_some_and(|f| f.exports.contains(&s.name))
+            })
+            .filter(|s| !ENTRY_POINT_NAMES.contains(&s.name.as_str()))
+            .filter(|s| !(s.name.starts_with("__") && s.name.ends_with("__")))
+            .filter(|s| !is_test_symbol(s))
+            .filter(|s| {
+                !s.container
+                    .as_ref()
+                    .is_some_and(|ty| self.trait_impls.iter().any(|imp| &imp.type_name == ty))
+            })
+            .collect();
+        orphans.sort_by(|a, b| (&a.file, a.line, &a.id).cmp(&(&b.file, b.line, &b.id)));
+        orphans
+    }
+
+    /// Compact overview led by the most-referenced symbols instead of file order
+    pub fn to_centrality_overview(&self) -> String {
+        let mut output = format!(
+            "# {} ({} files, {} symbols, most referenced first)\n\n",
+            self.root,
+            self.files.len(),
+            self.symbols.len()
+        );
+        for sym in self.symbols_by_centrality() {
+            output.push_str(&self.centrality_line(sym));
+        }
+        output
+    }
+
+    fn centrality_line(&self, sym: &Symbol) -> String {
+        format!(
+            "  {} {} [{}:{}, {} refs]{}\n",
+            self.kind_label(sym),
+            self.display_signature(sym),
+            sym.file,
+            sym.line,
+            sym.depended_by.len(),
+            if sym.summary.is_empty() {
+                String::new()
+            } else {
+                format!(" // {}", sym.summary)
+            }
+        )
+    }
+
     /// Generate ultra-compact overview (just signatures)
     pub fn to_minimal_overview(&self) -> String {
         let mut output = String::new();
@@ -222,6 +597,140 @@ impl CodeMap {
         output
     }
 
+    /// Structured export for external tooling (dashboards, editors)
+    ///
+    /// Symbols are ordered by file then line so the output is stable.
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut symbols: Vec<&Symbol> = self.symbols.values().collect();
+        symbols.sort_by(|a, b| (&a.file, a.line, &a.id).cmp(&(&b.file, b.line, &b.id)));
+
+        let mut files: Vec<&FileSummary> = self.files.values().collect();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        serde_json::json!({
+            "root": self.root,
+            "version": self.version,
+            "files": files
+                .iter()
+                .map(|f| serde_json::json!({
+                    "path": f.path,
+                    "language": f.language,
+                    "lines": f.lines,
+                    "summary": f.summary,
+                }))
+                .collect::<Vec<_>>(),
+            "symbols": symbols
+                .iter()
+                .map(|s| serde_json::json!({
+                    "id": s.id,
+                    "name": s.name,
+                    "container": s.container,
+                    "file": s.file,
+                    "line": s.line,
+                    "end_line": s.end_line,
+                    "kind": s.kind,
+                    "visibility": s.visibility,
+                    "signature": s.signature,
+                    "summary": s.summary,
+                }))
+                .collect::<Vec<_>>(),
+            "edges": self
+                .edges
+                .iter()
+                .map(|(from, to)| serde_json::json!({ "from": from, "to": to }))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    /// Graphviz digraph of symbol dependencies, colored by kind
+    ///
+    /// Edges come from `depends_on` (filled by `build_edges`); dependencies on
+    /// symbols outside the map are dropped.
+    pub fn to_dot(&self) -> String {
+        let mut symbols: Vec<&Symbol> = self.symbols.values().collect();
+        symbols.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let mut output = String::from("digraph codebase {\n");
+        output.push_str("    node [shape=box, style=filled];\n");
+        for sym in &symbols {
+            output.push_str(&format!(
+                "    {} [label={}, fillcolor={}];\n",
+                dot_quote(&sym.id),
+                dot_quote(&sym.qualified_name()),
+                sym.kind.dot_color()
+            ));
+        }
+        for sym in &symbols {
+            for dep in sym
+                .depends_on
+                .iter()
+                .filter(|d| self.symbols.contains_key(*d))
+            {
+                output.push_str(&format!(
+                    "    {} -> {};\n",
+                    dot_quote(&sym.id),
+                    dot_quote(dep)
+                ));
+            }
+        }
+        output.push_str("}\n");
+        output
+    }
+
+    /// Drop symbols of other kinds (and their edges and emptied files);
+    /// an empty `kinds` keeps everything
+    pub fn retain_kinds(&mut self, kinds: &[SymbolKind]) {
+        if !kinds.is_empty() {
+            self.retain_symbols(|s| kinds.contains(&s.kind));
+        }
+    }
+
+    /// Drop private and crate-internal symbols, leaving the exported surface
+    ///
+    /// Symbols whose visibility is unknown are kept.
+    pub fn retain_public(&mut self) {
+        self.retain_symbols(|s| !matches!(s.visibility, Visibility::Private | Visibility::Crate));
+    }
+
+    /// Keep only the `keep` most central symbols (see `symbols_by_centrality`)
+    pub fn retain_most_central(&mut self, keep: usize) {
+        let kept: HashSet<String> = self
+            .symbols_by_centrality()
+            .into_iter()
+            .take(keep)
+            .map(|s| s.id.clone())
+            .collect();
+        self.retain_symbols(|s| kept.contains(&s.id));
+    }
+
+    fn retain_symbols(&mut self, keep: impl Fn(&Symbol) -> bool) {
+        self.symbols.retain(|_, s| keep(s));
+        for file in self.files.values_mut() {
+            file.symbols.retain(|id| self.symbols.contains_key(id));
+        }
+        self.files.retain(|_, f| !f.symbols.is_empty());
+        self.edges
+            .retain(|(from, to)| self.symbols.contains_key(from) && self.symbols.contains_key(to));
+    }
+
+    /// Every symbol defined in `path` (relative to the root), in line order
+    pub fn symbols_in_file(&self, path: &str) -> Vec<&Symbol> {
+        let path = path.replace('\\', "/");
+        let path = path.trim_start_matches("./");
+        let mut symbols: Vec<&Symbol> = self.symbols.values().filter(|s| s.file == path).collect();
+        symbols.sort_by(|a, b| a.line.cmp(&b.line).then_with(|| a.id.cmp(&b.id)));
+        symbols
+    }
+
+    /// `search`, limited to symbols of the given kinds (all kinds when empty)
+    pub fn search_by_kind(&self, query: &str, kinds: &[SymbolKind]) -> Vec<&Symbol> {
+        let mut results = self.search(query);
+        if !kinds.is_empty() {
+            results.retain(|s| kinds.contains(&s.kind));
+        }
+        results
+    }
+
     /// Search symbols by query
     pub fn search(&self, query: &str) -> Vec<&Symbol> {
         let query_lower = query.to_lowercase();
@@ -239,12 +748,165 @@ impl CodeMap {
         results.sort_by(|a, b| {
             let a_exact = a.name.to_lowercase() == query_lower;
             let b_exact = b.name.to_lowercase() == query_lower;
-            b_exact.cmp(&a_exact)
+            b_exact
+                .cmp(&a_exact)
+                .then(a.file.cmp(&b.file))
+                .then(a.line.cmp(&b.line))
         });
 
         results
     }
 
+    /// Symbols whose name or signature matches `pattern`, in file order
+    ///
+    /// Limited to the given kinds (all kinds when empty).
+    pub fn search_regex(&self, pattern: &Regex, kinds: &[SymbolKind]) -> Vec<&Symbol> {
+        let mut results: Vec<&Symbol> = self
+            .symbols
+            .values()
+            .filter(|s| kinds.is_empty() || kinds.contains(&s.kind))
+            .filter(|s| pattern.is_match(&s.name) || pattern.is_match(&s.signature))
+            .collect();
+        results.sort_by(|a, b| (&a.file, a.line, &a.id).cmp(&(&b.file, b.line, &b.id)));
+        results
+    }
+
+    /// Whether any symbol has a captured summary/doc comment
+    pub fn has_symbol_summaries(&self) -> bool {
+        self.symbols.values().any(|s| !s.summary.is_empty())
+    }
+
+    /// Rank symbols by how well their summary matches a description
+    ///
+    /// Each query term scores 1.0 for an exact word match and 0.5 for a
+    /// shared stem (common prefix of 4+ chars); the total is averaged over
+    /// terms, plus 1.0 when the whole query appears verbatim. Symbols matching
+    /// fewer than half the terms are dropped.
+    pub fn search_summaries(&self, query: &str) -> Vec<(&Symbol, f32)> {
+        let query_lower = query.to_lowercase();
+        let terms = doc_terms(&query_lower);
+        if terms.is_empty() {
+            return Vec::new();
+        }
+
+        let mut results: Vec<(&Symbol, f32)> = self
+            .symbols
+            .values()
+            .filter(|s| !s.summary.is_empty())
+            .filter_map(|s| {
+                let summary = s.summary.to_lowercase();
+                let words = doc_terms(&summary);
+
+                let matched: f32 = terms
+                    .iter()
+                    .map(|term| {
+                        if words.contains(term) {
+                            1.0
+                        } else if words.iter().any(|w| shares_stem(w, term)) {
+                            0.5
+                        } else {
+                            0.0
+                        }
+                    })
+                    .sum();
+                let mut score = matched / terms.len() as f32;
+                if score < 0.5 {
+                    return None;
+                }
+                if summary.contains(query_lower.trim()) {
+                    score += 1.0;
+                }
+                Some((s, score))
+            })
+            .collect();
+
+        results.sort_by(|a, b| {
+            b.1.partial_cmp(&a.1)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(a.0.id.cmp(&b.0.id))
+        });
+        results
+    }
+
+    /// Link symbols to the symbols their bodies reference
+    ///
+    /// `sources` holds file contents keyed by the same relative paths as
+    /// `files`. Each function or method body (`line..=end_line`, comments and
+    /// strings masked) is scanned for identifiers naming a known symbol. Only
+    /// names that resolve to exactly one symbol are linked, so an ambiguous
+    /// `new` or `parse` yields no edge rather than a wrong one. Replaces any
+    /// existing `depends_on`/`depended_by`.
+    pub fn build_edges(&mut self, sources: &HashMap<String, String>) {
+        let mut by_name: HashMap<&str, Vec<&str>> = HashMap::new();
+        for sym in self.symbols.values() {
+            by_name.entry(&sym.name).or_default().push(&sym.id);
+        }
+
+        let mut links: Vec<(String, String)> = Vec::new();
+        for (path, source) in sources {
+            let language = self.files.get(path).map_or("", |f| f.language.as_str());
+            let code = mask_source(source, language);
+            let lines: Vec<&str> = code.lines().collect();
+
+            let bodies = self.symbols.values().filter(|s| {
+                s.file == *path && matches!(s.kind, SymbolKind::Function | SymbolKind::Method)
+            });
+            for caller in bodies {
+                let start = caller.line.saturating_sub(1).min(lines.len());
+                let end = caller.end_line.max(caller.line).min(lines.len());
+
+                let mut seen: HashSet<&str> = HashSet::new();
+                let identifiers = lines[start..end]
+                    .iter()
+                    .flat_map(|line| line.split(|c: char| !(c.is_alphanumeric() || c == '_')))
+                    .filter(|word| {
+                        !word.is_empty() && !word.starts_with(|c: char| c.is_ascii_digit())
+                    });
+                for word in identifiers {
+                    if !seen.insert(word) {
+                        continue;
+                    }
+                    if let Some([callee]) = by_name.get(word).map(|ids| ids.as_slice()) {
+                        if *callee != caller.id {
+                            links.push((caller.id.clone(), callee.to_string()));
+                        }
+                    }
+                }
+            }
+        }
+
+        for sym in self.symbols.values_mut() {
+            sym.depends_on.clear();
+            sym.depended_by.clear();
+        }
+        links.sort();
+        for (from, to) in links {
+            if let Some(sym) = self.symbols.get_mut(&from) {
+                sym.depends_on.push(to.clone());
+            }
+            if let Some(sym) = self.symbols.get_mut(&to) {
+                sym.depended_by.push(from);
+            }
+        }
+    }
+
+    /// Declarations sharing `id` once their `@line` suffix is dropped
+    ///
+    /// Lets a bare `file:name` lead to every declaration of an overloaded name.
+    pub fn overloads(&self, id: &str) -> Vec<&Symbol> {
+        let prefix = format!("{}@", id);
+        let mut found: Vec<&Symbol> = self
+            .symbols
+            .values()
+            .filter(|s| {
+                s.id.strip_prefix(&prefix)
+                    .is_some_and(|line| line.parse::<usize>().is_ok())
+            })
+            .collect();
+        found.sort_by_key(|s| s.line);
+        found
+    }
+
     /// Get symbol with its dependencies
     pub fn expand(&self, symbol_id: &str) -> Option<ExpandedSymbol> {
         let symbol = self.symbols.get(symbol_id)?;
@@ -261,13 +923,170 @@ impl CodeMap {
             .filter_map(|id| self.symbols.get(id))
             .collect();
 
+        let is_type = |s: &Symbol| {
+            matches!(
+                s.kind,
+                SymbolKind::Struct | SymbolKind::Enum | SymbolKind::Type
+            )
+        };
+
+        let implements: Vec<&TraitImpl> = if is_type(symbol) {
+            self.trait_impls
+                .iter()
+                .filter(|imp| imp.type_name == symbol.name)
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let mut implementors: Vec<&Symbol> = Vec::new();
+        if symbol.kind == SymbolKind::Trait {
+            let types: HashSet<&str> = self
+                .trait_impls
+                .iter()
+                .filter(|imp| imp.trait_name == symbol.name)
+                .map(|imp| imp.type_name.as_str())
+                .collect();
+            implementors = self
+                .symbols
+                .values()
+                .filter(|s| is_type(s) && types.contains(s.name.as_str()))
+                .collect();
+            implementors.sort_by(|a, b| a.id.cmp(&b.id));
+        }
+
         Some(ExpandedSymbol {
             symbol,
             dependencies,
             dependents,
+            implements,
+            implementors,
         })
     }
 
+    /// Aggregate the symbols of every file under a directory prefix
+    ///
+    /// An empty prefix covers the whole map. Key symbols are exported ones
+    /// (falling back to top-level ones), most depended-on first.
+    pub fn module_summary(&self, prefix: &str, max_symbols: usize) -> ModuleSummary {
+        let prefix = prefix.trim_start_matches("./").trim_end_matches('/');
+        let in_module = |path: &str| {
+            prefix.is_empty()
+                || path == prefix
+                || path
+                    .strip_prefix(prefix)
+                    .is_some_and(|rest| rest.starts_with('/'))
+        };
+
+        let mut files = 0;
+        let mut lines = 0;
+        let mut kinds: HashMap<String, usize> = HashMap::new();
+        let mut languages: HashMap<String, usize> = HashMap::new();
+        let mut imports: HashMap<String, usize> = HashMap::new();
+        let mut public: Vec<&Symbol> = Vec::new();
+        let mut top_level: Vec<&Symbol> = Vec::new();
+
+        for file in self.files.values().filter(|f| in_module(&f.path)) {
+            files += 1;
+            lines += file.lines;
+            *languages.entry(file.language.clone()).or_default() += 1;
+
+            let unique_imports: HashSet<&String> = file.imports.iter().collect();
+            for import in unique_imports {
+                *imports.entry(import.clone()).or_default() += 1;
+            }
+
+            for sym in file.symbols.iter().filter_map(|id| self.symbols.get(id)) {
+                *kinds.entry(self.kind_label(sym).to_string()).or_default() += 1;
+                if file.exports.contains(&sym.name) {
+                    public.push(sym);
+                } else if sym.kind != SymbolKind::Method {
+                    top_level.push(sym);
+                }
+            }
+        }
+
+        let mut key = if public.is_empty() { top_level } else { public };
+        key.sort_by(|a, b| {
+            b.depended_by
+                .len()
+                .cmp(&a.depended_by.len())
+                .then(a.file.cmp(&b.file))
+                .then(a.line.cmp(&b.line))
+        });
+
+        ModuleSummary {
+            prefix: prefix.to_string(),
+            files,
+            lines,
+            symbols: kinds.values().sum(),
+            kinds: sorted_counts(kinds),
+            languages: sorted_counts(languages),
+            key_symbols: key
+                .into_iter()
+                .take(max_symbols)
+                .map(|s| KeySymbol {
+                    kind: self.kind_label(s).to_string(),
+                    signature: self.display_signature(s),
+                    file: s.file.clone(),
+                    line: s.line,
+                })
+                .collect(),
+            top_imports: sorted_counts(imports).into_iter().take(10).collect(),
+        }
+    }
+
+    /// Dependency adjacency at the given granularity (sorted, self-edges dropped)
+    pub fn dependency_graph(&self, level: CycleLevel) -> BTreeMap<String, BTreeSet<String>> {
+        let symbol_edges = self
+            .symbols
+            .values()
+            .flat_map(|s| s.depends_on.iter().map(move |d| (&s.id, d)));
+        let map_edges = self.edges.iter().map(|(from, to)| (from, to));
+
+        let mut graph: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        for (from_id, to_id) in symbol_edges.chain(map_edges) {
+            let (from, to) = match (self.symbols.get(from_id), self.symbols.get(to_id)) {
+                (Some(f), Some(t)) => (f, t),
+                _ => continue,
+            };
+            let (from, to) = match level {
+                CycleLevel::Symbol => (&from.id, &to.id),
+                CycleLevel::File => (&from.file, &to.file),
+            };
+            if from != to {
+                graph.entry(from.clone()).or_default().insert(to.clone());
+            }
+        }
+
+        graph
+    }
+
+    /// Strongly connected components with more than one node (Tarjan)
+    ///
+    /// Every node of a component can reach every other, so each one is a set
+    /// of mutually dependent nodes. Members are sorted, and components are
+    /// ordered by their first member.
+    pub fn strongly_connected_components(&self, level: CycleLevel) -> Vec<Vec<String>> {
+        let graph = self.dependency_graph(level);
+        let mut components = tarjan_scc(&graph);
+        for component in &mut components {
+            component.sort();
+        }
+        components.sort();
+        components
+    }
+
+    /// Dependency cycles: the strongly connected components of the graph
+    ///
+    /// Stops after `max_cycles`; the returned flag is true if there were more.
+    pub fn find_cycles(&self, level: CycleLevel, max_cycles: usize) -> (Vec<Vec<String>>, bool) {
+        let mut cycles = self.strongly_connected_components(level);
+        let truncated = cycles.len() > max_cycles;
+        cycles.truncate(max_cycles);
+        (cycles, truncated)
+    }
+
     /// Get stats
     pub fn stats(&self) -> CodeMapStats {
         let mut functions = 0;
@@ -293,10 +1112,247 @@ impl CodeMap {
     }
 }
 
+/// Structured, prose-free summary of a directory in the map
+#[derive(Debug, Clone)]
+pub struct ModuleSummary {
+    pub prefix: String,
+    pub files: usize,
+    pub lines: usize,
+    pub symbols: usize,
+    /// (kind label, count), most common first
+    pub kinds: Vec<(String, usize)>,
+    /// (language, file count), primary language first
+    pub languages: Vec<(String, usize)>,
+    pub key_symbols: Vec<KeySymbol>,
+    /// (import, importing file count), most common first
+    pub top_imports: Vec<(String, usize)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct KeySymbol {
+    pub kind: String,
+    pub signature: String,
+    pub file: String,
+    pub line: usize,
+}
+
+impl ModuleSummary {
+    fn title(&self) -> &str {
+        if self.prefix.is_empty() {
+            "."
+        } else {
+            &self.prefix
+        }
+    }
+
+    /// Compact plain-text rendering
+    pub fn to_text(&self) -> String {
+        let mut out = format!(
+            "# Module: {} ({} files, {} lines, {} symbols)\n",
+            self.title(),
+            self.files,
+            self.lines,
+            self.symbols
+        );
+        out.push_str(&format!("Languages: {}\n", join_counts(&self.languages)));
+        out.push_str(&format!("Kinds: {}\n", join_counts(&self.kinds)));
+
+        if !self.key_symbols.is_empty() {
+            out.push_str("Key symbols:\n");
+            for sym in &self.key_symbols {
+                out.push_str(&format!(
+                    "  [{}] {} ({}:{})\n",
+                    sym.kind, sym.signature, sym.file, sym.line
+                ));
+            }
+        }
+
+        if !self.top_imports.is_empty() {
+            out.push_str(&format!(
+                "Top imports: {}\n",
+                join_counts(&self.top_imports)
+            ));
+        }
+
+        out
+    }
+
+    /// Markdown rendering for pasting into docs or PRs
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("## Module `{}`\n\n", self.title());
+        out.push_str("| Files | Lines | Symbols | Primary language |\n");
+        out.push_str("|---|---|---|---|\n");
+        out.push_str(&format!(
+            "| {} | {} | {} | {} |\n\n",
+            self.files,
+            self.lines,
+            self.symbols,
+            self.languages
+                .first()
+                .map(|(lang, _)| lang.as_str())
+                .unwrap_or("-")
+        ));
+
+        out.push_str("### Symbols by kind\n\n");
+        for (kind, count) in &self.kinds {
+            out.push_str(&format!("- {}: {}\n", kind, count));
+        }
+
+        if !self.key_symbols.is_empty() {
+            out.push_str("\n### Key symbols\n\n");
+            for sym in &self.key_symbols {
+                out.push_str(&format!(
+                    "- `{}` ({}) - `{}:{}`\n",
+                    sym.signature, sym.kind, sym.file, sym.line
+                ));
+            }
+        }
+
+        if !self.top_imports.is_empty() {
+            out.push_str("\n### Top imports\n\n");
+            for (import, count) in &self.top_imports {
+                out.push_str(&format!("- `{}` ({})\n", import, count));
+            }
+        }
+
+        out
+    }
+}
+
+/// Names the runtime calls on its own (program entry, Go package init)
+const ENTRY_POINT_NAMES: &[&str] = &["main", "init"];
+
+/// Test functions (by name) or anything in a test file or directory
+fn is_test_symbol(sym: &Symbol) -> bool {
+    if sym.name.starts_with("test") || sym.name.starts_with("Test") {
+        return true;
+    }
+
+    let path = Path::new(&sym.file);
+    let in_test_dir = path.components().any(|c| {
+        matches!(
+            c.as_os_str().to_str(),
+            Some("test" | "tests" | "__tests__" | "spec")
+        )
+    });
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    in_test_dir
+        || stem.starts_with("test_")
+        || stem.ends_with("_test")
+        || stem.ends_with(".test")
+        || stem.ends_with(".spec")
+}
+
+/// Lowercase words of 3+ chars, minus common filler
+fn doc_terms(text: &str) -> Vec<String> {
+    const STOPWORDS: &[&str] = &[
+        "the", "and", "for", "that", "with", "this", "from", "into", "are", "its",
+    ];
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.len() >= 3 && !STOPWORDS.contains(w))
+        .map(|w| w.to_string())
+        .collect()
+}
+
+fn shares_stem(a: &str, b: &str) -> bool {
+    a.chars().zip(b.chars()).take_while(|(x, y)| x == y).count() >= 4
+}
+
+/// Counts sorted by frequency, then name
+fn sorted_counts(counts: HashMap<String, usize>) -> Vec<(String, usize)> {
+    let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    counts
+}
+
+fn join_counts(counts: &[(String, usize)]) -> String {
+    counts
+        .iter()
+        .map(|(name, count)| format!("{} {}", name, count))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Tarjan's algorithm, iterative so deep call chains can't overflow the stack
+///
+/// Returns only components of two or more nodes; self-edges are already
+/// dropped from the graph.
+fn tarjan_scc(graph: &BTreeMap<String, BTreeSet<String>>) -> Vec<Vec<String>> {
+    let mut next_index = 0;
+    let mut index: HashMap<&str, usize> = HashMap::new();
+    let mut low: HashMap<&str, usize> = HashMap::new();
+    let mut stack: Vec<&str> = Vec::new();
+    let mut on_stack: HashSet<&str> = HashSet::new();
+    let mut components = Vec::new();
+    let no_deps = BTreeSet::new();
+    let deps_of = |node: &str| graph.get(node).unwrap_or(&no_deps).iter();
+
+    for root in graph.keys() {
+        if index.contains_key(root.as_str()) {
+            continue;
+        }
+
+        // Each frame is a node and its dependencies still to visit
+        let mut frames = Vec::new();
+        index.insert(root, next_index);
+        low.insert(root, next_index);
+        next_index += 1;
+        stack.push(root);
+        on_stack.insert(root);
+        frames.push((root.as_str(), deps_of(root)));
+
+        while let Some((node, deps)) = frames.last_mut() {
+            let node: &str = node;
+            match deps.next().map(String::as_str) {
+                Some(dep) if !index.contains_key(dep) => {
+                    index.insert(dep, next_index);
+                    low.insert(dep, next_index);
+                    next_index += 1;
+                    stack.push(dep);
+                    on_stack.insert(dep);
+                    frames.push((dep, deps_of(dep)));
+                }
+                Some(dep) => {
+                    if on_stack.contains(dep) {
+                        let lowest = low[node].min(index[dep]);
+                        low.insert(node, lowest);
+                    }
+                }
+                None => {
+                    frames.pop();
+                    if let Some(&(parent, _)) = frames.last() {
+                        let lowest = low[parent].min(low[node]);
+                        low.insert(parent, lowest);
+                    }
+                    if low[node] == index[node] {
+                        let mut component = Vec::new();
+                        while let Some(member) = stack.pop() {
+                            on_stack.remove(member);
+                            component.push(member.to_string());
+                            if member == node {
+                                break;
+                            }
+                        }
+                        if component.len() > 1 {
+                            components.push(component);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    components
+}
+
 pub struct ExpandedSymbol<'a> {
     pub symbol: &'a Symbol,
     pub dependencies: Vec<&'a Symbol>,
     pub dependents: Vec<&'a Symbol>,
+    /// Trait impls for this type
+    pub implements: Vec<&'a TraitImpl>,
+    /// Types implementing this trait
+    pub implementors: Vec<&'a Symbol>,
 }
 
 #[derive(Debug)]
@@ -308,3 +1364,511 @@ pub struct CodeMapStats {
     pub other: usize,
     pub edges: usize,
 }
+
+/// Parse a comma-separated list of symbol kinds, e.g. "trait,struct"
+pub fn parse_kinds(list: &str) -> Result<Vec<SymbolKind>> {
+    list.split(',')
+        .filter(|k| !k.trim().is_empty())
+        .map(|k| {
+            SymbolKind::parse(k).ok_or_else(|| {
+                let known: Vec<&str> = SymbolKind::ALL.iter().map(|k| k.as_str()).collect();
+                anyhow::anyhow!(
+                    "Unknown symbol kind '{}' (expected one of: {})",
+                    k.trim(),
+                    known.join(", ")
+                )
+            })
+        })
+        .collect()
+}
+
+/// Quote a DOT identifier, escaping what would end the string early
+fn dot_quote(s: &str) -> String {
+    let escaped = s
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{}\"", escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add_symbol(map: &mut CodeMap, file: &str, name: &str, depends_on: &[&str]) {
+        let id = format!("{}:{}", file, name);
+        map.symbols.insert(
+            id.clone(),
+            Symbol {
+                id,
+                name: name.to_string(),
+                file: file.to_string(),
+                line: 1,
+                end_line: 1,
+                kind: SymbolKind::Function,
+                signature: format!("fn {}()", name),
+                summary: String::new(),
+                depends_on: depends_on.iter().map(|d| d.to_string()).collect(),
+                depended_by: vec![],
+                embedding: vec![],
+                container: None,
+                visibility: Visibility::Unknown,
+            },
+        );
+    }
+
+    #[test]
+    fn test_to_json() {
+        let mut map = CodeMap::new("/repo");
+        add_symbol(&mut map, "a.rs", "parse", &["b.rs:Lexer"]);
+        add_symbol(&mut map, "b.rs", "Lexer", &[]);
+        map.symbols.get_mut("b.rs:Lexer").unwrap().kind = SymbolKind::Struct;
+        map.edges
+            .push(("a.rs:parse".to_string(), "b.rs:Lexer".to_string()));
+
+        let json: serde_json::Value = serde_json::from_str(&map.to_json().to_string()).unwrap();
+        let symbols = json["symbols"].as_array().unwrap();
+        assert_eq!(symbols.len(), map.symbols.len());
+        assert_eq!(symbols[0]["id"], "a.rs:parse");
+        assert_eq!(symbols[0]["kind"], "fn");
+        assert_eq!(symbols[1]["kind"], "struct");
+        for symbol in symbols {
+            let kind: SymbolKind = serde_json::from_value(symbol["kind"].clone()).unwrap();
+            let id = symbol["id"].as_str().unwrap();
+            assert_eq!(kind, map.symbols[id].kind);
+            assert_eq!(symbol["kind"], kind.as_str());
+        }
+        assert_eq!(json["edges"][0]["to"], "b.rs:Lexer");
+
+        // Maps saved with the old variant names still load
+        let legacy: SymbolKind = serde_json::from_str("\"Function\"").unwrap();
+        assert_eq!(legacy, SymbolKind::Function);
+    }
+
+    /// Split a DOT line into its unescaped quoted strings and the text between them
+    fn parse_dot_line(line: &str) -> (Vec<String>, String) {
+        let (mut strings, mut rest) = (Vec::new(), String::new());
+        let mut chars = line.chars();
+        while let Some(c) = chars.next() {
+            if c != '"' {
+                rest.push(c);
+                continue;
+            }
+            let mut s = String::new();
+            loop {
+                match chars.next().expect("unterminated string") {
+                    '"' => break,
+                    '\\' => match chars.next().expect("dangling escape") {
+                        'n' => s.push('\n'),
+                        escaped => s.push(escaped),
+                    },
+                    c => s.push(c),
+                }
+            }
+            strings.push(s);
+        }
+        (strings, rest)
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let mut map = CodeMap::new("/repo");
+        assert_eq!(
+            map.to_dot(),
+            "digraph codebase {\n    node [shape=box, style=filled];\n}\n"
+        );
+
+        add_symbol(&mut map, "a.rs", "parse", &["b.rs:\"quoted\"", "external"]);
+        add_symbol(&mut map, "b.rs", "\"quoted\"", &["a.rs:parse"]);
+        add_symbol(&mut map, "c\\d.rs", "lonely", &[]);
+        map.symbols.get_mut("c\\d.rs:lonely").unwrap().kind = SymbolKind::Struct;
+
+        let dot = map.to_dot();
+        let (mut nodes, mut edges) = (HashSet::new(), HashSet::new());
+        for line in dot.lines() {
+            let (strings, rest) = parse_dot_line(line);
+            if rest.contains("[label=") {
+                nodes.insert(strings[0].clone());
+            } else if rest.contains("->") {
+                edges.insert((strings[0].clone(), strings[1].clone()));
+            }
+        }
+
+        assert_eq!(nodes, map.symbols.keys().cloned().collect());
+        assert_eq!(edges.len(), 2);
+        assert!(edges.contains(&("a.rs:parse".to_string(), "b.rs:\"quoted\"".to_string())));
+        assert!(dot.contains("fillcolor=lightgoldenrod"));
+    }
+
+    #[test]
+    fn test_search_by_kind() {
+        let mut map = CodeMap::new("/repo");
+        add_symbol(&mut map, "a.rs", "Parser", &[]);
+        add_symbol(&mut map, "a.rs", "parse", &["a.rs:Parser"]);
+        add_symbol(&mut map, "b.rs", "Parse", &[]);
+        map.symbols.get_mut("a.rs:Parser").unwrap().kind = SymbolKind::Struct;
+        map.symbols.get_mut("b.rs:Parse").unwrap().kind = SymbolKind::Trait;
+
+        assert_eq!(map.search_by_kind("pars", &[]).len(), 3);
+        let structs = map.search_by_kind("pars", &[SymbolKind::Struct]);
+        assert_eq!(structs.len(), 1);
+        assert_eq!(structs[0].name, "Parser");
+
+        let kinds = parse_kinds("trait, function").unwrap();
+        assert_eq!(kinds, vec![SymbolKind::Trait, SymbolKind::Function]);
+        let found = map.search_by_kind("pars", &kinds);
+        assert_eq!(found.len(), 2);
+        assert!(found.iter().all(|s| kinds.contains(&s.kind)));
+
+        let err = parse_kinds("struct,widget").unwrap_err().to_string();
+        assert!(err.contains("Unknown symbol kind 'widget'"), "{}", err);
+
+        map.edges
+            .push(("a.rs:parse".to_string(), "a.rs:Parser".to_string()));
+        map.retain_kinds(&[SymbolKind::Struct]);
+        assert_eq!(map.symbols.len(), 1);
+        assert!(map.edges.is_empty());
+    }
+
+    #[test]
+    fn test_symbols_in_file() {
+        let mut map = CodeMap::new("/repo");
+        for (name, line) in [("parse", 40), ("Lexer", 3), ("next", 12)] {
+            add_symbol(&mut map, "src/lex.rs", name, &[]);
+            map.symbols
+                .get_mut(&format!("src/lex.rs:{}", name))
+                .unwrap()
+                .line = line;
+        }
+        add_symbol(&mut map, "src/main.rs", "main", &[]);
+
+        let names = |path: &str| -> Vec<String> {
+            map.symbols_in_file(path)
+                .iter()
+                .map(|s| s.name.clone())
+                .collect()
+        };
+        assert_eq!(names("src/lex.rs"), ["Lexer", "next", "parse"]);
+        assert_eq!(names("./src\\lex.rs"), ["Lexer", "next", "parse"]);
+        assert!(names("src/missing.rs").is_empty());
+    }
+
+    #[test]
+    fn test_symbols_by_centrality() {
+        let mut map = CodeMap::new("/repo");
+        add_symbol(&mut map, "a.rs", "main", &["z.rs:hub", "b.rs:leaf"]);
+        add_symbol(&mut map, "b.rs", "leaf", &["z.rs:hub"]);
+        add_symbol(&mut map, "c.rs", "other", &["z.rs:hub"]);
+        add_symbol(&mut map, "z.rs", "hub", &[]);
+        for (from, to) in [
+            ("a.rs:main", "z.rs:hub"),
+            ("a.rs:main", "b.rs:leaf"),
+            ("b.rs:leaf", "z.rs:hub"),
+            ("c.rs:other", "z.rs:hub"),
+        ] {
+            map.symbols
+                .get_mut(to)
+                .unwrap()
+                .depended_by
+                .push(from.to_string());
+        }
+
+        let ranked: Vec<&str> = map
+            .symbols_by_centrality()
+            .iter()
+            .map(|s| s.id.as_str())
+            .collect();
+        assert_eq!(
+            ranked,
+            vec!["z.rs:hub", "b.rs:leaf", "a.rs:main", "c.rs:other"]
+        );
+
+        let overview = map.to_centrality_overview();
+        let first = overview.lines().find(|l| l.starts_with("  ")).unwrap();
+        assert!(
+            first.contains("fn hub()") && first.contains("3 refs"),
+            "{}",
+            first
+        );
+    }
+
+    #[test]
+    fn test_find_cycles() {
+        let mut map = CodeMap::new("/repo");
+        add_symbol(&mut map, "a.rs", "parse", &["b.rs:lex"]);
+        add_symbol(&mut map, "b.rs", "lex", &["c.rs:emit"]);
+        add_symbol(&mut map, "c.rs", "emit", &["a.rs:parse"]);
+        add_symbol(&mut map, "c.rs", "helper", &["c.rs:helper", "a.rs:parse"]);
+
+        let (cycles, truncated) = map.find_cycles(CycleLevel::Symbol, 10);
+        assert!(!truncated);
+        assert_eq!(cycles, vec![vec!["a.rs:parse", "b.rs:lex", "c.rs:emit"]]);
+
+        let (cycles, _) = map.find_cycles(CycleLevel::File, 10);
+        assert_eq!(cycles, vec![vec!["a.rs", "b.rs", "c.rs"]]);
+
+        let (cycles, truncated) = map.find_cycles(CycleLevel::Symbol, 0);
+        assert!(cycles.is_empty());
+        assert!(truncated);
+    }
+
+    #[test]
+    fn test_find_cycles_needs_a_back_edge() {
+        let mut map = CodeMap::new("/repo");
+        add_symbol(&mut map, "x.rs", "a", &["x.rs:b"]);
+        add_symbol(&mut map, "x.rs", "b", &["x.rs:a"]);
+        let (cycles, _) = map.find_cycles(CycleLevel::Symbol, 10);
+        assert_eq!(cycles, vec![vec!["x.rs:a", "x.rs:b"]]);
+
+        let mut map = CodeMap::new("/repo");
+        add_symbol(&mut map, "x.rs", "a", &["x.rs:b"]);
+        add_symbol(&mut map, "x.rs", "b", &["x.rs:c"]);
+        add_symbol(&mut map, "x.rs", "c", &[]);
+        let (cycles, truncated) = map.find_cycles(CycleLevel::Symbol, 10);
+        assert!(cycles.is_empty());
+        assert!(!truncated);
+
+        // No edges at all
+        assert!(CodeMap::new("/repo")
+            .find_cycles(CycleLevel::File, 10)
+            .0
+            .is_empty());
+    }
+
+    #[test]
+    fn test_find_cycles_reports_every_member_of_a_component() {
+        // a -> b -> d -> a and a -> c -> d -> a: a DFS that never revisits d
+        // from c would miss c
+        let mut map = CodeMap::new("/repo");
+        add_symbol(&mut map, "x.rs", "a", &["x.rs:b", "x.rs:c"]);
+        add_symbol(&mut map, "x.rs", "b", &["x.rs:d"]);
+        add_symbol(&mut map, "x.rs", "c", &["x.rs:d"]);
+        add_symbol(&mut map, "x.rs", "d", &["x.rs:a"]);
+        // A separate two-node loop, and a tail hanging off the diamond
+        add_symbol(&mut map, "y.rs", "e", &["y.rs:f", "x.rs:a"]);
+        add_symbol(&mut map, "y.rs", "f", &["y.rs:e"]);
+
+        let components = map.strongly_connected_components(CycleLevel::Symbol);
+        assert_eq!(
+            components,
+            vec![
+                vec!["x.rs:a", "x.rs:b", "x.rs:c", "x.rs:d"],
+                vec!["y.rs:e", "y.rs:f"],
+            ]
+        );
+
+        let (cycles, truncated) = map.find_cycles(CycleLevel::Symbol, 1);
+        assert_eq!(cycles, vec![vec!["x.rs:a", "x.rs:b", "x.rs:c", "x.rs:d"]]);
+        assert!(truncated);
+
+        // y.rs depends on x.rs but not the other way round
+        let (cycles, _) = map.find_cycles(CycleLevel::File, 10);
+        assert!(cycles.is_empty());
+    }
+
+    #[test]
+    fn test_module_summary() {
+        let mut map = CodeMap::new("/repo");
+        add_symbol(&mut map, "src/core/store.rs", "load", &[]);
+        add_symbol(
+            &mut map,
+            "src/core/store.rs",
+            "save",
+            &["src/core/store.rs:load"],
+        );
+        add_symbol(&mut map, "src/core/search.rs", "search", &[]);
+        add_symbol(&mut map, "src/main.rs", "main", &[]);
+        map.symbols
+            .get_mut("src/core/store.rs:load")
+            .unwrap()
+            .depended_by = vec!["src/core/store.rs:save".to_string()];
+
+        for (path, exports, imports) in [
+            (
+                "src/core/store.rs",
+                vec!["load", "save"],
+                vec!["serde", "anyhow"],
+            ),
+            ("src/core/search.rs", vec![], vec!["serde"]),
+            ("src/main.rs", vec!["main"], vec!["clap"]),
+        ] {
+            map.files.insert(
+                path.to_string(),
+                FileSummary {
+                    path: path.to_string(),
+                    language: "rust".to_string(),
+                    symbols: map
+                        .symbols
+                        .values()
+                        .filter(|s| s.file == path)
+                        .map(|s| s.id.clone())
+                        .collect(),
+                    imports: imports.iter().map(|s| s.to_string()).collect(),
+                    exports: exports.iter().map(|s| s.to_string()).collect(),
+                    summary: String::new(),
+                    lines: 100,
+                },
+            );
+        }
+
+        let summary = map.module_summary("./src/core/", 10);
+        assert_eq!(summary.files, 2);
+        assert_eq!(summary.lines, 200);
+        assert_eq!(summary.kinds, vec![("fn".to_string(), 3)]);
+        assert_eq!(summary.languages, vec![("rust".to_string(), 2)]);
+        assert_eq!(summary.top_imports[0], ("serde".to_string(), 2));
+        // Exported symbols only, most depended-on first
+        let key: Vec<&str> = summary
+            .key_symbols
+            .iter()
+            .map(|s| s.signature.as_str())
+            .collect();
+        assert_eq!(key, vec!["fn load()", "fn save()"]);
+
+        assert!(summary.to_markdown().contains("## Module `src/core`"));
+        assert_eq!(map.module_summary("src/co", 10).files, 0);
+    }
+
+    #[test]
+    fn test_search_summaries() {
+        let mut map = CodeMap::new("/repo");
+        add_symbol(&mut map, "store.rs", "prune", &[]);
+        add_symbol(&mut map, "store.rs", "save", &[]);
+        add_symbol(&mut map, "search.rs", "rank", &[]);
+        assert!(!map.has_symbol_summaries());
+
+        for (id, summary) in [
+            (
+                "store.rs:prune",
+                "Remove indexed files that no longer exist on disk",
+            ),
+            ("store.rs:save", "Write the index to disk"),
+            ("search.rs:rank", "Order results by relevance"),
+        ] {
+            map.symbols.get_mut(id).unwrap().summary = summary.to_string();
+        }
+        assert!(map.has_symbol_summaries());
+
+        let ids = |query: &str| -> Vec<String> {
+            map.search_summaries(query)
+                .into_iter()
+                .map(|(s, _)| s.id.clone())
+                .collect()
+        };
+
+        // "removes" shares a stem with "remove"; save only half-matches "indexed"
+        assert_eq!(ids("removes indexed files"), vec!["store.rs:prune"]);
+        // Verbatim phrase outranks a partial word match
+        assert_eq!(
+            ids("index to disk"),
+            vec!["store.rs:save", "store.rs:prune"]
+        );
+        assert!(ids("tokenize").is_empty());
+    }
+
+    #[test]
+    fn test_build_edges() {
+        let mut map = CodeMap::new("/repo");
+        map.add_file(FileSummary {
+            path: "lib.rs".to_string(),
+            language: "rust".to_string(),
+            symbols: vec![],
+            imports: vec![],
+            exports: vec![],
+            summary: String::new(),
+            lines: 5,
+        });
+        for (name, line) in [("a", 1), ("b", 2), ("c", 3), ("helper", 4)] {
+            add_symbol(&mut map, "lib.rs", name, &[]);
+            let sym = map.symbols.get_mut(&format!("lib.rs:{}", name)).unwrap();
+            sym.line = line;
+            sym.end_line = line;
+        }
+        // `helper` exists twice, so a call to it can't be resolved
+        add_symbol(&mut map, "util.rs", "helper", &[]);
+
+        let source = "fn a() { b(); helper(); }\nfn b() {}\nfn c() { /* a() */ }\nfn helper() {}\n";
+        let sources = HashMap::from([("lib.rs".to_string(), source.to_string())]);
+        map.build_edges(&sources);
+
+        assert_eq!(map.symbols["lib.rs:a"].depends_on, vec!["lib.rs:b"]);
+        assert_eq!(map.symbols["lib.rs:b"].depended_by, vec!["lib.rs:a"]);
+        // Mentions inside comments don't count
+        assert!(map.symbols["lib.rs:c"].depends_on.is_empty());
+        assert!(map.symbols["lib.rs:a"].depended_by.is_empty());
+
+        let expanded = map.expand("lib.rs:a").unwrap();
+        assert_eq!(expanded.dependencies[0].name, "b");
+    }
+
+    #[test]
+    fn test_duplicate_names_get_line_ids() {
+        let symbol = |name: &str, line: usize| Symbol {
+            id: name.to_string(),
+            name: name.to_string(),
+            container: None,
+            visibility: Visibility::Unknown,
+            file: "/abs/handlers.ts".to_string(),
+            line,
+            end_line: line,
+            kind: SymbolKind::Function,
+            signature: format!("{}(): void", name),
+            summary: String::new(),
+            depends_on: vec![],
+            depended_by: vec![],
+            embedding: vec![],
+        };
+        let mut symbols = vec![symbol("foo", 3), symbol("foo", 9), symbol("bar", 12)];
+        assign_symbol_ids("handlers.ts", &mut symbols);
+
+        let ids: Vec<&str> = symbols.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec!["handlers.ts:foo@3", "handlers.ts:foo@9", "handlers.ts:bar"]
+        );
+        assert!(symbols.iter().all(|s| s.file == "handlers.ts"));
+
+        let mut map = CodeMap::new("/repo");
+        for symbol in symbols {
+            map.add_symbol(symbol);
+        }
+        assert_eq!(map.expand("handlers.ts:foo@3").unwrap().symbol.line, 3);
+        assert_eq!(map.expand("handlers.ts:foo@9").unwrap().symbol.line, 9);
+        assert!(map.expand("handlers.ts:foo").is_none());
+
+        let overloads: Vec<usize> = map
+            .overloads("handlers.ts:foo")
+            .iter()
+            .map(|s| s.line)
+            .collect();
+        assert_eq!(overloads, vec![3, 9]);
+        assert!(map.overloads("handlers.ts:bar").is_empty());
+    }
+
+    #[test]
+    fn test_orphans_report_only_uncalled_functions() {
+        let mut map = CodeMap::new("/repo");
+        add_symbol(&mut map, "src/main.rs", "main", &["src/lib.rs:load"]);
+        add_symbol(&mut map, "src/lib.rs", "load", &[]);
+        add_symbol(&mut map, "src/lib.rs", "legacy_load", &[]);
+        add_symbol(&mut map, "src/lib.rs", "open", &[]);
+        add_symbol(&mut map, "src/lib.rs", "test_load", &[]);
+        add_symbol(&mut map, "tests/helpers.rs", "fixture", &[]);
+        add_symbol(&mut map, "src/fmt.rs", "fmt", &[]);
+        map.symbols.get_mut("src/lib.rs:load").unwrap().depended_by =
+            vec!["src/main.rs:main".to_string()];
+        map.symbols.get_mut("src/lib.rs:open").unwrap().visibility = Visibility::Public;
+        let fmt = map.symbols.get_mut("src/fmt.rs:fmt").unwrap();
+        fmt.kind = SymbolKind::Method;
+        fmt.container = Some("Config".to_string());
+        map.add_trait_impl(TraitImpl {
+            type_name: "Config".to_string(),
+            trait_name: "Display".to_string(),
+            file: "src/fmt.rs".to_string(),
+            line: 1,
+        });
+
+        let ids: Vec<&str> = map.orphans().iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, ["src/lib.rs:legacy_load"]);
+    }
+}
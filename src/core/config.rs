@@ -1,8 +1,11 @@
-use anyhow::{Context, Result};
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 
+use crate::core::scanner::{DEFAULT_MAX_FILE_SIZE, DEFAULT_MAX_LINE_LENGTH};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     #[serde(default)]
@@ -15,6 +18,25 @@ pub struct Config {
     pub provider: String,
     #[serde(default)]
     pub local_url: Option<String>,
+    /// Display labels for symbol kinds per language, e.g. {"go": {"struct": "type"}}
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub kind_labels: HashMap<String, HashMap<String, String>>,
+    /// Seconds a writer waits for another to release the index lock (0 = fail fast)
+    #[serde(default)]
+    pub lock_wait_secs: u64,
+    /// Chunks embedded per forward pass while indexing
+    #[serde(default = "default_embed_batch_size")]
+    pub embed_batch_size: usize,
+    /// zstd level for saved indexes (0 = store uncompressed)
+    #[serde(default = "default_store_compression_level")]
+    pub store_compression_level: i32,
+    /// Files larger than this many bytes are not indexed
+    #[serde(default = "default_max_file_size")]
+    pub max_file_size: u64,
+    /// Files with a longer line are treated as minified/generated and not
+    /// indexed (0 = no limit)
+    #[serde(default = "default_max_line_length")]
+    pub max_line_length: usize,
 }
 
 fn default_model() -> String {
@@ -25,6 +47,22 @@ fn default_provider() -> String {
     "openai".to_string()
 }
 
+fn default_embed_batch_size() -> usize {
+    32
+}
+
+pub fn default_store_compression_level() -> i32 {
+    3
+}
+
+fn default_max_file_size() -> u64 {
+    DEFAULT_MAX_FILE_SIZE
+}
+
+fn default_max_line_length() -> usize {
+    DEFAULT_MAX_LINE_LENGTH
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -33,14 +71,32 @@ impl Default for Config {
             base_url: None,
             provider: default_provider(),
             local_url: None,
+            kind_labels: HashMap::new(),
+            lock_wait_secs: 0,
+            embed_batch_size: default_embed_batch_size(),
+            store_compression_level: default_store_compression_level(),
+            max_file_size: default_max_file_size(),
+            max_line_length: default_max_line_length(),
         }
     }
 }
 
+#[cfg(not(test))]
+fn sgrep_home() -> Result<PathBuf> {
+    let home = dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+    Ok(home.join(".sgrep"))
+}
+
+/// Unit tests get a per-process temp directory instead of `~/.sgrep`, so
+/// they never touch a developer's real indexes or need a home directory
+#[cfg(test)]
+fn sgrep_home() -> Result<PathBuf> {
+    Ok(std::env::temp_dir().join(format!("sgrep-test-home-{}", std::process::id())))
+}
+
 impl Config {
     pub fn config_dir() -> Result<PathBuf> {
-        let home = dirs::home_dir().context("Could not find home directory")?;
-        let config_dir = home.join(".sgrep");
+        let config_dir = sgrep_home()?;
         if !config_dir.exists() {
             fs::create_dir_all(&config_dir)?;
         }
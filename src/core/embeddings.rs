@@ -4,6 +4,7 @@ use std::sync::Mutex;
 
 use crate::core::config::Config;
 use crate::core::local_embeddings::{LocalEmbedder, SpeedMode};
+use crate::core::log;
 
 #[derive(Debug, Serialize)]
 struct OpenAIEmbeddingRequest {
@@ -44,12 +45,21 @@ impl EmbeddingProvider {
     }
 
     pub fn with_speed_mode(config: Config, speed_mode: SpeedMode) -> Self {
+        Self::load(config, || LocalEmbedder::with_speed_mode(speed_mode))
+    }
+
+    /// Use a specific local model instead of a SpeedMode preset
+    pub fn with_model(config: Config, model_id: &str) -> Self {
+        Self::load(config, || LocalEmbedder::with_model(model_id))
+    }
+
+    fn load(config: Config, load_local: impl FnOnce() -> Result<LocalEmbedder>) -> Self {
         // Try to load dual local embedder (CodeRankEmbed) if provider is "local"
         let local_embedder = if config.provider == "local" {
-            match LocalEmbedder::with_speed_mode(speed_mode) {
+            match load_local() {
                 Ok(embedder) => Some(Mutex::new(embedder)),
                 Err(e) => {
-                    eprintln!("Failed to load local embedder: {}", e);
+                    log::error(format_args!("Failed to load local embedder: {}", e));
                     None
                 }
             }
@@ -64,6 +74,16 @@ impl EmbeddingProvider {
         }
     }
 
+    /// Identity of the embedding model, recorded in index metadata
+    pub fn model_id(&self) -> Option<String> {
+        if self.config.provider == "local" {
+            let embedder = self.local_embedder.as_ref()?.lock().ok()?;
+            Some(embedder.model_id().to_string())
+        } else {
+            Some(self.config.model.clone())
+        }
+    }
+
     pub async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
         if self.config.provider == "local" {
             self.embed_local_model(texts)
@@ -81,7 +101,7 @@ impl EmbeddingProvider {
             })?
             .lock()
             .map_err(|e| anyhow!("Failed to lock embedder: {}", e))?;
-        embedder.embed(texts)
+        embedder.embed_batch(texts)
     }
 
     pub async fn embed_single(&self, text: &str) -> Result<Vec<f32>> {
@@ -160,6 +160,7 @@ impl FastIndexer {
         let abs_path = std::fs::canonicalize(path)?;
         let path_str = abs_path.to_string_lossy().to_string();
 
+        let _lock = VectorStore::lock(store_name)?;
         // Load existing store for incremental indexing
         let store = Arc::new(Mutex::new(VectorStore::load(store_name)?));
 
@@ -0,0 +1,170 @@
+//! Directory tree built from indexed file paths
+//!
+//! Reflects what is searchable (the index), not the raw filesystem.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// A directory or file in the tree
+#[derive(Debug, Clone, Serialize)]
+pub struct TreeNode {
+    pub name: String,
+    pub is_dir: bool,
+    /// Files under this node (recursive); 1 for a file
+    pub file_count: usize,
+    /// Directories first, then files, each sorted by name
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<TreeNode>,
+}
+
+#[derive(Default)]
+struct DirBuilder {
+    dirs: BTreeMap<String, DirBuilder>,
+    files: Vec<String>,
+}
+
+impl DirBuilder {
+    fn insert(&mut self, components: &[String]) {
+        match components {
+            [] => {}
+            [file] => self.files.push(file.clone()),
+            [dir, rest @ ..] => self.dirs.entry(dir.clone()).or_default().insert(rest),
+        }
+    }
+
+    fn build(self, name: String) -> TreeNode {
+        let mut children: Vec<TreeNode> = self
+            .dirs
+            .into_iter()
+            .map(|(name, dir)| dir.build(name))
+            .collect();
+
+        let mut files = self.files;
+        files.sort();
+        children.extend(files.into_iter().map(|name| TreeNode {
+            name,
+            is_dir: false,
+            file_count: 1,
+            children: vec![],
+        }));
+
+        TreeNode {
+            name,
+            is_dir: true,
+            file_count: children.iter().map(|c| c.file_count).sum(),
+            children,
+        }
+    }
+}
+
+/// Build a tree of `paths` relative to `root`; paths outside `root` are skipped
+pub fn build_file_tree(paths: &[String], root: &Path) -> TreeNode {
+    let mut builder = DirBuilder::default();
+
+    for path in paths {
+        if let Ok(relative) = Path::new(path).strip_prefix(root) {
+            let components: Vec<String> = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .collect();
+            builder.insert(&components);
+        }
+    }
+
+    let name = root
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| root.to_string_lossy().to_string());
+
+    builder.build(name)
+}
+
+/// Deepest directory containing every path
+pub fn common_root(paths: &[String]) -> Option<PathBuf> {
+    let mut parents = paths
+        .iter()
+        .map(|p| Path::new(p).parent().unwrap_or_else(|| Path::new("")));
+
+    let mut root = parents.next()?.to_path_buf();
+    for parent in parents {
+        while !parent.starts_with(&root) {
+            if !root.pop() {
+                break;
+            }
+        }
+    }
+
+    Some(root)
+}
+
+impl TreeNode {
+    /// Drop nodes more than `max_depth` levels below this one
+    ///
+    /// Collapsed directories keep their file counts.
+    pub fn limit_depth(&mut self, max_depth: usize) {
+        if max_depth == 0 {
+            self.children.clear();
+            return;
+        }
+        for child in &mut self.children {
+            child.limit_depth(max_depth - 1);
+        }
+    }
+
+    /// Compact indented rendering; directories show their file counts
+    pub fn render(&self) -> String {
+        let mut out = format!("{}/ ({} files)\n", self.name, self.file_count);
+        self.render_children(1, &mut out);
+        out
+    }
+
+    fn render_children(&self, depth: usize, out: &mut String) {
+        let indent = "  ".repeat(depth);
+        for child in &self.children {
+            if child.is_dir {
+                out.push_str(&format!(
+                    "{}{}/ ({} files)\n",
+                    indent, child.name, child.file_count
+                ));
+                child.render_children(depth + 1, out);
+            } else {
+                out.push_str(&format!("{}{}\n", indent, child.name));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_and_render_tree() {
+        let paths: Vec<String> = [
+            "/repo/src/main.rs",
+            "/repo/src/core/store.rs",
+            "/repo/src/core/search.rs",
+            "/repo/README.md",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+        let root = common_root(&paths).unwrap();
+        assert_eq!(root, PathBuf::from("/repo"));
+
+        let mut tree = build_file_tree(&paths, &root);
+        assert_eq!(tree.file_count, 4);
+        assert_eq!(
+            tree.render(),
+            "repo/ (4 files)\n  src/ (3 files)\n    core/ (2 files)\n      search.rs\n      store.rs\n    main.rs\n  README.md\n"
+        );
+
+        tree.limit_depth(1);
+        assert_eq!(
+            tree.render(),
+            "repo/ (4 files)\n  src/ (3 files)\n  README.md\n"
+        );
+    }
+}
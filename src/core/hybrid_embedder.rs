@@ -22,7 +22,7 @@
 
 use anyhow::{anyhow, Result};
 use candle_core::{DType, Device, Tensor};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 use std::time::Instant;
 
 use super::local_embeddings::{LocalEmbedder, SpeedMode};
@@ -51,11 +51,14 @@ impl Default for FusionStrategy {
 ///
 /// This model runs both embedding models in parallel and fuses their outputs
 /// using a lightweight learned fusion layer.
+///
+/// The models are shared handles, so a server can use the same loaded BGE and
+/// CodeRankEmbed on their own and in the hybrid.
 pub struct HybridEmbedder {
     /// BGE model for general text understanding
-    bge_embedder: LocalEmbedder,
+    bge_embedder: Arc<Mutex<LocalEmbedder>>,
     /// CodeRankEmbed for code-specific understanding
-    code_embedder: LocalEmbedder,
+    code_embedder: Arc<Mutex<LocalEmbedder>>,
     /// Fusion strategy
     strategy: FusionStrategy,
     /// Embedding dimension (same as input models)
@@ -90,13 +93,28 @@ impl HybridEmbedder {
 
         Ok(Self {
             embedding_dim: bge_embedder.embedding_dim(), // Both are 768-dim
-            bge_embedder,
-            code_embedder,
+            bge_embedder: Arc::new(Mutex::new(bge_embedder)),
+            code_embedder: Arc::new(Mutex::new(code_embedder)),
             strategy,
             device: Device::Cpu,
         })
     }
 
+    /// Share already-loaded BGE and CodeRankEmbed models (default fusion, no loading banner)
+    pub fn from_embedders(
+        bge_embedder: Arc<Mutex<LocalEmbedder>>,
+        code_embedder: Arc<Mutex<LocalEmbedder>>,
+    ) -> Self {
+        let embedding_dim = lock(&bge_embedder).embedding_dim();
+        Self {
+            embedding_dim,
+            bge_embedder,
+            code_embedder,
+            strategy: FusionStrategy::default(),
+            device: Device::Cpu,
+        }
+    }
+
     /// Create a fast hybrid embedder (uses cached models if available)
     pub fn fast() -> Result<Self> {
         // More weight to CodeRankEmbed for code-heavy searches
@@ -118,20 +136,42 @@ impl HybridEmbedder {
         Ok(results)
     }
 
+    /// Embed texts with each model in one batch, then fuse pairwise
+    pub fn embed_batch(&mut self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let bge_embs = lock(&self.bge_embedder).embed_batch(texts)?;
+        let code_embs = lock(&self.code_embedder).embed_batch(texts)?;
+
+        bge_embs
+            .iter()
+            .zip(&code_embs)
+            .map(|(bge, code)| self.fuse(bge, code))
+            .collect()
+    }
+
     /// Embed a search query with fusion
     pub fn embed_query(&mut self, query: &str) -> Result<Vec<f32>> {
         // For queries, we might want slightly different weighting
         // Giving more weight to semantic understanding
-        let bge_emb = self.bge_embedder.embed_query(query)?;
-        let code_emb = self.code_embedder.embed_query(query)?;
+        let bge_emb = lock(&self.bge_embedder).embed_query(query)?;
+        let code_emb = lock(&self.code_embedder).embed_query(query)?;
         self.fuse(&bge_emb, &code_emb)
     }
 
+    /// Embed a query with each model and fuse, returning (bge, code, fused)
+    ///
+    /// Lets callers compare balanced, code and hybrid results from one pass.
+    pub fn embed_query_parts(&mut self, query: &str) -> Result<(Vec<f32>, Vec<f32>, Vec<f32>)> {
+        let bge_emb = lock(&self.bge_embedder).embed_query(query)?;
+        let code_emb = lock(&self.code_embedder).embed_query(query)?;
+        let fused = self.fuse(&bge_emb, &code_emb)?;
+        Ok((bge_emb, code_emb, fused))
+    }
+
     /// Embed a single text with fusion
     fn embed_single(&mut self, text: &str) -> Result<Vec<f32>> {
         // Get embeddings from both models
-        let bge_emb = self.bge_embedder.embed(&[text.to_string()])?;
-        let code_emb = self.code_embedder.embed(&[text.to_string()])?;
+        let bge_emb = lock(&self.bge_embedder).embed(&[text.to_string()])?;
+        let code_emb = lock(&self.code_embedder).embed(&[text.to_string()])?;
 
         let bge = &bge_emb[0];
         let code = &code_emb[0];
@@ -262,6 +302,11 @@ impl HybridEmbedder {
     }
 }
 
+/// A model stays usable after a panic elsewhere; its weights are never mid-update
+fn lock(embedder: &Mutex<LocalEmbedder>) -> MutexGuard<'_, LocalEmbedder> {
+    embedder.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
 /// Fast hybrid search that caches the embedder
 pub struct CachedHybridEmbedder {
     embedder: Option<HybridEmbedder>,
@@ -287,8 +332,12 @@ mod tests {
     #[test]
     fn test_l2_normalize() {
         let embedder = HybridEmbedder {
-            bge_embedder: LocalEmbedder::with_speed_mode(SpeedMode::Balanced).unwrap(),
-            code_embedder: LocalEmbedder::with_speed_mode(SpeedMode::Code).unwrap(),
+            bge_embedder: Arc::new(Mutex::new(
+                LocalEmbedder::with_speed_mode(SpeedMode::Balanced).unwrap(),
+            )),
+            code_embedder: Arc::new(Mutex::new(
+                LocalEmbedder::with_speed_mode(SpeedMode::Code).unwrap(),
+            )),
             strategy: FusionStrategy::default(),
             embedding_dim: 768,
             device: Device::Cpu,
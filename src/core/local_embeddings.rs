@@ -3,9 +3,10 @@ use candle_core::{DType, Device, Tensor};
 use candle_nn::VarBuilder;
 use candle_transformers::models::bert::{BertModel, Config as BertConfig};
 use hf_hub::{api::sync::Api, Repo, RepoType};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tokenizers::Tokenizer;
 
+use super::log;
 use super::nomic_bert::{NomicBertConfig, NomicBertModel};
 
 /// Speed mode for embeddings - trades accuracy for speed
@@ -22,6 +23,80 @@ pub enum SpeedMode {
     Code,
 }
 
+impl SpeedMode {
+    /// Hugging Face model id loaded for this mode
+    pub fn model_id(&self) -> &'static str {
+        match self {
+            SpeedMode::Fast => "sentence-transformers/all-MiniLM-L6-v2",
+            SpeedMode::Balanced | SpeedMode::Quality => "BAAI/bge-base-en-v1.5",
+            SpeedMode::Code => "nomic-ai/CodeRankEmbed",
+        }
+    }
+}
+
+/// Environment variable naming the embedding model when no `model` is passed
+pub const MODEL_ENV: &str = "SEARCHGREP_MODEL";
+
+/// The explicitly requested model, else `SEARCHGREP_MODEL` if set
+pub fn model_override(explicit: Option<&str>) -> Option<String> {
+    explicit
+        .map(|m| m.to_string())
+        .or_else(|| std::env::var(MODEL_ENV).ok())
+        .filter(|m| !m.trim().is_empty())
+}
+
+/// Files a model is loaded from
+pub struct ModelFiles {
+    pub config: PathBuf,
+    pub tokenizer: PathBuf,
+    pub weights: PathBuf,
+}
+
+/// Find a model's files without loading it
+///
+/// A local directory must hold config.json, tokenizer.json and
+/// model.safetensors; anything else is a Hugging Face id, fetched into the
+/// hub cache on first use.
+pub fn locate_model(model_id: &str) -> Result<ModelFiles> {
+    let dir = Path::new(model_id);
+    if dir.is_dir() {
+        let file = |name: &str| {
+            let path = dir.join(name);
+            if path.is_file() {
+                Ok(path)
+            } else {
+                Err(anyhow!("Model directory {} has no {}", dir.display(), name))
+            }
+        };
+        return Ok(ModelFiles {
+            config: file("config.json")?,
+            tokenizer: file("tokenizer.json")?,
+            weights: file("model.safetensors")?,
+        });
+    }
+
+    let repo = Api::new()?.repo(Repo::with_revision(
+        model_id.to_string(),
+        RepoType::Model,
+        "main".to_string(),
+    ));
+    let fetch = |name: &str| {
+        repo.get(name).map_err(|e| {
+            anyhow!(
+                "Model '{}' is neither a local directory nor a Hugging Face model with {}: {}",
+                model_id,
+                name,
+                e
+            )
+        })
+    };
+    Ok(ModelFiles {
+        config: fetch("config.json")?,
+        tokenizer: fetch("tokenizer.json")?,
+        weights: fetch("model.safetensors")?,
+    })
+}
+
 /// Model type enum to support different architectures
 enum ModelType {
     Bert(BertModel),
@@ -38,6 +113,7 @@ pub struct LocalEmbedder {
     device: Device,
     speed_mode: SpeedMode,
     embedding_dim: usize,
+    model_id: String,
 }
 
 impl LocalEmbedder {
@@ -55,33 +131,73 @@ impl LocalEmbedder {
             return Self::load_coderankembed(&device);
         }
 
-        let (model_id, embedding_dim, dtype) = match speed_mode {
-            SpeedMode::Fast => {
-                println!("Loading MiniLM (fast mode) on CPU (Accelerate)...");
-                ("sentence-transformers/all-MiniLM-L6-v2", 384, DType::F32)
-            }
-            SpeedMode::Balanced => {
-                println!("Loading BGE-base (balanced mode) on CPU (Accelerate)...");
-                ("BAAI/bge-base-en-v1.5", 768, DType::F32) // F16 not supported by Accelerate matmul
-            }
-            SpeedMode::Quality => {
-                println!("Loading BGE-base (quality mode) on CPU (Accelerate)...");
-                ("BAAI/bge-base-en-v1.5", 768, DType::F32)
-            }
+        let (embedding_dim, dtype) = match speed_mode {
+            SpeedMode::Fast => (384, DType::F32),
+            SpeedMode::Balanced => (768, DType::F32), // F16 not supported by Accelerate matmul
+            SpeedMode::Quality => (768, DType::F32),
             SpeedMode::Code => unreachable!(), // Handled above
         };
 
-        let api = Api::new()?;
-        let repo = api.repo(Repo::with_revision(
-            model_id.to_string(),
-            RepoType::Model,
-            "main".to_string(),
-        ));
+        Self::load_bert(
+            speed_mode.model_id(),
+            embedding_dim,
+            dtype,
+            device,
+            speed_mode,
+        )
+    }
 
-        // Download model files
-        let config_path = repo.get("config.json")?;
-        let tokenizer_path = repo.get("tokenizer.json")?;
-        let weights_path = repo.get("model.safetensors")?;
+    /// Load a specific model, bypassing the SpeedMode presets
+    ///
+    /// `model_id` is a Hugging Face id or a local directory (see `locate_model`).
+    /// The architecture is picked from its config.json: NomicBert configs
+    /// (with `n_embd`) load as NomicBert, everything else as BERT.
+    pub fn with_model(model_id: &str) -> Result<Self> {
+        let device = Device::Cpu;
+
+        if model_id == SpeedMode::Code.model_id() {
+            return Self::load_coderankembed(&device);
+        }
+
+        let config_path = locate_model(model_id)?.config;
+        let config: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&config_path)?)?;
+
+        if config.get("n_embd").is_some() {
+            return Self::load_nomic_bert(model_id, &device, SpeedMode::default());
+        }
+
+        let embedding_dim = config
+            .get("hidden_size")
+            .and_then(|v| v.as_u64())
+            .ok_or_else(|| anyhow!("{} config.json has no hidden_size", model_id))?
+            as usize;
+
+        Self::load_bert(
+            model_id,
+            embedding_dim,
+            DType::F32,
+            device,
+            SpeedMode::default(),
+        )
+    }
+
+    /// Load a BERT-architecture model from the Hugging Face hub
+    fn load_bert(
+        model_id: &str,
+        embedding_dim: usize,
+        dtype: DType,
+        device: Device,
+        speed_mode: SpeedMode,
+    ) -> Result<Self> {
+        let ModelFiles {
+            config: config_path,
+            tokenizer: tokenizer_path,
+            weights: weights_path,
+        } = locate_model(model_id)?;
+
+        let start = std::time::Instant::now();
+        log::debug(format_args!("loading model={} arch=bert", model_id));
 
         // Load config
         let config: BertConfig = serde_json::from_str(&std::fs::read_to_string(&config_path)?)?;
@@ -95,7 +211,12 @@ impl LocalEmbedder {
         let vb = unsafe { VarBuilder::from_mmaped_safetensors(&[weights_path], dtype, &device)? };
         let model = BertModel::load(vb, &config)?;
 
-        println!("✓ Model loaded on {:?}", device);
+        log::info(format_args!(
+            "model loaded model={} dim={} elapsed_ms={}",
+            model_id,
+            embedding_dim,
+            start.elapsed().as_millis()
+        ));
 
         Ok(Self {
             model: ModelType::Bert(model),
@@ -103,28 +224,25 @@ impl LocalEmbedder {
             device,
             speed_mode,
             embedding_dim,
+            model_id: model_id.to_string(),
         })
     }
 
     /// Load CodeRankEmbed model (NomicBert architecture, optimized for code)
     fn load_coderankembed(device: &Device) -> Result<Self> {
-        println!("Loading CodeRankEmbed (code mode) on CPU (Accelerate)...");
-        println!("  137M params | 768-dim | Optimized for code search");
-
-        let model_id = "nomic-ai/CodeRankEmbed";
-        let embedding_dim = 768;
+        Self::load_nomic_bert(SpeedMode::Code.model_id(), device, SpeedMode::Code)
+    }
 
-        let api = Api::new()?;
-        let repo = api.repo(Repo::with_revision(
-            model_id.to_string(),
-            RepoType::Model,
-            "main".to_string(),
-        ));
+    /// Load a NomicBert-architecture model from the Hugging Face hub
+    fn load_nomic_bert(model_id: &str, device: &Device, speed_mode: SpeedMode) -> Result<Self> {
+        let ModelFiles {
+            config: config_path,
+            tokenizer: tokenizer_path,
+            weights: weights_path,
+        } = locate_model(model_id)?;
 
-        // Download model files
-        let config_path = repo.get("config.json")?;
-        let tokenizer_path = repo.get("tokenizer.json")?;
-        let weights_path = repo.get("model.safetensors")?;
+        let start = std::time::Instant::now();
+        log::debug(format_args!("loading model={} arch=nomic_bert", model_id));
 
         // Load NomicBert config
         let config: NomicBertConfig =
@@ -138,15 +256,20 @@ impl LocalEmbedder {
         let vb =
             unsafe { VarBuilder::from_mmaped_safetensors(&[weights_path], DType::F32, device)? };
         let model = NomicBertModel::load(vb, &config, device)?;
-
-        println!("✓ CodeRankEmbed loaded on {:?}", device);
+        log::info(format_args!(
+            "model loaded model={} dim={} elapsed_ms={}",
+            model_id,
+            config.n_embd,
+            start.elapsed().as_millis()
+        ));
 
         Ok(Self {
             model: ModelType::NomicBert(model),
             tokenizer,
             device: device.clone(),
-            speed_mode: SpeedMode::Code,
-            embedding_dim,
+            speed_mode,
+            embedding_dim: config.n_embd,
+            model_id: model_id.to_string(),
         })
     }
 
@@ -154,6 +277,11 @@ impl LocalEmbedder {
         self.speed_mode
     }
 
+    /// Hugging Face id of the loaded model
+    pub fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
     pub fn embedding_dim(&self) -> usize {
         self.embedding_dim
     }
@@ -186,6 +314,76 @@ impl LocalEmbedder {
         Ok(embeddings)
     }
 
+    /// Embed texts in a single forward pass, padded to the longest
+    ///
+    /// Padding is masked out of attention and pooling, so each vector matches
+    /// what `embed` returns for that text on its own. Callers choose the batch
+    /// size; memory grows with batch size times the longest text.
+    pub fn embed_batch(&mut self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let max_len = self.max_tokens();
+        let encodings = texts
+            .iter()
+            .map(|text| {
+                self.tokenizer
+                    .encode(text.as_str(), true)
+                    .map_err(|e| anyhow!("Tokenization failed: {}", e))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let seq_len = encodings
+            .iter()
+            .map(|e| e.get_ids().len().min(max_len))
+            .max()
+            .unwrap_or(0);
+
+        let mut input_ids = Vec::with_capacity(texts.len() * seq_len);
+        let mut attention_mask = Vec::with_capacity(texts.len() * seq_len);
+        let mut token_type_ids = Vec::with_capacity(texts.len() * seq_len);
+        for encoding in &encodings {
+            let len = encoding.get_ids().len().min(max_len);
+            let padding = std::iter::repeat_n(0, seq_len - len);
+            input_ids.extend(
+                encoding.get_ids()[..len]
+                    .iter()
+                    .copied()
+                    .chain(padding.clone()),
+            );
+            attention_mask.extend(
+                encoding.get_attention_mask()[..len]
+                    .iter()
+                    .copied()
+                    .chain(padding.clone()),
+            );
+            token_type_ids.extend(
+                encoding.get_type_ids()[..len]
+                    .iter()
+                    .copied()
+                    .chain(padding),
+            );
+        }
+
+        let shape = (texts.len(), seq_len);
+        let input_ids = Tensor::from_vec(input_ids, shape, &self.device)?;
+        let attention_mask = Tensor::from_vec(attention_mask, shape, &self.device)?;
+        let token_type_ids = Tensor::from_vec(token_type_ids, shape, &self.device)?;
+
+        let embeddings = self.forward(&input_ids, &token_type_ids, &attention_mask)?;
+
+        // Mean pooling over real (unpadded) tokens
+        let mask = attention_mask.to_dtype(DType::F32)?.unsqueeze(2)?;
+        let sum = embeddings.broadcast_mul(&mask)?.sum(1)?;
+        let mean = sum.broadcast_div(&mask.sum(1)?)?;
+
+        // L2 normalize
+        let norm = mean.sqr()?.sum_keepdim(1)?.sqrt()?;
+        let normalized = mean.broadcast_div(&norm)?;
+
+        Ok(normalized.to_vec2()?)
+    }
+
     /// Embed a search query (applies "search_query: " prefix for CodeRankEmbed)
     pub fn embed_query(&mut self, query: &str) -> Result<Vec<f32>> {
         // CodeRankEmbed uses prefixes for asymmetric retrieval
@@ -200,11 +398,7 @@ impl LocalEmbedder {
             .encode(text, true)
             .map_err(|e| anyhow!("Tokenization failed: {}", e))?;
 
-        // Max tokens depends on model (512 for BERT, 8192 for NomicBert)
-        let max_len = match &self.model {
-            ModelType::Bert(_) => 512,
-            ModelType::NomicBert(_) => 8192,
-        };
+        let max_len = self.max_tokens();
 
         let input_ids: Vec<u32> = encoding.get_ids().iter().take(max_len).copied().collect();
         let attention_mask: Vec<u32> = encoding
@@ -226,15 +420,7 @@ impl LocalEmbedder {
         let attention_mask = Tensor::new(attention_mask.as_slice(), &self.device)?.unsqueeze(0)?;
         let token_type_ids = Tensor::new(token_type_ids.as_slice(), &self.device)?.unsqueeze(0)?;
 
-        // Run model based on type
-        let embeddings = match &self.model {
-            ModelType::Bert(model) => {
-                model.forward(&input_ids, &token_type_ids, Some(&attention_mask))?
-            }
-            ModelType::NomicBert(model) => {
-                model.forward(&input_ids, Some(&token_type_ids), Some(&attention_mask))?
-            }
-        };
+        let embeddings = self.forward(&input_ids, &token_type_ids, &attention_mask)?;
 
         // Mean pooling over sequence dimension
         let sum = embeddings.sum(1)?;
@@ -250,6 +436,31 @@ impl LocalEmbedder {
         Ok(result)
     }
 
+    /// Max tokens depends on model (512 for BERT, 8192 for NomicBert)
+    fn max_tokens(&self) -> usize {
+        match &self.model {
+            ModelType::Bert(_) => 512,
+            ModelType::NomicBert(_) => 8192,
+        }
+    }
+
+    /// Run model based on type; returns `[batch, seq_len, dim]` hidden states
+    fn forward(
+        &self,
+        input_ids: &Tensor,
+        token_type_ids: &Tensor,
+        attention_mask: &Tensor,
+    ) -> Result<Tensor> {
+        Ok(match &self.model {
+            ModelType::Bert(model) => {
+                model.forward(input_ids, token_type_ids, Some(attention_mask))?
+            }
+            ModelType::NomicBert(model) => {
+                model.forward(input_ids, Some(token_type_ids), Some(attention_mask))?
+            }
+        })
+    }
+
     /// Get token-level embeddings
     pub fn embed_with_tokens(&mut self, text: &str) -> Result<(Vec<f32>, Vec<Vec<f32>>)> {
         let pooled = self.embed_single(text)?;
@@ -286,3 +497,63 @@ pub async fn download_model() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locate_local_model_dir() {
+        let dir = std::env::temp_dir().join(format!("sgrep-model-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["config.json", "tokenizer.json"] {
+            std::fs::write(dir.join(name), "{}").unwrap();
+        }
+
+        let model_id = dir.to_string_lossy().to_string();
+        let err = locate_model(&model_id).err().unwrap();
+        assert!(err.to_string().contains("has no model.safetensors"));
+
+        std::fs::write(dir.join("model.safetensors"), "").unwrap();
+        let files = locate_model(&model_id).unwrap();
+        assert_eq!(files.weights, dir.join("model.safetensors"));
+
+        assert_eq!(model_override(Some("  ")), None);
+        assert_eq!(
+            model_override(Some("BAAI/bge-small-en-v1.5")).as_deref(),
+            Some("BAAI/bge-small-en-v1.5")
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    #[ignore = "downloads the MiniLM model"]
+    fn test_embed_batch_matches_single() {
+        let mut embedder = LocalEmbedder::with_speed_mode(SpeedMode::Fast).unwrap();
+        let queries = [
+            "parse config file",
+            "fn main() {}",
+            "retry with exponential backoff",
+        ];
+
+        // Mixed lengths, so shorter texts are padded within the batch
+        let texts: Vec<String> = queries
+            .iter()
+            .map(|q| format!("search_query: {}", q))
+            .collect();
+        let batched = embedder.embed_batch(&texts).unwrap();
+        assert_eq!(batched.len(), queries.len());
+
+        for (query, batch_emb) in queries.iter().zip(&batched) {
+            let single = embedder.embed_query(query).unwrap();
+            assert_eq!(single.len(), batch_emb.len());
+            let max_diff = single
+                .iter()
+                .zip(batch_emb)
+                .map(|(a, b)| (a - b).abs())
+                .fold(0.0f32, f32::max);
+            assert!(max_diff < 1e-4, "{}: {}", query, max_diff);
+        }
+        assert!(embedder.embed_batch(&[]).unwrap().is_empty());
+    }
+}
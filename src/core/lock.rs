@@ -0,0 +1,179 @@
+//! Single-writer locking for on-disk indexes
+//!
+//! Writers hold an `IndexLock` (an OS advisory lock on a `.searchgrep.lock`
+//! file: `flock` on Unix, `LockFileEx` on Windows) for the whole
+//! load-modify-save cycle. The OS releases it when the lock is dropped or the
+//! process exits, crashes included, so a dead writer never leaves the index
+//! locked. Readers never take the lock; saves go through `write_atomic`, so a
+//! reader always sees the last complete write.
+
+use anyhow::{Context, Result};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How often a waiting writer re-checks the lock
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Exclusive writer lock, released on drop
+#[derive(Debug)]
+pub struct IndexLock {
+    /// Closing the file releases the OS lock
+    _file: File,
+}
+
+impl IndexLock {
+    /// Take the lock at `path`, waiting up to `wait` for another writer to finish
+    ///
+    /// A zero `wait` fails fast. The lock file itself is left in place: removing
+    /// it would let a later writer lock a fresh file while another still holds
+    /// the old one.
+    pub fn acquire(path: &Path, wait: Duration) -> Result<Self> {
+        let deadline = Instant::now() + wait;
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .with_context(|| format!("Failed to open lock {}", path.display()))?;
+
+        loop {
+            match file.try_lock() {
+                Ok(()) => {
+                    // Only names the holder in other writers' errors, so best-effort
+                    let _ = file
+                        .set_len(0)
+                        .and_then(|_| writeln!(file, "{}", std::process::id()));
+                    return Ok(Self { _file: file });
+                }
+                Err(TryLockError::WouldBlock) => {
+                    if Instant::now() >= deadline {
+                        let holder = read_holder(path)
+                            .map(|pid| format!("pid {}", pid))
+                            .unwrap_or_else(|| "unknown process".to_string());
+                        anyhow::bail!(
+                            "Index locked: another sgrep process ({}) is writing to it. \
+                             Retry when it finishes, or raise lock_wait_secs in the config",
+                            holder
+                        );
+                    }
+                    std::thread::sleep(POLL_INTERVAL);
+                }
+                Err(TryLockError::Error(e)) => {
+                    return Err(e).with_context(|| format!("Failed to lock {}", path.display()))
+                }
+            }
+        }
+    }
+}
+
+fn read_holder(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Write via a temp file and rename, so readers never see a partial file
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    fs::write(&tmp, contents)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Barrier};
+
+    fn lock_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "sgrep-lock-test-{}-{}.searchgrep.lock",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_second_writer_rejected() {
+        let path = lock_path("reject");
+        let held = Arc::new(Barrier::new(2));
+        let tried = Arc::new(Barrier::new(2));
+
+        let first = {
+            let (path, held, tried) = (path.clone(), held.clone(), tried.clone());
+            std::thread::spawn(move || {
+                let _lock = IndexLock::acquire(&path, Duration::ZERO).unwrap();
+                held.wait();
+                tried.wait();
+            })
+        };
+        let second = {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                held.wait();
+                let result = IndexLock::acquire(&path, Duration::ZERO);
+                tried.wait();
+                result
+            })
+        };
+
+        first.join().unwrap();
+        let err = second.join().unwrap().unwrap_err();
+        assert!(err.to_string().contains("Index locked"));
+
+        // Released on drop
+        assert!(IndexLock::acquire(&path, Duration::ZERO).is_ok());
+    }
+
+    #[test]
+    fn test_leftover_lock_file_is_not_held() {
+        // A writer that crashed leaves its lock file (and pid) behind, but the
+        // OS lock died with it
+        let path = lock_path("leftover");
+        fs::write(&path, "4294967295\n").unwrap();
+
+        let lock = IndexLock::acquire(&path, Duration::ZERO).unwrap();
+        assert_eq!(read_holder(&path), Some(std::process::id()));
+        let err = IndexLock::acquire(&path, Duration::ZERO).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains(&format!("pid {}", std::process::id())));
+
+        drop(lock);
+        assert!(IndexLock::acquire(&path, Duration::ZERO).is_ok());
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_waiting_writers_serialize() {
+        let path = lock_path("serialize");
+        let counter = path.with_extension("count");
+        fs::write(&counter, "0").unwrap();
+
+        let writers: Vec<_> = (0..2)
+            .map(|_| {
+                let (path, counter) = (path.clone(), counter.clone());
+                std::thread::spawn(move || {
+                    let _lock = IndexLock::acquire(&path, Duration::from_secs(10)).unwrap();
+                    let n: u32 = fs::read_to_string(&counter).unwrap().parse().unwrap();
+                    // Widen the race window; without the lock an update is lost
+                    std::thread::sleep(Duration::from_millis(200));
+                    write_atomic(&counter, (n + 1).to_string()).unwrap();
+                })
+            })
+            .collect();
+
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(&counter).unwrap(), "2");
+        let _ = fs::remove_file(&counter);
+    }
+}
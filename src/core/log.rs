@@ -0,0 +1,169 @@
+//! Diagnostic logging to stderr
+//!
+//! stdout carries the MCP protocol, so log lines only ever go to stderr. The
+//! level comes from `SEARCHGREP_LOG` (`error`, `info` or `debug`); without it
+//! only errors are written. Lines are `<timestamp> <LEVEL> <message>`, with
+//! messages written as `key=value` pairs where that helps grepping.
+
+use chrono::{SecondsFormat, Utc};
+use std::fmt::Display;
+use std::io::{self, Write};
+use std::sync::{Mutex, OnceLock};
+
+/// Environment variable selecting the log level
+pub const LOG_ENV: &str = "SEARCHGREP_LOG";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Error,
+    Info,
+    Debug,
+}
+
+impl LogLevel {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "error" => Some(Self::Error),
+            "info" => Some(Self::Info),
+            "debug" => Some(Self::Debug),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Error => "ERROR",
+            Self::Info => "INFO",
+            Self::Debug => "DEBUG",
+        }
+    }
+}
+
+pub struct Logger {
+    level: LogLevel,
+    sink: Mutex<Box<dyn Write + Send>>,
+}
+
+impl Logger {
+    pub fn new(level: LogLevel, sink: Box<dyn Write + Send>) -> Self {
+        Self {
+            level,
+            sink: Mutex::new(sink),
+        }
+    }
+
+    /// Logger writing to stderr at the level set in `SEARCHGREP_LOG`
+    pub fn from_env() -> Self {
+        let level = std::env::var(LOG_ENV)
+            .ok()
+            .and_then(|v| LogLevel::parse(&v))
+            .unwrap_or(LogLevel::Error);
+        Self::new(level, Box::new(io::stderr()))
+    }
+
+    pub fn enabled(&self, level: LogLevel) -> bool {
+        level <= self.level
+    }
+
+    pub fn log(&self, level: LogLevel, message: impl Display) {
+        if !self.enabled(level) {
+            return;
+        }
+
+        let timestamp = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+        if let Ok(mut sink) = self.sink.lock() {
+            // Logging must never take the server down
+            let _ = writeln!(sink, "{} {} {}", timestamp, level.as_str(), message);
+            let _ = sink.flush();
+        }
+    }
+
+    pub fn error(&self, message: impl Display) {
+        self.log(LogLevel::Error, message);
+    }
+
+    pub fn info(&self, message: impl Display) {
+        self.log(LogLevel::Info, message);
+    }
+
+    pub fn debug(&self, message: impl Display) {
+        self.log(LogLevel::Debug, message);
+    }
+}
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+/// Process-wide logger, configured from the environment on first use
+pub fn logger() -> &'static Logger {
+    LOGGER.get_or_init(Logger::from_env)
+}
+
+pub fn error(message: impl Display) {
+    logger().error(message);
+}
+
+pub fn info(message: impl Display) {
+    logger().info(message);
+}
+
+pub fn debug(message: impl Display) {
+    logger().debug(message);
+}
+
+/// In-memory sink standing in for stderr in tests
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub struct CapturedLog(std::sync::Arc<Mutex<Vec<u8>>>);
+
+#[cfg(test)]
+impl CapturedLog {
+    /// A leaked logger writing into this capture, for code that wants `&'static`
+    pub fn logger(&self, level: LogLevel) -> &'static Logger {
+        Box::leak(Box::new(Logger::new(level, Box::new(self.clone()))))
+    }
+
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.lock().unwrap()).to_string()
+    }
+}
+
+#[cfg(test)]
+impl Write for CapturedLog {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_gates_messages() {
+        assert_eq!(LogLevel::parse("DEBUG"), Some(LogLevel::Debug));
+        assert_eq!(LogLevel::parse("verbose"), None);
+
+        let captured = CapturedLog::default();
+        let logger = captured.logger(LogLevel::Info);
+        logger.error("store missing");
+        logger.info(format_args!("indexed files={}", 3));
+        logger.debug("request method=ping");
+
+        let lines: Vec<String> = captured.contents().lines().map(String::from).collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with(" ERROR store missing"));
+        assert!(lines[1].ends_with(" INFO indexed files=3"));
+
+        let captured = CapturedLog::default();
+        captured
+            .logger(LogLevel::Debug)
+            .debug("request method=ping");
+        assert!(captured
+            .contents()
+            .ends_with(" DEBUG request method=ping\n"));
+    }
+}
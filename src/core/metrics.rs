@@ -0,0 +1,175 @@
+//! Heuristic code metrics
+//!
+//! Approximates cyclomatic complexity by counting branch keywords and
+//! boolean operators within a symbol's body. Not a real control-flow
+//! analysis - good enough to rank refactoring candidates.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use super::codemap::{CodeMap, Symbol, SymbolKind};
+use super::parser::mask_source;
+
+/// Complexity estimate for a single symbol
+#[derive(Debug, Clone)]
+pub struct SymbolMetrics {
+    pub symbol_id: String,
+    pub name: String,
+    pub file: String,
+    pub kind: &'static str,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub lines: usize,
+    pub complexity: usize,
+}
+
+/// Compute metrics for every function/method in the map, most complex first
+///
+/// Source is read from disk relative to `root`; files that can't be read are skipped.
+/// Bodies span the parsed `line..=end_line`, with comments and strings masked.
+pub fn compute_symbol_metrics(map: &CodeMap, root: &Path) -> Vec<SymbolMetrics> {
+    let mut by_file: HashMap<&str, Vec<&Symbol>> = HashMap::new();
+    for sym in map.symbols.values() {
+        if matches!(sym.kind, SymbolKind::Function | SymbolKind::Method) {
+            by_file.entry(sym.file.as_str()).or_default().push(sym);
+        }
+    }
+
+    let mut metrics = Vec::new();
+
+    for (file, symbols) in by_file {
+        let content = match fs::read_to_string(root.join(file)) {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        let language = map
+            .files
+            .get(file)
+            .map(|f| f.language.as_str())
+            .unwrap_or("unknown");
+        let code = mask_source(&content, language);
+        let lines: Vec<&str> = code.lines().collect();
+
+        for sym in symbols {
+            let start = sym.line.saturating_sub(1);
+            if start >= lines.len() {
+                continue;
+            }
+            // Maps saved before end lines were tracked have `end_line` 0
+            let end = sym.end_line.max(sym.line).min(lines.len()) - 1;
+            let body = &lines[start..=end];
+
+            metrics.push(SymbolMetrics {
+                symbol_id: sym.id.clone(),
+                name: sym.name.clone(),
+                file: sym.file.clone(),
+                kind: sym.kind.as_str(),
+                start_line: start + 1,
+                end_line: end + 1,
+                lines: body.len(),
+                complexity: estimate_complexity(body, language),
+            });
+        }
+    }
+
+    metrics.sort_by(|a, b| {
+        b.complexity
+            .cmp(&a.complexity)
+            .then(b.lines.cmp(&a.lines))
+            .then(a.symbol_id.cmp(&b.symbol_id))
+    });
+
+    metrics
+}
+
+/// Branch keywords per language (word tokens)
+fn branch_keywords(language: &str) -> &'static [&'static str] {
+    match language {
+        "rust" => &["if", "for", "while", "loop", "match"],
+        "python" => &["if", "elif", "for", "while", "except", "and", "or", "case"],
+        "go" => &["if", "for", "switch", "case", "select"],
+        "typescript" | "javascript" => &["if", "for", "while", "switch", "case", "catch"],
+        _ => &["if", "for", "while", "switch", "case", "match"],
+    }
+}
+
+/// Branch operators per language (raw substrings)
+fn branch_operators(language: &str) -> &'static [&'static str] {
+    match language {
+        "python" => &[],
+        "typescript" | "javascript" => &["&&", "||", "??"],
+        _ => &["&&", "||"],
+    }
+}
+
+/// Estimate complexity (1 + decision points) for a block of lines
+///
+/// Expects source already run through `mask_source`, so keywords inside
+/// comments and strings don't count.
+pub fn estimate_complexity(lines: &[&str], language: &str) -> usize {
+    let keywords = branch_keywords(language);
+    let operators = branch_operators(language);
+    let mut complexity = 1;
+
+    for line in lines {
+        complexity += line
+            .split(|c: char| !c.is_alphanumeric() && c != '_')
+            .filter(|word| keywords.contains(word))
+            .count();
+
+        for op in operators {
+            complexity += line.matches(op).count();
+        }
+    }
+
+    complexity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::codemap::FileSummary;
+    use crate::core::parser::SymbolParser;
+
+    #[test]
+    fn test_estimate_complexity_counts_branches() {
+        let code = "fn f(x: i32) -> i32 {\n    if x > 0 && x < 10 {\n        for i in 0..x {}\n    }\n    // if this is a comment\n    let s = \"while || match\";\n    match x { _ => 0 }\n}";
+        let masked = mask_source(code, "rust");
+        let lines: Vec<&str> = masked.lines().collect();
+        // 1 + if + && + for + match
+        assert_eq!(estimate_complexity(&lines, "rust"), 5);
+    }
+
+    #[test]
+    fn test_metrics_use_parsed_symbol_span() {
+        let root = std::env::temp_dir().join(format!("sgrep-metrics-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let code = "fn a(x: i32) -> i32 {\n    let s = \"}\";\n    if x > 0 {\n        1\n    } else {\n        0\n    }\n}\n";
+        fs::write(root.join("lib.rs"), code).unwrap();
+
+        let parsed = SymbolParser::new()
+            .unwrap()
+            .parse_file(Path::new("lib.rs"), code)
+            .unwrap();
+        let mut map = CodeMap::new(&root.to_string_lossy());
+        map.add_file(FileSummary {
+            path: parsed.path,
+            language: parsed.language,
+            symbols: Vec::new(),
+            imports: Vec::new(),
+            exports: Vec::new(),
+            summary: String::new(),
+            lines: parsed.lines,
+        });
+        for symbol in parsed.symbols {
+            map.add_symbol(symbol);
+        }
+
+        let metrics = compute_symbol_metrics(&map, &root);
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(metrics.len(), 1);
+        assert_eq!((metrics[0].start_line, metrics[0].end_line), (1, 8));
+        assert_eq!(metrics[0].complexity, 2);
+    }
+}
@@ -4,10 +4,14 @@ pub mod config;
 pub mod daemon;
 pub mod embeddings;
 pub mod fast_indexer;
+pub mod file_tree;
 pub mod git;
 pub mod graph;
 pub mod hybrid_embedder;
 pub mod local_embeddings;
+pub mod lock;
+pub mod log;
+pub mod metrics;
 pub mod nomic_bert;
 pub mod parser;
 pub mod reranker;
@@ -5,9 +5,56 @@
 
 use anyhow::Result;
 use regex::Regex;
+use serde::Serialize;
 use std::path::Path;
 
-use super::codemap::{Symbol, SymbolKind};
+use super::codemap::{Symbol, SymbolKind, TraitImpl, Visibility};
+use super::scanner::shebang_language;
+
+/// `<...>` generic parameter list (captured), up to three levels deep
+const ANGLE_GENERICS: &str = r"<((?:->|[^<>]|<(?:->|[^<>]|<[^<>]*>)*>)*)>";
+/// `(...)` parameter list (captured), up to three levels deep and free to
+/// span lines
+const PAREN_PARAMS: &str = r"\(((?:[^()]|\((?:[^()]|\([^()]*\))*\))*)\)";
+/// Optional Rust visibility: `pub`, `pub(crate)`, `pub(in path)`
+const RUST_VISIBILITY: &str = r"(?:pub(?:\([^)]*\))?\s+)?";
+/// `[...]` generic parameter list (Go, Python 3.12), captured
+const SQUARE_GENERICS: &str = r"\[((?:[^\[\]]|\[[^\[\]]*\])*)\]";
+/// Leading Java annotations and modifiers (`@Override public static ...`)
+const JAVA_MODIFIERS: &str = r"(?:@\w+(?:\([^)]*\))?\s+)*(?:(?:public|protected|private|abstract|static|final|sealed|non-sealed|strictfp|synchronized|native|default)\s+)*";
+/// Words the Java method pattern can mistake for a return type or name
+const JAVA_NON_METHOD_WORDS: &[&str] = &[
+    "if",
+    "else",
+    "for",
+    "while",
+    "switch",
+    "catch",
+    "return",
+    "new",
+    "throw",
+    "try",
+    "do",
+    "synchronized",
+];
+/// C/C++ return type: one or more words, each optionally templated, ending in
+/// whitespace or a pointer/reference sigil (`static const char *`)
+const C_RETURN_TYPE: &str = r"(?:[A-Za-z_][\w:]*(?:<(?:[^<>]|<[^<>]*>)*>)?[ \t\*&]+)+";
+/// Words the C/C++ function patterns can mistake for a name
+const C_NON_FUNCTION_WORDS: &[&str] = &[
+    "if", "else", "for", "while", "switch", "case", "return", "sizeof", "do", "delete", "new",
+];
+/// Leading Swift attributes and declaration modifiers
+const SWIFT_MODIFIERS: &str = r"(?:@\w+(?:\([^)]*\))?\s+)*(?:(?:public|private|fileprivate|internal|open|static|class|final|override|mutating|nonmutating|convenience|required|indirect|nonisolated)\s+)*";
+/// Leading Kotlin annotations and modifiers
+const KOTLIN_MODIFIERS: &str = r"(?:@\w+(?:\([^)]*\))?\s+)*((?:(?:public|private|protected|internal|open|override|abstract|final|sealed|data|enum|inner|value|annotation|suspend|inline|operator|infix|tailrec|external|expect|actual)\s+)*)";
+/// Generic parameters longer than this keep their name but collapse the bound
+const GENERIC_PARAM_MAX_LEN: usize = 20;
+/// Default values longer than this are shown as `= ...` in compact signatures
+const PARAM_DEFAULT_MAX_LEN: usize = 16;
+/// Parameters longer than this have their generic arguments collapsed in
+/// compact signatures
+const PARAM_MAX_LEN: usize = 40;
 
 pub struct SymbolParser {
     // Rust patterns
@@ -18,13 +65,20 @@ pub struct SymbolParser {
     rust_impl: Regex,
     rust_mod: Regex,
     rust_use: Regex,
+    rust_const: Regex,
+    rust_static: Regex,
+    rust_type: Regex,
+    rust_macro: Regex,
 
     // TypeScript/JavaScript patterns
     ts_fn: Regex,
     ts_arrow: Regex,
     ts_class: Regex,
+    ts_method: Regex,
     ts_interface: Regex,
     ts_type: Regex,
+    ts_enum: Regex,
+    ts_namespace: Regex,
     ts_export: Regex,
     ts_import: Regex,
 
@@ -37,169 +91,538 @@ pub struct SymbolParser {
     go_fn: Regex,
     go_struct: Regex,
     go_interface: Regex,
+    go_package: Regex,
+    go_const: Regex,
+    go_const_block: Regex,
+    go_type: Regex,
+
+    // Java patterns
+    java_class: Regex,
+    java_interface: Regex,
+    java_enum: Regex,
+    java_method: Regex,
+    java_import: Regex,
+
+    // C/C++ patterns
+    c_fn: Regex,
+    c_struct: Regex,
+    c_enum: Regex,
+    c_typedef: Regex,
+    c_typedef_close: Regex,
+    c_include: Regex,
+    cpp_class: Regex,
+    cpp_namespace: Regex,
+    cpp_using: Regex,
+    cpp_member: Regex,
+
+    // Ruby patterns
+    rb_def: Regex,
+    rb_class: Regex,
+    rb_module: Regex,
+    rb_require: Regex,
+
+    // PHP patterns
+    php_fn: Regex,
+    php_class: Regex,
+    php_interface: Regex,
+    php_trait: Regex,
+    php_namespace: Regex,
+    php_use: Regex,
+
+    // Swift patterns
+    swift_fn: Regex,
+    swift_type: Regex,
+    swift_import: Regex,
+
+    // Kotlin patterns
+    kt_fn: Regex,
+    kt_class: Regex,
+    kt_import: Regex,
 }
 
 impl SymbolParser {
     pub fn new() -> Result<Self> {
         Ok(Self {
             // Rust
-            rust_fn: Regex::new(
-                r"(?m)^\s*(?:pub\s+)?(?:async\s+)?fn\s+(\w+)\s*(?:<[^>]*>)?\s*\(([^)]*)\)(?:\s*->\s*([^\{]+))?\s*\{",
-            )?,
-            rust_struct: Regex::new(r"(?m)^\s*(?:pub\s+)?struct\s+(\w+)(?:<[^>]*>)?")?,
-            rust_enum: Regex::new(r"(?m)^\s*(?:pub\s+)?enum\s+(\w+)(?:<[^>]*>)?")?,
-            rust_trait: Regex::new(r"(?m)^\s*(?:pub\s+)?trait\s+(\w+)(?:<[^>]*>)?")?,
-            rust_impl: Regex::new(r"(?m)^\s*impl(?:<[^>]*>)?\s+(?:(\w+)\s+for\s+)?(\w+)")?,
-            rust_mod: Regex::new(r"(?m)^\s*(?:pub\s+)?mod\s+(\w+)")?,
+            rust_fn: Regex::new(&format!(
+                r"(?m)^\s*{}(?:async\s+)?fn\s+(\w+)\s*(?:{})?\s*{}(?:\s*->\s*([^\{{]+))?\s*\{{",
+                RUST_VISIBILITY, ANGLE_GENERICS, PAREN_PARAMS
+            ))?,
+            rust_struct: Regex::new(&format!(
+                r"(?m)^\s*{}struct\s+(\w+)(?:{})?",
+                RUST_VISIBILITY, ANGLE_GENERICS
+            ))?,
+            rust_enum: Regex::new(&format!(
+                r"(?m)^\s*{}enum\s+(\w+)(?:{})?",
+                RUST_VISIBILITY, ANGLE_GENERICS
+            ))?,
+            rust_trait: Regex::new(&format!(
+                r"(?m)^\s*{}trait\s+(\w+)(?:{})?",
+                RUST_VISIBILITY, ANGLE_GENERICS
+            ))?,
+            rust_impl: Regex::new(&format!(
+                r"(?m)^\s*(?:unsafe\s+)?impl(?:{})?\s+(?:(!?[\w:]+(?:<(?:[^<>]|<[^<>]*>)*>)?)\s+for\s+)?(?:\w+::)*(\w+)",
+                ANGLE_GENERICS
+            ))?,
+            rust_mod: Regex::new(&format!(r"(?m)^\s*{}mod\s+(\w+)", RUST_VISIBILITY))?,
             rust_use: Regex::new(r"(?m)^\s*use\s+([^;]+);")?,
+            rust_const: Regex::new(&format!(
+                r"(?m)^\s*{}const\s+(\w+)\s*:\s*([^=;]+?)\s*[=;]",
+                RUST_VISIBILITY
+            ))?,
+            rust_static: Regex::new(&format!(
+                r"(?m)^\s*{}static\s+(mut\s+)?(\w+)\s*:\s*([^=;]+?)\s*=",
+                RUST_VISIBILITY
+            ))?,
+            rust_type: Regex::new(&format!(
+                r"(?m)^\s*{}type\s+(\w+)(?:{})?\s*=\s*([^;]+);",
+                RUST_VISIBILITY, ANGLE_GENERICS
+            ))?,
+            rust_macro: Regex::new(r"(?m)^\s*macro_rules!\s+(\w+)")?,
 
             // TypeScript/JavaScript
-            ts_fn: Regex::new(
-                r"(?m)^\s*(?:export\s+)?(?:async\s+)?function\s+(\w+)\s*(?:<[^>]*>)?\s*\(([^)]*)\)(?:\s*:\s*([^\{]+))?\s*\{",
-            )?,
+            ts_fn: Regex::new(&format!(
+                r"(?m)^\s*(?:export\s+)?(?:async\s+)?function\s+(\w+)\s*(?:{})?\s*{}(?:\s*:\s*([^\{{]+))?\s*\{{",
+                ANGLE_GENERICS, PAREN_PARAMS
+            ))?,
             ts_arrow: Regex::new(
                 r"(?m)^\s*(?:export\s+)?(?:const|let)\s+(\w+)\s*(?::\s*[^=]+)?\s*=\s*(?:async\s+)?\([^)]*\)\s*(?::\s*[^=]+)?\s*=>",
             )?,
-            ts_class: Regex::new(
-                r"(?m)^\s*(?:export\s+)?class\s+(\w+)(?:\s+extends\s+(\w+))?(?:\s+implements\s+([^{]+))?\s*\{",
-            )?,
-            ts_interface: Regex::new(
-                r"(?m)^\s*(?:export\s+)?interface\s+(\w+)(?:<[^>]*>)?(?:\s+extends\s+([^{]+))?\s*\{",
+            ts_class: Regex::new(&format!(
+                r"(?m)^\s*(?:export\s+)?class\s+(\w+)(?:{})?(?:\s+extends\s+(\w+(?:<(?:[^<>]|<[^<>]*>)*>)?))?(?:\s+implements\s+([^{{]+))?\s*\{{",
+                ANGLE_GENERICS
+            ))?,
+            ts_method: Regex::new(&format!(
+                r"(?m)^[ \t]*((?:(?:public|private|protected|static|readonly|abstract|override|async)\s+)*)(?:(get|set)\s+)?\*?\s*(#?\w+)\s*(?:{})?\s*{}(?:\s*:\s*([^{{;]+?))?\s*\{{",
+                ANGLE_GENERICS, PAREN_PARAMS
+            ))?,
+            ts_interface: Regex::new(&format!(
+                r"(?m)^\s*(?:export\s+)?interface\s+(\w+)(?:{})?(?:\s+extends\s+([^{{]+))?\s*\{{",
+                ANGLE_GENERICS
+            ))?,
+            ts_type: Regex::new(&format!(
+                r"(?m)^\s*(?:export\s+)?type\s+(\w+)(?:{})?\s*=",
+                ANGLE_GENERICS
+            ))?,
+            ts_enum: Regex::new(r"(?m)^\s*(?:export\s+)?(?:declare\s+)?(const\s+)?enum\s+(\w+)")?,
+            ts_namespace: Regex::new(
+                r"(?m)^\s*(?:export\s+)?(?:declare\s+)?(namespace|module)\s+([\w.]+)\s*\{",
             )?,
-            ts_type: Regex::new(r"(?m)^\s*(?:export\s+)?type\s+(\w+)(?:<[^>]*>)?\s*=")?,
             ts_export: Regex::new(r"(?m)^\s*export\s+\{([^}]+)\}")?,
             ts_import: Regex::new(
                 r#"(?m)^\s*import\s+(?:\{[^}]+\}|[^;]+)\s+from\s+['"]([^'"]+)['"]"#,
             )?,
 
             // Python
-            py_fn: Regex::new(r"(?m)^(?:async\s+)?def\s+(\w+)\s*\(([^)]*)\)(?:\s*->\s*([^:]+))?:")?,
-            py_class: Regex::new(r"(?m)^class\s+(\w+)(?:\(([^)]*)\))?:")?,
+            py_fn: Regex::new(&format!(
+                r"(?m)^([ \t]*)(?:async\s+)?def\s+(\w+)\s*(?:{})?\s*\(([^)]*)\)(?:\s*->\s*([^:]+))?:",
+                SQUARE_GENERICS
+            ))?,
+            py_class: Regex::new(&format!(
+                r"(?m)^class\s+(\w+)(?:{})?(?:\(([^)]*)\))?:",
+                SQUARE_GENERICS
+            ))?,
             py_import: Regex::new(r"(?m)^(?:from\s+(\S+)\s+)?import\s+(.+)$")?,
 
             // Go
-            go_fn: Regex::new(
-                r"(?m)^func\s+(?:\([^)]+\)\s+)?(\w+)\s*\(([^)]*)\)(?:\s*\(([^)]+)\)|\s*(\w+))?\s*\{",
+            go_fn: Regex::new(&format!(
+                r"(?m)^func\s+(?:\(([^)]+)\)\s+)?(\w+)(?:{})?\s*\(((?:[^()]|\([^()]*\))*)\)(?:\s*\(([^)]+)\)|\s*([\w.*\[\]]+))?\s*\{{",
+                SQUARE_GENERICS
+            ))?,
+            go_struct: Regex::new(&format!(
+                r"(?m)^type\s+(\w+)(?:{})?\s+struct\s*\{{",
+                SQUARE_GENERICS
+            ))?,
+            go_interface: Regex::new(&format!(
+                r"(?m)^type\s+(\w+)(?:{})?\s+interface\s*\{{",
+                SQUARE_GENERICS
+            ))?,
+            go_package: Regex::new(r"(?m)^package\s+(\w+)")?,
+            go_const: Regex::new(r"(?m)^(const|var)\s+(\w+)([^=\n]*)")?,
+            go_const_block: Regex::new(r"(?m)^(const|var)\s*\(")?,
+            go_type: Regex::new(&format!(
+                r"(?m)^type\s+(\w+)(?:{})?[ \t]+(=[ \t]*)?([^\s=][^\n]*)",
+                SQUARE_GENERICS
+            ))?,
+
+            // Java
+            java_class: Regex::new(&format!(
+                r"(?m)^\s*{}class\s+(\w+)(?:{})?(?:\s+extends\s+([\w.]+(?:<(?:[^<>]|<[^<>]*>)*>)?))?",
+                JAVA_MODIFIERS, ANGLE_GENERICS
+            ))?,
+            java_interface: Regex::new(&format!(
+                r"(?m)^\s*{}interface\s+(\w+)(?:{})?",
+                JAVA_MODIFIERS, ANGLE_GENERICS
+            ))?,
+            java_enum: Regex::new(&format!(r"(?m)^\s*{}enum\s+(\w+)", JAVA_MODIFIERS))?,
+            java_method: Regex::new(&format!(
+                r"(?m)^\s*{}(?:{}\s+)?([\w.]+(?:<(?:[^<>]|<[^<>]*>)*>)?(?:\[\])*)\s+(\w+)\s*\(([^)]*)\)(?:\s*throws\s+[\w.,\s]+?)?\s*\{{",
+                JAVA_MODIFIERS, ANGLE_GENERICS
+            ))?,
+            java_import: Regex::new(r"(?m)^\s*import\s+(?:static\s+)?([\w.]+(?:\.\*)?)\s*;")?,
+
+            // C/C++ (top-level definitions start in column 0)
+            c_fn: Regex::new(&format!(
+                r"(?m)^(?:template\s*{}\s*)?({})((?:\w+::)*~?\w+)\s*\(([^)]*)\)[ \t]*(?:const[ \t]*)?(?:noexcept[ \t]*)?(?:->[^{{;\n]+)?\s*\{{",
+                ANGLE_GENERICS, C_RETURN_TYPE
+            ))?,
+            c_struct: Regex::new(
+                r"(?m)^\s*(?:typedef\s+)?struct\s+(\w+)(?:\s+final)?(?:\s*:\s*[^{;]+?)?\s*\{",
+            )?,
+            c_enum: Regex::new(
+                r"(?m)^\s*(?:typedef\s+)?enum\s+(?:class\s+|struct\s+)?(\w+)(?:\s*:\s*[\w:]+)?\s*\{",
+            )?,
+            c_typedef: Regex::new(r"(?m)^\s*typedef\s+([^;{}]+?)\s*;")?,
+            c_typedef_close: Regex::new(r"(?m)^\}\s*(\w+)\s*;")?,
+            c_include: Regex::new(r#"(?m)^\s*#\s*include\s*[<"]([^>"]+)[>"]"#)?,
+            cpp_class: Regex::new(&format!(
+                r"(?m)^\s*(?:template\s*{}\s*)?class\s+(\w+)(?:\s+final)?(?:\s*:\s*([^{{;]+?))?\s*\{{",
+                ANGLE_GENERICS
+            ))?,
+            cpp_namespace: Regex::new(r"(?m)^\s*namespace\s+([\w:]+)\s*\{")?,
+            cpp_using: Regex::new(r"(?m)^\s*using\s+(\w+)\s*=\s*([^;]+);")?,
+            cpp_member: Regex::new(&format!(
+                r"(?m)^[ \t]+(?:(?:virtual|static|inline|explicit|constexpr|friend)[ \t]+)*({})?(~?\w+)\s*\(([^)]*)\)[^;{{\n]*?(?:\{{|;)",
+                C_RETURN_TYPE
+            ))?,
+
+            // Ruby
+            rb_def: Regex::new(
+                r"(?m)^\s*def\s+(self\.)?(\w+[?!=]?)(?:[ \t]*\(([^)]*)\)|[ \t]+([^\n#;=]+))?",
+            )?,
+            rb_class: Regex::new(r"(?m)^\s*class\s+([A-Z][\w:]*)(?:\s*<\s*([\w:]+))?")?,
+            rb_module: Regex::new(r"(?m)^\s*module\s+([A-Z][\w:]*)")?,
+            rb_require: Regex::new(r#"(?m)^\s*require(?:_relative)?\s*\(?\s*['"]([^'"]+)['"]"#)?,
+
+            // PHP
+            php_fn: Regex::new(
+                r"(?m)^\s*(?:(?:public|private|protected|static|abstract|final)\s+)*function\s+&?(\w+)\s*\(([^)]*)\)(?:\s*:\s*(\??[\w\\|]+))?",
+            )?,
+            php_class: Regex::new(
+                r"(?m)^\s*(?:(?:abstract|final|readonly)\s+)*class\s+(\w+)(?:\s+extends\s+([\w\\]+))?",
+            )?,
+            php_interface: Regex::new(r"(?m)^\s*interface\s+(\w+)")?,
+            php_trait: Regex::new(r"(?m)^\s*trait\s+(\w+)")?,
+            php_namespace: Regex::new(r"(?m)^\s*namespace\s+([\w\\]+)\s*[;{]")?,
+            // Column 0 only: an indented `use` inside a class imports a trait
+            php_use: Regex::new(r"(?m)^use\s+(?:function\s+|const\s+)?([\w\\]+)")?,
+
+            // Swift (protocol requirements have no body, hence `$`)
+            swift_fn: Regex::new(&format!(
+                r"(?m)^\s*{}func\s+(\w+)(?:{})?\s*\(((?:[^()]|\([^()]*\))*)\)\s*(?:async\s+)?(?:(?:re)?throws\s*)?(?:->\s*([^{{\n]+?))?\s*(?:where\s[^{{\n]*)?(?:\{{|$)",
+                SWIFT_MODIFIERS, ANGLE_GENERICS
+            ))?,
+            swift_type: Regex::new(&format!(
+                r"(?m)^\s*{}(class|struct|enum|protocol|extension|actor)\s+(\w+(?:\.\w+)*)(?:{})?(?:\s*:\s*([^{{\n]+?))?\s*(?:where\s[^{{\n]*)?\{{",
+                SWIFT_MODIFIERS, ANGLE_GENERICS
+            ))?,
+            swift_import: Regex::new(
+                r"(?m)^\s*(?:@testable\s+)?import\s+(?:(?:class|struct|func|enum|protocol|typealias|var|let)\s+)?([\w.]+)",
             )?,
-            go_struct: Regex::new(r"(?m)^type\s+(\w+)\s+struct\s*\{")?,
-            go_interface: Regex::new(r"(?m)^type\s+(\w+)\s+interface\s*\{")?,
+
+            // Kotlin (expression bodies use `=` instead of `{`)
+            kt_fn: Regex::new(&format!(
+                r"(?m)^\s*{}fun\s+(?:{}\s+)?(?:([\w.<>?, ]+?)\.)?(\w+)\s*\(((?:[^()]|\([^()]*\))*)\)(?:\s*:\s*([^={{\n]+?))?\s*(?:=|\{{|$)",
+                KOTLIN_MODIFIERS, ANGLE_GENERICS
+            ))?,
+            kt_class: Regex::new(&format!(
+                r"(?m)^\s*{}(class|interface|object)\s+(\w+)(?:{})?",
+                KOTLIN_MODIFIERS, ANGLE_GENERICS
+            ))?,
+            kt_import: Regex::new(r"(?m)^\s*import\s+([\w.]*\w(?:\.\*)?)")?,
         })
     }
 
     /// Parse a file and extract symbols
     pub fn parse_file(&self, path: &Path, content: &str) -> Result<ParsedFile> {
-        let lang = detect_language(path);
+        let mut lang = detect_language(path);
+        if lang == "unknown" {
+            // Extensionless scripts name their interpreter instead
+            if let Some(script_lang) = shebang_language(content) {
+                lang = script_lang.to_string();
+            }
+        }
+        self.parse_as(path, &lang, content)
+    }
+
+    /// Parse `content` as `lang` (e.g. "rust"), whatever the path's extension
+    pub fn parse_as(&self, path: &Path, lang: &str, content: &str) -> Result<ParsedFile> {
+        let lang = lang.to_string();
         let mut symbols = Vec::new();
         let mut imports = Vec::new();
         let lines: Vec<&str> = content.lines().collect();
 
+        // Patterns run over a copy with comments and string contents blanked,
+        // so commented-out code and code inside strings yields no symbols
+        let masked = mask_source(content, &lang);
+        let content = masked.as_str();
+
+        // Newline offsets, so symbol lines come from a binary search rather
+        // than a rescan of the file per symbol
+        let newlines: Vec<usize> = content
+            .bytes()
+            .enumerate()
+            .filter(|(_, b)| *b == b'\n')
+            .map(|(i, _)| i)
+            .collect();
+
         match lang.as_str() {
-            "rust" => self.parse_rust(path, content, &lines, &mut symbols, &mut imports),
+            "rust" => self.parse_rust(path, content, &newlines, &mut symbols, &mut imports),
             "typescript" | "javascript" => {
-                self.parse_typescript(path, content, &lines, &mut symbols, &mut imports)
+                self.parse_typescript(path, content, &newlines, &mut symbols, &mut imports)
             }
-            "python" => self.parse_python(path, content, &lines, &mut symbols, &mut imports),
-            "go" => self.parse_go(path, content, &lines, &mut symbols, &mut imports),
+            "python" => self.parse_python(path, content, &newlines, &mut symbols, &mut imports),
+            "go" => self.parse_go(path, content, &newlines, &mut symbols, &mut imports),
+            "java" => self.parse_java(path, content, &newlines, &mut symbols, &mut imports),
+            "c" => self.parse_c(path, content, &newlines, &mut symbols, &mut imports),
+            "cpp" => self.parse_cpp(path, content, &newlines, &mut symbols, &mut imports),
+            "ruby" => self.parse_ruby(path, content, &newlines, &mut symbols, &mut imports),
+            "php" => self.parse_php(path, content, &newlines, &mut symbols, &mut imports),
+            "swift" => self.parse_swift(path, content, &newlines, &mut symbols, &mut imports),
+            "kotlin" => self.parse_kotlin(path, content, &newlines, &mut symbols, &mut imports),
             _ => {} // Unsupported language
         }
 
+        let symbols: Vec<Symbol> = symbols
+            .into_iter()
+            .map(|symbol| {
+                let end_line = symbol_end_line(&lang, content, &newlines, symbol.line);
+                let visibility = symbol_visibility(&lang, &lines, &symbol);
+                let mut symbol = symbol.with_end_line(end_line).with_visibility(visibility);
+                symbol.summary = doc_summary(&lang, &lines, symbol.line, symbol.end_line);
+                if lang == "python" {
+                    // Read from the unmasked lines so decorator arguments survive
+                    let decorators = python_decorators(&lines, symbol.line);
+                    if !decorators.is_empty() {
+                        symbol.signature = format!("{} {}", decorators.join(" "), symbol.signature);
+                    }
+                }
+                symbol
+            })
+            .collect();
+
+        let summary = match lang.as_str() {
+            "go" => self.go_package_doc(&lines),
+            _ => String::new(),
+        };
+
+        let trait_impls = match lang.as_str() {
+            "rust" => self.rust_trait_impls(path, content, &newlines),
+            _ => Vec::new(),
+        };
+
         Ok(ParsedFile {
             path: path.to_string_lossy().to_string(),
             language: lang,
             symbols,
             imports,
+            summary,
+            trait_impls,
             lines: lines.len(),
         })
     }
 
+    /// `impl Trait for Type` blocks; inherent and negative (`!Send`) impls are skipped
+    fn rust_trait_impls(&self, path: &Path, content: &str, newlines: &[usize]) -> Vec<TraitImpl> {
+        self.rust_impl
+            .captures_iter(content)
+            .filter_map(|cap| {
+                let trait_path = cap.get(2)?.as_str();
+                if trait_path.starts_with('!') {
+                    return None;
+                }
+                let trait_path = trait_path.split('<').next().unwrap_or(trait_path);
+                let trait_name = trait_path.rsplit("::").next().unwrap_or(trait_path);
+                Some(TraitImpl {
+                    type_name: cap.get(3)?.as_str().to_string(),
+                    trait_name: trait_name.to_string(),
+                    file: path.to_string_lossy().to_string(),
+                    line: line_for_offset(newlines, content, cap.get(0).unwrap().start()),
+                })
+            })
+            .collect()
+    }
+
     fn parse_rust(
         &self,
         path: &Path,
         content: &str,
-        lines: &[&str],
+        newlines: &[usize],
         symbols: &mut Vec<Symbol>,
         imports: &mut Vec<String>,
     ) {
         let file_path = path.to_string_lossy().to_string();
 
-        // Functions
+        // `impl Type` / `impl Trait for Type` bodies, so methods can name their type
+        let impls: Vec<(usize, usize, &str)> = self
+            .rust_impl
+            .captures_iter(content)
+            .filter_map(|cap| {
+                let whole = cap.get(0).unwrap();
+                let open = whole.end() + content[whole.end()..].find('{')?;
+                let (start, end) = brace_block(content, open);
+                Some((start, end, cap.get(3)?.as_str()))
+            })
+            .collect();
+
+        // Innermost enclosing impl block, if any
+        let container_at = |offset: usize| {
+            impls
+                .iter()
+                .filter(|(start, end, _)| (*start..*end).contains(&offset))
+                .max_by_key(|(start, _, _)| *start)
+                .map(|(_, _, ty)| ty.to_string())
+        };
+
+        // Functions and methods
         for cap in self.rust_fn.captures_iter(content) {
             let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
-            let params = cap.get(2).map(|m| m.as_str()).unwrap_or("");
-            let ret = cap.get(3).map(|m| m.as_str().trim()).unwrap_or("()");
-            let line = find_line_number(content, cap.get(0).unwrap().start());
+            let generics = format_generics(cap.get(2).map(|m| m.as_str()), '<', '>');
+            let params = cap.get(3).map(|m| m.as_str()).unwrap_or("");
+            let ret = cap
+                .get(4)
+                .map(|m| m.as_str().split_whitespace().collect::<Vec<_>>().join(" "))
+                .unwrap_or_else(|| "()".to_string());
+            let offset = cap.get(0).unwrap().start();
+            let line = line_for_offset(newlines, content, offset);
+            let container = container_at(offset);
 
-            let signature = format!("{}({}) -> {}", name, simplify_params(params), ret);
+            let qualified = match &container {
+                Some(ty) => format!("{}::{}", ty, name),
+                None => name.to_string(),
+            };
+            let signature = format!(
+                "{}{}({}) -> {}",
+                qualified,
+                generics,
+                simplify_params(params),
+                ret
+            );
 
-            symbols.push(Symbol {
-                id: format!("{}:{}", file_path, name),
-                name: name.to_string(),
-                file: file_path.clone(),
-                line,
-                kind: SymbolKind::Function,
-                signature,
-                summary: String::new(),
-                depends_on: Vec::new(),
-                depended_by: Vec::new(),
-                embedding: Vec::new(),
-            });
+            let kind = if container.is_some() {
+                SymbolKind::Method
+            } else {
+                SymbolKind::Function
+            };
+            symbols.push(
+                Symbol::new(&file_path, name, line, kind, signature).with_container(container),
+            );
         }
 
         // Structs
         for cap in self.rust_struct.captures_iter(content) {
             let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
-            let line = find_line_number(content, cap.get(0).unwrap().start());
-
-            symbols.push(Symbol {
-                id: format!("{}:{}", file_path, name),
-                name: name.to_string(),
-                file: file_path.clone(),
-                line,
-                kind: SymbolKind::Struct,
-                signature: format!("struct {}", name),
-                summary: String::new(),
-                depends_on: Vec::new(),
-                depended_by: Vec::new(),
-                embedding: Vec::new(),
-            });
+            let generics = format_generics(cap.get(2).map(|m| m.as_str()), '<', '>');
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Struct,
+                format!("struct {}{}", name, generics),
+            ));
         }
 
         // Enums
         for cap in self.rust_enum.captures_iter(content) {
             let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
-            let line = find_line_number(content, cap.get(0).unwrap().start());
-
-            symbols.push(Symbol {
-                id: format!("{}:{}", file_path, name),
-                name: name.to_string(),
-                file: file_path.clone(),
-                line,
-                kind: SymbolKind::Enum,
-                signature: format!("enum {}", name),
-                summary: String::new(),
-                depends_on: Vec::new(),
-                depended_by: Vec::new(),
-                embedding: Vec::new(),
-            });
+            let generics = format_generics(cap.get(2).map(|m| m.as_str()), '<', '>');
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Enum,
+                format!("enum {}{}", name, generics),
+            ));
         }
 
         // Traits
         for cap in self.rust_trait.captures_iter(content) {
             let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
-            let line = find_line_number(content, cap.get(0).unwrap().start());
-
-            symbols.push(Symbol {
-                id: format!("{}:{}", file_path, name),
-                name: name.to_string(),
-                file: file_path.clone(),
-                line,
-                kind: SymbolKind::Trait,
-                signature: format!("trait {}", name),
-                summary: String::new(),
-                depends_on: Vec::new(),
-                depended_by: Vec::new(),
-                embedding: Vec::new(),
-            });
+            let generics = format_generics(cap.get(2).map(|m| m.as_str()), '<', '>');
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Trait,
+                format!("trait {}{}", name, generics),
+            ));
+        }
+
+        // Constants, statics, and type aliases (associated ones name their impl)
+        let items = self
+            .rust_const
+            .captures_iter(content)
+            .filter_map(|cap| {
+                let name = cap.get(1)?.as_str();
+                let ty = cap
+                    .get(2)?
+                    .as_str()
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let signature = format!("const {}: {}", name, ty);
+                Some((cap.get(0)?.start(), name, SymbolKind::Const, signature))
+            })
+            .chain(self.rust_static.captures_iter(content).filter_map(|cap| {
+                let name = cap.get(2)?.as_str();
+                let ty = cap
+                    .get(3)?
+                    .as_str()
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let keyword = if cap.get(1).is_some() {
+                    "static mut"
+                } else {
+                    "static"
+                };
+                let signature = format!("{} {}: {}", keyword, name, ty);
+                Some((cap.get(0)?.start(), name, SymbolKind::Var, signature))
+            }))
+            .chain(self.rust_type.captures_iter(content).filter_map(|cap| {
+                let name = cap.get(1)?.as_str();
+                let generics = format_generics(cap.get(2).map(|m| m.as_str()), '<', '>');
+                let target = cap
+                    .get(3)?
+                    .as_str()
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let signature = format!("type {}{} = {}", name, generics, target);
+                Some((cap.get(0)?.start(), name, SymbolKind::Type, signature))
+            }));
+
+        for (offset, name, kind, signature) in items {
+            // `const _: () = ...` is an unnamed compile-time assertion
+            if name == "_" {
+                continue;
+            }
+            let container = container_at(offset);
+            let line = line_for_offset(newlines, content, offset);
+
+            symbols.push(
+                Symbol::new(&file_path, name, line, kind, signature).with_container(container),
+            );
+        }
+
+        // macro_rules! definitions
+        for cap in self.rust_macro.captures_iter(content) {
+            let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Macro,
+                format!("macro_rules! {}!", name),
+            ));
         }
 
         // Imports
@@ -214,7 +637,7 @@ impl SymbolParser {
         &self,
         path: &Path,
         content: &str,
-        _lines: &[&str],
+        newlines: &[usize],
         symbols: &mut Vec<Symbol>,
         imports: &mut Vec<String>,
     ) {
@@ -223,107 +646,162 @@ impl SymbolParser {
         // Functions
         for cap in self.ts_fn.captures_iter(content) {
             let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
-            let params = cap.get(2).map(|m| m.as_str()).unwrap_or("");
-            let ret = cap.get(3).map(|m| m.as_str().trim()).unwrap_or("void");
-            let line = find_line_number(content, cap.get(0).unwrap().start());
+            let generics = format_generics(cap.get(2).map(|m| m.as_str()), '<', '>');
+            let params = cap.get(3).map(|m| m.as_str()).unwrap_or("");
+            let ret = cap
+                .get(4)
+                .map(|m| m.as_str().split_whitespace().collect::<Vec<_>>().join(" "))
+                .unwrap_or_else(|| "void".to_string());
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
 
-            let signature = format!("{}({}): {}", name, simplify_params(params), ret);
+            let signature = format!("{}{}({}): {}", name, generics, simplify_params(params), ret);
 
-            symbols.push(Symbol {
-                id: format!("{}:{}", file_path, name),
-                name: name.to_string(),
-                file: file_path.clone(),
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
                 line,
-                kind: SymbolKind::Function,
+                SymbolKind::Function,
                 signature,
-                summary: String::new(),
-                depends_on: Vec::new(),
-                depended_by: Vec::new(),
-                embedding: Vec::new(),
-            });
+            ));
         }
 
         // Arrow functions
         for cap in self.ts_arrow.captures_iter(content) {
             let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
-            let line = find_line_number(content, cap.get(0).unwrap().start());
-
-            symbols.push(Symbol {
-                id: format!("{}:{}", file_path, name),
-                name: name.to_string(),
-                file: file_path.clone(),
-                line,
-                kind: SymbolKind::Function,
-                signature: format!("{} = () => ...", name),
-                summary: String::new(),
-                depends_on: Vec::new(),
-                depended_by: Vec::new(),
-                embedding: Vec::new(),
-            });
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Function,
+                format!("{} = () => ...", name),
+            ));
         }
 
         // Classes
         for cap in self.ts_class.captures_iter(content) {
             let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
-            let extends = cap.get(2).map(|m| m.as_str());
-            let line = find_line_number(content, cap.get(0).unwrap().start());
+            let generics = format_generics(cap.get(2).map(|m| m.as_str()), '<', '>');
+            let extends = cap.get(3).map(|m| m.as_str());
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
 
             let signature = if let Some(parent) = extends {
-                format!("class {} extends {}", name, parent)
+                format!("class {}{} extends {}", name, generics, parent)
             } else {
-                format!("class {}", name)
+                format!("class {}{}", name, generics)
             };
 
-            symbols.push(Symbol {
-                id: format!("{}:{}", file_path, name),
-                name: name.to_string(),
-                file: file_path.clone(),
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
                 line,
-                kind: SymbolKind::Class,
+                SymbolKind::Class,
                 signature,
-                summary: String::new(),
-                depends_on: Vec::new(),
-                depended_by: Vec::new(),
-                embedding: Vec::new(),
-            });
+            ));
+
+            // Methods: direct members of the class body only
+            let (start, end) = brace_block(content, cap.get(0).unwrap().end() - 1);
+            let body = &content[start..end];
+            for member in self.ts_method.captures_iter(body) {
+                let offset = member.get(0).unwrap().start();
+                if brace_depth(&body[..offset]) != 0 {
+                    continue;
+                }
+
+                let modifiers = member.get(1).map(|m| m.as_str()).unwrap_or("");
+                let accessor = member.get(2).map(|m| m.as_str());
+                let method = member.get(3).map(|m| m.as_str()).unwrap_or("");
+                let method_generics = format_generics(member.get(4).map(|m| m.as_str()), '<', '>');
+                let params = member.get(5).map(|m| m.as_str()).unwrap_or("");
+                let line = line_for_offset(newlines, content, start + offset);
+
+                // `static async`, `get`, ... stay in front: `async AuthService.login(...)`
+                let mut prefix: Vec<&str> = modifiers.split_whitespace().collect();
+                prefix.extend(accessor);
+                prefix.push("");
+                let mut signature = format!(
+                    "{}{}.{}{}({})",
+                    prefix.join(" "),
+                    name,
+                    method,
+                    method_generics,
+                    simplify_params(params)
+                );
+                if let Some(ret) = member.get(6) {
+                    let ret = ret.as_str().split_whitespace().collect::<Vec<_>>();
+                    signature.push_str(&format!(": {}", ret.join(" ")));
+                }
+
+                symbols.push(
+                    Symbol::new(&file_path, method, line, SymbolKind::Method, signature)
+                        .with_container(Some(name.to_string())),
+                );
+            }
         }
 
         // Interfaces
         for cap in self.ts_interface.captures_iter(content) {
             let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
-            let line = find_line_number(content, cap.get(0).unwrap().start());
-
-            symbols.push(Symbol {
-                id: format!("{}:{}", file_path, name),
-                name: name.to_string(),
-                file: file_path.clone(),
-                line,
-                kind: SymbolKind::Interface,
-                signature: format!("interface {}", name),
-                summary: String::new(),
-                depends_on: Vec::new(),
-                depended_by: Vec::new(),
-                embedding: Vec::new(),
-            });
+            let generics = format_generics(cap.get(2).map(|m| m.as_str()), '<', '>');
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Interface,
+                format!("interface {}{}", name, generics),
+            ));
         }
 
         // Types
         for cap in self.ts_type.captures_iter(content) {
             let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
-            let line = find_line_number(content, cap.get(0).unwrap().start());
-
-            symbols.push(Symbol {
-                id: format!("{}:{}", file_path, name),
-                name: name.to_string(),
-                file: file_path.clone(),
-                line,
-                kind: SymbolKind::Type,
-                signature: format!("type {}", name),
-                summary: String::new(),
-                depends_on: Vec::new(),
-                depended_by: Vec::new(),
-                embedding: Vec::new(),
-            });
+            let generics = format_generics(cap.get(2).map(|m| m.as_str()), '<', '>');
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Type,
+                format!("type {}{}", name, generics),
+            ));
+        }
+
+        // Enums (including `const enum`)
+        for cap in self.ts_enum.captures_iter(content) {
+            let name = cap.get(2).map(|m| m.as_str()).unwrap_or("");
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+            let keyword = if cap.get(1).is_some() {
+                "const enum"
+            } else {
+                "enum"
+            };
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Enum,
+                format!("{} {}", keyword, name),
+            ));
+        }
+
+        // Namespaces (and legacy `module Name {}`; `declare module 'pkg'` has no name)
+        for cap in self.ts_namespace.captures_iter(content) {
+            let keyword = cap.get(1).map(|m| m.as_str()).unwrap_or("namespace");
+            let name = cap.get(2).map(|m| m.as_str()).unwrap_or("");
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Module,
+                format!("{} {}", keyword, name),
+            ));
         }
 
         // Imports
@@ -338,59 +816,103 @@ impl SymbolParser {
         &self,
         path: &Path,
         content: &str,
-        _lines: &[&str],
+        newlines: &[usize],
         symbols: &mut Vec<Symbol>,
         imports: &mut Vec<String>,
     ) {
         let file_path = path.to_string_lossy().to_string();
 
-        // Functions
+        // Top-level class bodies (start, end, name, body indent), so a `def`
+        // indented exactly one level into one is known to be its method
+        let classes: Vec<(usize, usize, &str, usize)> = self
+            .py_class
+            .captures_iter(content)
+            .filter_map(|cap| {
+                let header = cap.get(0).unwrap();
+                let body_start = header.end() + content[header.end()..].find('\n')? + 1;
+                // The body runs until the next line that starts at column 0
+                let mut end = content.len();
+                let mut indent = None;
+                let mut offset = body_start;
+                for line in content[body_start..].split_inclusive('\n') {
+                    let code = line.trim_start_matches([' ', '\t']);
+                    if !code.trim().is_empty() {
+                        let width = line.len() - code.len();
+                        if width == 0 {
+                            end = offset;
+                            break;
+                        }
+                        indent.get_or_insert(width);
+                    }
+                    offset += line.len();
+                }
+                Some((header.start(), end, cap.get(1)?.as_str(), indent?))
+            })
+            .collect();
+
+        // Functions and methods; functions nested in other functions are skipped
         for cap in self.py_fn.captures_iter(content) {
-            let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
-            let params = cap.get(2).map(|m| m.as_str()).unwrap_or("");
-            let ret = cap.get(3).map(|m| m.as_str().trim()).unwrap_or("None");
-            let line = find_line_number(content, cap.get(0).unwrap().start());
+            let indent = cap.get(1).map(|m| m.as_str().len()).unwrap_or(0);
+            let name = cap.get(2).map(|m| m.as_str()).unwrap_or("");
+            let generics = format_generics(cap.get(3).map(|m| m.as_str()), '[', ']');
+            let params = cap.get(4).map(|m| m.as_str()).unwrap_or("");
+            let ret = cap.get(5).map(|m| m.as_str().trim()).unwrap_or("None");
+            let offset = cap.get(0).unwrap().start();
+            let line = line_for_offset(newlines, content, offset);
 
-            let signature = format!("def {}({}) -> {}", name, simplify_params(params), ret);
+            let container = if indent == 0 {
+                None
+            } else {
+                match classes.iter().find(|(start, end, _, body_indent)| {
+                    (*start..*end).contains(&offset) && *body_indent == indent
+                }) {
+                    Some((_, _, class, _)) => Some(class.to_string()),
+                    None => continue,
+                }
+            };
 
-            symbols.push(Symbol {
-                id: format!("{}:{}", file_path, name),
-                name: name.to_string(),
-                file: file_path.clone(),
-                line,
-                kind: SymbolKind::Function,
-                signature,
-                summary: String::new(),
-                depends_on: Vec::new(),
-                depended_by: Vec::new(),
-                embedding: Vec::new(),
-            });
+            let qualified = match &container {
+                Some(class) => format!("{}.{}", class, name),
+                None => name.to_string(),
+            };
+            let signature = format!(
+                "def {}{}({}) -> {}",
+                qualified,
+                generics,
+                simplify_params(params),
+                ret
+            );
+
+            let kind = if container.is_some() {
+                SymbolKind::Method
+            } else {
+                SymbolKind::Function
+            };
+            symbols.push(
+                Symbol::new(&file_path, name, line, kind, signature).with_container(container),
+            );
         }
 
         // Classes
         for cap in self.py_class.captures_iter(content) {
             let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
-            let bases = cap.get(2).map(|m| m.as_str());
-            let line = find_line_number(content, cap.get(0).unwrap().start());
+            let generics = format_generics(cap.get(2).map(|m| m.as_str()), '[', ']');
+            let bases = cap.get(3).map(|m| m.as_str());
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
 
             let signature = if let Some(b) = bases {
-                format!("class {}({})", name, b)
+                format!("class {}{}({})", name, generics, b)
             } else {
-                format!("class {}", name)
+                format!("class {}{}", name, generics)
             };
 
-            symbols.push(Symbol {
-                id: format!("{}:{}", file_path, name),
-                name: name.to_string(),
-                file: file_path.clone(),
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
                 line,
-                kind: SymbolKind::Class,
+                SymbolKind::Class,
                 signature,
-                summary: String::new(),
-                depends_on: Vec::new(),
-                depended_by: Vec::new(),
-                embedding: Vec::new(),
-            });
+            ));
         }
 
         // Imports
@@ -407,126 +929,3205 @@ impl SymbolParser {
         &self,
         path: &Path,
         content: &str,
-        _lines: &[&str],
+        newlines: &[usize],
         symbols: &mut Vec<Symbol>,
         _imports: &mut Vec<String>,
     ) {
         let file_path = path.to_string_lossy().to_string();
 
-        // Functions
+        // Functions and methods
         for cap in self.go_fn.captures_iter(content) {
-            let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
-            let params = cap.get(2).map(|m| m.as_str()).unwrap_or("");
+            let receiver = cap
+                .get(1)
+                .map(|m| m.as_str().split_whitespace().collect::<Vec<_>>().join(" "));
+            let name = cap.get(2).map(|m| m.as_str()).unwrap_or("");
+            let generics = format_generics(cap.get(3).map(|m| m.as_str()), '[', ']');
+            let params = cap.get(4).map(|m| m.as_str()).unwrap_or("");
             let ret = cap
-                .get(3)
-                .or(cap.get(4))
-                .map(|m| m.as_str().trim())
-                .unwrap_or("");
-            let line = find_line_number(content, cap.get(0).unwrap().start());
+                .get(5)
+                .map(|m| format!("({})", m.as_str().trim()))
+                .or(cap.get(6).map(|m| m.as_str().to_string()))
+                .unwrap_or_default();
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            // `s *Stack[T]` -> `Stack`
+            let container = receiver.as_deref().and_then(|r| {
+                let ty = r.rsplit(' ').next()?.trim_start_matches('*');
+                let ty = ty.split('[').next()?;
+                (!ty.is_empty()).then(|| ty.to_string())
+            });
 
+            let head = match &receiver {
+                Some(r) => format!("func ({}) {}", r, name),
+                None => format!("func {}", name),
+            };
             let signature = if ret.is_empty() {
-                format!("func {}({})", name, simplify_params(params))
+                format!("{}{}({})", head, generics, simplify_params(params))
             } else {
-                format!("func {}({}) {}", name, simplify_params(params), ret)
+                format!("{}{}({}) {}", head, generics, simplify_params(params), ret)
             };
 
-            symbols.push(Symbol {
-                id: format!("{}:{}", file_path, name),
-                name: name.to_string(),
-                file: file_path.clone(),
-                line,
-                kind: SymbolKind::Function,
-                signature,
-                summary: String::new(),
-                depends_on: Vec::new(),
-                depended_by: Vec::new(),
-                embedding: Vec::new(),
-            });
+            let kind = if container.is_some() {
+                SymbolKind::Method
+            } else {
+                SymbolKind::Function
+            };
+            symbols.push(
+                Symbol::new(&file_path, name, line, kind, signature).with_container(container),
+            );
         }
 
         // Structs
         for cap in self.go_struct.captures_iter(content) {
             let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
-            let line = find_line_number(content, cap.get(0).unwrap().start());
-
-            symbols.push(Symbol {
-                id: format!("{}:{}", file_path, name),
-                name: name.to_string(),
-                file: file_path.clone(),
-                line,
-                kind: SymbolKind::Struct,
-                signature: format!("type {} struct", name),
-                summary: String::new(),
-                depends_on: Vec::new(),
-                depended_by: Vec::new(),
-                embedding: Vec::new(),
-            });
+            let generics = format_generics(cap.get(2).map(|m| m.as_str()), '[', ']');
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Struct,
+                format!("type {}{} struct", name, generics),
+            ));
         }
 
         // Interfaces
         for cap in self.go_interface.captures_iter(content) {
             let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
-            let line = find_line_number(content, cap.get(0).unwrap().start());
-
-            symbols.push(Symbol {
-                id: format!("{}:{}", file_path, name),
-                name: name.to_string(),
-                file: file_path.clone(),
-                line,
-                kind: SymbolKind::Interface,
-                signature: format!("type {} interface", name),
-                summary: String::new(),
-                depends_on: Vec::new(),
-                depended_by: Vec::new(),
-                embedding: Vec::new(),
-            });
+            let generics = format_generics(cap.get(2).map(|m| m.as_str()), '[', ']');
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Interface,
+                format!("type {}{} interface", name, generics),
+            ));
+        }
+
+        // Type aliases (`type Name = Other`) and other named types (`type ID string`)
+        for cap in self.go_type.captures_iter(content) {
+            let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
+            let generics = format_generics(cap.get(2).map(|m| m.as_str()), '[', ']');
+            let target = cap.get(4).map(|m| m.as_str().trim()).unwrap_or("");
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            // Structs and interfaces have their own patterns above
+            let keyword = target.split(|c: char| !c.is_alphanumeric()).next();
+            if matches!(keyword, Some("struct" | "interface")) {
+                continue;
+            }
+
+            let signature = if cap.get(3).is_some() {
+                format!("type {}{} = {}", name, generics, target)
+            } else {
+                format!("type {}{} {}", name, generics, target)
+            };
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Type,
+                signature,
+            ));
+        }
+
+        // Exported consts/vars: `const Name = ...`
+        for cap in self.go_const.captures_iter(content) {
+            let keyword = cap.get(1).map(|m| m.as_str()).unwrap_or("const");
+            let name = cap.get(2).map(|m| m.as_str()).unwrap_or("");
+            let ty = cap.get(3).map(|m| m.as_str().trim()).unwrap_or("");
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            if is_go_exported(name) {
+                symbols.push(go_value_symbol(&file_path, keyword, name, ty, line));
+            }
+        }
+
+        // Grouped `const ( ... )` / `var ( ... )` blocks
+        for cap in self.go_const_block.captures_iter(content) {
+            let keyword = cap.get(1).map(|m| m.as_str()).unwrap_or("const");
+            let block_start = cap.get(0).unwrap().end();
+            let mut offset = block_start;
+
+            for raw_line in content[block_start..].split_inclusive('\n') {
+                let line_offset = offset;
+                offset += raw_line.len();
+
+                let entry = raw_line.trim();
+                if entry.starts_with(')') {
+                    break;
+                }
+                if entry.is_empty() || entry.starts_with("//") {
+                    continue;
+                }
+
+                // `A, B = 1, 2` declares several names; the type sits between names and `=`
+                let decl = entry.split('=').next().unwrap_or("");
+                let (names, ty) = match decl.rfind(',') {
+                    Some(pos) => {
+                        let rest = decl[pos + 1..].trim();
+                        let (last, ty) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                        (format!("{},{}", &decl[..pos], last), ty.trim().to_string())
+                    }
+                    None => {
+                        let decl = decl.trim();
+                        let (name, ty) = decl.split_once(char::is_whitespace).unwrap_or((decl, ""));
+                        (name.to_string(), ty.trim().to_string())
+                    }
+                };
+
+                let line = line_for_offset(newlines, content, line_offset);
+                for name in names.split(',').map(|n| n.trim()) {
+                    if is_go_exported(name) {
+                        symbols.push(go_value_symbol(&file_path, keyword, name, &ty, line));
+                    }
+                }
+            }
         }
     }
-}
 
-pub struct ParsedFile {
-    pub path: String,
-    pub language: String,
-    pub symbols: Vec<Symbol>,
-    pub imports: Vec<String>,
-    pub lines: usize,
-}
+    fn parse_java(
+        &self,
+        path: &Path,
+        content: &str,
+        newlines: &[usize],
+        symbols: &mut Vec<Symbol>,
+        imports: &mut Vec<String>,
+    ) {
+        let file_path = path.to_string_lossy().to_string();
 
-fn detect_language(path: &Path) -> String {
-    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        // Classes
+        for cap in self.java_class.captures_iter(content) {
+            let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
+            let generics = format_generics(cap.get(2).map(|m| m.as_str()), '<', '>');
+            let extends = cap.get(3).map(|m| m.as_str());
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
 
-    match ext {
-        "rs" => "rust",
-        "ts" | "tsx" => "typescript",
-        "js" | "jsx" | "mjs" => "javascript",
-        "py" => "python",
-        "go" => "go",
-        "java" => "java",
-        "cpp" | "cc" | "cxx" | "hpp" | "h" => "cpp",
-        "c" => "c",
-        "rb" => "ruby",
-        "php" => "php",
-        "swift" => "swift",
-        "kt" | "kts" => "kotlin",
-        "scala" => "scala",
-        "zig" => "zig",
-        _ => "unknown",
+            let signature = if let Some(parent) = extends {
+                format!("class {}{} extends {}", name, generics, parent)
+            } else {
+                format!("class {}{}", name, generics)
+            };
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Class,
+                signature,
+            ));
+        }
+
+        // Interfaces
+        for cap in self.java_interface.captures_iter(content) {
+            let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
+            let generics = format_generics(cap.get(2).map(|m| m.as_str()), '<', '>');
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Interface,
+                format!("interface {}{}", name, generics),
+            ));
+        }
+
+        // Enums
+        for cap in self.java_enum.captures_iter(content) {
+            let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Enum,
+                format!("enum {}", name),
+            ));
+        }
+
+        // Methods and constructors
+        for cap in self.java_method.captures_iter(content) {
+            let generics = format_generics(cap.get(1).map(|m| m.as_str()), '<', '>');
+            let ret = cap.get(2).map(|m| m.as_str()).unwrap_or("");
+            let name = cap.get(3).map(|m| m.as_str()).unwrap_or("");
+            let params = cap.get(4).map(|m| m.as_str()).unwrap_or("");
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            // `else if (...) {`, `new Foo(...) {` and the like
+            if JAVA_NON_METHOD_WORDS.contains(&ret) || JAVA_NON_METHOD_WORDS.contains(&name) {
+                continue;
+            }
+
+            // Constructors have no return type, so a modifier lands in its slot;
+            // they belong to their class so their id doesn't collide with it
+            if is_java_modifier(ret) {
+                let signature = format!("{}({})", name, simplify_params(params));
+                symbols.push(
+                    Symbol::new(&file_path, name, line, SymbolKind::Method, signature)
+                        .with_container(Some(name.to_string())),
+                );
+                continue;
+            }
+
+            let signature = if generics.is_empty() {
+                format!("{} {}({})", ret, name, simplify_params(params))
+            } else {
+                format!("{} {} {}({})", generics, ret, name, simplify_params(params))
+            };
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Function,
+                signature,
+            ));
+        }
+
+        // Imports
+        for cap in self.java_import.captures_iter(content) {
+            if let Some(m) = cap.get(1) {
+                imports.push(m.as_str().to_string());
+            }
+        }
     }
-    .to_string()
-}
 
-fn find_line_number(content: &str, byte_offset: usize) -> usize {
-    content[..byte_offset].matches('\n').count() + 1
-}
+    fn parse_c(
+        &self,
+        path: &Path,
+        content: &str,
+        newlines: &[usize],
+        symbols: &mut Vec<Symbol>,
+        imports: &mut Vec<String>,
+    ) {
+        let file_path = path.to_string_lossy().to_string();
 
-fn simplify_params(params: &str) -> String {
-    // Simplify long parameter lists
-    let params = params.trim();
-    if params.len() > 50 {
-        let parts: Vec<&str> = params.split(',').collect();
-        if parts.len() > 3 {
-            return format!("{}...", parts.len());
+        // Functions (C++ templates and out-of-class `Foo::bar` definitions too)
+        for cap in self.c_fn.captures_iter(content) {
+            let template = cap.get(1).map(|m| m.as_str());
+            let ret = cap.get(2).map(|m| m.as_str()).unwrap_or("");
+            let name = cap.get(3).map(|m| m.as_str()).unwrap_or("");
+            let params = cap.get(4).map(|m| m.as_str()).unwrap_or("");
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            if C_NON_FUNCTION_WORDS.contains(&name) {
+                continue;
+            }
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Function,
+                c_signature(template, ret, name, params),
+            ));
+        }
+
+        // Structs
+        for cap in self.c_struct.captures_iter(content) {
+            let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Struct,
+                format!("struct {}", name),
+            ));
+        }
+
+        // Enums
+        for cap in self.c_enum.captures_iter(content) {
+            let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Enum,
+                format!("enum {}", name),
+            ));
+        }
+
+        // Single-line typedefs: `typedef unsigned long ulong;`, `typedef int (*cmp)(...);`
+        for cap in self.c_typedef.captures_iter(content) {
+            let text = cap.get(1).map(|m| m.as_str()).unwrap_or("");
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            let name = match text.split_once("(*") {
+                Some((_, rest)) => rest
+                    .split(|c: char| !c.is_alphanumeric() && c != '_')
+                    .next(),
+                None => text
+                    .rsplit(|c: char| !c.is_alphanumeric() && c != '_')
+                    .next(),
+            }
+            .unwrap_or("");
+            if name.is_empty() {
+                continue;
+            }
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Type,
+                format!(
+                    "typedef {}",
+                    text.split_whitespace().collect::<Vec<_>>().join(" ")
+                ),
+            ));
         }
+
+        // Block typedefs, named at the closing brace: `typedef struct { ... } point_t;`
+        for cap in self.c_typedef_close.captures_iter(content) {
+            let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Type,
+                format!("typedef {{...}} {}", name),
+            ));
+        }
+
+        // Includes
+        for cap in self.c_include.captures_iter(content) {
+            if let Some(m) = cap.get(1) {
+                imports.push(m.as_str().to_string());
+            }
+        }
+    }
+
+    fn parse_cpp(
+        &self,
+        path: &Path,
+        content: &str,
+        newlines: &[usize],
+        symbols: &mut Vec<Symbol>,
+        imports: &mut Vec<String>,
+    ) {
+        // Functions, structs, enums, typedefs and includes are shared with C
+        self.parse_c(path, content, newlines, symbols, imports);
+
+        let file_path = path.to_string_lossy().to_string();
+
+        // Namespaces
+        for cap in self.cpp_namespace.captures_iter(content) {
+            let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Module,
+                format!("namespace {}", name),
+            ));
+        }
+
+        // Type aliases
+        for cap in self.cpp_using.captures_iter(content) {
+            let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
+            let target = cap.get(2).map(|m| m.as_str().trim()).unwrap_or("");
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Type,
+                format!("using {} = {}", name, target),
+            ));
+        }
+
+        // Classes, then the member functions declared in each class or struct body
+        let mut bodies = Vec::new();
+        for cap in self.cpp_class.captures_iter(content) {
+            let template = format_generics(cap.get(1).map(|m| m.as_str()), '<', '>');
+            let name = cap.get(2).map(|m| m.as_str()).unwrap_or("");
+            let bases = cap.get(3).map(|m| m.as_str().trim());
+            let whole = cap.get(0).unwrap();
+            let line = line_for_offset(newlines, content, whole.start());
+
+            let mut signature = if template.is_empty() {
+                format!("class {}", name)
+            } else {
+                format!("template{} class {}", template, name)
+            };
+            if let Some(bases) = bases {
+                signature.push_str(&format!(" : {}", bases));
+            }
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Class,
+                signature,
+            ));
+            bodies.push((name, whole.end() - 1));
+        }
+        for cap in self.c_struct.captures_iter(content) {
+            let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
+            bodies.push((name, cap.get(0).unwrap().end() - 1));
+        }
+
+        for (class, open) in bodies {
+            let (start, end) = brace_block(content, open);
+            let body = &content[start..end];
+
+            for cap in self.cpp_member.captures_iter(body) {
+                let offset = cap.get(0).unwrap().start();
+                // Only direct members, not statements inside method bodies
+                if brace_depth(&body[..offset]) != 0 {
+                    continue;
+                }
+
+                let ret = cap.get(1).map(|m| m.as_str()).unwrap_or("");
+                let name = cap.get(2).map(|m| m.as_str()).unwrap_or("");
+                let params = cap.get(3).map(|m| m.as_str()).unwrap_or("");
+
+                let is_special = name == class || name.strip_prefix('~') == Some(class);
+                let ret_word = ret.split_whitespace().next().unwrap_or("");
+                if C_NON_FUNCTION_WORDS.contains(&name)
+                    || C_NON_FUNCTION_WORDS.contains(&ret_word)
+                    || (ret.is_empty() && !is_special)
+                {
+                    continue;
+                }
+
+                let qualified = format!("{}::{}", class, name);
+                let line = line_for_offset(newlines, content, start + offset);
+
+                symbols.push(
+                    Symbol::new(
+                        &file_path,
+                        name,
+                        line,
+                        SymbolKind::Method,
+                        c_signature(None, ret, &qualified, params),
+                    )
+                    .with_container(Some(class.to_string())),
+                );
+            }
+        }
+    }
+
+    fn parse_ruby(
+        &self,
+        path: &Path,
+        content: &str,
+        newlines: &[usize],
+        symbols: &mut Vec<Symbol>,
+        imports: &mut Vec<String>,
+    ) {
+        let file_path = path.to_string_lossy().to_string();
+
+        // Methods, including `def self.name` and `name?` / `name!` / `name=`
+        for cap in self.rb_def.captures_iter(content) {
+            let receiver = if cap.get(1).is_some() { "self." } else { "" };
+            let name = cap.get(2).map(|m| m.as_str()).unwrap_or("");
+            let params = cap
+                .get(3)
+                .or(cap.get(4))
+                .map(|m| m.as_str().trim())
+                .unwrap_or("");
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Function,
+                format!("def {}{}({})", receiver, name, simplify_params(params)),
+            ));
+        }
+
+        // Classes
+        for cap in self.rb_class.captures_iter(content) {
+            let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
+            let parent = cap.get(2).map(|m| m.as_str());
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            let signature = if let Some(parent) = parent {
+                format!("class {} < {}", name, parent)
+            } else {
+                format!("class {}", name)
+            };
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Class,
+                signature,
+            ));
+        }
+
+        // Modules
+        for cap in self.rb_module.captures_iter(content) {
+            let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Module,
+                format!("module {}", name),
+            ));
+        }
+
+        // Imports
+        for cap in self.rb_require.captures_iter(content) {
+            if let Some(m) = cap.get(1) {
+                imports.push(m.as_str().to_string());
+            }
+        }
+    }
+
+    fn parse_php(
+        &self,
+        path: &Path,
+        content: &str,
+        newlines: &[usize],
+        symbols: &mut Vec<Symbol>,
+        imports: &mut Vec<String>,
+    ) {
+        let file_path = path.to_string_lossy().to_string();
+
+        // Functions and methods
+        for cap in self.php_fn.captures_iter(content) {
+            let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
+            let params = cap.get(2).map(|m| m.as_str()).unwrap_or("");
+            let ret = cap.get(3).map(|m| m.as_str());
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            let signature = match ret {
+                Some(ret) => format!("function {}({}): {}", name, simplify_params(params), ret),
+                None => format!("function {}({})", name, simplify_params(params)),
+            };
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Function,
+                signature,
+            ));
+        }
+
+        // Classes
+        for cap in self.php_class.captures_iter(content) {
+            let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
+            let extends = cap.get(2).map(|m| m.as_str());
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            let signature = if let Some(parent) = extends {
+                format!("class {} extends {}", name, parent)
+            } else {
+                format!("class {}", name)
+            };
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Class,
+                signature,
+            ));
+        }
+
+        // Interfaces
+        for cap in self.php_interface.captures_iter(content) {
+            let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Interface,
+                format!("interface {}", name),
+            ));
+        }
+
+        // Traits
+        for cap in self.php_trait.captures_iter(content) {
+            let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Trait,
+                format!("trait {}", name),
+            ));
+        }
+
+        // Namespaces
+        for cap in self.php_namespace.captures_iter(content) {
+            let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Module,
+                format!("namespace {}", name),
+            ));
+        }
+
+        // Imports
+        for cap in self.php_use.captures_iter(content) {
+            if let Some(m) = cap.get(1) {
+                imports.push(m.as_str().to_string());
+            }
+        }
+    }
+
+    fn parse_swift(
+        &self,
+        path: &Path,
+        content: &str,
+        newlines: &[usize],
+        symbols: &mut Vec<Symbol>,
+        imports: &mut Vec<String>,
+    ) {
+        let file_path = path.to_string_lossy().to_string();
+
+        // Functions; parameter labels (`from source: Int`) are kept as written
+        for cap in self.swift_fn.captures_iter(content) {
+            let name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
+            let generics = format_generics(cap.get(2).map(|m| m.as_str()), '<', '>');
+            let params = cap.get(3).map(|m| m.as_str()).unwrap_or("");
+            let ret = cap.get(4).map(|m| m.as_str().trim());
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            let mut signature = format!("func {}{}({})", name, generics, simplify_params(params));
+            if let Some(ret) = ret {
+                signature.push_str(&format!(" -> {}", ret));
+            }
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Function,
+                signature,
+            ));
+        }
+
+        // Classes, structs, enums, protocols and extensions
+        for cap in self.swift_type.captures_iter(content) {
+            let keyword = cap.get(1).map(|m| m.as_str()).unwrap_or("");
+            let name = cap.get(2).map(|m| m.as_str()).unwrap_or("");
+            let generics = format_generics(cap.get(3).map(|m| m.as_str()), '<', '>');
+            let conformances = cap.get(4).map(|m| m.as_str().trim());
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            let kind = match keyword {
+                "struct" => SymbolKind::Struct,
+                "enum" => SymbolKind::Enum,
+                "protocol" => SymbolKind::Interface,
+                "extension" => SymbolKind::Type,
+                _ => SymbolKind::Class,
+            };
+
+            let mut signature = format!("{} {}{}", keyword, name, generics);
+            if let Some(conformances) = conformances {
+                signature.push_str(&format!(": {}", conformances));
+            }
+
+            symbols.push(Symbol::new(&file_path, name, line, kind, signature));
+        }
+
+        // Imports
+        for cap in self.swift_import.captures_iter(content) {
+            if let Some(m) = cap.get(1) {
+                imports.push(m.as_str().to_string());
+            }
+        }
+    }
+
+    fn parse_kotlin(
+        &self,
+        path: &Path,
+        content: &str,
+        newlines: &[usize],
+        symbols: &mut Vec<Symbol>,
+        imports: &mut Vec<String>,
+    ) {
+        let file_path = path.to_string_lossy().to_string();
+
+        // Functions, including extension functions (`fun String.shout()`)
+        for cap in self.kt_fn.captures_iter(content) {
+            let generics = format_generics(cap.get(2).map(|m| m.as_str()), '<', '>');
+            let receiver = cap.get(3).map(|m| format!("{}.", m.as_str().trim()));
+            let name = cap.get(4).map(|m| m.as_str()).unwrap_or("");
+            let params = cap.get(5).map(|m| m.as_str()).unwrap_or("");
+            let ret = cap.get(6).map(|m| m.as_str().trim());
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            let generics = if generics.is_empty() {
+                generics
+            } else {
+                format!("{} ", generics)
+            };
+            let mut signature = format!(
+                "fun {}{}{}({})",
+                generics,
+                receiver.unwrap_or_default(),
+                name,
+                simplify_params(params)
+            );
+            if let Some(ret) = ret {
+                signature.push_str(&format!(": {}", ret));
+            }
+
+            symbols.push(Symbol::new(
+                &file_path,
+                name,
+                line,
+                SymbolKind::Function,
+                signature,
+            ));
+        }
+
+        // Classes, data/enum classes, interfaces and objects
+        for cap in self.kt_class.captures_iter(content) {
+            let modifiers: Vec<&str> = cap
+                .get(1)
+                .map(|m| m.as_str())
+                .unwrap_or("")
+                .split_whitespace()
+                .filter(|m| !matches!(*m, "public" | "private" | "protected" | "internal"))
+                .collect();
+            let keyword = cap.get(2).map(|m| m.as_str()).unwrap_or("");
+            let name = cap.get(3).map(|m| m.as_str()).unwrap_or("");
+            let generics = format_generics(cap.get(4).map(|m| m.as_str()), '<', '>');
+            let line = line_for_offset(newlines, content, cap.get(0).unwrap().start());
+
+            let kind = match keyword {
+                "interface" => SymbolKind::Interface,
+                _ if modifiers.contains(&"enum") => SymbolKind::Enum,
+                _ => SymbolKind::Class,
+            };
+
+            let mut signature = modifiers.join(" ");
+            if !signature.is_empty() {
+                signature.push(' ');
+            }
+            signature.push_str(&format!("{} {}{}", keyword, name, generics));
+
+            symbols.push(Symbol::new(&file_path, name, line, kind, signature));
+        }
+
+        // Imports
+        for cap in self.kt_import.captures_iter(content) {
+            if let Some(m) = cap.get(1) {
+                imports.push(m.as_str().to_string());
+            }
+        }
+    }
+
+    /// Package doc comment (the `//` block directly above `package`), first sentence
+    fn go_package_doc(&self, lines: &[&str]) -> String {
+        let package_line = match lines.iter().position(|l| self.go_package.is_match(l)) {
+            Some(i) => i,
+            None => return String::new(),
+        };
+
+        let mut doc_lines = Vec::new();
+        for line in lines[..package_line].iter().rev() {
+            match line.trim().strip_prefix("//") {
+                Some(text) => doc_lines.push(text.trim()),
+                None => break,
+            }
+        }
+        doc_lines.reverse();
+
+        first_sentence(&doc_lines.join(" "))
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ParsedFile {
+    pub path: String,
+    pub language: String,
+    pub symbols: Vec<Symbol>,
+    pub imports: Vec<String>,
+    /// File-level description (e.g. Go package doc comment)
+    pub summary: String,
+    /// `impl Trait for Type` blocks (Rust)
+    pub trait_impls: Vec<TraitImpl>,
+    pub lines: usize,
+}
+
+fn detect_language(path: &Path) -> String {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+
+    match ext {
+        "rs" => "rust",
+        // `.d.ts` declaration files end in `.ts` too
+        "ts" | "tsx" | "mts" | "cts" => "typescript",
+        "js" | "jsx" | "mjs" => "javascript",
+        "py" | "pyi" => "python",
+        "go" => "go",
+        "java" => "java",
+        "cpp" | "cc" | "cxx" | "hpp" | "h" => "cpp",
+        "c" => "c",
+        "rb" => "ruby",
+        "php" => "php",
+        "swift" => "swift",
+        "kt" | "kts" => "kotlin",
+        "scala" => "scala",
+        "zig" => "zig",
+        _ => "unknown",
+    }
+    .to_string()
+}
+
+/// Comment and string syntax of a language, as far as masking needs it
+struct LexSyntax {
+    line_comments: &'static [&'static str],
+    block_comments: bool,
+    nested_blocks: bool,
+    triple_quotes: &'static [&'static str],
+    quotes: &'static [u8],
+    /// Whether `"`/`'` strings may run past the end of a line
+    multiline_quotes: bool,
+    /// `'x'` is a char literal, so a lone `'` (Rust lifetime, C++ digit
+    /// separator) is left alone
+    char_literals: bool,
+}
+
+fn lex_syntax(lang: &str) -> Option<LexSyntax> {
+    let c_like = LexSyntax {
+        line_comments: &["//"],
+        block_comments: true,
+        nested_blocks: false,
+        triple_quotes: &[],
+        quotes: b"\"",
+        multiline_quotes: false,
+        char_literals: true,
+    };
+
+    let syntax = match lang {
+        "rust" => LexSyntax {
+            nested_blocks: true,
+            multiline_quotes: true,
+            ..c_like
+        },
+        "typescript" | "javascript" => LexSyntax {
+            quotes: b"\"'`",
+            char_literals: false,
+            ..c_like
+        },
+        "go" => LexSyntax {
+            quotes: b"\"`",
+            ..c_like
+        },
+        "java" => LexSyntax {
+            triple_quotes: &["\"\"\""],
+            ..c_like
+        },
+        "c" | "cpp" => c_like,
+        "swift" => LexSyntax {
+            nested_blocks: true,
+            triple_quotes: &["\"\"\""],
+            char_literals: false,
+            ..c_like
+        },
+        "kotlin" => LexSyntax {
+            nested_blocks: true,
+            triple_quotes: &["\"\"\""],
+            ..c_like
+        },
+        "python" => LexSyntax {
+            line_comments: &["#"],
+            block_comments: false,
+            triple_quotes: &["\"\"\"", "'''"],
+            quotes: b"\"'",
+            char_literals: false,
+            ..c_like
+        },
+        "ruby" => LexSyntax {
+            line_comments: &["#"],
+            block_comments: false,
+            quotes: b"\"'`",
+            multiline_quotes: true,
+            char_literals: false,
+            ..c_like
+        },
+        "php" => LexSyntax {
+            line_comments: &["//", "#"],
+            quotes: b"\"'",
+            multiline_quotes: true,
+            char_literals: false,
+            ..c_like
+        },
+        _ => return None,
+    };
+    Some(syntax)
+}
+
+/// Words whose string argument names a module (`from "x"`, `#include "x.h"`,
+/// `require 'x'`); those strings keep their contents so imports still parse
+const IMPORT_WORDS: &[&str] = &["import", "from", "require", "require_relative", "include"];
+
+/// Blank out comments and string contents with spaces
+///
+/// Byte offsets and newlines are preserved, so positions found in the masked
+/// text (and `line_for_offset`) line up with the original. String
+/// delimiters stay in place; only their contents go.
+pub fn mask_source(content: &str, lang: &str) -> String {
+    let Some(syntax) = lex_syntax(lang) else {
+        return content.to_string();
+    };
+
+    let bytes = content.as_bytes();
+    let mut out = bytes.to_vec();
+    let mut blank = |range: std::ops::Range<usize>| {
+        for b in &mut out[range] {
+            if *b != b'\n' && *b != b'\r' {
+                *b = b' ';
+            }
+        }
+    };
+
+    let n = bytes.len();
+    let mut i = 0;
+    'scan: while i < n {
+        let rest = &bytes[i..];
+
+        for prefix in syntax.line_comments {
+            // PHP 8 attributes look like `#` comments
+            if rest.starts_with(prefix.as_bytes()) && !(lang == "php" && rest.starts_with(b"#[")) {
+                let end = line_end(bytes, i);
+                blank(i..end);
+                i = end;
+                continue 'scan;
+            }
+        }
+
+        if syntax.block_comments && rest.starts_with(b"/*") {
+            let mut depth = 1;
+            let mut j = i + 2;
+            while j < n && depth > 0 {
+                if syntax.nested_blocks && bytes[j..].starts_with(b"/*") {
+                    depth += 1;
+                    j += 2;
+                } else if bytes[j..].starts_with(b"*/") {
+                    depth -= 1;
+                    j += 2;
+                } else {
+                    j += 1;
+                }
+            }
+            blank(i..j);
+            i = j;
+            continue;
+        }
+
+        if lang == "rust" {
+            if let Some((open, close)) = rust_raw_string(bytes, i) {
+                let end = find_from(bytes, open, &close).unwrap_or(n);
+                blank(open..end);
+                i = (end + close.len()).min(n);
+                continue;
+            }
+        }
+
+        for quote in syntax.triple_quotes {
+            if rest.starts_with(quote.as_bytes()) {
+                let open = i + quote.len();
+                let end = find_from(bytes, open, quote.as_bytes()).unwrap_or(n);
+                blank(open..end);
+                i = (end + quote.len()).min(n);
+                continue 'scan;
+            }
+        }
+
+        let b = bytes[i];
+        if syntax.quotes.contains(&b) {
+            let multiline = syntax.multiline_quotes || b == b'`';
+            let mut j = i + 1;
+            while j < n && bytes[j] != b {
+                if bytes[j] == b'\\' {
+                    j += 1;
+                } else if bytes[j] == b'\n' && !multiline {
+                    break;
+                }
+                j += 1;
+            }
+            let end = j.min(n);
+            if !follows_import_word(bytes, i) {
+                blank(i + 1..end);
+            }
+            i = end + 1;
+            continue;
+        }
+
+        if b == b'\'' && syntax.char_literals {
+            if let Some(len) = char_literal_len(bytes, i) {
+                blank(i + 1..i + len - 1);
+                i += len;
+                continue;
+            }
+        }
+
+        i += 1;
+    }
+
+    String::from_utf8(out).unwrap_or_else(|_| content.to_string())
+}
+
+fn line_end(bytes: &[u8], from: usize) -> usize {
+    bytes[from..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map_or(bytes.len(), |p| from + p)
+}
+
+fn find_from(bytes: &[u8], from: usize, needle: &[u8]) -> Option<usize> {
+    bytes
+        .get(from..)?
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|p| from + p)
+}
+
+/// `r"..."` / `br#"..."#` at `i`: returns (content start, closing delimiter)
+fn rust_raw_string(bytes: &[u8], i: usize) -> Option<(usize, Vec<u8>)> {
+    let start = if bytes.get(i) == Some(&b'b') {
+        i + 1
+    } else {
+        i
+    };
+    if bytes.get(start) != Some(&b'r') {
+        return None;
+    }
+    if i > 0 && (bytes[i - 1].is_ascii_alphanumeric() || bytes[i - 1] == b'_') {
+        return None;
+    }
+
+    let hashes = bytes[start + 1..]
+        .iter()
+        .take_while(|&&b| b == b'#')
+        .count();
+    if bytes.get(start + 1 + hashes) != Some(&b'"') {
+        return None;
+    }
+
+    let mut close = vec![b'"'];
+    close.extend(std::iter::repeat_n(b'#', hashes));
+    Some((start + 2 + hashes, close))
+}
+
+/// Length of the char literal starting at `i` (`'a'`, `'\n'`, `'é'`), if any
+fn char_literal_len(bytes: &[u8], i: usize) -> Option<usize> {
+    let first = *bytes.get(i + 1)?;
+    if first == b'\\' {
+        // Escapes are short: `'\u{1F600}'` is the longest
+        let close = bytes[i + 2..]
+            .iter()
+            .take(10)
+            .position(|&b| b == b'\'' || b == b'\n')?;
+        return (bytes[i + 2 + close] == b'\'').then_some(close + 3);
+    }
+    if first == b'\'' || first == b'\n' {
+        return None;
+    }
+
+    let width = match first {
+        0xF0.. => 4,
+        0xE0.. => 3,
+        0xC0.. => 2,
+        _ => 1,
+    };
+    (bytes.get(i + 1 + width) == Some(&b'\'')).then_some(width + 2)
+}
+
+/// Whether the string opening at `i` is the argument of an import word
+fn follows_import_word(bytes: &[u8], i: usize) -> bool {
+    let before = &bytes[..i];
+    let end = before
+        .iter()
+        .rposition(|&b| !matches!(b, b' ' | b'\t' | b'('))
+        .map_or(0, |p| p + 1);
+    let start = before[..end]
+        .iter()
+        .rposition(|&b| !(b.is_ascii_alphanumeric() || b == b'_'))
+        .map_or(0, |p| p + 1);
+
+    std::str::from_utf8(&before[start..end]).is_ok_and(|word| IMPORT_WORDS.contains(&word))
+}
+
+fn is_go_exported(name: &str) -> bool {
+    name.chars().next().is_some_and(|c| c.is_ascii_uppercase())
+}
+
+/// `template<typename T> T max_of(T a, T b)`, `char *dup(const char *s)`
+fn c_signature(template: Option<&str>, ret: &str, name: &str, params: &str) -> String {
+    let template = format_generics(template, '<', '>');
+    let prefix = if template.is_empty() {
+        String::new()
+    } else {
+        format!("template{} ", template)
+    };
+
+    let ret = ret.split_whitespace().collect::<Vec<_>>().join(" ");
+    let sep = if ret.is_empty() || ret.ends_with('*') || ret.ends_with('&') {
+        ""
+    } else {
+        " "
+    };
+
+    format!(
+        "{}{}{}{}({})",
+        prefix,
+        ret,
+        sep,
+        name,
+        simplify_params(params)
+    )
+}
+
+/// Byte range inside the `{ ... }` block whose opening brace is at `open`
+fn brace_block(content: &str, open: usize) -> (usize, usize) {
+    let mut depth = 0usize;
+    for (i, c) in content[open..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return (open + 1, open + i);
+                }
+            }
+            _ => {}
+        }
+    }
+    (open + 1, content.len())
+}
+
+/// Lines that carry on a declaration whose head ended on the previous line
+const DECL_CONTINUATIONS: &[&str] = &["{", "where", "->", "extends", "implements", "throws"];
+
+/// Last line of the symbol declared on `line`, or `line` when it has no body
+///
+/// `content` is the masked source. Python bodies end where indentation
+/// drops back, Ruby bodies at the `end` aligned with the declaration, and
+/// everything else at the brace matching the first `{` after the head.
+fn symbol_end_line(lang: &str, content: &str, newlines: &[usize], line: usize) -> usize {
+    match lang {
+        "python" => indent_end_line(content, line),
+        "ruby" => ruby_end_line(content, line),
+        _ => brace_end_line(content, newlines, line),
+    }
+}
+
+fn brace_end_line(content: &str, newlines: &[usize], line: usize) -> usize {
+    let line_of = |offset: usize| newlines.partition_point(|&nl| nl < offset) + 1;
+    let bytes = content.as_bytes();
+    let head_start = if line > 1 { newlines[line - 2] + 1 } else { 0 };
+    let mut line_start = head_start;
+    let mut parens = 0isize;
+
+    for i in head_start..bytes.len() {
+        match bytes[i] {
+            b'(' | b'[' => parens += 1,
+            b')' | b']' => parens -= 1,
+            b'{' => return line_of(brace_block(content, i).1),
+            b';' if parens <= 0 => return line_of(i),
+            b'\n' if parens <= 0 => {
+                // Attributes, annotations and templates sit on their own line;
+                // `where` clauses and bound lists wrap with trailing commas
+                let current = content[line_start..i].trim();
+                let next = content[i + 1..].trim_start();
+                let head_continues = current.starts_with('@')
+                    || current.starts_with("#[")
+                    || current.starts_with("template")
+                    || current.starts_with("where")
+                    || current.ends_with(',')
+                    || DECL_CONTINUATIONS.iter().any(|c| next.starts_with(c));
+                if !head_continues {
+                    return line_of(i);
+                }
+                line_start = i + 1;
+            }
+            b'\n' => line_start = i + 1,
+            _ => {}
+        }
+    }
+    line
+}
+
+fn indent_of(text: &str) -> usize {
+    text.len() - text.trim_start().len()
+}
+
+/// Net `()`/`[]`/`{}` nesting at the end of `text`
+fn bracket_depth(text: &str) -> isize {
+    text.chars().fold(0, |depth, c| match c {
+        '(' | '[' | '{' => depth + 1,
+        ')' | ']' | '}' => depth - 1,
+        _ => depth,
+    })
+}
+
+fn indent_end_line(content: &str, line: usize) -> usize {
+    let mut lines = content.lines().enumerate().skip(line - 1);
+    let Some((_, head)) = lines.next() else {
+        return line;
+    };
+    let base = indent_of(head);
+
+    // A wrapped signature ends where its brackets balance
+    let mut depth = bracket_depth(head);
+    let mut end = line;
+    for (i, text) in lines {
+        if depth > 0 {
+            depth += bracket_depth(text);
+            end = i + 1;
+            continue;
+        }
+        if text.trim().is_empty() {
+            continue;
+        }
+        if indent_of(text) <= base {
+            break;
+        }
+        end = i + 1;
+    }
+    end
+}
+
+fn ruby_end_line(content: &str, line: usize) -> usize {
+    let mut lines = content.lines().enumerate().skip(line - 1);
+    let Some((_, head)) = lines.next() else {
+        return line;
+    };
+    let base = indent_of(head);
+
+    for (i, text) in lines {
+        let trimmed = text.trim();
+        if trimmed.is_empty() || indent_of(text) > base {
+            continue;
+        }
+        let closes = trimmed
+            .strip_prefix("end")
+            .is_some_and(|rest| !rest.starts_with(|c: char| c.is_alphanumeric() || c == '_'));
+        // One-liners (`def x = 1`, `def x; end`) are followed by a sibling
+        return if closes && indent_of(text) == base {
+            i + 1
+        } else {
+            line
+        };
+    }
+    line
+}
+
+/// Net brace nesting at the end of `text`
+fn brace_depth(text: &str) -> isize {
+    text.chars().fold(0, |depth, c| match c {
+        '{' => depth + 1,
+        '}' => depth - 1,
+        _ => depth,
+    })
+}
+
+fn is_java_modifier(word: &str) -> bool {
+    matches!(
+        word,
+        "public" | "protected" | "private" | "static" | "final" | "abstract"
+    )
+}
+
+fn go_value_symbol(file_path: &str, keyword: &str, name: &str, ty: &str, line: usize) -> Symbol {
+    let (kind, signature) = match (keyword, ty.is_empty()) {
+        ("var", true) => (SymbolKind::Var, format!("var {}", name)),
+        ("var", false) => (SymbolKind::Var, format!("var {} {}", name, ty)),
+        (_, true) => (SymbolKind::Const, format!("const {}", name)),
+        (_, false) => (SymbolKind::Const, format!("const {} {}", name, ty)),
+    };
+
+    Symbol::new(file_path, name, line, kind, signature)
+}
+
+/// Visibility from the modifiers on a symbol's declaration line
+///
+/// Rust, TypeScript, Java, Kotlin, Swift, PHP, and C read keywords (each with
+/// its language's default); Go and Python go by the name's spelling.
+fn symbol_visibility(lang: &str, lines: &[&str], symbol: &Symbol) -> Visibility {
+    let decl = lines
+        .get(symbol.line.saturating_sub(1))
+        .map(|l| l.trim())
+        .unwrap_or("");
+    let has = |word: &str| decl.split_whitespace().any(|w| w == word);
+
+    match lang {
+        "rust" => {
+            let exported_macro = || {
+                lines[..symbol.line.saturating_sub(1).min(lines.len())]
+                    .iter()
+                    .rev()
+                    .map(|l| l.trim())
+                    .take_while(|l| l.starts_with("#["))
+                    .any(|l| l.starts_with("#[macro_export"))
+            };
+            if decl.starts_with("pub(self)") {
+                Visibility::Private
+            } else if decl.starts_with("pub(") {
+                Visibility::Crate
+            } else if decl.starts_with("pub ") || exported_macro() {
+                Visibility::Public
+            } else {
+                Visibility::Private
+            }
+        }
+        "typescript" | "javascript" if symbol.container.is_some() => {
+            // Class members are public unless marked otherwise
+            if has("private") || symbol.name.starts_with('#') {
+                Visibility::Private
+            } else {
+                Visibility::Public
+            }
+        }
+        "typescript" | "javascript" => {
+            if decl.starts_with("export") || symbol.kind == SymbolKind::Export {
+                Visibility::Public
+            } else {
+                Visibility::Private
+            }
+        }
+        "go" => {
+            if is_go_exported(&symbol.name) {
+                Visibility::Public
+            } else {
+                Visibility::Private
+            }
+        }
+        "python" => {
+            let dunder = symbol.name.starts_with("__") && symbol.name.ends_with("__");
+            if symbol.name.starts_with('_') && !dunder {
+                Visibility::Private
+            } else {
+                Visibility::Public
+            }
+        }
+        "java" | "kotlin" | "swift" | "php" => {
+            if has("private") || has("fileprivate") {
+                Visibility::Private
+            } else if has("internal") {
+                Visibility::Crate
+            } else if has("public") || has("open") || has("protected") {
+                Visibility::Public
+            } else if matches!(lang, "java" | "swift") {
+                // Package-private / `internal` by default
+                Visibility::Crate
+            } else {
+                Visibility::Public
+            }
+        }
+        "c" => {
+            if has("static") {
+                Visibility::Private
+            } else {
+                Visibility::Public
+            }
+        }
+        _ => Visibility::Unknown,
+    }
+}
+
+/// First sentence of the doc comment attached to the symbol on `line`
+///
+/// Python reads the docstring opening the body; other languages read the
+/// comment block directly above the declaration (skipping attributes and
+/// annotations): `///` or `/** */`, `//` for Go, `#` for Ruby.
+fn doc_summary(lang: &str, lines: &[&str], line: usize, end_line: usize) -> String {
+    let text = match lang {
+        "python" => python_docstring(lines, line, end_line),
+        _ => leading_comment(lang, lines, line),
+    };
+    first_sentence(&text)
+}
+
+fn leading_comment(lang: &str, lines: &[&str], line: usize) -> String {
+    let above = lines[..line.saturating_sub(1).min(lines.len())]
+        .iter()
+        .rev()
+        .map(|l| l.trim())
+        .skip_while(|l| l.starts_with("#[") || l.starts_with('@'));
+
+    let mut doc: Vec<&str> = Vec::new();
+    let mut in_block = false;
+    for text in above {
+        if in_block || text.ends_with("*/") {
+            // `/** ... */`, read bottom-up
+            let body = text.trim_end_matches("*/");
+            if let Some(first) = body.strip_prefix("/**") {
+                doc.push(first.trim());
+                break;
+            }
+            if body.starts_with("/*") {
+                // Plain block comment, not documentation
+                return String::new();
+            }
+            doc.push(body.trim_start_matches('*').trim());
+            in_block = true;
+            continue;
+        }
+
+        let prefix = match lang {
+            "go" => "//",
+            "ruby" => "#",
+            _ => "///",
+        };
+        match text.strip_prefix(prefix) {
+            Some(rest) if !rest.starts_with('/') || lang == "go" => doc.push(rest.trim()),
+            _ => break,
+        }
+    }
+    doc.reverse();
+    doc.retain(|l| !l.is_empty());
+    doc.join(" ")
+}
+
+/// Decorators stacked above the `def`/`class` on `line`, top to bottom
+///
+/// A decorator wrapped over several lines is joined onto one, with its
+/// whitespace collapsed.
+fn python_decorators(lines: &[&str], line: usize) -> Vec<String> {
+    let mut decorators = Vec::new();
+    let mut pending: Vec<&str> = Vec::new();
+    let mut depth = 0;
+
+    for text in lines[..line.saturating_sub(1).min(lines.len())]
+        .iter()
+        .rev()
+        .map(|l| l.trim())
+    {
+        pending.push(text);
+        depth += bracket_depth(text);
+        if depth < 0 {
+            // Inside a wrapped decorator's arguments, still reading upward
+            continue;
+        }
+        if depth > 0 || !text.starts_with('@') {
+            break;
+        }
+
+        let joined = pending.drain(..).rev().collect::<Vec<_>>().join(" ");
+        decorators.push(joined.split_whitespace().collect::<Vec<_>>().join(" "));
+        depth = 0;
+    }
+
+    decorators.reverse();
+    decorators
+}
+
+fn python_docstring(lines: &[&str], line: usize, end_line: usize) -> String {
+    let end = end_line.min(lines.len());
+    let mut i = line.saturating_sub(1);
+
+    // Skip the (possibly wrapped) `def`/`class` header
+    let mut depth = 0;
+    while i < end {
+        depth += bracket_depth(lines[i]);
+        i += 1;
+        if depth <= 0 && lines[i - 1].trim_end().ends_with(':') {
+            break;
+        }
+    }
+
+    let Some(first) = lines[i..end].iter().position(|l| !l.trim().is_empty()) else {
+        return String::new();
+    };
+    let opening = lines[i + first]
+        .trim()
+        .trim_start_matches(['r', 'u', 'R', 'U']);
+    let Some(quote) = ["\"\"\"", "'''"]
+        .into_iter()
+        .find(|q| opening.starts_with(q))
+    else {
+        return String::new();
+    };
+
+    let mut doc = Vec::new();
+    let mut text = &opening[quote.len()..];
+    let mut rest = lines[i + first + 1..end].iter();
+    loop {
+        if let Some(close) = text.find(quote) {
+            doc.push(text[..close].trim());
+            break;
+        }
+        doc.push(text.trim());
+        match rest.next() {
+            Some(next) => text = next,
+            None => break,
+        }
+    }
+
+    doc.retain(|l| !l.is_empty());
+    doc.join(" ")
+}
+
+/// First sentence of a comment, trimmed to ~120 chars
+fn first_sentence(text: &str) -> String {
+    let text = text.trim();
+    let sentence = match text.find(". ") {
+        Some(pos) => &text[..=pos],
+        None => text,
+    };
+
+    if sentence.chars().count() > 120 {
+        let truncated: String = sentence.chars().take(117).collect();
+        format!("{}...", truncated.trim_end())
+    } else {
+        sentence.to_string()
+    }
+}
+
+/// 1-based line of `offset`, given the sorted newline offsets of `content`
+fn line_for_offset(newlines: &[usize], content: &str, offset: usize) -> usize {
+    // Patterns start with `^\s*`, which can swallow blank (or masked) lines
+    let rest = &content[offset..];
+    let offset = offset + rest.len() - rest.trim_start().len();
+    newlines.partition_point(|&nl| nl < offset) + 1
+}
+
+fn simplify_params(params: &str) -> String {
+    // Wrapped parameter lists collapse onto one line, minus any trailing comma.
+    // Nothing else is dropped: listings shorten them with `compact_signature`
+    let params = params.split_whitespace().collect::<Vec<_>>().join(" ");
+    params.trim_end_matches(',').to_string()
+}
+
+/// Shorten a signature for compact listings without losing any parameter
+///
+/// Every parameter in each top-level `(...)` group keeps its name and type.
+/// Only a default value longer than `PARAM_DEFAULT_MAX_LEN` becomes `= ...`,
+/// and a parameter still longer than `PARAM_MAX_LEN` has its generic
+/// arguments collapsed (`HashMap<...>`).
+pub fn compact_signature(signature: &str) -> String {
+    let mut out = String::with_capacity(signature.len());
+    let mut depth = 0usize;
+    let mut start = 0;
+
+    for (i, c) in signature.char_indices() {
+        match c {
+            '(' if depth == 0 => {
+                out.push(c);
+                start = i + 1;
+                depth = 1;
+            }
+            '(' => depth += 1,
+            ')' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    out.push_str(&compact_params(&signature[start..i]));
+                    out.push(c);
+                }
+            }
+            _ if depth == 0 => out.push(c),
+            _ => {}
+        }
+    }
+    if depth > 0 {
+        // Unbalanced (truncated) signature: keep the rest as-is
+        out.push_str(&signature[start..]);
+    }
+    out
+}
+
+fn compact_params(params: &str) -> String {
+    let parts = split_top_level(params);
+    let compacted: Vec<String> = parts.iter().map(|p| compact_param(p.trim())).collect();
+    if parts.iter().zip(&compacted).all(|(p, c)| p.trim() == c) {
+        return params.to_string();
+    }
+    compacted.join(", ")
+}
+
+/// `opts: Options = Options::default()` -> `opts: Options = ...`,
+/// `cache: HashMap<String, Vec<Entry>>` (when long) -> `cache: HashMap<...>`
+fn compact_param(param: &str) -> String {
+    let mut param = param.to_string();
+    if let Some(eq) = default_separator(&param) {
+        if param[eq + 1..].trim().len() > PARAM_DEFAULT_MAX_LEN {
+            param = format!("{} = ...", param[..eq].trim_end());
+        }
+    }
+    if param.len() > PARAM_MAX_LEN {
+        param = collapse_generic_args(&param);
+    }
+    param
+}
+
+/// Byte offset of the `=` introducing a parameter's default value, if any
+fn default_separator(param: &str) -> Option<usize> {
+    let bytes = param.as_bytes();
+    let mut depth = 0usize;
+    for (i, &b) in bytes.iter().enumerate() {
+        let prev = if i > 0 { bytes[i - 1] } else { b' ' };
+        match b {
+            b'<' | b'(' | b'[' | b'{' => depth += 1,
+            b'>' if prev == b'-' || prev == b'=' => {}
+            b'>' | b')' | b']' | b'}' => depth = depth.saturating_sub(1),
+            b'=' if depth == 0
+                && !matches!(prev, b'=' | b'!' | b'<' | b'>')
+                && !matches!(bytes.get(i + 1), Some(b'=' | b'>')) =>
+            {
+                return Some(i);
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Replace the contents of outermost `<...>` / `[...]` with `...`
+fn collapse_generic_args(param: &str) -> String {
+    let mut out = String::with_capacity(param.len());
+    let mut depth = 0usize;
+    let mut start = 0;
+    let mut prev = ' ';
+
+    for (i, c) in param.char_indices() {
+        match c {
+            '<' | '[' if depth == 0 => {
+                out.push(c);
+                start = i + 1;
+                depth = 1;
+            }
+            '<' | '[' => depth += 1,
+            // `->` and `=>` in closure types are not closing brackets
+            '>' | ']' if depth > 0 && !(c == '>' && matches!(prev, '-' | '=')) => {
+                depth -= 1;
+                if depth == 0 {
+                    if !param[start..i].trim().is_empty() {
+                        out.push_str("...");
+                    }
+                    out.push(c);
+                }
+            }
+            _ if depth == 0 => out.push(c),
+            _ => {}
+        }
+        prev = c;
+    }
+    if depth > 0 {
+        out.push_str(&param[start..]);
+    }
+    out
+}
+
+/// Render a captured generic parameter list for a signature
+///
+/// Shared by every language so signatures search the same way: bounds are
+/// kept (`<T: Clone>`, `[K comparable]`), whitespace is normalized, and a
+/// parameter longer than `GENERIC_PARAM_MAX_LEN` keeps its name but has its
+/// bound collapsed to `...`. Returns an empty string when there are none.
+fn format_generics(generics: Option<&str>, open: char, close: char) -> String {
+    let params: Vec<String> = split_top_level(generics.unwrap_or(""))
+        .into_iter()
+        .map(|p| p.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            if p.len() > GENERIC_PARAM_MAX_LEN {
+                collapse_bound(&p)
+            } else {
+                p
+            }
+        })
+        .collect();
+
+    if params.is_empty() {
+        return String::new();
+    }
+    format!("{}{}{}", open, params.join(", "), close)
+}
+
+/// `T: Into<String> + Send` -> `T: ...`, `T extends Foo` -> `T extends ...`,
+/// `T interface{ ~int }` -> `T ...`
+fn collapse_bound(param: &str) -> String {
+    if let Some((name, _)) = param.split_once(':') {
+        format!("{}: ...", name.trim_end())
+    } else if let Some((name, _)) = param.split_once(" extends ") {
+        format!("{} extends ...", name)
+    } else if let Some((name, _)) = param.split_once(' ') {
+        format!("{} ...", name)
+    } else {
+        param.to_string()
+    }
+}
+
+/// Split on commas that are not nested inside brackets of any kind
+fn split_top_level(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    let mut prev = ' ';
+
+    for (i, c) in s.char_indices() {
+        match c {
+            '<' | '(' | '[' | '{' => depth += 1,
+            // `->` in closure bounds is not a closing bracket
+            '>' if prev == '-' => {}
+            '>' | ')' | ']' | '}' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+        prev = c;
+    }
+    parts.push(&s[start..]);
+
+    parts.into_iter().filter(|p| !p.trim().is_empty()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(file: &str, content: &str) -> ParsedFile {
+        SymbolParser::new()
+            .unwrap()
+            .parse_file(Path::new(file), content)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_go_package_doc_and_consts() {
+        let code = r#"// Package cache implements an in-memory LRU cache. It is safe
+// for concurrent use.
+package cache
+
+const DefaultSize = 128
+
+const (
+	MaxEntries = 1024
+	minEntries = 1
+	Version string = "1.0"
+	KindA, KindB = 1, 2
+)
+
+var (
+	ErrMiss = errors.New("miss")
+)
+"#;
+        let parsed = parse("cache.go", code);
+        assert_eq!(
+            parsed.summary,
+            "Package cache implements an in-memory LRU cache."
+        );
+
+        let names: Vec<&str> = parsed.symbols.iter().map(|s| s.name.as_str()).collect();
+        for expected in [
+            "DefaultSize",
+            "MaxEntries",
+            "Version",
+            "KindA",
+            "KindB",
+            "ErrMiss",
+        ] {
+            assert!(names.contains(&expected), "missing {}", expected);
+        }
+        assert!(!names.contains(&"minEntries"));
+
+        let version = parsed.symbols.iter().find(|s| s.name == "Version").unwrap();
+        assert_eq!(version.kind, SymbolKind::Const);
+        assert_eq!(version.signature, "const Version string");
+        assert_eq!(version.line, 10);
+
+        let err = parsed.symbols.iter().find(|s| s.name == "ErrMiss").unwrap();
+        assert_eq!(err.kind, SymbolKind::Var);
+    }
+
+    fn signature<'a>(parsed: &'a ParsedFile, name: &str) -> &'a str {
+        parsed
+            .symbols
+            .iter()
+            .find(|s| s.name == name)
+            .map(|s| s.signature.as_str())
+            .unwrap_or_else(|| panic!("missing symbol {}", name))
+    }
+
+    #[test]
+    fn test_rust_generic_signatures() {
+        let code = r#"
+pub fn convert<T: Into<String>, U>(value: T, other: HashMap<String, Vec<u8>>) -> Result<U> {
+}
+
+pub fn spawn<F: FnOnce() -> T + Send + 'static, T>(f: F) -> JoinHandle<T> {
+}
+
+pub struct Cache<K: Hash + Eq, V> {
+}
+
+pub trait Store<T> {
+}
+"#;
+        let parsed = parse("lib.rs", code);
+        assert_eq!(
+            signature(&parsed, "convert"),
+            "convert<T: Into<String>, U>(value: T, other: HashMap<String, Vec<u8>>) -> Result<U>"
+        );
+        // Long bounds collapse, names stay
+        assert_eq!(
+            signature(&parsed, "spawn"),
+            "spawn<F: ..., T>(f: F) -> JoinHandle<T>"
+        );
+        assert_eq!(signature(&parsed, "Cache"), "struct Cache<K: Hash + Eq, V>");
+        assert_eq!(signature(&parsed, "Store"), "trait Store<T>");
+    }
+
+    #[test]
+    fn test_rust_consts_statics_and_type_aliases() {
+        let code = r#"
+pub const MAX: usize = 10;
+pub(crate) static mut COUNTER: AtomicUsize = AtomicUsize::new(0);
+type Id = u64;
+pub type Result<T> = std::result::Result<T, Error>;
+const _: () = assert!(MAX > 0);
+
+impl Limits {
+    const DEFAULT: Limits = Limits { max: MAX };
+
+    const fn max(&self) -> usize {
+        self.max
+    }
+}
+"#;
+        let parsed = parse("lib.rs", code);
+
+        let max = parsed.symbols.iter().find(|s| s.name == "MAX").unwrap();
+        assert_eq!(max.kind, SymbolKind::Const);
+        assert_eq!(max.signature, "const MAX: usize");
+        assert_eq!(max.line, 2);
+
+        let id = parsed.symbols.iter().find(|s| s.name == "Id").unwrap();
+        assert_eq!(id.kind, SymbolKind::Type);
+        assert_eq!(id.signature, "type Id = u64");
+
+        assert_eq!(
+            signature(&parsed, "COUNTER"),
+            "static mut COUNTER: AtomicUsize"
+        );
+        assert_eq!(
+            signature(&parsed, "Result"),
+            "type Result<T> = std::result::Result<T, Error>"
+        );
+
+        let default = parsed.symbols.iter().find(|s| s.name == "DEFAULT").unwrap();
+        assert_eq!(default.id, "lib.rs:Limits::DEFAULT");
+        assert_eq!(default.container.as_deref(), Some("Limits"));
+        assert!(!parsed.symbols.iter().any(|s| s.name == "_"));
+    }
+
+    #[test]
+    fn test_rust_macro_rules() {
+        let code = r#"
+macro_rules! ensure {
+    ($cond:expr) => {
+        if !$cond {
+            return Err(Error::Invalid);
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! hashmap {
+    ($($k:expr => $v:expr),*) => {{ HashMap::from([$(($k, $v)),*]) }};
+}
+"#;
+        let parsed = parse("src/macros.rs", code);
+        let macros: Vec<&Symbol> = parsed
+            .symbols
+            .iter()
+            .filter(|s| s.kind == SymbolKind::Macro)
+            .collect();
+        assert_eq!(macros.len(), 2);
+
+        assert_eq!(macros[0].id, "src/macros.rs:ensure");
+        assert_eq!(macros[0].signature, "macro_rules! ensure!");
+        assert_eq!(macros[0].line, 2);
+        assert_eq!(macros[1].id, "src/macros.rs:hashmap");
+        assert_eq!(macros[1].line, 11);
+    }
+
+    #[test]
+    fn test_typescript_generic_signatures() {
+        let code = r#"
+export function identity<T extends object>(arg: T): T {
+}
+
+export function merge<T extends Record<string, unknown>>(a: T, b: T): T {
+}
+
+export class Repo<T extends Entity> extends Base<T> {
+}
+
+export interface Box<T> {
+}
+
+export type Pair<A, B> = [A, B];
+"#;
+        let parsed = parse("lib.ts", code);
+        assert_eq!(
+            signature(&parsed, "identity"),
+            "identity<T extends object>(arg: T): T"
+        );
+        assert_eq!(
+            signature(&parsed, "merge"),
+            "merge<T extends ...>(a: T, b: T): T"
+        );
+        assert_eq!(
+            signature(&parsed, "Repo"),
+            "class Repo<T extends Entity> extends Base<T>"
+        );
+        assert_eq!(signature(&parsed, "Box"), "interface Box<T>");
+        assert_eq!(signature(&parsed, "Pair"), "type Pair<A, B>");
+    }
+
+    #[test]
+    fn test_typescript_enums_and_namespaces() {
+        let code = r#"
+export enum Color {}
+
+const enum Direction {
+  Up,
+  Down,
+}
+
+namespace Utils {}
+
+export declare namespace Api.V2 {
+}
+
+declare module "lodash" {
+}
+"#;
+        let parsed = parse("types.ts", code);
+
+        let color = parsed.symbols.iter().find(|s| s.name == "Color").unwrap();
+        assert_eq!(color.kind, SymbolKind::Enum);
+        assert_eq!(color.signature, "enum Color");
+        assert_eq!(color.line, 2);
+        assert_eq!(signature(&parsed, "Direction"), "const enum Direction");
+
+        let utils = parsed.symbols.iter().find(|s| s.name == "Utils").unwrap();
+        assert_eq!(utils.kind, SymbolKind::Module);
+        assert_eq!(utils.signature, "namespace Utils");
+        assert_eq!(signature(&parsed, "Api.V2"), "namespace Api.V2");
+        // Ambient module declarations name a package, not a symbol
+        assert_eq!(parsed.symbols.len(), 4);
+    }
+
+    #[test]
+    fn test_python_methods_belong_to_their_class() {
+        let code = r#"
+class Reader:
+    def __init__(self, path):
+        self.path = path
+
+    @property
+    def size(self) -> int:
+        def helper():
+            pass
+        return 0
+
+class Writer:
+    def __init__(self, path, mode="w"):
+        pass
+
+def open_reader(path):
+    return Reader(path)
+"#;
+        let parsed = parse("io.py", code);
+        let ids: Vec<&str> = parsed
+            .symbols
+            .iter()
+            .filter(|s| s.kind != SymbolKind::Class)
+            .map(|s| s.id.as_str())
+            .collect();
+        assert_eq!(
+            ids,
+            [
+                "io.py:Reader.__init__",
+                "io.py:Reader.size",
+                "io.py:Writer.__init__",
+                "io.py:open_reader"
+            ]
+        );
+
+        let size = parsed.symbols.iter().find(|s| s.name == "size").unwrap();
+        assert_eq!(size.kind, SymbolKind::Method);
+        assert_eq!(size.container.as_deref(), Some("Reader"));
+        assert_eq!(size.signature, "@property def Reader.size(self) -> int");
+        assert_eq!(size.line, 7);
+        assert_eq!(size.qualified_name(), "Reader.size");
+
+        let open = parsed
+            .symbols
+            .iter()
+            .find(|s| s.name == "open_reader")
+            .unwrap();
+        assert_eq!(open.kind, SymbolKind::Function);
+        assert_eq!(open.signature, "def open_reader(path) -> None");
+
+        // Ids assigned at compile time keep the dotted form
+        let mut symbols = parsed.symbols.clone();
+        crate::core::codemap::assign_symbol_ids("src/io.py", &mut symbols);
+        assert!(symbols.iter().any(|s| s.id == "src/io.py:Writer.__init__"));
+    }
+
+    #[test]
+    fn test_python_decorators_in_signature() {
+        let code = r#"
+import pytest
+
+@app.route("/users", methods=["GET"])
+@login_required
+def list_users():
+    return []
+
+@pytest.fixture(
+    scope="module",
+    autouse=True,
+)
+def database():
+    pass
+
+class Point:
+    @staticmethod
+    def origin():
+        return Point()
+
+x = 1
+def plain():
+    pass
+"#;
+        let parsed = parse("views.py", code);
+        assert_eq!(
+            signature(&parsed, "list_users"),
+            r#"@app.route("/users", methods=["GET"]) @login_required def list_users() -> None"#
+        );
+        assert_eq!(
+            signature(&parsed, "database"),
+            r#"@pytest.fixture( scope="module", autouse=True, ) def database() -> None"#
+        );
+        assert_eq!(
+            signature(&parsed, "origin"),
+            "@staticmethod def Point.origin() -> None"
+        );
+        assert_eq!(signature(&parsed, "plain"), "def plain() -> None");
+
+        // The symbol still points at the `def` line
+        let list_users = parsed
+            .symbols
+            .iter()
+            .find(|s| s.name == "list_users")
+            .unwrap();
+        assert_eq!(list_users.line, 6);
+    }
+
+    #[test]
+    fn test_go_const_blocks_and_type_aliases() {
+        let code = r#"package http
+
+const (
+	StatusOK       = 200
+	StatusNotFound = 404 // not found
+	DefaultTimeout time.Duration = 30 * time.Second
+)
+
+type Header = map[string][]string
+type HandlerFunc func(w ResponseWriter, r *Request)
+type List[T any] []T
+type Server struct {
+}
+"#;
+        let parsed = parse("http.go", code);
+
+        let consts: Vec<(&str, usize)> = parsed
+            .symbols
+            .iter()
+            .filter(|s| s.kind == SymbolKind::Const)
+            .map(|s| (s.name.as_str(), s.line))
+            .collect();
+        assert_eq!(
+            consts,
+            [
+                ("StatusOK", 4),
+                ("StatusNotFound", 5),
+                ("DefaultTimeout", 6)
+            ]
+        );
+        assert_eq!(
+            signature(&parsed, "DefaultTimeout"),
+            "const DefaultTimeout time.Duration"
+        );
+
+        let header = parsed.symbols.iter().find(|s| s.name == "Header").unwrap();
+        assert_eq!(header.kind, SymbolKind::Type);
+        assert_eq!(header.signature, "type Header = map[string][]string");
+        assert_eq!(header.line, 9);
+        assert_eq!(
+            signature(&parsed, "HandlerFunc"),
+            "type HandlerFunc func(w ResponseWriter, r *Request)"
+        );
+        assert_eq!(signature(&parsed, "List"), "type List[T any] []T");
+
+        // Structs are still reported once, by the struct pattern
+        let servers = parsed.symbols.iter().filter(|s| s.name == "Server");
+        assert_eq!(servers.count(), 1);
+    }
+
+    #[test]
+    fn test_symbol_visibility() {
+        let code = r#"
+pub fn open() {
+}
+
+fn helper() {
+}
+
+pub(crate) struct Cache {
+}
+
+#[macro_export]
+macro_rules! ensure {
+    () => {};
+}
+"#;
+        let parsed = parse("lib.rs", code);
+        let visibility = |parsed: &ParsedFile, name: &str| {
+            parsed
+                .symbols
+                .iter()
+                .find(|s| s.name == name)
+                .map(|s| s.visibility)
+                .unwrap_or_else(|| panic!("missing symbol {}", name))
+        };
+        assert_eq!(visibility(&parsed, "open"), Visibility::Public);
+        assert_eq!(visibility(&parsed, "helper"), Visibility::Private);
+        assert_eq!(visibility(&parsed, "Cache"), Visibility::Crate);
+        assert_eq!(visibility(&parsed, "ensure"), Visibility::Public);
+
+        let ts = "export function load() {\n}\n\nfunction parse() {\n}\n";
+        let parsed = parse("app.ts", ts);
+        assert_eq!(visibility(&parsed, "load"), Visibility::Public);
+        assert_eq!(visibility(&parsed, "parse"), Visibility::Private);
+
+        let py = "def run():\n    pass\n\ndef _setup():\n    pass\n";
+        let parsed = parse("cli.py", py);
+        assert_eq!(visibility(&parsed, "run"), Visibility::Public);
+        assert_eq!(visibility(&parsed, "_setup"), Visibility::Private);
+
+        // The public surface keeps exported symbols only
+        let mut map = crate::core::codemap::CodeMap::new("/repo");
+        for symbol in parse("lib.rs", code).symbols {
+            map.add_symbol(symbol);
+        }
+        map.retain_public();
+        let mut names: Vec<&str> = map.symbols.values().map(|s| s.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, ["ensure", "open"]);
+    }
+
+    #[test]
+    fn test_detect_stub_and_module_extensions() {
+        for (file, lang) in [
+            ("stubs/requests.pyi", "python"),
+            ("types/index.d.ts", "typescript"),
+            ("src/server.mts", "typescript"),
+            ("src/legacy.cts", "typescript"),
+        ] {
+            assert_eq!(detect_language(Path::new(file)), lang, "{}", file);
+            assert_eq!(
+                crate::core::scanner::detect_language(Path::new(file)).as_deref(),
+                Some(lang),
+                "{}",
+                file
+            );
+        }
+
+        let stub = "class Session:\n    def get(self, url: str) -> Response: ...\n";
+        let parsed = parse("requests.pyi", stub);
+        let get = parsed.symbols.iter().find(|s| s.name == "get").unwrap();
+        assert_eq!(get.id, "requests.pyi:Session.get");
+        assert_eq!(get.qualified_name(), "Session.get");
+
+        let decl = "export interface Options {\n  timeout: number;\n}\n";
+        let parsed = parse("index.d.ts", decl);
+        assert_eq!(signature(&parsed, "Options"), "interface Options");
+    }
+
+    #[test]
+    fn test_shebang_detects_script_language() {
+        let script = r#"#!/usr/bin/env python3
+import sys
+
+def main(argv):
+    return 0
+"#;
+        let parsed = parse("bin/deploy", script);
+        assert_eq!(parsed.language, "python");
+        assert_eq!(signature(&parsed, "main"), "def main(argv) -> None");
+
+        assert_eq!(shebang_language("#!/bin/bash\nset -e"), Some("shell"));
+        assert_eq!(shebang_language("#!/usr/bin/python3.11"), Some("python"));
+        assert_eq!(
+            shebang_language("#!/usr/bin/env -S node --no-warnings"),
+            Some("javascript")
+        );
+        assert_eq!(shebang_language("#!/usr/bin/awk -f"), None);
+        assert_eq!(shebang_language("import sys"), None);
+
+        // The extension wins over the shebang
+        assert_eq!(
+            parse("tool.rb", "#!/usr/bin/env python3\n").language,
+            "ruby"
+        );
+        assert_eq!(parse("notes", "just text").language, "unknown");
+    }
+
+    #[test]
+    fn test_go_generic_signatures() {
+        let code = r#"package collections
+
+func Map[T any, U any](s []T, f func(T) U) []U {
+}
+
+func Keys[M ~map[K]V, K comparable, V any](m M) []K {
+}
+
+func (s *Stack[T]) Push(v T) {
+}
+
+type Stack[T any] struct {
+}
+
+type Number interface {
+}
+"#;
+        let parsed = parse("collections.go", code);
+        assert_eq!(
+            signature(&parsed, "Map"),
+            "func Map[T any, U any](s []T, f func(T) U) []U"
+        );
+        assert_eq!(
+            signature(&parsed, "Keys"),
+            "func Keys[M ~map[K]V, K comparable, V any](m M) []K"
+        );
+        assert_eq!(signature(&parsed, "Push"), "func (s *Stack[T]) Push(v T)");
+        assert_eq!(signature(&parsed, "Stack"), "type Stack[T any] struct");
+        assert_eq!(signature(&parsed, "Number"), "type Number interface");
+    }
+
+    #[test]
+    fn test_go_methods_belong_to_their_receiver() {
+        let code = r#"package store
+
+func (f *File) Close() error {
+}
+
+func (c Conn) Close() error {
+}
+
+func Open(path string) (*File, error) {
+}
+"#;
+        let parsed = parse("store.go", code);
+        let ids: Vec<&str> = parsed.symbols.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            [
+                "store.go:File.Close",
+                "store.go:Conn.Close",
+                "store.go:Open"
+            ]
+        );
+
+        let file_close = &parsed.symbols[0];
+        assert_eq!(file_close.kind, SymbolKind::Method);
+        assert_eq!(file_close.container.as_deref(), Some("File"));
+        assert_eq!(file_close.signature, "func (f *File) Close() error");
+        assert_eq!(parsed.symbols[1].signature, "func (c Conn) Close() error");
+
+        let open = &parsed.symbols[2];
+        assert_eq!(open.kind, SymbolKind::Function);
+        assert_eq!(open.signature, "func Open(path string) (*File, error)");
+
+        // Ids assigned at compile time keep the dotted form
+        let mut symbols = parsed.symbols.clone();
+        crate::core::codemap::assign_symbol_ids("pkg/store.go", &mut symbols);
+        assert_eq!(symbols[1].id, "pkg/store.go:Conn.Close");
+    }
+
+    #[test]
+    fn test_java_symbols() {
+        let code = r#"package com.example.store;
+
+import java.util.List;
+import static java.util.Objects.requireNonNull;
+
+public class Repository<T extends Entity> extends BaseRepository<T> {
+    private final List<T> items;
+
+    public Repository(List<T> items) {
+        this.items = requireNonNull(items);
+    }
+
+    @Override
+    public String toString() {
+        if (items.isEmpty()) {
+            return "empty";
+        } else if (items.size() == 1) {
+            return "one";
+        }
+        return "many";
+    }
+
+    public static <K> Map<K, List<T>> groupBy(Function<T, K> key) throws IOException {
+        return null;
+    }
+}
+
+interface Entity {
+    long id();
+}
+
+enum Status { ACTIVE, DELETED }
+"#;
+        let parsed = parse("Repository.java", code);
+        assert_eq!(parsed.language, "java");
+        assert_eq!(
+            signature(&parsed, "Repository"),
+            "class Repository<T extends Entity> extends BaseRepository<T>"
+        );
+        assert_eq!(signature(&parsed, "toString"), "String toString()");
+        assert_eq!(
+            signature(&parsed, "groupBy"),
+            "<K> Map<K, List<T>> groupBy(Function<T, K> key)"
+        );
+        assert_eq!(signature(&parsed, "Entity"), "interface Entity");
+        assert_eq!(signature(&parsed, "Status"), "enum Status");
+
+        let constructor = parsed
+            .symbols
+            .iter()
+            .find(|s| s.id == "Repository.java:Repository::Repository")
+            .unwrap();
+        assert_eq!(constructor.kind, SymbolKind::Method);
+        assert_eq!(constructor.signature, "Repository(List<T> items)");
+        let class = parsed
+            .symbols
+            .iter()
+            .find(|s| s.id == "Repository.java:Repository")
+            .unwrap();
+        assert_eq!(class.kind, SymbolKind::Class);
+
+        let names: Vec<&str> = parsed.symbols.iter().map(|s| s.name.as_str()).collect();
+        assert!(!names.contains(&"if"));
+        assert_eq!(
+            parsed.imports,
+            vec!["java.util.List", "java.util.Objects.requireNonNull"]
+        );
+    }
+
+    #[test]
+    fn test_cpp_symbols() {
+        let code = r#"#include <vector>
+#include "shape.h"
+
+namespace geo {
+
+template <typename T>
+T max_of(T a, T b) {
+    return a > b ? a : b;
+}
+
+class Shape : public Drawable {
+public:
+    explicit Shape(int sides) : sides_(sides) {}
+    virtual double area() const = 0;
+    int sides() const {
+        if (sides_ > 0) {
+            return sides_;
+        }
+        return 0;
+    }
+
+private:
+    int sides_;
+};
+
+}
+"#;
+        let parsed = parse("shape.cpp", code);
+        assert_eq!(
+            signature(&parsed, "max_of"),
+            "template<typename T> T max_of(T a, T b)"
+        );
+        assert_eq!(signature(&parsed, "Shape"), "class Shape : public Drawable");
+        assert_eq!(signature(&parsed, "area"), "double Shape::area()");
+        assert_eq!(signature(&parsed, "sides"), "int Shape::sides()");
+        assert_eq!(signature(&parsed, "geo"), "namespace geo");
+
+        let member = |id: &str| parsed.symbols.iter().find(|s| s.id == id).unwrap();
+        assert_eq!(
+            member("shape.cpp:Shape::Shape").signature,
+            "Shape::Shape(int sides)"
+        );
+        let sides = member("shape.cpp:Shape::sides");
+        assert_eq!(sides.kind, SymbolKind::Method);
+        assert_eq!(sides.container.as_deref(), Some("Shape"));
+        assert_eq!(sides.line, 15);
+
+        // Statements inside method bodies are not members
+        let names: Vec<&str> = parsed.symbols.iter().map(|s| s.name.as_str()).collect();
+        assert!(!names.iter().any(|n| n.ends_with("if")));
+        assert_eq!(parsed.imports, vec!["vector", "shape.h"]);
+    }
+
+    #[test]
+    fn test_c_symbols() {
+        let code = r#"#include <stdio.h>
+
+typedef int (*cmp_fn)(const void *, const void *);
+typedef unsigned long ulong;
+
+struct node {
+    int value;
+};
+
+typedef struct {
+    int x, y;
+} point_t;
+
+enum color { RED, GREEN };
+
+static char *dup_name(const char *name) {
+    if (name == NULL) {
+        return NULL;
+    }
+    return strdup(name);
+}
+
+int main(void)
+{
+    return 0;
+}
+"#;
+        let parsed = parse("main.c", code);
+        assert_eq!(
+            signature(&parsed, "dup_name"),
+            "static char *dup_name(const char *name)"
+        );
+        assert_eq!(signature(&parsed, "main"), "int main(void)");
+        assert_eq!(signature(&parsed, "node"), "struct node");
+        assert_eq!(signature(&parsed, "color"), "enum color");
+        assert_eq!(signature(&parsed, "ulong"), "typedef unsigned long ulong");
+        assert_eq!(
+            signature(&parsed, "cmp_fn"),
+            "typedef int (*cmp_fn)(const void *, const void *)"
+        );
+        assert_eq!(signature(&parsed, "point_t"), "typedef {...} point_t");
+        assert_eq!(parsed.imports, vec!["stdio.h"]);
+    }
+
+    #[test]
+    fn test_ruby_symbols() {
+        let code = r#"require "json"
+require_relative 'support/cache'
+
+class Account < ApplicationRecord
+  def self.find_by_email(email)
+    where(email: email).first
+  end
+
+  def active?
+    !closed_at
+  end
+
+  def name=(value)
+    @name = value.strip
+  end
+end
+"#;
+        let parsed = parse("account.rb", code);
+        assert_eq!(parsed.symbols.len(), 4);
+        assert_eq!(
+            signature(&parsed, "Account"),
+            "class Account < ApplicationRecord"
+        );
+        assert_eq!(
+            signature(&parsed, "find_by_email"),
+            "def self.find_by_email(email)"
+        );
+        assert_eq!(signature(&parsed, "active?"), "def active?()");
+        assert_eq!(signature(&parsed, "name="), "def name=(value)");
+        assert_eq!(parsed.imports, vec!["json", "support/cache"]);
+
+        let module = parse("util.rb", "module Util\n  def helper x, y\n  end\nend\n");
+        assert_eq!(signature(&module, "Util"), "module Util");
+        assert_eq!(signature(&module, "helper"), "def helper(x, y)");
+        assert_eq!(module.symbols[0].kind, SymbolKind::Function);
+    }
+
+    #[test]
+    fn test_php_symbols() {
+        let code = r#"<?php
+
+namespace App\Billing;
+
+use App\Models\Invoice;
+use function App\Support\money;
+
+interface Payable
+{
+}
+
+trait Loggable
+{
+}
+
+final class InvoiceService extends BaseService
+{
+    use Loggable;
+
+    public function __construct(private Invoice $invoice)
+    {
+    }
+
+    public static function total(array $lines, ?float $tax = null): float
+    {
+        return 0.0;
+    }
+
+    protected function &items()
+    {
+    }
+}
+
+function helper($value): ?string
+{
+}
+"#;
+        let parsed = parse("InvoiceService.php", code);
+        assert_eq!(signature(&parsed, r"App\Billing"), r"namespace App\Billing");
+        assert_eq!(signature(&parsed, "Payable"), "interface Payable");
+        assert_eq!(signature(&parsed, "Loggable"), "trait Loggable");
+        assert_eq!(
+            signature(&parsed, "InvoiceService"),
+            "class InvoiceService extends BaseService"
+        );
+        assert_eq!(
+            signature(&parsed, "total"),
+            "function total(array $lines, ?float $tax = null): float"
+        );
+        assert_eq!(signature(&parsed, "items"), "function items()");
+        assert_eq!(
+            signature(&parsed, "helper"),
+            "function helper($value): ?string"
+        );
+        assert_eq!(
+            parsed.imports,
+            vec![r"App\Models\Invoice", r"App\Support\money"]
+        );
+    }
+
+    #[test]
+    fn test_swift_symbols() {
+        let code = r#"import Foundation
+@testable import NetworkKit
+
+public protocol Store {
+    func load(id: String) async throws -> Data
+}
+
+final class DiskStore<Key: Hashable>: Store, Sendable {
+    public func move(from source: URL, to destination: URL) throws -> Bool {
+        return true
+    }
+
+    func fetch(_ completion: @escaping (Result<Data, Error>) -> Void) {
+    }
+
+    class func shared() -> DiskStore {
+    }
+}
+
+struct Point {
+}
+
+enum Mode: String {
+}
+
+extension Point: Codable {
+}
+"#;
+        let parsed = parse("Store.swift", code);
+        assert_eq!(signature(&parsed, "load"), "func load(id: String) -> Data");
+        assert_eq!(
+            signature(&parsed, "move"),
+            "func move(from source: URL, to destination: URL) -> Bool"
+        );
+        assert_eq!(
+            signature(&parsed, "fetch"),
+            "func fetch(_ completion: @escaping (Result<Data, Error>) -> Void)"
+        );
+        assert_eq!(signature(&parsed, "shared"), "func shared() -> DiskStore");
+        assert_eq!(
+            signature(&parsed, "DiskStore"),
+            "class DiskStore<Key: Hashable>: Store, Sendable"
+        );
+        assert_eq!(signature(&parsed, "Store"), "protocol Store");
+
+        let kind = |name: &str, sig: &str| {
+            parsed
+                .symbols
+                .iter()
+                .find(|s| s.name == name && s.signature.starts_with(sig))
+                .map(|s| s.kind.clone())
+                .unwrap()
+        };
+        assert_eq!(kind("Store", "protocol"), SymbolKind::Interface);
+        assert_eq!(kind("Point", "struct"), SymbolKind::Struct);
+        assert_eq!(kind("Point", "extension"), SymbolKind::Type);
+        assert_eq!(kind("Mode", "enum"), SymbolKind::Enum);
+        assert!(!parsed.symbols.iter().any(|s| s.name == "func"));
+        assert_eq!(parsed.imports, vec!["Foundation", "NetworkKit"]);
+    }
+
+    #[test]
+    fn test_kotlin_symbols() {
+        let code = r#"package com.example
+
+import kotlinx.coroutines.flow.Flow
+import com.example.util.*
+
+data class User(val id: Long, val name: String)
+
+enum class Role { ADMIN, USER }
+
+interface Repository<T> {
+    suspend fun findAll(): List<T>
+}
+
+object Registry {
+    fun register(user: User) {
+    }
+}
+
+fun String.shout(): String = uppercase()
+
+fun <T : Comparable<T>> maxOf(a: T, b: T): T = if (a > b) a else b
+
+private fun log(message: String) {
+}
+"#;
+        let parsed = parse("User.kt", code);
+        assert_eq!(signature(&parsed, "User"), "data class User");
+        assert_eq!(signature(&parsed, "Role"), "enum class Role");
+        assert_eq!(signature(&parsed, "Repository"), "interface Repository<T>");
+        assert_eq!(signature(&parsed, "Registry"), "object Registry");
+        assert_eq!(signature(&parsed, "findAll"), "fun findAll(): List<T>");
+        assert_eq!(signature(&parsed, "register"), "fun register(user: User)");
+        assert_eq!(signature(&parsed, "shout"), "fun String.shout(): String");
+        assert_eq!(
+            signature(&parsed, "maxOf"),
+            "fun <T : Comparable<T>> maxOf(a: T, b: T): T"
+        );
+        assert_eq!(signature(&parsed, "log"), "fun log(message: String)");
+
+        let role = parsed.symbols.iter().find(|s| s.name == "Role").unwrap();
+        assert_eq!(role.kind, SymbolKind::Enum);
+        assert_eq!(
+            parsed.imports,
+            vec!["kotlinx.coroutines.flow.Flow", "com.example.util.*"]
+        );
+    }
+
+    #[test]
+    fn test_rust_impl_methods() {
+        let code = r#"
+pub struct Reader;
+pub struct Writer<T>;
+
+impl Reader {
+    pub fn new() -> Self {
+        Reader
+    }
+}
+
+impl<T: Write> Writer<T> {
+    pub fn new(inner: T) -> Self {
+        Writer(inner)
+    }
+}
+
+impl fmt::Display for Reader {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Ok(())
+    }
+}
+
+pub fn open(path: &Path) -> Reader {
+}
+"#;
+        let parsed = parse("io.rs", code);
+        let ids: Vec<&str> = parsed.symbols.iter().map(|s| s.id.as_str()).collect();
+        assert!(ids.contains(&"io.rs:Reader::new"));
+        assert!(ids.contains(&"io.rs:Writer::new"));
+        assert!(ids.contains(&"io.rs:Reader::fmt"));
+        // Free functions keep the plain `file:name` form
+        assert!(ids.contains(&"io.rs:open"));
+
+        let writer_new = parsed
+            .symbols
+            .iter()
+            .find(|s| s.id == "io.rs:Writer::new")
+            .unwrap();
+        assert_eq!(writer_new.signature, "Writer::new(inner: T) -> Self");
+        assert_eq!(writer_new.container.as_deref(), Some("Writer"));
+        assert_eq!(writer_new.kind, SymbolKind::Method);
+        assert_eq!(writer_new.qualified_name(), "Writer::new");
+
+        let open = parsed.symbols.iter().find(|s| s.name == "open").unwrap();
+        assert_eq!(open.container, None);
+        assert_eq!(open.kind, SymbolKind::Function);
+    }
+
+    #[test]
+    fn test_multiline_signatures() {
+        let rust = r#"
+pub fn rect(
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32,
+) -> Rect {
+    Rect { x, y, w, h }
+}
+
+fn retry(
+    attempts: u32,
+    on_error: impl Fn(&Error) -> bool,
+) -> Result<()> {
+    Ok(())
+}
+"#;
+        let parsed = parse("geom.rs", rust);
+        assert_eq!(
+            signature(&parsed, "rect"),
+            "rect(x: f32, y: f32, w: f32, h: f32) -> Rect"
+        );
+        assert_eq!(
+            signature(&parsed, "retry"),
+            "retry(attempts: u32, on_error: impl Fn(&Error) -> bool) -> Result<()>"
+        );
+
+        let ts = r#"
+export function clamp(
+  n: number,
+  lo: number,
+  hi: number,
+  step: number,
+): number {
+  return n;
+}
+
+function on(
+  event: string,
+  handler: (e: Event) => void,
+) {
+}
+"#;
+        let parsed = parse("math.ts", ts);
+        assert_eq!(
+            signature(&parsed, "clamp"),
+            "clamp(n: number, lo: number, hi: number, step: number): number"
+        );
+        assert_eq!(
+            signature(&parsed, "on"),
+            "on(event: string, handler: (e: Event) => void): void"
+        );
+    }
+
+    #[test]
+    fn test_comments_and_strings_yield_no_symbols() {
+        let rust = r##"
+// fn ghost() {}
+/* fn block_ghost() {
+   /* nested */ fn still_ghost() {}
+} */
+/// Call like `fn example() {}`
+pub fn real<'a>(s: &'a str) -> &'a str {
+    let _ = "fn phantom() {";
+    let _ = r#"fn raw_phantom() {"#;
+    let _ = '{';
+    s
+}
+
+fn after() {}
+"##;
+        let parsed = parse("lib.rs", rust);
+        let names: Vec<&str> = parsed.symbols.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["real", "after"]);
+        assert_eq!(
+            signature(&parsed, "real"),
+            "real<'a>(s: &'a str) -> &'a str"
+        );
+        assert_eq!(parsed.symbols[0].line, 7);
+        assert_eq!(parsed.symbols[1].line, 14);
+
+        let py = r#"
+import os
+# def ghost():
+def real():
+    """
+    class Ghost:
+    """
+    return "def phantom():"
+"#;
+        let parsed = parse("mod.py", py);
+        let names: Vec<&str> = parsed.symbols.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["real"]);
+
+        // Module specifiers keep their contents
+        let ts = r#"
+import { a } from "./a";
+// import { b } from "./b";
+const s = "function phantom() {";
+"#;
+        let parsed = parse("app.ts", ts);
+        assert_eq!(parsed.imports, vec!["./a"]);
+        assert!(parsed.symbols.is_empty());
+    }
+
+    #[test]
+    fn test_line_numbers_on_large_file() {
+        // 300 functions spread over 5000 lines
+        let mut code = String::new();
+        let mut expected = Vec::new();
+        for line in 1..=5000 {
+            if line % 16 == 0 && expected.len() < 300 {
+                code.push_str(&format!("pub fn f{}() {{}}\n", line));
+                expected.push(line);
+            } else {
+                code.push_str("    let x = 1;\n");
+            }
+        }
+
+        let parsed = parse("big.rs", &code);
+        let lines: Vec<usize> = parsed.symbols.iter().map(|s| s.line).collect();
+        assert_eq!(lines.len(), 300);
+        assert_eq!(lines, expected);
+        assert_eq!(parsed.lines, 5000);
+    }
+
+    #[test]
+    fn test_symbol_end_lines() {
+        let rust = r#"
+/// Sums the even values
+pub fn sum_even(values: &[i32]) -> i32 {
+    let mut total = 0;
+    for v in values {
+        if v % 2 == 0 {
+            // braces in strings don't count: "}"
+            total += v;
+        }
+    }
+    let _ = '}';
+    debug_assert!(total % 2 == 0);
+    total
+}
+
+pub struct Unit;
+
+pub fn generic<T>(value: T) -> T
+where
+    T: Clone,
+{
+    value
+}
+"#;
+        let parsed = parse("sum.rs", rust);
+        let sum_even = &parsed.symbols[0];
+        assert_eq!((sum_even.line, sum_even.end_line), (3, 14));
+        let end_of = |name: &str| {
+            let symbol = parsed.symbols.iter().find(|s| s.name == name).unwrap();
+            (symbol.line, symbol.end_line)
+        };
+        assert_eq!(end_of("Unit"), (16, 16));
+        assert_eq!(end_of("generic"), (18, 23));
+
+        let py = r#"
+def handler(
+    event,
+    context,
+):
+    if event:
+        return 1
+
+    return 0
+
+def after():
+    pass
+"#;
+        let parsed = parse("app.py", py);
+        assert_eq!(parsed.symbols[0].end_line, 9);
+        assert_eq!(parsed.symbols[1].end_line, 12);
+
+        let rb = r#"
+class Greeter
+  def greet(name)
+    "hi #{name}"
+  end
+
+  def short = 1
+end
+"#;
+        let parsed = parse("greeter.rb", rb);
+        let end_of = |name: &str| {
+            let symbol = parsed.symbols.iter().find(|s| s.name == name).unwrap();
+            (symbol.line, symbol.end_line)
+        };
+        assert_eq!(end_of("Greeter"), (2, 8));
+        assert_eq!(end_of("greet"), (3, 5));
+        assert_eq!(end_of("short"), (7, 7));
+    }
+
+    #[test]
+    fn test_doc_comment_summaries() {
+        let rust = r#"
+/// Opens the store at `path`. Creates it when missing.
+pub fn open(path: &Path) -> Store {
+    todo!()
+}
+
+/**
+ * Flushes pending writes
+ * to disk.
+ */
+pub fn flush() {}
+
+/// Cached entry
+#[derive(Debug, Clone)]
+pub struct Entry;
+
+// Not documentation
+fn helper() {}
+"#;
+        let parsed = parse("store.rs", rust);
+        let summary = |name: &str| {
+            let symbol = parsed.symbols.iter().find(|s| s.name == name).unwrap();
+            symbol.summary.clone()
+        };
+        assert_eq!(summary("open"), "Opens the store at `path`.");
+        assert_eq!(summary("flush"), "Flushes pending writes to disk.");
+        assert_eq!(summary("Entry"), "Cached entry");
+        assert_eq!(summary("helper"), "");
+
+        let py = r#"
+def load(
+    path,
+):
+    """Load a config file.
+
+    Missing keys fall back to defaults.
+    """
+    return {}
+
+class Cache:
+    '''
+    Least-recently-used cache with a fixed capacity and optional per-entry expiry times
+    that are checked lazily on every access and on every write
+    '''
+
+def bare():
+    return 1
+"#;
+        let parsed = parse("config.py", py);
+        let summary = |name: &str| {
+            let symbol = parsed.symbols.iter().find(|s| s.name == name).unwrap();
+            symbol.summary.clone()
+        };
+        assert_eq!(summary("load"), "Load a config file.");
+        assert!(summary("Cache").starts_with("Least-recently-used cache with a fixed capacity"));
+        assert!(summary("Cache").ends_with("..."));
+        assert!(summary("Cache").chars().count() <= 120);
+        assert_eq!(summary("bare"), "");
+    }
+
+    #[test]
+    fn test_long_parameter_lists_keep_every_name() {
+        let python = r#"
+def connect(host: str, port: int, timeout: float = DEFAULT_TIMEOUT_SECONDS, retries: int = 3, pool: dict[str, list[Connection]] = None):
+    pass
+"#;
+        let parsed = parse("net.py", python);
+        let full = signature(&parsed, "connect");
+        assert_eq!(
+            full,
+            "def connect(host: str, port: int, timeout: float = DEFAULT_TIMEOUT_SECONDS, retries: int = 3, pool: dict[str, list[Connection]] = None) -> None"
+        );
+
+        // Compact form keeps names and types, shortening only the long default
+        assert_eq!(
+            compact_signature(full),
+            "def connect(host: str, port: int, timeout: float = ..., retries: int = 3, pool: dict[str, list[Connection]] = None) -> None"
+        );
+
+        // Long generic arguments collapse, closure arrows are not brackets
+        assert_eq!(
+            compact_signature(
+                "build(cache: HashMap<String, Vec<CachedIndexEntry>>, on_miss: impl Fn(&str) -> Entry)"
+            ),
+            "build(cache: HashMap<...>, on_miss: impl Fn(&str) -> Entry)"
+        );
+        assert_eq!(
+            compact_signature("rect(x: f32,y: f32)"),
+            "rect(x: f32,y: f32)"
+        );
+    }
+
+    #[test]
+    fn test_rust_trait_impls_link_types_and_traits() {
+        let code = r#"
+pub trait Shape {
+    fn area(&self) -> f64;
+}
+
+pub struct Foo;
+
+impl Display for Foo {}
+
+impl<T: Clone> shapes::Shape<T> for Foo {
+    fn area(&self) -> f64 {
+        0.0
+    }
+}
+
+impl Foo {
+    fn new() -> Self {
+        Foo
+    }
+}
+
+impl !Send for Foo {}
+"#;
+        let parsed = parse("foo.rs", code);
+        let impls: Vec<(&str, &str, usize)> = parsed
+            .trait_impls
+            .iter()
+            .map(|i| (i.type_name.as_str(), i.trait_name.as_str(), i.line))
+            .collect();
+        assert_eq!(impls, [("Foo", "Display", 8), ("Foo", "Shape", 10)]);
+
+        let mut map = crate::core::codemap::CodeMap::new("/repo");
+        for symbol in parsed.symbols {
+            map.add_symbol(symbol);
+        }
+        for imp in parsed.trait_impls {
+            map.add_trait_impl(imp);
+        }
+
+        // Foo -> Display, Shape
+        let foo = map.expand("foo.rs:Foo").unwrap();
+        let traits: Vec<&str> = foo
+            .implements
+            .iter()
+            .map(|i| i.trait_name.as_str())
+            .collect();
+        assert_eq!(traits, ["Display", "Shape"]);
+
+        // Shape -> Foo
+        let shape = map.expand("foo.rs:Shape").unwrap();
+        let types: Vec<&str> = shape.implementors.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(types, ["Foo"]);
+        assert!(map.expand("foo.rs:Foo::new").unwrap().implements.is_empty());
+    }
+
+    #[test]
+    fn test_typescript_class_methods() {
+        let code = r#"
+export class AuthService {
+    private token = "";
+
+    constructor(private readonly api: Api) {}
+
+    async login(user: string, password: string): Promise<Session> {
+        if (!user) {
+            throw new Error("missing user");
+        }
+        return this.api.post("/login", { user, password });
+    }
+
+    static get instance(): AuthService {
+        return shared;
+    }
+
+    private hash(password: string) {
+        return digest(password);
+    }
+}
+"#;
+        let parsed = parse("auth.ts", code);
+        let methods: Vec<(&str, &str, Visibility)> = parsed
+            .symbols
+            .iter()
+            .filter(|s| s.kind == SymbolKind::Method)
+            .map(|s| (s.id.as_str(), s.signature.as_str(), s.visibility))
+            .collect();
+        assert_eq!(
+            methods,
+            [
+                (
+                    "auth.ts:AuthService.constructor",
+                    "AuthService.constructor(private readonly api: Api)",
+                    Visibility::Public
+                ),
+                (
+                    "auth.ts:AuthService.login",
+                    "async AuthService.login(user: string, password: string): Promise<Session>",
+                    Visibility::Public
+                ),
+                (
+                    "auth.ts:AuthService.instance",
+                    "static get AuthService.instance(): AuthService",
+                    Visibility::Public
+                ),
+                (
+                    "auth.ts:AuthService.hash",
+                    "private AuthService.hash(password: string)",
+                    Visibility::Private
+                ),
+            ]
+        );
+
+        let login = parsed.symbols.iter().find(|s| s.name == "login").unwrap();
+        assert_eq!(login.container.as_deref(), Some("AuthService"));
+        assert_eq!((login.line, login.end_line), (7, 12));
     }
-    params.to_string()
 }
@@ -1,12 +1,68 @@
 use anyhow::Result;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
 use ignore::WalkBuilder;
 use std::collections::HashSet;
+use std::fmt;
 use std::fs;
 use std::path::Path;
 
+/// Files larger than this (bytes) are skipped unless configured otherwise
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 1_000_000;
+/// A line longer than this marks a file as minified/generated
+pub const DEFAULT_MAX_LINE_LENGTH: usize = 1_000;
+
+/// Project ignore files read alongside `.gitignore` (same syntax)
+pub const IGNORE_FILES: &[&str] = &[".sgrepignore", ".searchgrepignore"];
+
+/// Directories never indexed, even with ignore files disabled
+const SKIPPED_DIRS: &[&str] = &[".git", ".sgrep"];
+
 pub struct FileScanner {
     root: String,
     extensions: HashSet<String>,
+    /// Index files excluded by `.gitignore` and `IGNORE_FILES` too
+    include_ignored: bool,
+    /// Only keep files in these languages (empty = every language)
+    languages: HashSet<String>,
+    /// Never keep files in these languages
+    exclude_languages: HashSet<String>,
+    max_file_size: u64,
+    /// 0 = no limit
+    max_line_length: usize,
+}
+
+/// Why a file was left out of a scan
+#[derive(Debug, Clone, PartialEq)]
+pub enum SkipReason {
+    /// Not valid UTF-8, or contains NUL bytes
+    Binary,
+    /// Size in bytes
+    TooLarge(u64),
+    /// Length of the longest line
+    Minified(usize),
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::Binary => write!(f, "binary"),
+            SkipReason::TooLarge(size) => write!(f, "{} bytes, too large", size),
+            SkipReason::Minified(len) => write!(f, "{}-char line, likely minified", len),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SkippedFile {
+    pub path: String,
+    pub reason: SkipReason,
+}
+
+/// Result of a full scan: files to index, and indexable-looking files that weren't
+#[derive(Debug, Default)]
+pub struct ScanReport {
+    pub files: Vec<ScannedFile>,
+    pub skipped: Vec<SkippedFile>,
 }
 
 impl FileScanner {
@@ -17,9 +73,12 @@ impl FileScanner {
             "rs",
             "ts",
             "tsx",
+            "mts",
+            "cts",
             "js",
             "jsx",
             "py",
+            "pyi",
             "go",
             "java",
             "c",
@@ -73,6 +132,45 @@ impl FileScanner {
         Self {
             root: root.to_string(),
             extensions,
+            include_ignored: false,
+            languages: HashSet::new(),
+            exclude_languages: HashSet::new(),
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            max_line_length: DEFAULT_MAX_LINE_LENGTH,
+        }
+    }
+
+    /// Skip files over `max_file_size` bytes or with a line longer than
+    /// `max_line_length` (0 = no line limit)
+    pub fn with_limits(mut self, max_file_size: u64, max_line_length: usize) -> Self {
+        self.max_file_size = max_file_size;
+        self.max_line_length = max_line_length;
+        self
+    }
+
+    /// Walk everything, ignoring `.gitignore` and `IGNORE_FILES`
+    pub fn with_include_ignored(mut self, enabled: bool) -> Self {
+        self.include_ignored = enabled;
+        self
+    }
+
+    /// Keep only files in `include` (every language when empty), minus `exclude`
+    ///
+    /// Names are those `detect_language` returns, e.g. "rust" or "typescript".
+    /// Files of no known language only pass an empty `include`.
+    pub fn with_languages(mut self, include: &[String], exclude: &[String]) -> Self {
+        self.languages = include.iter().map(|l| l.to_lowercase()).collect();
+        self.exclude_languages = exclude.iter().map(|l| l.to_lowercase()).collect();
+        self
+    }
+
+    fn language_allowed(&self, language: Option<&str>) -> bool {
+        match language {
+            Some(lang) => {
+                (self.languages.is_empty() || self.languages.contains(lang))
+                    && !self.exclude_languages.contains(lang)
+            }
+            None => self.languages.is_empty(),
         }
     }
 
@@ -82,16 +180,34 @@ impl FileScanner {
     }
 
     pub fn scan(&self) -> Result<Vec<ScannedFile>> {
-        let mut files = Vec::new();
+        Ok(self.scan_report()?.files)
+    }
+
+    /// Like `scan`, also listing the files skipped as binary, too large or minified
+    pub fn scan_report(&self) -> Result<ScanReport> {
+        let mut report = ScanReport::default();
 
-        let walker = WalkBuilder::new(&self.root)
+        let respect_ignores = !self.include_ignored;
+        let mut builder = WalkBuilder::new(&self.root);
+        builder
             .hidden(false)
-            .git_ignore(true)
-            .git_global(true)
-            .git_exclude(true)
-            .ignore(true)
-            .parents(true)
-            .build();
+            .git_ignore(respect_ignores)
+            .git_global(respect_ignores)
+            .git_exclude(respect_ignores)
+            .ignore(respect_ignores)
+            .parents(respect_ignores)
+            // Honor .gitignore outside git repositories too
+            .require_git(false)
+            .filter_entry(|entry| {
+                let name = entry.file_name().to_string_lossy();
+                !SKIPPED_DIRS.contains(&name.as_ref())
+            });
+        if respect_ignores {
+            for name in IGNORE_FILES {
+                builder.add_custom_ignore_filename(name);
+            }
+        }
+        let walker = builder.build();
 
         for entry in walker {
             let entry = match entry {
@@ -105,7 +221,9 @@ impl FileScanner {
                 continue;
             }
 
-            // Check extension
+            // Check extension; other extensionless files are kept only if
+            // they're scripts with a recognized shebang
+            let mut needs_shebang = false;
             if let Some(ext) = path.extension() {
                 let ext_str = ext.to_string_lossy().to_lowercase();
                 if !self.extensions.contains(&ext_str) {
@@ -116,32 +234,79 @@ impl FileScanner {
                 if let Some(name) = path.file_name() {
                     let name_lower = name.to_string_lossy().to_lowercase();
                     if !["dockerfile", "makefile", "cargo"].contains(&name_lower.as_str()) {
-                        continue;
+                        needs_shebang = true;
                     }
                 } else {
                     continue;
                 }
             }
 
-            // Read file content
-            match fs::read_to_string(path) {
-                Ok(content) => {
-                    // Skip binary or very large files
-                    if content.len() > 1_000_000 || content.contains('\0') {
-                        continue;
+            let content = match self.read_source(path) {
+                Ok(Some(content)) => content,
+                Ok(None) => continue,
+                Err(reason) => {
+                    // An extensionless non-script was never going to be indexed
+                    if !needs_shebang {
+                        report.skipped.push(SkippedFile {
+                            path: path.to_string_lossy().to_string(),
+                            reason,
+                        });
                     }
-
-                    files.push(ScannedFile {
-                        path: path.to_string_lossy().to_string(),
-                        content,
-                        language: detect_language(path),
-                    });
+                    continue;
                 }
-                Err(_) => continue,
+            };
+
+            let language =
+                detect_language(path).or_else(|| shebang_language(&content).map(str::to_string));
+            if needs_shebang && language.is_none() {
+                continue;
+            }
+            if !self.language_allowed(language.as_deref()) {
+                continue;
             }
+
+            report.files.push(ScannedFile {
+                path: path.to_string_lossy().to_string(),
+                content,
+                language,
+            });
         }
 
-        Ok(files)
+        Ok(report)
+    }
+
+    /// File contents, unless the file is binary, too large or minified
+    ///
+    /// `Ok(None)` when it can't be read at all.
+    fn read_source(&self, path: &Path) -> Result<Option<String>, SkipReason> {
+        let size = match fs::metadata(path) {
+            Ok(meta) => meta.len(),
+            Err(_) => return Ok(None),
+        };
+        if size > self.max_file_size {
+            return Err(SkipReason::TooLarge(size));
+        }
+
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(_) => return Ok(None),
+        };
+        let content = match String::from_utf8(bytes) {
+            Ok(content) if !content.contains('\0') => content,
+            _ => return Err(SkipReason::Binary),
+        };
+
+        if self.max_line_length > 0 {
+            let longest = content
+                .lines()
+                .map(|l| l.chars().count())
+                .max()
+                .unwrap_or(0);
+            if longest > self.max_line_length {
+                return Err(SkipReason::Minified(longest));
+            }
+        }
+        Ok(Some(content))
     }
 
     pub fn scan_single(&self, path: &Path) -> Result<Option<ScannedFile>> {
@@ -150,34 +315,42 @@ impl FileScanner {
         }
 
         // Check extension
-        let has_valid_ext = if let Some(ext) = path.extension() {
-            self.extensions
-                .contains(&ext.to_string_lossy().to_lowercase())
+        let (has_valid_ext, needs_shebang) = if let Some(ext) = path.extension() {
+            let valid = self
+                .extensions
+                .contains(&ext.to_string_lossy().to_lowercase());
+            (valid, false)
         } else if let Some(name) = path.file_name() {
             let name_lower = name.to_string_lossy().to_lowercase();
-            ["dockerfile", "makefile", "cargo"].contains(&name_lower.as_str())
+            let named = ["dockerfile", "makefile", "cargo"].contains(&name_lower.as_str());
+            (true, !named)
         } else {
-            false
+            (false, false)
         };
 
         if !has_valid_ext {
             return Ok(None);
         }
 
-        match fs::read_to_string(path) {
-            Ok(content) => {
-                if content.len() > 1_000_000 || content.contains('\0') {
-                    return Ok(None);
-                }
+        let content = match self.read_source(path) {
+            Ok(Some(content)) => content,
+            _ => return Ok(None),
+        };
 
-                Ok(Some(ScannedFile {
-                    path: path.to_string_lossy().to_string(),
-                    content,
-                    language: detect_language(path),
-                }))
-            }
-            Err(_) => Ok(None),
+        let language =
+            detect_language(path).or_else(|| shebang_language(&content).map(str::to_string));
+        if needs_shebang && language.is_none() {
+            return Ok(None);
+        }
+        if !self.language_allowed(language.as_deref()) {
+            return Ok(None);
         }
+
+        Ok(Some(ScannedFile {
+            path: path.to_string_lossy().to_string(),
+            content,
+            language,
+        }))
     }
 }
 
@@ -188,14 +361,14 @@ pub struct ScannedFile {
     pub language: Option<String>,
 }
 
-fn detect_language(path: &Path) -> Option<String> {
+pub fn detect_language(path: &Path) -> Option<String> {
     let ext = path.extension()?.to_string_lossy().to_lowercase();
 
     let lang = match ext.as_str() {
         "rs" => "rust",
-        "ts" | "tsx" => "typescript",
+        "ts" | "tsx" | "mts" | "cts" => "typescript",
         "js" | "jsx" => "javascript",
-        "py" => "python",
+        "py" | "pyi" => "python",
         "go" => "go",
         "java" => "java",
         "c" | "h" => "c",
@@ -233,7 +406,204 @@ fn detect_language(path: &Path) -> Option<String> {
     Some(lang.to_string())
 }
 
+/// Language of a script from its `#!` line, e.g. `#!/usr/bin/env python3`
+///
+/// Covers interpreters run directly (`#!/bin/bash`) and through `env`,
+/// ignoring version suffixes (`python3.11`) and `env` flags (`env -S node`).
+pub fn shebang_language(content: &str) -> Option<&'static str> {
+    let line = content.lines().next()?.strip_prefix("#!")?;
+    let mut words = line.split_whitespace();
+    let mut interpreter = words.next()?.rsplit('/').next()?;
+    if interpreter == "env" {
+        interpreter = words.find(|w| !w.starts_with('-') && !w.contains('='))?;
+    }
+    let interpreter = interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+
+    let lang = match interpreter {
+        "python" | "pypy" => "python",
+        "node" | "nodejs" | "bun" => "javascript",
+        "deno" | "ts-node" | "tsx" => "typescript",
+        "ruby" => "ruby",
+        "php" => "php",
+        "swift" => "swift",
+        "sh" | "bash" | "zsh" | "dash" | "ksh" => "shell",
+        _ => return None,
+    };
+    Some(lang)
+}
+
+/// Matcher for the root `.gitignore` and `IGNORE_FILES`, for checking single
+/// paths (e.g. watch events) the way `scan` would
+pub fn ignore_matcher(root: &Path) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(root);
+    for name in [".gitignore"].iter().chain(IGNORE_FILES) {
+        let path = root.join(name);
+        if path.is_file() {
+            // Unparseable lines are skipped, as `scan` does
+            let _ = builder.add(path);
+        }
+    }
+    builder.build().unwrap_or_else(|_| Gitignore::empty())
+}
+
 pub fn get_file_type(path: &str) -> Option<String> {
     let path = Path::new(path);
     path.extension().map(|e| e.to_string_lossy().to_lowercase())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ignore_files_exclude_paths() {
+        let root = std::env::temp_dir().join(format!("sgrep-scan-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for dir in ["src", "target/debug", "vendor"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        fs::write(root.join(".searchgrepignore"), "vendor/\n").unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(root.join("target/debug/build.rs"), "fn build() {}\n").unwrap();
+        fs::write(root.join("vendor/dep.rs"), "fn dep() {}\n").unwrap();
+
+        let scanned = |scanner: FileScanner| -> Vec<String> {
+            let mut paths: Vec<String> = scanner
+                .scan()
+                .unwrap()
+                .into_iter()
+                .map(|f| {
+                    let path = Path::new(&f.path).strip_prefix(&root).unwrap();
+                    path.to_string_lossy().replace('\\', "/")
+                })
+                .collect();
+            paths.sort();
+            paths
+        };
+
+        // Not a git repository: .gitignore applies anyway
+        let root_str = root.to_string_lossy();
+        assert_eq!(scanned(FileScanner::new(&root_str)), ["src/main.rs"]);
+        assert_eq!(
+            scanned(FileScanner::new(&root_str).with_include_ignored(true)),
+            ["src/main.rs", "target/debug/build.rs", "vendor/dep.rs"]
+        );
+
+        let matcher = ignore_matcher(&root);
+        assert!(matcher
+            .matched_path_or_any_parents("vendor/dep.rs", false)
+            .is_ignore());
+        assert!(!matcher
+            .matched_path_or_any_parents("src/main.rs", false)
+            .is_ignore());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_binary_oversized_and_minified_files_skipped() {
+        let root = std::env::temp_dir().join(format!("sgrep-skip-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("main.rs"), "fn main() {}\n").unwrap();
+        fs::write(
+            root.join("logo.json"),
+            [0x89, b'P', b'N', b'G', 0x00, 0xff, 0xfe],
+        )
+        .unwrap();
+        fs::write(root.join("generated.rs"), "// generated\n".repeat(500)).unwrap();
+        fs::write(
+            root.join("bundle.min.js"),
+            format!("var a={};\n", "1+".repeat(400)),
+        )
+        .unwrap();
+
+        let report = FileScanner::new(&root.to_string_lossy())
+            .with_limits(2_000, 500)
+            .scan_report()
+            .unwrap();
+
+        let files: Vec<&str> = report.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(files, [root.join("main.rs").to_string_lossy()]);
+
+        let mut skipped: Vec<(String, SkipReason)> = report
+            .skipped
+            .into_iter()
+            .map(|s| {
+                let name = Path::new(&s.path).file_name().unwrap();
+                (name.to_string_lossy().to_string(), s.reason)
+            })
+            .collect();
+        skipped.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            skipped,
+            [
+                ("bundle.min.js".to_string(), SkipReason::Minified(807)),
+                ("generated.rs".to_string(), SkipReason::TooLarge(6_500)),
+                ("logo.json".to_string(), SkipReason::Binary),
+            ]
+        );
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_language_filter() {
+        let root = std::env::temp_dir().join(format!("sgrep-langs-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("web")).unwrap();
+        fs::write(root.join("main.rs"), "fn main() {}\n").unwrap();
+        fs::write(root.join("server.go"), "package main\n").unwrap();
+        fs::write(root.join("web/client.ts"), "export const x = 1;\n").unwrap();
+        fs::write(root.join("deploy"), "#!/bin/bash\necho hi\n").unwrap();
+        fs::write(root.join("notes.txt"), "todo\n").unwrap();
+
+        let root_str = root.to_string_lossy();
+        let names = |include: &[&str], exclude: &[&str]| -> Vec<String> {
+            let include: Vec<String> = include.iter().map(|s| s.to_string()).collect();
+            let exclude: Vec<String> = exclude.iter().map(|s| s.to_string()).collect();
+            let mut names: Vec<String> = FileScanner::new(&root_str)
+                .with_languages(&include, &exclude)
+                .scan()
+                .unwrap()
+                .into_iter()
+                .map(|f| {
+                    Path::new(&f.path)
+                        .file_name()
+                        .unwrap()
+                        .to_string_lossy()
+                        .to_string()
+                })
+                .collect();
+            names.sort();
+            names
+        };
+
+        assert_eq!(
+            names(&[], &[]),
+            ["client.ts", "deploy", "main.rs", "notes.txt", "server.go"]
+        );
+        assert_eq!(names(&["rust"], &[]), ["main.rs"]);
+        assert_eq!(names(&["Rust", "go"], &[]), ["main.rs", "server.go"]);
+        // Shebang scripts are classified by their interpreter
+        assert_eq!(names(&["shell"], &[]), ["deploy"]);
+        assert_eq!(
+            names(&[], &["typescript"]),
+            ["deploy", "main.rs", "notes.txt", "server.go"]
+        );
+        assert_eq!(names(&["rust", "go"], &["go"]), ["main.rs"]);
+
+        let scanner = FileScanner::new(&root_str).with_languages(&["go".to_string()], &[]);
+        assert!(scanner
+            .scan_single(&root.join("main.rs"))
+            .unwrap()
+            .is_none());
+        assert!(scanner
+            .scan_single(&root.join("server.go"))
+            .unwrap()
+            .is_some());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
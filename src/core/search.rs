@@ -1,3 +1,5 @@
+use anyhow::Result;
+use serde::Serialize;
 use std::collections::HashMap;
 
 use crate::core::embeddings::{colbert_max_sim, cosine_similarity};
@@ -12,20 +14,47 @@ pub struct SearchResult {
     pub colbert_score: Option<f32>,
 }
 
+/// How a result's combined score was composed (weights already applied)
+#[derive(Debug, Clone, Serialize)]
+pub struct ScoreBreakdown {
+    /// Embedding (cosine) contribution
+    pub embedding: f32,
+    /// BM25 contribution, sigmoid-normalized
+    pub lexical: f32,
+    /// ColBERT token-matching contribution, when enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub colbert: Option<f32>,
+    /// Final combined score
+    pub total: f32,
+}
+
+/// MMR picks from this many times `limit` of the top-ranked candidates
+const MMR_POOL_FACTOR: usize = 4;
+
+/// Without MMR, the ANN fast path fetches this many times `limit` candidates
+/// to leave room for file-type filtering and re-scoring
+const ANN_POOL_FACTOR: usize = 3;
+
+/// Share of the combined score given to embedding similarity by default
+pub const DEFAULT_SEMANTIC_WEIGHT: f32 = 0.7;
+
 pub struct HybridSearcher {
     bm25_weight: f32,
     vector_weight: f32,
     k1: f32,
     b: f32,
+    /// MMR relevance weight (lambda); None ranks by relevance alone
+    mmr_lambda: Option<f32>,
 }
 
 impl Default for HybridSearcher {
     fn default() -> Self {
         Self {
-            bm25_weight: 0.3,
-            vector_weight: 0.7,
+            bm25_weight: 1.0 - DEFAULT_SEMANTIC_WEIGHT,
+            vector_weight: DEFAULT_SEMANTIC_WEIGHT,
             k1: 1.2,
             b: 0.75,
+            mmr_lambda: None,
         }
     }
 }
@@ -35,9 +64,30 @@ impl HybridSearcher {
         Self {
             bm25_weight,
             vector_weight,
-            k1: 1.2,
-            b: 0.75,
+            ..Self::default()
+        }
+    }
+
+    /// Weigh embedding similarity against BM25 keyword matching
+    ///
+    /// Weights are relative: they're normalized to sum to 1, so `(2.0, 1.0)`
+    /// and `(0.66, 0.33)` behave the same. Negative weights count as zero, and
+    /// if neither is positive the default balance is used.
+    pub fn with_weights(semantic: f32, lexical: f32) -> Self {
+        let (semantic, lexical) = (semantic.max(0.0), lexical.max(0.0));
+        let total = semantic + lexical;
+        if total <= 0.0 || !total.is_finite() {
+            return Self::default();
         }
+        Self::new(lexical / total, semantic / total)
+    }
+
+    /// Rerank with Maximal Marginal Relevance; `diversity` in 0..=1 trades
+    /// relevance for dissimilarity to results already picked (0 = off)
+    pub fn with_diversity(mut self, diversity: f32) -> Self {
+        let diversity = diversity.clamp(0.0, 1.0);
+        self.mmr_lambda = (diversity > 0.0).then_some(1.0 - diversity);
+        self
     }
 
     pub fn search(
@@ -50,26 +100,17 @@ impl HybridSearcher {
         use_colbert: bool,
         query_token_embeddings: Option<&[Vec<f32>]>,
     ) -> Vec<SearchResult> {
-        let query_terms: Vec<String> = query_text
-            .to_lowercase()
-            .split_whitespace()
-            .map(|s| s.to_string())
-            .collect();
+        // No embedding (model unavailable, or lexical mode): keywords only
+        if query_embedding.is_empty() {
+            return self.lexical_search(store, query_text, limit, file_types);
+        }
 
-        let avg_doc_len = if store.doc_count > 0 {
-            store
-                .chunks
-                .values()
-                .map(|c| c.content.len())
-                .sum::<usize>() as f32
-                / store.doc_count as f32
-        } else {
-            500.0
-        };
+        let query_terms = query_terms(query_text);
+        let avg_doc_len = Self::avg_doc_len(store);
 
         // Try ANN fast path first (for large indexes)
         // Fetch more candidates than limit to allow for filtering and reranking
-        let ann_candidates = store.ann_search(query_embedding, limit * 3);
+        let ann_candidates = store.ann_search(query_embedding, self.candidate_pool(limit));
 
         let chunks_iter: Box<dyn Iterator<Item = &FileChunk>> =
             if let Some(ref candidates) = ann_candidates {
@@ -85,24 +126,10 @@ impl HybridSearcher {
             };
 
         let mut results: Vec<SearchResult> = chunks_iter
-            .filter(|chunk| {
-                // Filter by file type if specified
-                if let Some(types) = file_types {
-                    let file_ext = std::path::Path::new(&chunk.file_path)
-                        .extension()
-                        .map(|e| e.to_string_lossy().to_lowercase());
-
-                    match file_ext {
-                        Some(ext) => types.iter().any(|t| t.to_lowercase() == ext),
-                        None => false,
-                    }
-                } else {
-                    true
-                }
-            })
+            .filter(|chunk| matches_file_types(chunk, file_types))
             .map(|chunk| {
                 // Vector similarity (recompute for exact score, ANN gives approximate)
-                let vector_score = cosine_similarity(query_embedding, &chunk.embedding);
+                let vector_score = cosine_similarity(query_embedding, &store.embedding(chunk));
 
                 // BM25 score
                 let bm25_score =
@@ -148,11 +175,151 @@ impl HybridSearcher {
                 .unwrap_or(std::cmp::Ordering::Equal)
         });
 
+        if let Some(lambda) = self.mmr_lambda {
+            results.truncate(limit.saturating_mul(MMR_POOL_FACTOR));
+            return Self::mmr(store, results, lambda, limit);
+        }
+
         // Return top results
         results.truncate(limit);
         results
     }
 
+    /// ANN candidates to fetch for `limit` results; MMR needs its whole pool
+    fn candidate_pool(&self, limit: usize) -> usize {
+        match self.mmr_lambda {
+            Some(_) => limit.saturating_mul(MMR_POOL_FACTOR),
+            None => limit.saturating_mul(ANN_POOL_FACTOR),
+        }
+    }
+
+    /// Greedily pick the candidate maximizing
+    /// `lambda * score - (1 - lambda) * max similarity to those already picked`
+    fn mmr(
+        store: &VectorStore,
+        mut candidates: Vec<SearchResult>,
+        lambda: f32,
+        limit: usize,
+    ) -> Vec<SearchResult> {
+        let embeddings: Vec<Vec<f32>> = candidates
+            .iter()
+            .map(|r| store.embedding(&r.chunk).into_owned())
+            .collect();
+        // Highest similarity of each candidate to any selected result
+        let mut max_sim = vec![f32::NEG_INFINITY; candidates.len()];
+        let mut remaining: Vec<usize> = (0..candidates.len()).collect();
+        let mut order = Vec::with_capacity(limit.min(candidates.len()));
+
+        while order.len() < limit && !remaining.is_empty() {
+            let marginal = |i: usize| {
+                let redundancy = if order.is_empty() { 0.0 } else { max_sim[i] };
+                lambda * candidates[i].score - (1.0 - lambda) * redundancy
+            };
+            let (pos, &picked) = remaining
+                .iter()
+                .enumerate()
+                .max_by(|(_, &a), (_, &b)| {
+                    marginal(a)
+                        .partial_cmp(&marginal(b))
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                        // Prefer the better-ranked candidate on ties
+                        .then(b.cmp(&a))
+                })
+                .expect("remaining is non-empty");
+            remaining.swap_remove(pos);
+            order.push(picked);
+
+            for &i in &remaining {
+                let sim = cosine_similarity(&embeddings[i], &embeddings[picked]);
+                max_sim[i] = max_sim[i].max(sim);
+            }
+        }
+
+        let mut picked: Vec<Option<SearchResult>> = candidates.drain(..).map(Some).collect();
+        order.into_iter().filter_map(|i| picked[i].take()).collect()
+    }
+
+    /// Keyword-only BM25 ranking, usable without an embedding model
+    ///
+    /// Chunks matching no query term are left out. `score` is relative to the
+    /// best hit (1.0), so it stays in the same 0-1 range as hybrid scores.
+    pub fn lexical_search(
+        &self,
+        store: &VectorStore,
+        query_text: &str,
+        limit: usize,
+        file_types: Option<&[String]>,
+    ) -> Vec<SearchResult> {
+        let query_terms = query_terms(query_text);
+        let avg_doc_len = Self::avg_doc_len(store);
+
+        let mut results: Vec<SearchResult> = store
+            .chunks
+            .values()
+            .filter(|chunk| matches_file_types(chunk, file_types))
+            .filter_map(|chunk| {
+                let bm25_score =
+                    self.compute_bm25(&chunk.content, &query_terms, &store.bm25_idf, avg_doc_len);
+                (bm25_score > 0.0).then(|| SearchResult {
+                    chunk: chunk.clone(),
+                    score: bm25_score,
+                    bm25_score,
+                    vector_score: 0.0,
+                    colbert_score: None,
+                })
+            })
+            .collect();
+
+        results.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.chunk.file_path.cmp(&b.chunk.file_path))
+                .then(a.chunk.start_line.cmp(&b.chunk.start_line))
+        });
+        results.truncate(limit);
+
+        if let Some(best) = results.first().map(|r| r.score) {
+            for result in &mut results {
+                result.score /= best;
+            }
+        }
+        results
+    }
+
+    fn avg_doc_len(store: &VectorStore) -> f32 {
+        if store.doc_count > 0 {
+            store
+                .chunks
+                .values()
+                .map(|c| c.content.len())
+                .sum::<usize>() as f32
+                / store.doc_count as f32
+        } else {
+            500.0
+        }
+    }
+
+    /// Break a result's score down into the weighted components used by `search`
+    pub fn explain(&self, result: &SearchResult) -> ScoreBreakdown {
+        let lexical = self.bm25_weight * Self::normalize_bm25(result.bm25_score);
+
+        match result.colbert_score {
+            Some(col_score) => ScoreBreakdown {
+                embedding: self.vector_weight * 0.5 * result.vector_score,
+                lexical,
+                colbert: Some(self.vector_weight * 0.5 * col_score),
+                total: result.score,
+            },
+            None => ScoreBreakdown {
+                embedding: self.vector_weight * result.vector_score,
+                lexical,
+                colbert: None,
+                total: result.score,
+            },
+        }
+    }
+
     fn compute_bm25(
         &self,
         content: &str,
@@ -190,6 +357,104 @@ impl HybridSearcher {
     }
 }
 
+/// Fail when a query embedding can't be compared with the index's embeddings
+///
+/// Vectors of different lengths still produce a cosine score (over the shared
+/// prefix), just a meaningless one, so a model switch would otherwise show up
+/// as plausible-looking garbage. Empty queries (keyword search) and empty
+/// stores always pass.
+pub fn check_query_dim(store: &VectorStore, query_embedding: &[f32]) -> Result<()> {
+    let index_dim = store.embedding_dim();
+    if query_embedding.is_empty() || index_dim == 0 || query_embedding.len() == index_dim {
+        return Ok(());
+    }
+
+    let built_with = match store.model.as_deref() {
+        Some(model) => format!("model '{}'", model),
+        None => "a different model".to_string(),
+    };
+    anyhow::bail!(
+        "Embedding dimension mismatch: the query embedding has {} dimensions but the index \
+         holds {}-dimensional embeddings from {}. Search with the mode or model the index was \
+         built with, or re-index with the one you want to search with.",
+        query_embedding.len(),
+        index_dim,
+        built_with
+    )
+}
+
+/// Terms BM25 scores a query by: lowercased, whitespace-separated words
+pub fn query_terms(query_text: &str) -> Vec<String> {
+    query_text
+        .to_lowercase()
+        .split_whitespace()
+        .map(|s| s.to_string())
+        .collect()
+}
+
+/// Wrap each word of `text` that BM25 would match against `terms` in `**`
+///
+/// Words are compared exactly as the scorer compares them (whitespace split,
+/// case-insensitive), so only words that counted toward the score are marked.
+/// Whitespace is preserved.
+pub fn highlight_terms(text: &str, terms: &[String]) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while !rest.is_empty() {
+        let word_start = rest
+            .find(|c: char| !c.is_whitespace())
+            .unwrap_or(rest.len());
+        output.push_str(&rest[..word_start]);
+        rest = &rest[word_start..];
+
+        let word_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let word = &rest[..word_end];
+        if !word.is_empty() && terms.contains(&word.to_lowercase()) {
+            output.push_str("**");
+            output.push_str(word);
+            output.push_str("**");
+        } else {
+            output.push_str(word);
+        }
+        rest = &rest[word_end..];
+    }
+    output
+}
+
+/// Clean up a pasted query before embedding
+///
+/// Strips surrounding markdown code fences (including a language tag) or
+/// inline backticks, and collapses runs of whitespace to single spaces.
+pub fn normalize_query(query: &str) -> String {
+    let mut text = query.trim();
+
+    if let Some(rest) = text.strip_prefix("```") {
+        // Drop the opening fence line (e.g. "```rust") and a closing fence
+        text = rest.split_once('\n').map(|(_, body)| body).unwrap_or("");
+        text = text.trim_end();
+        text = text.strip_suffix("```").unwrap_or(text);
+    } else if text.len() > 1 && text.starts_with('`') && text.ends_with('`') {
+        text = text.trim_matches('`');
+    }
+
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Whether a chunk's extension is in `file_types` (always true without a filter)
+fn matches_file_types(chunk: &FileChunk, file_types: Option<&[String]>) -> bool {
+    let Some(types) = file_types else {
+        return true;
+    };
+    let file_ext = std::path::Path::new(&chunk.file_path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase());
+
+    match file_ext {
+        Some(ext) => types.iter().any(|t| t.to_lowercase() == ext),
+        None => false,
+    }
+}
+
 // Quick vector-only search (uses ANN when available)
 pub fn vector_search(
     store: &VectorStore,
@@ -198,7 +463,7 @@ pub fn vector_search(
     file_types: Option<&[String]>,
 ) -> Vec<SearchResult> {
     // Try ANN fast path
-    let ann_candidates = store.ann_search(query_embedding, limit * 3);
+    let ann_candidates = store.ann_search(query_embedding, limit.saturating_mul(ANN_POOL_FACTOR));
 
     let chunks_iter: Box<dyn Iterator<Item = &FileChunk>> =
         if let Some(ref candidates) = ann_candidates {
@@ -212,22 +477,9 @@ pub fn vector_search(
         };
 
     let mut results: Vec<SearchResult> = chunks_iter
-        .filter(|chunk| {
-            if let Some(types) = file_types {
-                let file_ext = std::path::Path::new(&chunk.file_path)
-                    .extension()
-                    .map(|e| e.to_string_lossy().to_lowercase());
-
-                match file_ext {
-                    Some(ext) => types.iter().any(|t| t.to_lowercase() == ext),
-                    None => false,
-                }
-            } else {
-                true
-            }
-        })
+        .filter(|chunk| matches_file_types(chunk, file_types))
         .map(|chunk| {
-            let score = cosine_similarity(query_embedding, &chunk.embedding);
+            let score = cosine_similarity(query_embedding, &store.embedding(chunk));
             SearchResult {
                 chunk: chunk.clone(),
                 score,
@@ -246,3 +498,197 @@ pub fn vector_search(
     results.truncate(limit);
     results
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::store::generate_chunk_id;
+
+    fn chunk(file: &str, start: usize, embedding: Vec<f32>) -> FileChunk {
+        FileChunk {
+            id: generate_chunk_id(file, start, start + 9),
+            file_path: file.to_string(),
+            content: String::new(),
+            start_line: start,
+            end_line: start + 9,
+            chunk_type: "block".to_string(),
+            language: None,
+            embedding,
+            token_embeddings: None,
+            symbol_name: None,
+            parent_name: None,
+            hierarchy_path: None,
+        }
+    }
+
+    #[test]
+    fn test_lexical_search_ranks_matching_chunk_first() {
+        let mut store = VectorStore::default();
+        let contents = [
+            (
+                "config.rs",
+                "fn parse config file and apply config defaults",
+            ),
+            (
+                "server.rs",
+                "fn start server and listen on the configured port",
+            ),
+            ("retry.rs", "fn retry request with exponential backoff"),
+            ("notes.rs", "the config is parse d elsewhere"),
+        ];
+        for (file, content) in contents {
+            let mut chunk = chunk(file, 1, vec![]);
+            chunk.content = content.to_string();
+            store.add_chunk(chunk);
+        }
+        store.update_bm25_stats();
+
+        let searcher = HybridSearcher::default();
+        let results = searcher.lexical_search(&store, "parse config", 10, None);
+        assert_eq!(results[0].chunk.file_path, "config.rs");
+        assert_eq!(results[0].score, 1.0);
+        assert!(results.iter().all(|r| r.score <= 1.0 && r.bm25_score > 0.0));
+        // Chunks without any query term are left out
+        assert!(results.iter().all(|r| r.chunk.file_path != "retry.rs"));
+
+        // An empty query embedding falls back to the same ranking
+        let fallback = searcher.search(&store, &[], "parse config", 10, None, false, None);
+        let ids = |results: &[SearchResult]| -> Vec<String> {
+            results.iter().map(|r| r.chunk.id.clone()).collect()
+        };
+        assert_eq!(ids(&fallback), ids(&results));
+    }
+
+    #[test]
+    fn test_lexical_weight_favors_exact_identifier() {
+        let mut store = VectorStore::default();
+        // Conceptually closest to the query, but never names the identifier
+        let mut conceptual = chunk("loader.rs", 1, vec![1.0, 0.0, 0.0]);
+        conceptual.content = "fn load settings from disk and fill in defaults".to_string();
+        store.add_chunk(conceptual);
+        // Slightly further in embedding space, with an exact identifier match
+        let mut exact = chunk("config.rs", 1, vec![0.97, 0.243, 0.0]);
+        exact.content = "fn parse_config reads the file".to_string();
+        store.add_chunk(exact);
+        for (i, content) in ["fn start_server binds the port", "fn retry sleeps"]
+            .into_iter()
+            .enumerate()
+        {
+            let mut other = chunk("other.rs", i * 10 + 1, vec![0.0, 0.0, 1.0]);
+            other.content = content.to_string();
+            store.add_chunk(other);
+        }
+        store.update_bm25_stats();
+
+        let query = [1.0, 0.0, 0.0];
+        let top = |searcher: HybridSearcher| {
+            let results = searcher.search(&store, &query, "parse_config", 2, None, false, None);
+            results[0].chunk.file_path.clone()
+        };
+
+        assert_eq!(top(HybridSearcher::default()), "loader.rs");
+        assert_eq!(top(HybridSearcher::with_weights(0.2, 0.8)), "config.rs");
+        // Weights are relative
+        assert_eq!(top(HybridSearcher::with_weights(1.0, 4.0)), "config.rs");
+        assert_eq!(top(HybridSearcher::with_weights(0.0, 0.0)), "loader.rs");
+    }
+
+    #[test]
+    fn test_diversity_spreads_results_across_files() {
+        let mut store = VectorStore::default();
+        // A cluster of near-duplicates in one file, closest to the query
+        for i in 0..6 {
+            let jitter = i as f32 * 0.01;
+            store.add_chunk(chunk("a.rs", i * 10 + 1, vec![1.0, jitter, 0.0, 0.0]));
+        }
+        // Slightly less relevant matches elsewhere, each in its own direction
+        store.add_chunk(chunk("b.rs", 1, vec![0.8, 0.0, 0.6, 0.0]));
+        store.add_chunk(chunk("c.rs", 1, vec![0.8, 0.0, 0.0, 0.6]));
+        store.add_chunk(chunk("d.rs", 1, vec![0.8, 0.0, -0.6, 0.0]));
+
+        let query = [1.0, 0.0, 0.0, 0.0];
+        let files = |searcher: &HybridSearcher| {
+            let results = searcher.search(&store, &query, "", 4, None, false, None);
+            assert_eq!(results.len(), 4);
+            results
+                .into_iter()
+                .map(|r| r.chunk.file_path)
+                .collect::<std::collections::HashSet<_>>()
+                .len()
+        };
+
+        assert_eq!(files(&HybridSearcher::default()), 1);
+        assert_eq!(files(&HybridSearcher::default().with_diversity(0.0)), 1);
+        assert!(files(&HybridSearcher::default().with_diversity(0.7)) >= 3);
+
+        // On large indexes the ANN fast path must fetch MMR's whole pool
+        assert_eq!(HybridSearcher::default().candidate_pool(10), 30);
+        assert_eq!(
+            HybridSearcher::default()
+                .with_diversity(0.5)
+                .candidate_pool(10),
+            10 * MMR_POOL_FACTOR
+        );
+        assert_eq!(
+            HybridSearcher::default().candidate_pool(usize::MAX),
+            usize::MAX
+        );
+    }
+
+    #[test]
+    fn test_highlight_terms() {
+        let terms = query_terms("Parse CONFIG");
+        assert_eq!(
+            highlight_terms("fn parse(path) {\n\tlet config = Config::load();", &terms),
+            "fn parse(path) {\n\tlet **config** = Config::load();"
+        );
+        assert_eq!(
+            highlight_terms("  parse the\tconfig  ", &terms),
+            "  **parse** the\t**config**  "
+        );
+        assert_eq!(highlight_terms("nothing here", &terms), "nothing here");
+        assert_eq!(highlight_terms("parse config", &[]), "parse config");
+    }
+
+    #[test]
+    fn test_normalize_query_strips_fences() {
+        let fenced = "```rust\nfn  parse_config(path: &str)\n    -> Config\n```\n";
+        assert_eq!(
+            normalize_query(fenced),
+            "fn parse_config(path: &str) -> Config"
+        );
+        assert_eq!(
+            normalize_query("`retry_with_backoff`"),
+            "retry_with_backoff"
+        );
+        assert_eq!(
+            normalize_query("  where is   auth handled? "),
+            "where is auth handled?"
+        );
+    }
+
+    #[test]
+    fn test_query_dimension_mismatch_is_an_error() {
+        let mut store = VectorStore::default();
+        assert!(check_query_dim(&store, &[0.5; 768]).is_ok());
+
+        store.add_chunk(chunk("lib.rs", 1, vec![0.1; 384]));
+        assert!(check_query_dim(&store, &[0.5; 384]).is_ok());
+        // Keyword-only searches carry no embedding
+        assert!(check_query_dim(&store, &[]).is_ok());
+
+        store.model = Some("sentence-transformers/all-MiniLM-L6-v2".to_string());
+        let err = check_query_dim(&store, &[0.5; 768])
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("query embedding has 768 dimensions"),
+            "{}",
+            err
+        );
+        assert!(err.contains(
+            "384-dimensional embeddings from model 'sentence-transformers/all-MiniLM-L6-v2'"
+        ));
+        assert!(err.contains("re-index"));
+    }
+}
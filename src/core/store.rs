@@ -1,15 +1,19 @@
 use anyhow::{Context, Result};
+use memmap2::Mmap;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::core::config::Config;
+use crate::core::config::{default_store_compression_level, Config};
 use crate::core::graph::KnowledgeGraph;
+use crate::core::lock::{write_atomic, IndexLock};
 use crate::core::vector_index::VectorIndex;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FileChunk {
     pub id: String,
     pub file_path: String,
@@ -32,7 +36,7 @@ pub struct FileChunk {
     pub hierarchy_path: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct IndexedFile {
     pub path: String,
     pub hash: String,
@@ -40,6 +44,164 @@ pub struct IndexedFile {
     pub indexed_at: String,
 }
 
+/// Header of a zstd-compressed binary store; older stores are plain bincode
+const COMPRESSED_MAGIC: &[u8; 4] = b"SGZ1";
+
+/// Header of an int8-quantized payload (inside any compression)
+const INT8_MAGIC: &[u8; 4] = b"SGQ8";
+
+/// How embeddings are written to disk
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Quantization {
+    /// Full-precision f32
+    #[default]
+    None,
+    /// int8 with a per-vector scale, a quarter of the size; vectors are
+    /// dequantized to approximate f32 on load, so search is unchanged
+    Int8,
+}
+
+impl Quantization {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Quantization::None => "none",
+            Quantization::Int8 => "int8",
+        }
+    }
+}
+
+/// int8 embedding; each value is approximately `q * scale`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Int8Embedding {
+    scale: f32,
+    values: Vec<i8>,
+}
+
+impl Int8Embedding {
+    fn quantize(vector: &[f32]) -> Self {
+        let max = vector.iter().fold(0.0f32, |max, x| max.max(x.abs()));
+        let scale = if max > 0.0 { max / 127.0 } else { 1.0 };
+        Self {
+            scale,
+            values: vector
+                .iter()
+                .map(|x| (x / scale).round().clamp(-127.0, 127.0) as i8)
+                .collect(),
+        }
+    }
+
+    fn dequantize(&self) -> Vec<f32> {
+        self.values.iter().map(|&q| q as f32 * self.scale).collect()
+    }
+}
+
+/// Store data with the embeddings moved out into int8 form
+#[derive(Serialize, Deserialize)]
+struct QuantizedStoreData {
+    data: VectorStoreData,
+    embeddings: HashMap<String, Int8Embedding>,
+}
+
+/// Header of a split payload, whose embeddings live in the `.vec` file
+const SPLIT_MAGIC: &[u8; 4] = b"SGSP";
+
+/// Header of a `.vec` file: magic, dim (u32), rows (u64), stamp (u64),
+/// followed by the rows as little-endian f32
+const VECTORS_MAGIC: &[u8; 4] = b"SGV1";
+const VECTORS_HEADER_LEN: usize = 24;
+
+/// Store data with the embeddings moved out to the `.vec` file
+#[derive(Serialize, Deserialize)]
+struct SplitStoreData {
+    data: VectorStoreData,
+    layout: VectorLayout,
+}
+
+/// Where each chunk's embedding sits in the `.vec` file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct VectorLayout {
+    dim: usize,
+    /// Chunk id of each row
+    rows: Vec<String>,
+    /// Written to both files, so a `.vec` from another save is rejected
+    stamp: u64,
+}
+
+impl VectorLayout {
+    fn header(&self) -> Vec<u8> {
+        let mut header = VECTORS_MAGIC.to_vec();
+        header.extend((self.dim as u32).to_le_bytes());
+        header.extend((self.rows.len() as u64).to_le_bytes());
+        header.extend(self.stamp.to_le_bytes());
+        header
+    }
+
+    /// Check `bytes` is the `.vec` file written alongside this layout
+    fn check(&self, bytes: &[u8]) -> Result<()> {
+        let expected_len = VECTORS_HEADER_LEN + self.rows.len() * self.dim * 4;
+        if !bytes.starts_with(&self.header()) || bytes.len() != expected_len {
+            anyhow::bail!(
+                "Vector file doesn't match the store (saved concurrently?); retry, or re-index"
+            );
+        }
+        Ok(())
+    }
+
+    fn row<'a>(&self, bytes: &'a [u8], row: usize) -> &'a [u8] {
+        let start = VECTORS_HEADER_LEN + row * self.dim * 4;
+        &bytes[start..start + self.dim * 4]
+    }
+
+    /// Copy every row back into its chunk
+    fn fill(&self, chunks: &mut HashMap<String, FileChunk>, bytes: &[u8]) -> Result<()> {
+        self.check(bytes)?;
+        for (row, id) in self.rows.iter().enumerate() {
+            if let Some(chunk) = chunks.get_mut(id) {
+                chunk.embedding = read_f32s(self.row(bytes, row));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Memory-mapped `.vec` file; rows are decoded on access
+struct MappedVectors {
+    mmap: Mmap,
+    layout: VectorLayout,
+    rows: HashMap<String, usize>,
+}
+
+impl MappedVectors {
+    fn open(path: &Path, layout: VectorLayout) -> Result<Self> {
+        let file = fs::File::open(path)
+            .with_context(|| format!("Failed to open vector file {}", path.display()))?;
+        // Saves replace the file by rename and never write to it in place,
+        // so the mapping stays valid while this store is alive
+        let mmap = unsafe { Mmap::map(&file)? };
+        layout.check(&mmap)?;
+
+        let rows = layout
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(row, id)| (id.clone(), row))
+            .collect();
+        Ok(Self { mmap, layout, rows })
+    }
+
+    fn get(&self, chunk_id: &str) -> Option<Vec<f32>> {
+        let row = *self.rows.get(chunk_id)?;
+        Some(read_f32s(self.layout.row(&self.mmap, row)))
+    }
+}
+
+fn read_f32s(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
 /// Serializable store data (no usearch index)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VectorStoreData {
@@ -51,6 +213,17 @@ pub struct VectorStoreData {
     pub doc_count: usize,
 }
 
+/// Index metadata kept in a sidecar file so the bincode layout stays compatible
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct StoreMeta {
+    /// Embedding model the index was built with
+    #[serde(default)]
+    model: Option<String>,
+    /// Chunks were embedded with a file path header
+    #[serde(default)]
+    embed_path_context: bool,
+}
+
 /// Vector store with optional usearch ANN index and knowledge graph
 pub struct VectorStore {
     pub files: HashMap<String, IndexedFile>,
@@ -63,6 +236,14 @@ pub struct VectorStore {
     ann_threshold: usize,
     /// Knowledge graph for relationships
     pub graph: KnowledgeGraph,
+    /// Embedding model the index was built with (None for older indexes)
+    pub model: Option<String>,
+    /// Chunks were embedded with a file path header (see `path_context_text`)
+    pub embed_path_context: bool,
+    /// On-disk embedding format; kept in the file header, so it survives reloads
+    pub quantization: Quantization,
+    /// Embeddings left on disk by `load_mmap`; such a store is read-only
+    vectors: Option<MappedVectors>,
 }
 
 impl Default for VectorStore {
@@ -75,6 +256,10 @@ impl Default for VectorStore {
             ann_index: None,
             ann_threshold: 1000, // Use brute force below 1K chunks
             graph: KnowledgeGraph::new(),
+            model: None,
+            embed_path_context: false,
+            quantization: Quantization::None,
+            vectors: None,
         }
     }
 }
@@ -101,6 +286,114 @@ impl VectorStore {
         Ok(config_dir.join(format!("{}.graph.bin", name)))
     }
 
+    /// Raw embedding rows of a binary store (memory-mapped by `load_mmap`)
+    pub fn vectors_path(store_name: Option<&str>) -> Result<PathBuf> {
+        let config_dir = Config::config_dir()?;
+        let name = store_name.unwrap_or("default");
+        Ok(config_dir.join(format!("{}.vec", name)))
+    }
+
+    /// Index metadata path
+    pub fn meta_path(store_name: Option<&str>) -> Result<PathBuf> {
+        let config_dir = Config::config_dir()?;
+        let name = store_name.unwrap_or("default");
+        Ok(config_dir.join(format!("{}.meta.json", name)))
+    }
+
+    /// Writer lock path
+    pub fn lock_path(store_name: Option<&str>) -> Result<PathBuf> {
+        let config_dir = Config::config_dir()?;
+        let name = store_name.unwrap_or("default");
+        Ok(config_dir.join(format!("{}.searchgrep.lock", name)))
+    }
+
+    /// Take the writer lock; hold it across load, modify and save
+    pub fn lock(store_name: Option<&str>) -> Result<IndexLock> {
+        let wait = Config::load().map(|c| c.lock_wait_secs).unwrap_or(0);
+        IndexLock::acquire(
+            &Self::lock_path(store_name)?,
+            std::time::Duration::from_secs(wait),
+        )
+    }
+
+    /// Check a user-supplied store name is safe to use as a file name
+    ///
+    /// Names share the config directory, so separate indexes of one repo
+    /// (e.g. `fast` and `code`) can coexist as long as their names differ.
+    pub fn validate_name(name: &str) -> Result<()> {
+        let valid = !name.is_empty()
+            && name.len() <= 64
+            && !name.starts_with('.')
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        if !valid {
+            anyhow::bail!(
+                "Invalid index name '{}': use up to 64 letters, digits, '-', '_' or '.'",
+                name
+            );
+        }
+        Ok(())
+    }
+
+    /// Whether an index has been saved under this name (binary or legacy JSON)
+    pub fn exists(store_name: Option<&str>) -> Result<bool> {
+        Ok(Self::store_path_bin(store_name)?.exists() || Self::store_path(store_name)?.exists())
+    }
+
+    /// Delete every file saved under this name, returning the number of chunks removed
+    ///
+    /// Takes the writer lock so an in-progress sync can't resurrect the index.
+    /// The count is best-effort: a corrupt or half-written index reports 0 but
+    /// is still removed, since that's when clearing it matters most.
+    pub fn delete(store_name: Option<&str>) -> Result<usize> {
+        let _lock = Self::lock(store_name)?;
+        let chunks = Self::load(store_name).map(|s| s.chunk_count()).unwrap_or(0);
+
+        for path in [
+            Self::store_path_bin(store_name)?,
+            Self::vectors_path(store_name)?,
+            Self::store_path(store_name)?,
+            Self::graph_path(store_name)?,
+            Self::meta_path(store_name)?,
+            VectorIndex::index_path(store_name)?,
+        ] {
+            if path.exists() {
+                fs::remove_file(&path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?;
+            }
+        }
+        Ok(chunks)
+    }
+
+    /// Read sidecar metadata, if present
+    fn load_meta(&mut self, store_name: Option<&str>) -> Result<()> {
+        let meta_path = Self::meta_path(store_name)?;
+        if meta_path.exists() {
+            let meta: StoreMeta = serde_json::from_str(&fs::read_to_string(&meta_path)?)?;
+            self.model = meta.model;
+            self.embed_path_context = meta.embed_path_context;
+        }
+        Ok(())
+    }
+
+    /// Latest modification time across the store's files, `None` if it has none
+    ///
+    /// Every save rewrites these files, so a new value means the index changed.
+    pub fn modified(store_name: Option<&str>) -> Result<Option<SystemTime>> {
+        let paths = [
+            Self::store_path_bin(store_name)?,
+            Self::store_path(store_name)?,
+            Self::vectors_path(store_name)?,
+            Self::graph_path(store_name)?,
+            Self::meta_path(store_name)?,
+        ];
+        Ok(paths
+            .iter()
+            .filter_map(|p| fs::metadata(p).and_then(|m| m.modified()).ok())
+            .max())
+    }
+
     /// Load store - prefers binary format, falls back to JSON
     pub fn load(store_name: Option<&str>) -> Result<Self> {
         let bin_path = Self::store_path_bin(store_name)?;
@@ -109,11 +402,17 @@ impl VectorStore {
 
         // Try binary format first (fast)
         if bin_path.exists() {
-            let data = fs::read(&bin_path)?;
-            let store_data: VectorStoreData =
-                bincode::deserialize(&data).context("Failed to deserialize binary store")?;
+            let mut decoded = decode_store(&fs::read(&bin_path)?)?;
+            if let Some(layout) = &decoded.layout {
+                let vectors_path = Self::vectors_path(store_name)?;
+                let bytes = fs::read(&vectors_path).with_context(|| {
+                    format!("Failed to read vector file {}", vectors_path.display())
+                })?;
+                layout.fill(&mut decoded.data.chunks, &bytes)?;
+            }
 
-            let mut store = Self::from_data(store_data);
+            let mut store = Self::from_data(decoded.data);
+            store.quantization = decoded.quantization;
             store.maybe_build_ann_index()?;
 
             // Load graph if exists
@@ -125,6 +424,8 @@ impl VectorStore {
                 }
             }
 
+            store.load_meta(store_name)?;
+
             return Ok(store);
         }
 
@@ -145,12 +446,44 @@ impl VectorStore {
                 }
             }
 
+            store.load_meta(store_name)?;
+
             return Ok(store);
         }
 
         Ok(VectorStore::default())
     }
 
+    /// Load for read-only search, memory-mapping the embeddings
+    ///
+    /// Vectors are decoded from the mapping as search scores them instead of
+    /// all being read up front, which cuts cold-start time and resident
+    /// memory on large indexes. No ANN index is built, since that would touch
+    /// every vector. Stores without a `.vec` file (int8, older formats) load
+    /// fully. The result can't be saved; use `load` to modify an index.
+    pub fn load_mmap(store_name: Option<&str>) -> Result<Self> {
+        let bin_path = Self::store_path_bin(store_name)?;
+        if !bin_path.exists() {
+            return Self::load(store_name);
+        }
+
+        let decoded = decode_store(&fs::read(&bin_path)?)?;
+        let Some(layout) = decoded.layout else {
+            return Self::load(store_name);
+        };
+
+        let mut store = Self::mapped(decoded.data, &Self::vectors_path(store_name)?, layout)?;
+        store.graph = Self::load_graph_only(store_name).unwrap_or_default();
+        store.load_meta(store_name)?;
+        Ok(store)
+    }
+
+    fn mapped(data: VectorStoreData, vectors_path: &Path, layout: VectorLayout) -> Result<Self> {
+        let mut store = Self::from_data(data);
+        store.vectors = Some(MappedVectors::open(vectors_path, layout)?);
+        Ok(store)
+    }
+
     /// Load just the knowledge graph (fast - skips ANN index building)
     pub fn load_graph_only(store_name: Option<&str>) -> Result<KnowledgeGraph> {
         let graph_path = Self::graph_path(store_name)?;
@@ -175,6 +508,10 @@ impl VectorStore {
             ann_index: None,
             ann_threshold: 1000,
             graph: KnowledgeGraph::new(),
+            model: None,
+            embed_path_context: false,
+            quantization: Quantization::None,
+            vectors: None,
         }
     }
 
@@ -188,11 +525,42 @@ impl VectorStore {
         }
     }
 
-    /// Save store in binary format (fast)
+    /// Save store in binary format, compressed at the configured zstd level
     pub fn save(&self, store_name: Option<&str>) -> Result<()> {
+        let level = Config::load()
+            .map(|c| c.store_compression_level)
+            .unwrap_or_else(|_| default_store_compression_level());
+        self.save_with_compression(store_name, level)
+    }
+
+    /// Save store in binary format, zstd-compressed at `level` (0 = uncompressed)
+    ///
+    /// Full-precision embeddings of a uniform size go to the `.vec` file so
+    /// `load_mmap` can map them; anything else is stored inline.
+    pub fn save_with_compression(&self, store_name: Option<&str>, level: i32) -> Result<()> {
+        if self.vectors.is_some() {
+            anyhow::bail!(
+                "Store was loaded read-only (memory-mapped); use VectorStore::load to modify it"
+            );
+        }
+
         let bin_path = Self::store_path_bin(store_name)?;
-        let data = bincode::serialize(&self.to_data())?;
-        fs::write(&bin_path, data)?;
+        let vectors_path = Self::vectors_path(store_name)?;
+        let data = self.to_data();
+        match uniform_dim(&data).filter(|_| self.quantization == Quantization::None) {
+            Some(dim) => {
+                let (bin, vectors) = encode_split_store(data, dim, level)?;
+                // Vectors first: whoever sees the new store also finds its rows
+                write_atomic(&vectors_path, vectors)?;
+                write_atomic(&bin_path, bin)?;
+            }
+            None => {
+                write_atomic(&bin_path, encode_store(data, level, self.quantization)?)?;
+                if vectors_path.exists() {
+                    fs::remove_file(&vectors_path)?;
+                }
+            }
+        }
 
         // Save ANN index separately
         if let Some(ref ann) = self.ann_index {
@@ -202,7 +570,16 @@ impl VectorStore {
         // Save knowledge graph separately
         let graph_path = Self::graph_path(store_name)?;
         let graph_data = bincode::serialize(&self.graph)?;
-        fs::write(graph_path, graph_data)?;
+        write_atomic(&graph_path, graph_data)?;
+
+        let meta = StoreMeta {
+            model: self.model.clone(),
+            embed_path_context: self.embed_path_context,
+        };
+        write_atomic(
+            &Self::meta_path(store_name)?,
+            serde_json::to_string_pretty(&meta)?,
+        )?;
 
         Ok(())
     }
@@ -217,6 +594,7 @@ impl VectorStore {
 
     /// Migrate from JSON to binary format
     pub fn migrate_to_binary(store_name: Option<&str>) -> Result<bool> {
+        let _lock = Self::lock(store_name)?;
         let json_path = Self::store_path(store_name)?;
         let bin_path = Self::store_path_bin(store_name)?;
 
@@ -286,6 +664,10 @@ impl VectorStore {
         self.bm25_idf.clear();
         self.doc_count = 0;
         self.graph.clear();
+        self.model = None;
+        self.embed_path_context = false;
+        self.quantization = Quantization::None;
+        self.vectors = None;
     }
 
     pub fn add_file(&mut self, file: IndexedFile) {
@@ -304,6 +686,89 @@ impl VectorStore {
         }
     }
 
+    /// Replace a file's chunks and metadata with a freshly indexed version
+    pub fn upsert_file(&mut self, file: IndexedFile, chunks: Vec<FileChunk>) {
+        self.remove_file(&file.path);
+        for chunk in chunks {
+            self.add_chunk(chunk);
+        }
+        self.add_file(file);
+    }
+
+    /// Append another store's chunks, e.g. to stitch together subrepos indexed separately
+    ///
+    /// A chunk whose (file_path, start_line) is already present is skipped, so
+    /// overlapping stores keep this store's version. Both stores must share an
+    /// embedding dimension and, when recorded, a model. The knowledge graph
+    /// isn't merged.
+    pub fn merge(&mut self, other: VectorStore) -> Result<()> {
+        if self.vectors.is_some() {
+            anyhow::bail!(
+                "Store was loaded read-only (memory-mapped); use VectorStore::load to modify it"
+            );
+        }
+
+        let (dim, other_dim) = (self.embedding_dim(), other.embedding_dim());
+        if self.chunk_count() > 0 && other.chunk_count() > 0 && dim != other_dim {
+            anyhow::bail!(
+                "Cannot merge stores with different embedding dimensions ({} vs {})",
+                dim,
+                other_dim
+            );
+        }
+        if let (Some(model), Some(other_model)) = (&self.model, &other.model) {
+            if model != other_model {
+                anyhow::bail!(
+                    "Cannot merge stores built with different models ('{}' vs '{}')",
+                    model,
+                    other_model
+                );
+            }
+        }
+
+        let mut taken: std::collections::HashSet<(String, usize)> = self
+            .chunks
+            .values()
+            .map(|c| (c.file_path.clone(), c.start_line))
+            .collect();
+
+        let mut added: HashMap<String, Vec<String>> = HashMap::new();
+        for chunk in other.chunks.values() {
+            if !taken.insert((chunk.file_path.clone(), chunk.start_line)) {
+                continue;
+            }
+            let embedding = other.embedding(chunk).into_owned();
+            added
+                .entry(chunk.file_path.clone())
+                .or_default()
+                .push(chunk.id.clone());
+            self.add_chunk(FileChunk {
+                embedding,
+                ..chunk.clone()
+            });
+        }
+
+        for (path, mut file) in other.files {
+            match self.files.get_mut(&path) {
+                Some(existing) => existing
+                    .chunks
+                    .extend(added.remove(&path).unwrap_or_default()),
+                None => {
+                    file.chunks
+                        .retain(|id| added.get(&path).is_some_and(|ids| ids.contains(id)));
+                    self.add_file(file);
+                }
+            }
+        }
+
+        if self.model.is_none() {
+            self.model = other.model;
+        }
+        self.update_bm25_stats();
+        self.ann_index = None;
+        self.maybe_build_ann_index()
+    }
+
     pub fn get_file(&self, path: &str) -> Option<&IndexedFile> {
         self.files.get(path)
     }
@@ -323,10 +788,90 @@ impl VectorStore {
         self.chunks.len()
     }
 
+    /// Embedding dimension, or 0 for an empty store
+    pub fn embedding_dim(&self) -> usize {
+        if let Some(vectors) = &self.vectors {
+            return vectors.layout.dim;
+        }
+        self.chunks.values().next().map_or(0, |c| c.embedding.len())
+    }
+
+    /// Most recent `indexed_at` across files (RFC 3339), if any
+    pub fn last_indexed(&self) -> Option<&str> {
+        self.files
+            .values()
+            .map(|f| f.indexed_at.as_str())
+            .filter(|t| !t.is_empty())
+            .max()
+    }
+
     pub fn list_files(&self) -> Vec<String> {
         self.files.keys().cloned().collect()
     }
 
+    /// Indexed file paths that no longer exist on disk, sorted
+    pub fn orphaned_files(&self) -> Vec<String> {
+        let mut orphans: Vec<String> = self
+            .files
+            .keys()
+            .filter(|path| !Path::new(path).exists())
+            .cloned()
+            .collect();
+        orphans.sort();
+        orphans
+    }
+
+    /// Drop files and chunks whose file no longer exists on disk
+    ///
+    /// Relative paths resolve against `root`. Also catches chunks left without
+    /// a file entry. Returns (files removed, chunks removed); callers should
+    /// refresh BM25 stats and save the store afterwards.
+    pub fn prune_missing(&mut self, root: &Path) -> (usize, usize) {
+        let mut present: HashMap<String, bool> = HashMap::new();
+        let mut exists = |path: &str| {
+            *present
+                .entry(path.to_string())
+                .or_insert_with(|| root.join(path).exists())
+        };
+
+        let chunks_before = self.chunks.len();
+        let missing: Vec<String> = self
+            .files
+            .keys()
+            .filter(|path| !exists(path))
+            .cloned()
+            .collect();
+        for path in &missing {
+            self.remove_file(path);
+        }
+        self.chunks.retain(|_, chunk| exists(&chunk.file_path));
+        (missing.len(), chunks_before - self.chunks.len())
+    }
+
+    /// Remove orphaned files and their chunks
+    ///
+    /// Returns (files removed, chunks removed). Callers should refresh BM25
+    /// stats and save the store afterwards.
+    pub fn prune_orphans(&mut self) -> (usize, usize) {
+        let orphans = self.orphaned_files();
+        let chunks_before = self.chunks.len();
+        for path in &orphans {
+            self.remove_file(path);
+        }
+        (orphans.len(), chunks_before - self.chunks.len())
+    }
+
+    /// A chunk's embedding, read from the mapped `.vec` file when not in memory
+    pub fn embedding<'a>(&'a self, chunk: &'a FileChunk) -> Cow<'a, [f32]> {
+        match &self.vectors {
+            Some(vectors) if chunk.embedding.is_empty() => vectors
+                .get(&chunk.id)
+                .map(Cow::Owned)
+                .unwrap_or(Cow::Borrowed(&[])),
+            _ => Cow::Borrowed(&chunk.embedding),
+        }
+    }
+
     pub fn all_chunks(&self) -> impl Iterator<Item = &FileChunk> {
         self.chunks.values()
     }
@@ -369,6 +914,138 @@ impl VectorStore {
     }
 }
 
+/// Serialize store data, zstd-compressed behind `COMPRESSED_MAGIC` unless `level` is 0
+///
+/// With int8 quantization the payload is marked by `INT8_MAGIC` and carries
+/// the embeddings separately, so neither format can be misread as the other.
+fn encode_store(
+    mut data: VectorStoreData,
+    level: i32,
+    quantization: Quantization,
+) -> Result<Vec<u8>> {
+    let raw = match quantization {
+        Quantization::None => bincode::serialize(&data)?,
+        Quantization::Int8 => {
+            let embeddings = data
+                .chunks
+                .iter_mut()
+                .map(|(id, chunk)| {
+                    let embedding = std::mem::take(&mut chunk.embedding);
+                    (id.clone(), Int8Embedding::quantize(&embedding))
+                })
+                .collect();
+            let mut raw = INT8_MAGIC.to_vec();
+            raw.extend(bincode::serialize(&QuantizedStoreData {
+                data,
+                embeddings,
+            })?);
+            raw
+        }
+    };
+    compress(raw, level)
+}
+
+/// Wrap a payload in `COMPRESSED_MAGIC` + zstd, unless `level` is 0
+fn compress(raw: Vec<u8>, level: i32) -> Result<Vec<u8>> {
+    if level == 0 {
+        return Ok(raw);
+    }
+
+    let mut bytes = COMPRESSED_MAGIC.to_vec();
+    bytes.extend(zstd::encode_all(raw.as_slice(), level)?);
+    Ok(bytes)
+}
+
+/// Embedding size shared by every chunk, if there is one
+fn uniform_dim(data: &VectorStoreData) -> Option<usize> {
+    let dim = data.chunks.values().next()?.embedding.len();
+    (dim > 0 && data.chunks.values().all(|c| c.embedding.len() == dim)).then_some(dim)
+}
+
+/// Serialize store data with its embeddings moved out, returning the
+/// `.bin` payload (behind `SPLIT_MAGIC`) and the `.vec` file
+fn encode_split_store(
+    mut data: VectorStoreData,
+    dim: usize,
+    level: i32,
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    let mut layout = VectorLayout {
+        dim,
+        rows: Vec::with_capacity(data.chunks.len()),
+        stamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default(),
+    };
+
+    let mut body = Vec::with_capacity(data.chunks.len() * dim * 4);
+    for (id, chunk) in data.chunks.iter_mut() {
+        for x in std::mem::take(&mut chunk.embedding) {
+            body.extend(x.to_le_bytes());
+        }
+        layout.rows.push(id.clone());
+    }
+    let mut vectors = layout.header();
+    vectors.extend(body);
+
+    let mut raw = SPLIT_MAGIC.to_vec();
+    raw.extend(bincode::serialize(&SplitStoreData { data, layout })?);
+    Ok((compress(raw, level)?, vectors))
+}
+
+/// A decoded `.bin` payload
+struct DecodedStore {
+    data: VectorStoreData,
+    quantization: Quantization,
+    /// Set when the embeddings are in the `.vec` file
+    layout: Option<VectorLayout>,
+}
+
+/// Read a binary store in any format, told apart by the headers
+fn decode_store(bytes: &[u8]) -> Result<DecodedStore> {
+    let decompressed;
+    let raw = match bytes.strip_prefix(COMPRESSED_MAGIC.as_slice()) {
+        Some(compressed) => {
+            decompressed = zstd::decode_all(compressed).context("Failed to decompress store")?;
+            decompressed.as_slice()
+        }
+        None => bytes,
+    };
+
+    if let Some(payload) = raw.strip_prefix(INT8_MAGIC.as_slice()) {
+        let QuantizedStoreData {
+            mut data,
+            embeddings,
+        } = bincode::deserialize(payload).context("Failed to deserialize binary store")?;
+        for (id, embedding) in embeddings {
+            if let Some(chunk) = data.chunks.get_mut(&id) {
+                chunk.embedding = embedding.dequantize();
+            }
+        }
+        return Ok(DecodedStore {
+            data,
+            quantization: Quantization::Int8,
+            layout: None,
+        });
+    }
+
+    if let Some(payload) = raw.strip_prefix(SPLIT_MAGIC.as_slice()) {
+        let SplitStoreData { data, layout } =
+            bincode::deserialize(payload).context("Failed to deserialize binary store")?;
+        return Ok(DecodedStore {
+            data,
+            quantization: Quantization::None,
+            layout: Some(layout),
+        });
+    }
+
+    Ok(DecodedStore {
+        data: bincode::deserialize(raw).context("Failed to deserialize binary store")?,
+        quantization: Quantization::None,
+        layout: None,
+    })
+}
+
 pub fn compute_file_hash(content: &str) -> String {
     let mut hasher = Sha256::new();
     hasher.update(content.as_bytes());
@@ -380,3 +1057,418 @@ pub fn generate_chunk_id(file_path: &str, start_line: usize, end_line: usize) ->
     hasher.update(format!("{}:{}:{}", file_path, start_line, end_line).as_bytes());
     hex::encode(&hasher.finalize()[..8])
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::search::HybridSearcher;
+    use std::time::Duration;
+
+    fn indexed(store: &mut VectorStore, path: &str) {
+        let chunk_id = generate_chunk_id(path, 1, 10);
+        store.add_chunk(FileChunk {
+            id: chunk_id.clone(),
+            file_path: path.to_string(),
+            content: "fn main() {}".to_string(),
+            start_line: 1,
+            end_line: 10,
+            chunk_type: "function".to_string(),
+            language: Some("rust".to_string()),
+            embedding: vec![],
+            token_embeddings: None,
+            symbol_name: None,
+            parent_name: None,
+            hierarchy_path: None,
+        });
+        store.add_file(IndexedFile {
+            path: path.to_string(),
+            hash: String::new(),
+            chunks: vec![chunk_id],
+            indexed_at: String::new(),
+        });
+    }
+
+    fn chunk_at(path: &str, start: usize, end: usize) -> FileChunk {
+        FileChunk {
+            id: generate_chunk_id(path, start, end),
+            file_path: path.to_string(),
+            content: String::new(),
+            start_line: start,
+            end_line: end,
+            chunk_type: "block".to_string(),
+            language: None,
+            embedding: vec![],
+            token_embeddings: None,
+            symbol_name: None,
+            parent_name: None,
+            hierarchy_path: None,
+        }
+    }
+
+    fn file_entry(path: &str, hash: &str, chunks: &[FileChunk]) -> IndexedFile {
+        IndexedFile {
+            path: path.to_string(),
+            hash: hash.to_string(),
+            chunks: chunks.iter().map(|c| c.id.clone()).collect(),
+            indexed_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_upsert_file_replaces_chunks() {
+        let mut store = VectorStore::default();
+        indexed(&mut store, "src/other.rs");
+
+        let v1 = vec![
+            chunk_at("src/lib.rs", 1, 20),
+            chunk_at("src/lib.rs", 21, 40),
+        ];
+        store.upsert_file(file_entry("src/lib.rs", "v1", &v1), v1.clone());
+        assert_eq!(store.chunk_count(), 3);
+
+        // The file shrank: one chunk, covering different lines
+        let v2 = vec![chunk_at("src/lib.rs", 1, 15)];
+        store.upsert_file(file_entry("src/lib.rs", "v2", &v2), v2.clone());
+
+        assert_eq!(store.chunk_count(), 2);
+        assert_eq!(store.chunks_for_file("src/lib.rs").len(), 1);
+        assert!(v1.iter().all(|c| !store.chunks.contains_key(&c.id)));
+        assert_eq!(store.get_file("src/lib.rs").unwrap().hash, "v2");
+        assert!(!store.file_needs_update("src/lib.rs", "v2"));
+
+        store.remove_file("src/lib.rs");
+        assert_eq!(store.chunk_count(), 1);
+        assert!(store.get_file("src/other.rs").is_some());
+    }
+
+    #[test]
+    fn test_compressed_store_round_trip() {
+        let mut store = VectorStore::default();
+        for i in 0..20 {
+            let mut chunk = chunk_at("src/lib.rs", i * 10 + 1, i * 10 + 10);
+            chunk.content = format!("fn item_{}() {{ /* body */ }}", i);
+            chunk.embedding = (0..384).map(|d| ((d * 7 + i) % 13) as f32 / 13.0).collect();
+            store.add_chunk(chunk);
+        }
+        indexed(&mut store, "src/main.rs");
+        let data = store.to_data();
+
+        let compressed = encode_store(data.clone(), 3, Quantization::None).unwrap();
+        assert!(compressed.starts_with(COMPRESSED_MAGIC));
+        let loaded = decode_store(&compressed).unwrap();
+        assert_eq!(loaded.data.chunks, data.chunks);
+        assert_eq!(loaded.data.files, data.files);
+        assert_eq!(loaded.quantization, Quantization::None);
+
+        // Stores written before compression still load
+        let plain = encode_store(data.clone(), 0, Quantization::None).unwrap();
+        assert_eq!(plain, bincode::serialize(&data).unwrap());
+        assert_eq!(decode_store(&plain).unwrap().data.chunks, data.chunks);
+    }
+
+    /// Deterministic pseudo-random unit vector (xorshift)
+    fn unit_vector(seed: &mut u32, dim: usize) -> Vec<f32> {
+        let v: Vec<f32> = (0..dim)
+            .map(|_| {
+                *seed ^= *seed << 13;
+                *seed ^= *seed >> 17;
+                *seed ^= *seed << 5;
+                (*seed as f32 / u32::MAX as f32) - 0.5
+            })
+            .collect();
+        let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+        v.into_iter().map(|x| x / norm).collect()
+    }
+
+    fn random_store(seed: &mut u32, chunks: usize) -> VectorStore {
+        let mut store = VectorStore::default();
+        for i in 0..chunks {
+            let mut chunk = chunk_at("src/lib.rs", i * 10 + 1, i * 10 + 10);
+            chunk.embedding = unit_vector(seed, 384);
+            store.add_chunk(chunk);
+        }
+        store
+    }
+
+    #[test]
+    fn test_int8_store_keeps_top_k() {
+        let mut seed = 0x2545_f491_u32;
+        let store = random_store(&mut seed, 300);
+
+        for level in [0, 3] {
+            let bytes = encode_store(store.to_data(), level, Quantization::Int8).unwrap();
+            let plain = encode_store(store.to_data(), 0, Quantization::None).unwrap();
+            if level == 0 {
+                assert!(bytes.starts_with(INT8_MAGIC));
+                assert!(bytes.len() * 3 < plain.len());
+            }
+
+            let decoded = decode_store(&bytes).unwrap();
+            assert_eq!(decoded.quantization, Quantization::Int8);
+            let int8_store = VectorStore::from_data(decoded.data);
+
+            let searcher = HybridSearcher::default();
+            for _ in 0..5 {
+                let query = unit_vector(&mut seed, 384);
+                let top = |store: &VectorStore| -> Vec<String> {
+                    searcher
+                        .search(store, &query, "", 10, None, false, None)
+                        .into_iter()
+                        .map(|r| r.chunk.id)
+                        .collect()
+                };
+                let expected = top(&store);
+                let actual = top(&int8_store);
+                let overlap = actual.iter().filter(|id| expected.contains(id)).count();
+                assert!(overlap >= 9, "top-10 overlap {}", overlap);
+            }
+        }
+    }
+
+    #[test]
+    fn test_mmap_store_matches_in_memory() {
+        let mut seed = 0x9e37_79b9_u32;
+        let mut store = random_store(&mut seed, 200);
+        for chunk in store.chunks.values_mut() {
+            chunk.content = format!("fn item_{}() {{}}", chunk.start_line / 10);
+        }
+        store.update_bm25_stats();
+        let data = store.to_data();
+        let dim = uniform_dim(&data).unwrap();
+        let (bin, vectors) = encode_split_store(data, dim, 3).unwrap();
+
+        let path = std::env::temp_dir().join(format!("sgrep-mmap-{}.vec", std::process::id()));
+        fs::write(&path, &vectors).unwrap();
+
+        let decoded = decode_store(&bin).unwrap();
+        let layout = decoded.layout.unwrap();
+        assert!(decoded.data.chunks.values().all(|c| c.embedding.is_empty()));
+        let mapped = VectorStore::mapped(decoded.data, &path, layout.clone()).unwrap();
+        assert_eq!(mapped.embedding_dim(), 384);
+        assert!(mapped.save_with_compression(Some("unused"), 0).is_err());
+
+        let searcher = HybridSearcher::default();
+        for query_text in ["item_7", "", "fn item_42"] {
+            let query = unit_vector(&mut seed, 384);
+            let search = |store: &VectorStore| -> Vec<(String, f32)> {
+                searcher
+                    .search(store, &query, query_text, 10, None, false, None)
+                    .into_iter()
+                    .map(|r| (r.chunk.id, r.score))
+                    .collect()
+            };
+            assert_eq!(search(&mapped), search(&store));
+        }
+
+        // The full load path restores the same vectors
+        let mut full = decode_store(&bin).unwrap().data;
+        layout.fill(&mut full.chunks, &vectors).unwrap();
+        assert_eq!(full.chunks, store.chunks);
+
+        // A vector file from a different save is rejected
+        let stale = VectorLayout {
+            stamp: layout.stamp + 1,
+            ..layout
+        };
+        assert!(VectorStore::mapped(store.to_data(), &path, stale).is_err());
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_named_stores_are_independent() {
+        let names = [
+            format!("sgrep-test-{}-fast", std::process::id()),
+            format!("sgrep-test-{}-code", std::process::id()),
+        ];
+        for name in &names {
+            VectorStore::validate_name(name).unwrap();
+        }
+        for bad in ["", "../escape", ".hidden", "a/b"] {
+            assert!(VectorStore::validate_name(bad).is_err());
+        }
+
+        let mut fast = VectorStore::default();
+        fast.add_chunk(chunk_at("src/lib.rs", 1, 10));
+        fast.model = Some("fast-model".to_string());
+        let mut code = VectorStore::default();
+        for i in 0..3 {
+            code.add_chunk(chunk_at("src/main.rs", i * 10 + 1, i * 10 + 10));
+        }
+        code.model = Some("code-model".to_string());
+
+        fast.save_with_compression(Some(&names[0]), 0).unwrap();
+        code.save_with_compression(Some(&names[1]), 3).unwrap();
+
+        let loaded_fast = VectorStore::load(Some(&names[0])).unwrap();
+        let loaded_code = VectorStore::load(Some(&names[1])).unwrap();
+        assert_eq!(loaded_fast.chunks, fast.chunks);
+        assert_eq!(loaded_fast.model.as_deref(), Some("fast-model"));
+        assert_eq!(loaded_code.chunks, code.chunks);
+        assert_eq!(loaded_code.model.as_deref(), Some("code-model"));
+
+        // Deleting one leaves the other in place
+        assert_eq!(VectorStore::delete(Some(&names[0])).unwrap(), 1);
+        assert!(!VectorStore::exists(Some(&names[0])).unwrap());
+        assert_eq!(VectorStore::load(Some(&names[1])).unwrap().chunk_count(), 3);
+        VectorStore::delete(Some(&names[1])).unwrap();
+    }
+
+    #[test]
+    fn test_delete_removes_unreadable_store() {
+        let name = format!("sgrep-test-{}-corrupt", std::process::id());
+        let mut store = VectorStore::default();
+        store.add_chunk(chunk_at("src/lib.rs", 1, 10));
+        store.save_with_compression(Some(&name), 0).unwrap();
+
+        // Garbage where the store header should be: loading fails
+        fs::write(
+            VectorStore::store_path_bin(Some(&name)).unwrap(),
+            b"not a store",
+        )
+        .unwrap();
+        assert!(VectorStore::load(Some(&name)).is_err());
+
+        assert_eq!(VectorStore::delete(Some(&name)).unwrap(), 0);
+        assert!(!VectorStore::exists(Some(&name)).unwrap());
+        for path in [
+            VectorStore::vectors_path(Some(&name)).unwrap(),
+            VectorStore::meta_path(Some(&name)).unwrap(),
+            VectorStore::graph_path(Some(&name)).unwrap(),
+        ] {
+            assert!(!path.exists(), "{} left behind", path.display());
+        }
+    }
+
+    #[test]
+    fn test_concurrent_writers_keep_both_updates() {
+        let name = format!("sgrep-test-{}-concurrent", std::process::id());
+        let _ = VectorStore::delete(Some(&name));
+
+        let writers: Vec<_> = ["src/a.rs", "src/b.rs"]
+            .into_iter()
+            .map(|file| {
+                let name = name.clone();
+                std::thread::spawn(move || {
+                    let lock_path = VectorStore::lock_path(Some(&name)).unwrap();
+                    let _lock = IndexLock::acquire(&lock_path, Duration::from_secs(10)).unwrap();
+                    let mut store = VectorStore::load(Some(&name)).unwrap();
+                    // Widen the race window; without the lock one update is lost
+                    std::thread::sleep(Duration::from_millis(200));
+                    store.add_chunk(chunk_at(file, 1, 10));
+                    store.save_with_compression(Some(&name), 0).unwrap();
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        let store = VectorStore::load(Some(&name)).unwrap();
+        let mut files: Vec<&str> = store
+            .chunks
+            .values()
+            .map(|c| c.file_path.as_str())
+            .collect();
+        files.sort();
+        assert_eq!(files, ["src/a.rs", "src/b.rs"]);
+        // Released once both writers are done
+        drop(VectorStore::lock(Some(&name)).unwrap());
+        VectorStore::delete(Some(&name)).unwrap();
+    }
+
+    #[test]
+    fn test_merge_stores() {
+        let mut a = VectorStore::default();
+        let mut b = VectorStore::default();
+        for (start, end) in [(1, 10), (11, 20)] {
+            let mut chunk = chunk_at("shared.rs", start, end);
+            chunk.content = "from a".to_string();
+            chunk.embedding = vec![1.0, 0.0];
+            a.add_chunk(chunk);
+        }
+        indexed(&mut a, "a.rs");
+        a.chunks
+            .get_mut(&generate_chunk_id("a.rs", 1, 10))
+            .unwrap()
+            .embedding = vec![0.0, 1.0];
+
+        // Same start line as a's first chunk, different end: still a duplicate
+        for (start, end) in [(1, 12), (21, 30)] {
+            let mut chunk = chunk_at("shared.rs", start, end);
+            chunk.content = "from b".to_string();
+            chunk.embedding = vec![0.5, 0.5];
+            b.add_chunk(chunk);
+        }
+        indexed(&mut b, "b.rs");
+        b.chunks
+            .get_mut(&generate_chunk_id("b.rs", 1, 10))
+            .unwrap()
+            .embedding = vec![0.5, 0.5];
+
+        a.merge(b).unwrap();
+        assert_eq!(a.chunk_count(), 5);
+        assert_eq!(a.file_count(), 2);
+        assert!(a.get_file("b.rs").is_some());
+        assert!(!a
+            .chunks
+            .contains_key(&generate_chunk_id("shared.rs", 1, 12)));
+        let first = &a.chunks[&generate_chunk_id("shared.rs", 1, 10)];
+        assert_eq!(first.content, "from a");
+        assert!(a
+            .chunks
+            .contains_key(&generate_chunk_id("shared.rs", 21, 30)));
+        assert_eq!(a.doc_count, 5);
+
+        let mut wide = VectorStore::default();
+        let mut chunk = chunk_at("wide.rs", 1, 10);
+        chunk.embedding = vec![0.0; 3];
+        wide.add_chunk(chunk);
+        let err = a.merge(wide).unwrap_err();
+        assert!(err.to_string().contains("different embedding dimensions"));
+        assert_eq!(a.chunk_count(), 5);
+    }
+
+    #[test]
+    fn test_prune_missing() {
+        let root = std::env::temp_dir().join(format!("sgrep-prune-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("kept.rs"), "fn kept() {}").unwrap();
+        fs::write(root.join("deleted.rs"), "fn deleted() {}").unwrap();
+
+        let mut store = VectorStore::default();
+        let deleted = root.join("deleted.rs").to_string_lossy().to_string();
+        indexed(&mut store, &deleted);
+        // Relative paths resolve against the root
+        indexed(&mut store, "kept.rs");
+        // A chunk whose file entry was lost
+        store.add_chunk(chunk_at(&deleted, 50, 60));
+
+        assert_eq!(store.prune_missing(&root), (0, 0));
+        assert_eq!(store.chunk_count(), 3);
+
+        fs::remove_file(root.join("deleted.rs")).unwrap();
+        assert_eq!(store.prune_missing(&root), (1, 2));
+        assert!(store.get_file(&deleted).is_none());
+        assert!(store.get_file("kept.rs").is_some());
+        assert_eq!(store.chunk_count(), 1);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_prune_orphans() {
+        let existing = concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml");
+        let missing = "/nonexistent/sgrep/deleted.rs";
+
+        let mut store = VectorStore::default();
+        indexed(&mut store, existing);
+        indexed(&mut store, missing);
+
+        assert_eq!(store.orphaned_files(), vec![missing.to_string()]);
+        assert_eq!(store.prune_orphans(), (1, 1));
+        assert!(store.get_file(missing).is_none());
+        assert!(store.get_file(existing).is_some());
+        assert_eq!(store.chunk_count(), 1);
+    }
+}
@@ -503,7 +503,7 @@ pub fn detect_language(file_path: &str) -> Option<&'static str> {
 
     match extension.to_lowercase().as_str() {
         "rs" => Some("rust"),
-        "py" | "pyw" => Some("python"),
+        "py" | "pyw" | "pyi" => Some("python"),
         "js" | "mjs" | "cjs" => Some("javascript"),
         "ts" | "mts" | "cts" => Some("typescript"),
         "tsx" => Some("tsx"),
@@ -203,7 +203,7 @@ impl VectorIndex {
         Ok(vi)
     }
 
-    fn index_path(store_name: Option<&str>) -> Result<PathBuf> {
+    pub fn index_path(store_name: Option<&str>) -> Result<PathBuf> {
         let config_dir = Config::config_dir()?;
         let name = store_name.unwrap_or("default");
         Ok(config_dir.join(format!("{}.usearch", name)))
@@ -168,7 +168,15 @@ enum Commands {
 
     /// Run as MCP server for Claude Code integration
     #[command(name = "mcp-server")]
-    McpServer,
+    McpServer {
+        /// Expose developer tools (e.g. debug_parse)
+        #[arg(long)]
+        debug_tools: bool,
+
+        /// Never return file contents, only paths, line ranges and signatures
+        #[arg(long)]
+        no_content: bool,
+    },
 
     /// Remove stored indexes for privacy/storage management
     Clean {
@@ -482,8 +490,13 @@ async fn main() -> Result<()> {
         Some(Commands::Status { store, files, json }) => {
             status::run(status::StatusOptions { store, files, json }).await?;
         }
-        Some(Commands::McpServer) => {
-            let mut server = mcp::McpServer::new();
+        Some(Commands::McpServer {
+            debug_tools,
+            no_content,
+        }) => {
+            let mut server = mcp::McpServer::new()
+                .with_debug_tools(debug_tools)
+                .with_no_content(no_content);
             server.run()?;
         }
         Some(Commands::Clean {
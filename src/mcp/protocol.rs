@@ -2,6 +2,7 @@
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashMap;
 
 /// JSON-RPC Request
 #[derive(Debug, Deserialize)]
@@ -25,6 +26,24 @@ pub struct JsonRpcResponse {
     pub error: Option<JsonRpcError>,
 }
 
+/// JSON-RPC Notification (server to client, no response expected)
+#[derive(Debug, Serialize)]
+pub struct JsonRpcNotification {
+    pub jsonrpc: String,
+    pub method: String,
+    pub params: Value,
+}
+
+impl JsonRpcNotification {
+    pub fn new(method: &str, params: Value) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            method: method.to_string(),
+            params,
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct JsonRpcError {
     pub code: i32,
@@ -76,6 +95,8 @@ pub struct InitializeResult {
 #[derive(Debug, Serialize)]
 pub struct ServerCapabilities {
     pub tools: ToolsCapability,
+    pub resources: ResourcesCapability,
+    pub prompts: PromptsCapability,
 }
 
 #[derive(Debug, Serialize)]
@@ -84,6 +105,114 @@ pub struct ToolsCapability {
     pub list_changed: bool,
 }
 
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourcesCapability {
+    pub subscribe: bool,
+    pub list_changed: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PromptsCapability {
+    #[serde(rename = "listChanged")]
+    pub list_changed: bool,
+}
+
+/// Resource Definition - one indexed file
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceDefinition {
+    pub uri: String,
+    pub name: String,
+    pub mime_type: String,
+}
+
+/// Resources List Result
+#[derive(Debug, Serialize)]
+pub struct ResourcesListResult {
+    pub resources: Vec<ResourceDefinition>,
+}
+
+/// Resource Read Parameters
+#[derive(Debug, Deserialize)]
+pub struct ResourceReadParams {
+    pub uri: String,
+}
+
+/// Resource Contents
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceContents {
+    pub uri: String,
+    pub mime_type: String,
+    pub text: String,
+}
+
+/// Resource Read Result
+#[derive(Debug, Serialize)]
+pub struct ResourceReadResult {
+    pub contents: Vec<ResourceContents>,
+}
+
+/// Prompt Definition - a canned workflow clients can offer
+#[derive(Debug, Serialize)]
+pub struct PromptDefinition {
+    pub name: String,
+    pub description: String,
+    pub arguments: Vec<PromptArgument>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PromptArgument {
+    pub name: String,
+    pub description: String,
+    pub required: bool,
+}
+
+/// Prompts List Result
+#[derive(Debug, Serialize)]
+pub struct PromptsListResult {
+    pub prompts: Vec<PromptDefinition>,
+}
+
+/// Prompt Get Parameters
+#[derive(Debug, Deserialize)]
+pub struct PromptGetParams {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: HashMap<String, String>,
+}
+
+/// Prompt Message
+#[derive(Debug, Serialize)]
+pub struct PromptMessage {
+    pub role: String,
+    pub content: TextContent,
+}
+
+/// Prompt Get Result
+#[derive(Debug, Serialize)]
+pub struct PromptGetResult {
+    pub description: String,
+    pub messages: Vec<PromptMessage>,
+}
+
+impl PromptGetResult {
+    /// A prompt made of one user message
+    pub fn user(description: &str, text: String) -> Self {
+        Self {
+            description: description.to_string(),
+            messages: vec![PromptMessage {
+                role: "user".to_string(),
+                content: TextContent {
+                    content_type: "text".to_string(),
+                    text,
+                },
+            }],
+        }
+    }
+}
+
 /// Tool Definition
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -144,4 +273,16 @@ impl ToolCallResult {
             is_error: Some(true),
         }
     }
+
+    /// Append a note to the last text block
+    pub fn with_note(mut self, note: &str) -> Self {
+        if let Some(last) = self.content.last_mut() {
+            if !last.text.ends_with('\n') {
+                last.text.push('\n');
+            }
+            last.text.push('\n');
+            last.text.push_str(note);
+        }
+        self
+    }
 }
@@ -3,72 +3,325 @@
 //! Runs as a stdio JSON-RPC server for Claude Code integration.
 
 use anyhow::Result;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::io::{self, BufRead, Write};
 use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Instant, SystemTime};
 
-use crate::core::codemap::CodeMap;
+use crate::commands::compile::build_map;
+use crate::commands::watch::{
+    sync_files_with_options, ProgressFn, SyncOptions, SyncSummary, MAX_LISTED_SKIPS,
+};
+use crate::core::chunker::{ChunkStrategy, CodeChunker};
+use crate::core::codemap::{parse_kinds, CodeMap, CodeMapStats, CycleLevel, Symbol, SymbolKind};
+use crate::core::config::Config;
+use crate::core::file_tree::{build_file_tree, common_root};
 use crate::core::hybrid_embedder::HybridEmbedder;
-use crate::core::local_embeddings::{LocalEmbedder, SpeedMode};
-use crate::core::search::HybridSearcher;
-use crate::core::store::VectorStore;
+use crate::core::local_embeddings::{locate_model, model_override, LocalEmbedder, SpeedMode};
+use crate::core::log::{self, Logger};
+use crate::core::metrics::compute_symbol_metrics;
+use crate::core::parser::SymbolParser;
+use crate::core::scanner::detect_language;
+use crate::core::search::{
+    check_query_dim, highlight_terms, normalize_query, query_terms, HybridSearcher, SearchResult,
+    DEFAULT_SEMANTIC_WEIGHT,
+};
+use crate::core::store::{FileChunk, Quantization, VectorStore};
 
 use super::protocol::*;
 
+/// Tools whose output can include raw source code
+const CONTENT_TOOLS: &[&str] = &[
+    "semantic_search",
+    "multi_mode_search",
+    "expand_symbol",
+    "read_symbols",
+    "find_similar_code",
+    "ask_codebase",
+    "get_file_context",
+    "result_context",
+];
+
+/// Cap on merged hits returned by `multi_mode_search`
+const MULTI_MODE_MAX_TOTAL: usize = 30;
+
+/// Argument flags that request source code in a tool response
+const CONTENT_ARGS: &[&str] = &["include_content", "include_code"];
+
+/// Error for map tools called before a map exists
+const NO_CODE_MAP: &str =
+    "No codebase map found. Run the compile_map tool (or 'sgrep compile') first.";
+
+/// Cache key of the fused embedder behind `hybrid` mode
+const HYBRID_KEY: &str = "hybrid";
+
+/// Embedders loaded by this server process, keyed by model
+///
+/// A model load takes seconds, so each embedder is built on first use and
+/// shared by every later call. Failed loads are not cached.
+struct EmbedderCache<E> {
+    loaded: Mutex<HashMap<String, Arc<Mutex<E>>>>,
+}
+
+impl<E> EmbedderCache<E> {
+    fn new() -> Self {
+        Self {
+            loaded: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        self.loaded
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .contains_key(key)
+    }
+
+    /// The embedder cached under `key`, built with `load` if there isn't one yet
+    fn get_or_load(&self, key: &str, load: impl FnOnce() -> Result<E>) -> Result<Arc<Mutex<E>>> {
+        let mut loaded = self.loaded.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(embedder) = loaded.get(key) {
+            return Ok(Arc::clone(embedder));
+        }
+
+        let embedder = Arc::new(Mutex::new(load()?));
+        loaded.insert(key.to_string(), Arc::clone(&embedder));
+        Ok(embedder)
+    }
+}
+
+/// Read-only stores loaded by this server, keyed by store name
+///
+/// An entry is reused while the store's files keep the modification time it
+/// was loaded at, so a re-index or `clear_index` shows up on the next call.
+struct StoreCache {
+    loaded: Mutex<HashMap<String, (Option<SystemTime>, Arc<VectorStore>)>>,
+}
+
+impl StoreCache {
+    fn new() -> Self {
+        Self {
+            loaded: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The store cached under `key` if it was loaded at `modified`, else `load`'s
+    fn get_or_load(
+        &self,
+        key: &str,
+        modified: Option<SystemTime>,
+        load: impl FnOnce() -> Result<VectorStore>,
+    ) -> Result<Arc<VectorStore>> {
+        let mut loaded = self.loaded.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some((stamp, store)) = loaded.get(key) {
+            if *stamp == modified {
+                return Ok(Arc::clone(store));
+            }
+        }
+
+        let store = Arc::new(load()?);
+        loaded.insert(key.to_string(), (modified, Arc::clone(&store)));
+        Ok(store)
+    }
+}
+
 pub struct McpServer {
     initialized: bool,
+    /// Expose developer-only tools (e.g. debug_parse)
+    debug_tools: bool,
+    /// Never return file contents, only metadata (paths, line ranges, signatures)
+    no_content: bool,
+    /// JSON-encoded ids of requests currently being handled
+    in_flight: HashSet<String>,
+    /// Set by an `exit` message; the read loop stops after it
+    exit_requested: bool,
+    /// Diagnostics sink (stderr, level from `SEARCHGREP_LOG`)
+    logger: &'static Logger,
+    /// Local embedding models, keyed by model id
+    embedders: EmbedderCache<LocalEmbedder>,
+    /// The fused BGE + CodeRankEmbed embedder for `hybrid` mode
+    hybrid: EmbedderCache<HybridEmbedder>,
+    /// Memory-mapped indexes, reloaded when they change on disk
+    stores: StoreCache,
 }
 
 impl McpServer {
     pub fn new() -> Self {
-        Self { initialized: false }
+        Self {
+            initialized: false,
+            debug_tools: false,
+            no_content: false,
+            in_flight: HashSet::new(),
+            exit_requested: false,
+            logger: log::logger(),
+            embedders: EmbedderCache::new(),
+            hybrid: EmbedderCache::new(),
+            stores: StoreCache::new(),
+        }
+    }
+
+    /// Enable developer-only tools such as `debug_parse`
+    pub fn with_debug_tools(mut self, enabled: bool) -> Self {
+        self.debug_tools = enabled;
+        self
+    }
+
+    /// Suppress file contents in every tool response
+    pub fn with_no_content(mut self, enabled: bool) -> Self {
+        self.no_content = enabled;
+        self
     }
 
     /// Run the MCP server (blocking, reads from stdin, writes to stdout)
+    ///
+    /// Requests are handled strictly in order, one line at a time. Every
+    /// response echoes its request's `id` verbatim, so string and numeric ids
+    /// (`"7"` vs `7`) stay distinct. Notifications (messages without an `id`)
+    /// are handled but never answered. A line holding a JSON array is a batch,
+    /// answered with an array of responses in request order.
     pub fn run(&mut self) -> Result<()> {
         let stdin = io::stdin();
-        let mut stdout = io::stdout();
+        self.serve(stdin.lock(), &mut io::stdout())
+    }
 
-        for line in stdin.lock().lines() {
+    /// Read loop behind `run`, generic over its streams so it can be tested
+    fn serve(&mut self, input: impl BufRead, output: &mut impl Write) -> Result<()> {
+        for line in input.lines() {
             let line = line?;
             if line.is_empty() {
                 continue;
             }
 
-            // Parse JSON-RPC request
-            let request: JsonRpcRequest = match serde_json::from_str(&line) {
-                Ok(req) => req,
-                Err(e) => {
-                    let response =
-                        JsonRpcResponse::error(None, -32700, format!("Parse error: {}", e));
-                    self.write_response(&mut stdout, &response)?;
-                    continue;
+            if line.trim_start().starts_with('[') {
+                self.serve_batch(&line, output)?;
+            } else {
+                // Parse JSON-RPC request
+                let request: JsonRpcRequest = match serde_json::from_str(&line) {
+                    Ok(req) => req,
+                    Err(e) => {
+                        let response =
+                            JsonRpcResponse::error(None, -32700, format!("Parse error: {}", e));
+                        Self::write_response(output, &response)?;
+                        continue;
+                    }
+                };
+
+                if let Some(response) = self.handle_message(request) {
+                    Self::write_response(output, &response)?;
                 }
-            };
+            }
 
-            // Handle the request
-            let response = self.handle_request(request);
-            self.write_response(&mut stdout, &response)?;
+            if self.exit_requested {
+                break;
+            }
         }
 
         Ok(())
     }
 
-    fn write_response(&self, stdout: &mut io::Stdout, response: &JsonRpcResponse) -> Result<()> {
+    /// Answer a batch line; an all-notification batch gets no reply
+    fn serve_batch(&mut self, line: &str, output: &mut impl Write) -> Result<()> {
+        let messages: Vec<Value> = match serde_json::from_str(line) {
+            Ok(messages) => messages,
+            Err(e) => {
+                let response = JsonRpcResponse::error(None, -32700, format!("Parse error: {}", e));
+                return Self::write_response(output, &response);
+            }
+        };
+
+        if messages.is_empty() {
+            let response =
+                JsonRpcResponse::error(None, -32600, "Invalid Request: empty batch".to_string());
+            return Self::write_response(output, &response);
+        }
+
+        let mut responses = Vec::new();
+        for message in messages {
+            match serde_json::from_value::<JsonRpcRequest>(message) {
+                Ok(request) => responses.extend(self.handle_message(request)),
+                Err(e) => responses.push(JsonRpcResponse::error(
+                    None,
+                    -32600,
+                    format!("Invalid Request: {}", e),
+                )),
+            }
+        }
+
+        if responses.is_empty() {
+            return Ok(());
+        }
+        Self::write_response(output, &responses)
+    }
+
+    /// Handle one message, returning the response unless it was a notification
+    fn handle_message(&mut self, request: JsonRpcRequest) -> Option<JsonRpcResponse> {
+        let is_notification = request.id.is_none();
+        let response = self.dispatch(request);
+        (!is_notification).then_some(response)
+    }
+
+    /// Write one JSON-RPC message (response or notification) as a line
+    fn write_response(stdout: &mut impl Write, response: &impl Serialize) -> Result<()> {
         let json = serde_json::to_string(response)?;
         writeln!(stdout, "{}", json)?;
         stdout.flush()?;
         Ok(())
     }
 
+    /// Handle a request while tracking its id as in-flight
+    ///
+    /// A client reusing an id that is still being handled violates JSON-RPC;
+    /// we log it and still answer, since the response carries the id.
+    fn dispatch(&mut self, request: JsonRpcRequest) -> JsonRpcResponse {
+        let id_key = request.id.as_ref().map(|id| id.to_string());
+
+        if let Some(ref key) = id_key {
+            if !self.in_flight.insert(key.clone()) {
+                self.logger
+                    .error(format_args!("duplicate in-flight request id={}", key));
+            }
+        }
+
+        let method = request.method.clone();
+        let start = Instant::now();
+        let response = self.handle_request(request);
+        self.logger.debug(format_args!(
+            "request method={} id={} elapsed_ms={}",
+            method,
+            id_key.as_deref().unwrap_or("-"),
+            start.elapsed().as_millis()
+        ));
+
+        if let Some(key) = id_key {
+            self.in_flight.remove(&key);
+        }
+
+        response
+    }
+
     fn handle_request(&mut self, request: JsonRpcRequest) -> JsonRpcResponse {
         match request.method.as_str() {
             "initialize" => self.handle_initialize(request.id),
-            "initialized" => JsonRpcResponse::success(request.id, json!({})),
+            "initialized" | "notifications/initialized" => {
+                JsonRpcResponse::success(request.id, json!({}))
+            }
             "tools/list" => self.handle_tools_list(request.id),
             "tools/call" => self.handle_tools_call(request.id, request.params),
+            "resources/list" => self.handle_resources_list(request.id),
+            "resources/read" => self.handle_resources_read(request.id, request.params),
+            "prompts/list" => self.handle_prompts_list(request.id),
+            "prompts/get" => self.handle_prompts_get(request.id, request.params),
             "ping" => JsonRpcResponse::success(request.id, json!({})),
+            "shutdown" => JsonRpcResponse::success(request.id, json!({})),
+            "exit" => {
+                self.exit_requested = true;
+                JsonRpcResponse::success(request.id, json!({}))
+            }
             _ => JsonRpcResponse::error(
                 request.id,
                 -32601,
@@ -86,6 +339,13 @@ impl McpServer {
                 tools: ToolsCapability {
                     list_changed: false,
                 },
+                resources: ResourcesCapability {
+                    subscribe: false,
+                    list_changed: false,
+                },
+                prompts: PromptsCapability {
+                    list_changed: false,
+                },
             },
             server_info: ServerInfo {
                 name: "sgrep".to_string(),
@@ -96,8 +356,113 @@ impl McpServer {
         JsonRpcResponse::success(id, serde_json::to_value(result).unwrap())
     }
 
+    fn handle_resources_list(&self, id: Option<Value>) -> JsonRpcResponse {
+        let store = match self.cached_store(None) {
+            Ok(s) => s,
+            Err(e) => {
+                return JsonRpcResponse::error(id, -32603, format!("Failed to load index: {}", e))
+            }
+        };
+
+        let result = list_resources(&store.list_files());
+        JsonRpcResponse::success(id, serde_json::to_value(result).unwrap())
+    }
+
+    fn handle_resources_read(&self, id: Option<Value>, params: Option<Value>) -> JsonRpcResponse {
+        let params: ResourceReadParams = match params.map(serde_json::from_value) {
+            Some(Ok(p)) => p,
+            Some(Err(e)) => {
+                return JsonRpcResponse::error(id, -32602, format!("Invalid params: {}", e))
+            }
+            None => return JsonRpcResponse::error(id, -32602, "Missing params".to_string()),
+        };
+
+        if self.no_content {
+            return JsonRpcResponse::error(
+                id,
+                -32602,
+                "Reading resources is disabled (--no-content)".to_string(),
+            );
+        }
+
+        let store = match self.cached_store(None) {
+            Ok(s) => s,
+            Err(e) => {
+                return JsonRpcResponse::error(id, -32603, format!("Failed to load index: {}", e))
+            }
+        };
+
+        match read_resource(&store.list_files(), &params.uri) {
+            Ok(result) => JsonRpcResponse::success(id, serde_json::to_value(result).unwrap()),
+            // -32002 is MCP's "resource not found"
+            Err(e) => JsonRpcResponse::error(id, -32002, e.to_string()),
+        }
+    }
+
+    fn handle_prompts_list(&self, id: Option<Value>) -> JsonRpcResponse {
+        let path_arg = || PromptArgument {
+            name: "path".to_string(),
+            description: "Project root with a compiled codebase map (default: current directory)"
+                .to_string(),
+            required: false,
+        };
+
+        let result = PromptsListResult {
+            prompts: vec![
+                PromptDefinition {
+                    name: "explain_module".to_string(),
+                    description: "Explain what a module does, starting from its codebase map summary (files, key symbols, imports)".to_string(),
+                    arguments: vec![
+                        PromptArgument {
+                            name: "module".to_string(),
+                            description: "Directory or file path relative to the project root (e.g. 'src/core')".to_string(),
+                            required: true,
+                        },
+                        path_arg(),
+                    ],
+                },
+                PromptDefinition {
+                    name: "find_entry_point".to_string(),
+                    description: "Find where execution starts, given main functions, entry files and the most referenced symbols from the codebase map".to_string(),
+                    arguments: vec![path_arg()],
+                },
+            ],
+        };
+
+        JsonRpcResponse::success(id, serde_json::to_value(result).unwrap())
+    }
+
+    fn handle_prompts_get(&self, id: Option<Value>, params: Option<Value>) -> JsonRpcResponse {
+        let params: PromptGetParams = match params.map(serde_json::from_value) {
+            Some(Ok(p)) => p,
+            Some(Err(e)) => {
+                return JsonRpcResponse::error(id, -32602, format!("Invalid params: {}", e))
+            }
+            None => return JsonRpcResponse::error(id, -32602, "Missing params".to_string()),
+        };
+
+        let path = params.arguments.get("path").map_or(".", |p| p.as_str());
+        let root = match Path::new(path).canonicalize() {
+            Ok(p) => p,
+            Err(e) => return JsonRpcResponse::error(id, -32602, format!("Invalid path: {}", e)),
+        };
+
+        let map = match load_code_map(&root) {
+            Ok(Some(m)) => m,
+            Ok(None) => return JsonRpcResponse::error(id, -32603, NO_CODE_MAP.to_string()),
+            Err(e) => {
+                return JsonRpcResponse::error(id, -32603, format!("Failed to load map: {}", e))
+            }
+        };
+
+        match render_prompt(&map, &params.name, &params.arguments) {
+            Ok(result) => JsonRpcResponse::success(id, serde_json::to_value(result).unwrap()),
+            Err(e) => JsonRpcResponse::error(id, -32602, e),
+        }
+    }
+
     fn handle_tools_list(&self, id: Option<Value>) -> JsonRpcResponse {
-        let tools = vec![
+        let mut tools = vec![
             ToolDefinition {
                 name: "semantic_search".to_string(),
                 description: "Search code semantically using natural language. Finds relevant code based on meaning, not just keywords. Uses AI embeddings to understand code context and find related files, functions, and patterns.".to_string(),
@@ -117,16 +482,126 @@ impl McpServer {
                             "description": "Maximum number of results to return (default: 10, max: 50)",
                             "default": 10
                         },
+                        "offset": {
+                            "type": "integer",
+                            "description": "Number of results to skip, for paging past max_results (default: 0)",
+                            "default": 0
+                        },
                         "mode": {
                             "type": "string",
-                            "enum": ["balanced", "code", "hybrid"],
-                            "description": "Search mode: 'balanced' (general), 'code' (code-optimized), 'hybrid' (best quality, combines both)",
+                            "enum": ["balanced", "code", "hybrid", "lexical"],
+                            "description": "Search mode: 'balanced' (general), 'code' (code-optimized), 'hybrid' (best quality, combines both), 'lexical' (BM25 keyword matching, no model needed). If the embedding model can't load, search falls back to 'lexical'.",
                             "default": "balanced"
                         },
                         "include_content": {
                             "type": "boolean",
                             "description": "Include file content in results",
                             "default": true
+                        },
+                        "model": {
+                            "type": "string",
+                            "description": "Embedding model (Hugging Face id or local model directory) to embed the query with; overrides 'mode' and must match the model the index was built with. Defaults to $SEARCHGREP_MODEL when set."
+                        },
+                        "normalize_query": {
+                            "type": "boolean",
+                            "description": "Strip surrounding code fences/backticks and extra whitespace before embedding (the raw query is still used for keyword matching)",
+                            "default": true
+                        },
+                        "explain": {
+                            "type": "boolean",
+                            "description": "Attach a per-result breakdown of how the score was composed (embedding, lexical, ColBERT)",
+                            "default": false
+                        },
+                        "highlight": {
+                            "type": "boolean",
+                            "description": "Wrap words in the shown content that matched the query's keywords in **markers**, to see why a chunk matched (text output only)",
+                            "default": false
+                        },
+                        "dedup": {
+                            "type": "boolean",
+                            "description": "Merge results from the same file whose line ranges overlap or nearly touch into one entry (best score, combined range), freeing slots for other files",
+                            "default": true
+                        },
+                        "context_lines": {
+                            "type": "integer",
+                            "description": "Read each result's file and show this many lines before and after the chunk, numbered, with the chunk's own lines marked '>' (text output; max 50)",
+                            "default": 0
+                        },
+                        "auto_index": {
+                            "type": "boolean",
+                            "description": "If nothing is indexed yet and 'path' is given, index that directory first and then search (may take a while)",
+                            "default": false
+                        },
+                        "index_name": {
+                            "type": "string",
+                            "description": "Search a named index created with index_directory's 'index_name' (default: the unnamed index)"
+                        },
+                        "min_score": {
+                            "type": "number",
+                            "description": "Drop results scoring below this (0.0-1.0). Default 0.0 keeps everything; raise it to avoid weak matches when nothing relevant exists",
+                            "default": 0.0
+                        },
+                        "diversity": {
+                            "type": "number",
+                            "description": "Rerank so results differ from each other (Maximal Marginal Relevance), 0.0-1.0. 0 is pure relevance; around 0.3-0.5 surfaces matches from more files and modules",
+                            "default": 0.0
+                        },
+                        "semantic_weight": {
+                            "type": "number",
+                            "description": "Share of the score from embedding similarity, 0.0-1.0; the rest comes from keyword (BM25) matching. Lower it for exact identifiers, raise it for conceptual queries",
+                            "default": DEFAULT_SEMANTIC_WEIGHT
+                        },
+                        "language": {
+                            "type": "string",
+                            "description": "Only return results from this language, by name or extension (e.g. 'rust', 'python', 'ts')"
+                        },
+                        "path_glob": {
+                            "type": "string",
+                            "description": "Only return results whose file path matches this glob (e.g. 'src/**', '**/*_test.go'); relative patterns may match from any directory"
+                        },
+                        "exclude_globs": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Drop results whose file path matches any of these globs (e.g. ['tests/**', 'vendor/**', 'node_modules/**']). Matched like path_glob; exclusion wins over path_glob"
+                        },
+                        "output_format": {
+                            "type": "string",
+                            "enum": ["text", "json"],
+                            "description": "'text' (readable summary) or 'json' (first content block is a JSON array of {file_path, start_line, end_line, score, content})",
+                            "default": "text"
+                        }
+                    },
+                    "required": ["query"]
+                }),
+            },
+            ToolDefinition {
+                name: "multi_mode_search".to_string(),
+                description: "Run a query in balanced, code and hybrid modes at once and return one merged ranking, with each hit tagged by the modes (and ranks) that found it. Useful for ambiguous queries or to see which mode suits a query best.".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "query": {
+                            "type": "string",
+                            "description": "Natural language search query"
+                        },
+                        "index_name": {
+                            "type": "string",
+                            "description": "Search a named index created with index_directory's 'index_name' (default: the unnamed index)"
+                        },
+                        "max_results": {
+                            "type": "integer",
+                            "description": "Results per mode before merging (default: 5, max: 20). Identical hits are merged, and at most 30 are returned.",
+                            "default": 5
+                        },
+                        "include_content": {
+                            "type": "boolean",
+                            "description": "Include a short excerpt of each hit",
+                            "default": false
+                        },
+                        "normalize_query": {
+                            "type": "boolean",
+                            "description": "Strip surrounding code fences/backticks and extra whitespace before embedding",
+                            "default": true
                         }
                     },
                     "required": ["query"]
@@ -147,11 +622,79 @@ impl McpServer {
                             "enum": ["fast", "balanced", "code"],
                             "description": "Indexing mode: 'fast' (quick, lower quality), 'balanced' (default), 'code' (code-optimized)",
                             "default": "balanced"
+                        },
+                        "align_boundaries": {
+                            "type": "boolean",
+                            "description": "Start line-based chunks at blank lines instead of cutting mid-paragraph",
+                            "default": false
+                        },
+                        "include_ignored": {
+                            "type": "boolean",
+                            "description": "Also index files excluded by .gitignore, .sgrepignore or .searchgrepignore (e.g. build output, vendored deps)",
+                            "default": false
+                        },
+                        "languages": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Only index files in these languages, e.g. [\"rust\", \"go\"] (names: rust, typescript, javascript, python, go, java, c, cpp, ...). Empty or absent: every language. Files already in the index stay until it's cleared."
+                        },
+                        "exclude_languages": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Skip files in these languages, e.g. [\"typescript\"] for generated TS"
+                        },
+                        "chunking": {
+                            "type": "string",
+                            "enum": ["window", "symbol"],
+                            "description": "How files are split: 'window' (default) uses pattern-detected blocks and line windows; 'symbol' makes each top-level function/class its own chunk, falling back to 'window' for languages without a symbol parser",
+                            "default": "window"
+                        },
+                        "model": {
+                            "type": "string",
+                            "description": "Embedding model: a Hugging Face id (e.g. 'BAAI/bge-small-en-v1.5') or a local directory with config.json, tokenizer.json and model.safetensors; overrides 'mode'. Defaults to $SEARCHGREP_MODEL when set. Searches must use the same model."
+                        },
+                        "embed_path_context": {
+                            "type": "boolean",
+                            "description": "Prefix each chunk with its file path before embedding, so path-specific concepts (e.g. 'auth') match. Fixed per index.",
+                            "default": false
+                        },
+                        "threads": {
+                            "type": "integer",
+                            "description": "Worker threads for chunking files (default: all available cores); lower it to cap CPU use",
+                            "minimum": 1
+                        },
+                        "quantization": {
+                            "type": "string",
+                            "enum": ["none", "int8"],
+                            "description": "Store embeddings as int8 with a per-vector scale (about 4x smaller on disk, near-identical ranking). Defaults to the existing index's setting."
+                        },
+                        "index_name": {
+                            "type": "string",
+                            "description": "Write to a named index (letters, digits, '-', '_', '.') so several can coexist, e.g. a 'fast' one for iteration and a 'hybrid' one for deep search. Default: the unnamed index."
+                        },
+                        "build_map": {
+                            "type": "boolean",
+                            "description": "Also compile the codebase map for 'path' (as compile_map does), so the map tools work straight away",
+                            "default": true
                         }
                     },
                     "required": ["path"]
                 }),
             },
+            ToolDefinition {
+                name: "compile_map".to_string(),
+                description: "Parse every file under a directory and save its codebase map (symbols, signatures and call dependencies). The map tools (get_codebase_map, search_symbols, find_references, ...) read this map; index_directory builds it too unless build_map is false.".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "path": {
+                            "type": "string",
+                            "description": "Directory to map (defaults to the current directory)"
+                        }
+                    },
+                    "required": []
+                }),
+            },
             ToolDefinition {
                 name: "get_codebase_map".to_string(),
                 description: "Get a compact semantic map of the codebase. Returns all symbols (functions, structs, classes) with signatures - 90% fewer tokens than reading files. Use this FIRST to understand codebase structure before reading individual files.".to_string(),
@@ -166,6 +709,42 @@ impl McpServer {
                             "type": "boolean",
                             "description": "Return ultra-compact view (just function names per file)",
                             "default": false
+                        },
+                        "include_unparsed": {
+                            "type": "boolean",
+                            "description": "Also list files without symbols (e.g. Makefile, Dockerfile) as name-only entries",
+                            "default": false
+                        },
+                        "full_signatures": {
+                            "type": "boolean",
+                            "description": "Show every signature verbatim; by default long default values and generic arguments are shortened to '...'",
+                            "default": false
+                        },
+                        "public_only": {
+                            "type": "boolean",
+                            "description": "Only include the exported API surface (pub/export/public symbols); symbols in languages without visibility markers are kept",
+                            "default": false
+                        },
+                        "max_tokens": {
+                            "type": "integer",
+                            "description": "Token budget for the text overview; the least-referenced symbols are dropped until it fits",
+                            "minimum": 1
+                        },
+                        "sort": {
+                            "type": "string",
+                            "enum": ["file", "centrality"],
+                            "description": "Order of the text overview: 'file' groups symbols by file; 'centrality' leads with the most-referenced symbols",
+                            "default": "file"
+                        },
+                        "format": {
+                            "type": "string",
+                            "enum": ["text", "json", "dot"],
+                            "description": "'json' returns structured data (files, symbols with id/name/file/line/kind/visibility/signature/summary, and edges) for tooling instead of prose; 'dot' returns a Graphviz call graph (render with `dot -Tsvg`)",
+                            "default": "text"
+                        },
+                        "kind": {
+                            "type": "string",
+                            "description": "Only include symbols of these kinds, comma-separated (e.g. 'trait' or 'struct,class'). Kinds: fn, method, struct, enum, trait, interface, class, type, const, var, mod, export, macro"
                         }
                     },
                     "required": []
@@ -189,37 +768,167 @@ impl McpServer {
                             "type": "integer",
                             "description": "Maximum results to return (default: 20)",
                             "default": 20
+                        },
+                        "offset": {
+                            "type": "integer",
+                            "description": "Number of results to skip, for paging past max_results (default: 0)",
+                            "default": 0
+                        },
+                        "full_signatures": {
+                            "type": "boolean",
+                            "description": "Show every signature verbatim; by default long default values and generic arguments are shortened to '...'",
+                            "default": false
+                        },
+                        "kind": {
+                            "type": "string",
+                            "description": "Only include symbols of these kinds, comma-separated (e.g. 'trait' or 'struct,class'). Kinds: fn, method, struct, enum, trait, interface, class, type, const, var, mod, export, macro"
+                        },
+                        "regex": {
+                            "type": "boolean",
+                            "description": "Treat 'query' as a regular expression matched against each symbol's name and signature (e.g. '^get.*Async$'); case-sensitive unless it starts with (?i)",
+                            "default": false
                         }
                     },
                     "required": ["query"]
                 }),
             },
             ToolDefinition {
-                name: "expand_symbol".to_string(),
-                description: "Get detailed info about a specific symbol including its dependencies and dependents. Use after search_symbols to understand code relationships.".to_string(),
+                name: "search_by_doc".to_string(),
+                description: "Find symbols by what their documentation says they do, rather than by name. Matches the query against symbol summaries/doc comments and returns signature plus summary.".to_string(),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
-                        "symbol_id": {
+                        "query": {
                             "type": "string",
-                            "description": "Symbol ID in format 'file:name' (from search_symbols results)"
+                            "description": "Description of what the symbol does (e.g., 'remove files missing from disk')"
                         },
                         "path": {
                             "type": "string",
                             "description": "Directory path (defaults to current indexed directory)"
                         },
-                        "include_code": {
-                            "type": "boolean",
-                            "description": "Include source code snippet for the symbol",
-                            "default": false
+                        "max_results": {
+                            "type": "integer",
+                            "description": "Maximum results to return (default: 10)",
+                            "default": 10
                         }
                     },
-                    "required": ["symbol_id"]
+                    "required": ["query"]
                 }),
             },
             ToolDefinition {
-                name: "find_similar_code".to_string(),
-                description: "Find code similar to a given snippet or file. Useful for finding duplicates, similar patterns, or related implementations.".to_string(),
+                name: "expand_symbol".to_string(),
+                description: "Get detailed info about a specific symbol including its dependencies and dependents. Use after search_symbols to understand code relationships.".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "symbol_id": {
+                            "type": "string",
+                            "description": "Symbol ID in format 'file:name' (from search_symbols results)"
+                        },
+                        "path": {
+                            "type": "string",
+                            "description": "Directory path (defaults to current indexed directory)"
+                        },
+                        "include_code": {
+                            "type": "boolean",
+                            "description": "Include source code snippet for the symbol",
+                            "default": false
+                        }
+                    },
+                    "required": ["symbol_id"]
+                }),
+            },
+            ToolDefinition {
+                name: "find_references".to_string(),
+                description: "List every place that calls or references a symbol, one file:line per line. The inverse of expand_symbol's dependencies; use before changing a signature to see what breaks.".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "symbol_id": {
+                            "type": "string",
+                            "description": "Symbol ID in format 'file:name' (from search_symbols results)"
+                        },
+                        "path": {
+                            "type": "string",
+                            "description": "Directory path (defaults to current indexed directory)"
+                        }
+                    },
+                    "required": ["symbol_id"]
+                }),
+            },
+            ToolDefinition {
+                name: "rename_impact".to_string(),
+                description: "Preview what renaming a symbol would touch: its declaration and every file:line in its dependents that mentions the old name, with counts. Read-only; no files are modified.".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "symbol_id": {
+                            "type": "string",
+                            "description": "Symbol ID in format 'file:name' (from search_symbols results)"
+                        },
+                        "new_name": {
+                            "type": "string",
+                            "description": "Proposed new name for the symbol"
+                        },
+                        "path": {
+                            "type": "string",
+                            "description": "Directory path (defaults to current indexed directory)"
+                        }
+                    },
+                    "required": ["symbol_id", "new_name"]
+                }),
+            },
+            ToolDefinition {
+                name: "find_dead_code".to_string(),
+                description: "List functions and methods that nothing in the codebase map calls, with file:line. Heuristic: dynamic dispatch, reflection and callers outside the map aren't visible, so entry points, exported symbols and tests are skipped and every hit should be checked before deleting.".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "path": {
+                            "type": "string",
+                            "description": "Directory path (defaults to current indexed directory)"
+                        },
+                        "max_results": {
+                            "type": "integer",
+                            "description": "Maximum symbols to list (default: 50)",
+                            "default": 50
+                        }
+                    },
+                    "required": []
+                }),
+            },
+            ToolDefinition {
+                name: "read_symbols".to_string(),
+                description: "Read the source of several symbols in one call, each under its own header. Cheaper than calling expand_symbol repeatedly when gathering context for an edit.".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "symbol_ids": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Symbol IDs in format 'file:name' (from search_symbols results)"
+                        },
+                        "path": {
+                            "type": "string",
+                            "description": "Directory path (defaults to current indexed directory)"
+                        },
+                        "max_bytes": {
+                            "type": "integer",
+                            "description": "Maximum total bytes of source to return (default: 40000); symbols past the limit are listed as omitted",
+                            "default": 40000
+                        },
+                        "include_code": {
+                            "type": "boolean",
+                            "description": "Include source code (otherwise only headers with line ranges)",
+                            "default": true
+                        }
+                    },
+                    "required": ["symbol_ids"]
+                }),
+            },
+            ToolDefinition {
+                name: "find_similar_code".to_string(),
+                description: "Find code similar to a given snippet or file. Useful for finding duplicates, similar patterns, or related implementations.".to_string(),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
@@ -235,6 +944,11 @@ impl McpServer {
                             "type": "integer",
                             "description": "Maximum results to return (default: 10)",
                             "default": 10
+                        },
+                        "include_content": {
+                            "type": "boolean",
+                            "description": "Include matching code in results",
+                            "default": true
                         }
                     },
                     "required": []
@@ -254,6 +968,11 @@ impl McpServer {
                             "type": "integer",
                             "description": "Maximum number of code snippets to use as context (default: 5)",
                             "default": 5
+                        },
+                        "include_content": {
+                            "type": "boolean",
+                            "description": "Include the code snippets (otherwise only their locations)",
+                            "default": true
                         }
                     },
                     "required": ["question"]
@@ -278,6 +997,66 @@ impl McpServer {
                     "required": ["file_path"]
                 }),
             },
+            ToolDefinition {
+                name: "warm_up".to_string(),
+                description: "Load an embedding model ahead of the first search. Loaded models stay in memory for the life of the server, so only the first semantic search in a mode pays the load time; call this at session start to pay it up front.".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "mode": {
+                            "type": "string",
+                            "enum": ["balanced", "code", "hybrid"],
+                            "description": "Search mode whose model to load",
+                            "default": "balanced"
+                        },
+                        "model": {
+                            "type": "string",
+                            "description": "Load this model instead of the mode's default (Hugging Face id or local directory, as in semantic_search)"
+                        }
+                    },
+                    "required": []
+                }),
+            },
+            ToolDefinition {
+                name: "get_index_status".to_string(),
+                description: "Report whether a semantic index exists and how big it is (files, chunks, embedding dimension, model, last indexed), and whether a codebase map has been compiled. Call before searching to decide whether index_directory is needed.".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "path": {
+                            "type": "string",
+                            "description": "Directory to check for a codebase map (defaults to the current directory)"
+                        },
+                        "index_name": {
+                            "type": "string",
+                            "description": "Report on a named index created with index_directory's 'index_name' (default: the unnamed index)"
+                        }
+                    },
+                    "required": []
+                }),
+            },
+            ToolDefinition {
+                name: "clear_index".to_string(),
+                description: "Delete the semantic index and codebase map for a directory. Use when switching embedding modes or models, or when the index is corrupt; re-run index_directory afterwards. Requires confirm: true.".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "path": {
+                            "type": "string",
+                            "description": "Directory whose codebase map should be deleted"
+                        },
+                        "index_name": {
+                            "type": "string",
+                            "description": "Delete a named index created with index_directory's 'index_name' (default: the unnamed index)"
+                        },
+                        "confirm": {
+                            "type": "boolean",
+                            "description": "Must be true; guards against accidental deletion"
+                        }
+                    },
+                    "required": ["path", "confirm"]
+                }),
+            },
             ToolDefinition {
                 name: "list_indexed_files".to_string(),
                 description: "List all files currently indexed for semantic search. Useful to check what's available to search.".to_string(),
@@ -292,140 +1071,619 @@ impl McpServer {
                     "required": []
                 }),
             },
-        ];
-
-        let result = ToolsListResult { tools };
-        JsonRpcResponse::success(id, serde_json::to_value(result).unwrap())
-    }
-
-    fn handle_tools_call(&self, id: Option<Value>, params: Option<Value>) -> JsonRpcResponse {
-        let params = match params {
-            Some(p) => p,
-            None => {
-                return JsonRpcResponse::error(id, -32602, "Missing params".to_string());
-            }
-        };
-
-        let call: ToolCallParams = match serde_json::from_value(params) {
-            Ok(c) => c,
-            Err(e) => {
-                return JsonRpcResponse::error(id, -32602, format!("Invalid params: {}", e));
-            }
-        };
-
-        let result = match call.name.as_str() {
-            "semantic_search" => self.execute_semantic_search(call.arguments),
-            "index_directory" => self.execute_index_directory(call.arguments),
-            "get_codebase_map" => self.execute_get_codebase_map(call.arguments),
-            "search_symbols" => self.execute_search_symbols(call.arguments),
-            "expand_symbol" => self.execute_expand_symbol(call.arguments),
-            "find_similar_code" => self.execute_find_similar_code(call.arguments),
-            "ask_codebase" => self.execute_ask_codebase(call.arguments),
-            "get_file_context" => self.execute_get_file_context(call.arguments),
-            "list_indexed_files" => self.execute_list_indexed_files(call.arguments),
-            _ => ToolCallResult::error(format!("Unknown tool: {}", call.name)),
-        };
-
-        JsonRpcResponse::success(id, serde_json::to_value(result).unwrap())
-    }
-
-    fn execute_semantic_search(&self, args: Option<Value>) -> ToolCallResult {
-        let args = match args {
-            Some(a) => a,
-            None => return ToolCallResult::error("Missing arguments".to_string()),
-        };
-
-        let query = match args.get("query").and_then(|v| v.as_str()) {
-            Some(q) => q.to_string(),
-            None => return ToolCallResult::error("Missing required 'query' argument".to_string()),
-        };
-
-        let max_results = args
-            .get("max_results")
-            .and_then(|v| v.as_u64())
-            .unwrap_or(10)
-            .min(50) as usize;
-
-        let mode = args
-            .get("mode")
-            .and_then(|v| v.as_str())
-            .unwrap_or("balanced");
-
-        let include_content = args
-            .get("include_content")
-            .and_then(|v| v.as_bool())
-            .unwrap_or(true);
-
-        let store_path = args.get("path").and_then(|v| v.as_str());
-
-        // Load the vector store
-        let store = match VectorStore::load(store_path) {
-            Ok(s) => s,
-            Err(e) => return ToolCallResult::error(format!("Failed to load index: {}", e)),
-        };
-
-        if store.chunk_count() == 0 {
-            return ToolCallResult::error(
-                "No files indexed. Run 'sgrep watch <path>' first to index your codebase."
-                    .to_string(),
-            );
-        }
-
-        // Generate query embedding based on mode
-        let query_embedding = match mode {
-            "hybrid" => match HybridEmbedder::new() {
-                Ok(mut embedder) => match embedder.embed_query(&query) {
-                    Ok(emb) => emb,
-                    Err(e) => return ToolCallResult::error(format!("Embedding failed: {}", e)),
-                },
-                Err(e) => return ToolCallResult::error(format!("Model load failed: {}", e)),
+            ToolDefinition {
+                name: "result_context".to_string(),
+                description: "Show the lines around a search hit, read fresh from disk. The matched range is marked with '>' so it stands out from the surrounding context. Natural follow-up to semantic_search instead of reading the whole file.".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "file_path": {
+                            "type": "string",
+                            "description": "Path to the file (as shown in search results)"
+                        },
+                        "start_line": {
+                            "type": "integer",
+                            "description": "First line of the matched range (1-based)"
+                        },
+                        "end_line": {
+                            "type": "integer",
+                            "description": "Last line of the matched range (defaults to start_line)"
+                        },
+                        "radius": {
+                            "type": "integer",
+                            "description": "Context lines to show on each side of the range (default: 25, max: 200)",
+                            "default": 25
+                        },
+                        "include_content": {
+                            "type": "boolean",
+                            "description": "Include the lines themselves (otherwise only the resolved line ranges)",
+                            "default": true
+                        }
+                    },
+                    "required": ["file_path", "start_line"]
+                }),
             },
-            "code" => match LocalEmbedder::with_speed_mode(SpeedMode::Code) {
-                Ok(mut embedder) => match embedder.embed_query(&query) {
-                    Ok(emb) => emb,
-                    Err(e) => return ToolCallResult::error(format!("Embedding failed: {}", e)),
-                },
-                Err(e) => return ToolCallResult::error(format!("Model load failed: {}", e)),
+            ToolDefinition {
+                name: "file_tree".to_string(),
+                description: "Show the indexed files as a directory tree with per-directory file counts. Reflects what is searchable, not the raw filesystem.".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "path": {
+                            "type": "string",
+                            "description": "Root directory for the tree (defaults to the common root of all indexed files)"
+                        },
+                        "max_depth": {
+                            "type": "integer",
+                            "description": "Only expand this many directory levels; deeper directories are shown collapsed with their file counts"
+                        },
+                        "format": {
+                            "type": "string",
+                            "enum": ["text", "json"],
+                            "description": "Output format: compact indented 'text' (default) or nested 'json'",
+                            "default": "text"
+                        }
+                    },
+                    "required": []
+                }),
             },
-            _ => {
-                // balanced mode
-                match LocalEmbedder::with_speed_mode(SpeedMode::Balanced) {
-                    Ok(mut embedder) => match embedder.embed_query(&query) {
-                        Ok(emb) => emb,
-                        Err(e) => return ToolCallResult::error(format!("Embedding failed: {}", e)),
+            ToolDefinition {
+                name: "find_cycles".to_string(),
+                description: "Detect circular dependencies in the codebase map, at symbol or file granularity. Each cycle is a strongly connected component: a group of nodes that all reach each other through dependencies, listed in sorted order.".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "path": {
+                            "type": "string",
+                            "description": "Directory path (defaults to current indexed directory)"
+                        },
+                        "level": {
+                            "type": "string",
+                            "enum": ["symbol", "file"],
+                            "description": "Granularity: 'file' (default) or 'symbol'",
+                            "default": "file"
+                        },
+                        "max_cycles": {
+                            "type": "integer",
+                            "description": "Maximum cycles to report (default: 20, max: 200)",
+                            "default": 20
+                        }
                     },
-                    Err(e) => return ToolCallResult::error(format!("Model load failed: {}", e)),
-                }
+                    "required": []
+                }),
+            },
+            ToolDefinition {
+                name: "module_summary".to_string(),
+                description: "Structured summary of a directory from the codebase map: symbol counts by kind, primary language, key public symbols, and top imports.".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "prefix": {
+                            "type": "string",
+                            "description": "Directory prefix relative to the project root (e.g., 'src/core')"
+                        },
+                        "path": {
+                            "type": "string",
+                            "description": "Directory path (defaults to current indexed directory)"
+                        },
+                        "format": {
+                            "type": "string",
+                            "enum": ["text", "markdown"],
+                            "description": "Output format: 'text' (default) or 'markdown'",
+                            "default": "text"
+                        },
+                        "max_symbols": {
+                            "type": "integer",
+                            "description": "Maximum key symbols to list (default: 15)",
+                            "default": 15
+                        }
+                    },
+                    "required": ["prefix"]
+                }),
+            },
+            ToolDefinition {
+                name: "get_file_symbols".to_string(),
+                description: "Outline of one file from the codebase map: every symbol it defines, in line order, with kind and signature.".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "file": {
+                            "type": "string",
+                            "description": "File path relative to the project root (e.g., 'src/core/search.rs')"
+                        },
+                        "path": {
+                            "type": "string",
+                            "description": "Directory path (defaults to current indexed directory)"
+                        }
+                    },
+                    "required": ["file"]
+                }),
+            },
+            ToolDefinition {
+                name: "find_orphans".to_string(),
+                description: "List indexed files that no longer exist on disk. Their chunks can still show up in search results until pruned with prune_orphans.".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {},
+                    "required": []
+                }),
+            },
+            ToolDefinition {
+                name: "prune_orphans".to_string(),
+                description: "Remove indexed files that no longer exist on disk (and their chunks) from the index. Reports how many were removed.".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {},
+                    "required": []
+                }),
+            },
+            ToolDefinition {
+                name: "symbol_metrics".to_string(),
+                description: "Estimate per-function complexity (approximate cyclomatic complexity from branch keywords like if/for/while/match and &&/||) plus line counts, sorted most complex first. Useful for finding refactoring candidates.".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "path": {
+                            "type": "string",
+                            "description": "Directory path (defaults to current indexed directory)"
+                        },
+                        "max_results": {
+                            "type": "integer",
+                            "description": "Maximum symbols to return (default: 20, max: 100)",
+                            "default": 20
+                        }
+                    },
+                    "required": []
+                }),
+            },
+        ];
+
+        if self.debug_tools {
+            tools.push(ToolDefinition {
+                name: "debug_parse".to_string(),
+                description: "[Developer] Run the symbol parser on a single file and return the raw parse output (language, symbols, imports, line count, warnings) as JSON. Useful for reporting parser bugs.".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "file_path": {
+                            "type": "string",
+                            "description": "Path to the file to parse"
+                        }
+                    },
+                    "required": ["file_path"]
+                }),
+            });
+        }
+
+        let result = ToolsListResult { tools };
+        JsonRpcResponse::success(id, serde_json::to_value(result).unwrap())
+    }
+
+    fn handle_tools_call(&self, id: Option<Value>, params: Option<Value>) -> JsonRpcResponse {
+        let params = match params {
+            Some(p) => p,
+            None => {
+                return JsonRpcResponse::error(id, -32602, "Missing params".to_string());
             }
         };
 
-        // Search
-        let searcher = HybridSearcher::default();
-        let results = searcher.search(
+        // Clients opt into progress notifications by sending a token
+        let progress_token = params
+            .get("_meta")
+            .and_then(|m| m.get("progressToken"))
+            .cloned();
+
+        let call: ToolCallParams = match serde_json::from_value(params) {
+            Ok(c) => c,
+            Err(e) => {
+                return JsonRpcResponse::error(id, -32602, format!("Invalid params: {}", e));
+            }
+        };
+
+        // Content policy is enforced here, before any handler sees the arguments
+        let suppress_content = self.no_content && CONTENT_TOOLS.contains(&call.name.as_str());
+        let arguments = if suppress_content {
+            let mut args = call.arguments.unwrap_or(json!({}));
+            if let Some(obj) = args.as_object_mut() {
+                for flag in CONTENT_ARGS {
+                    obj.insert(flag.to_string(), json!(false));
+                }
+            }
+            Some(args)
+        } else {
+            call.arguments
+        };
+
+        let start = Instant::now();
+        let mut result = match call.name.as_str() {
+            "semantic_search" => self.execute_semantic_search(arguments),
+            "multi_mode_search" => self.execute_multi_mode_search(arguments),
+            "index_directory" => self.execute_index_directory(arguments, progress_token),
+            "get_codebase_map" => self.execute_get_codebase_map(arguments),
+            "compile_map" => self.execute_compile_map(arguments),
+            "search_symbols" => self.execute_search_symbols(arguments),
+            "search_by_doc" => self.execute_search_by_doc(arguments),
+            "expand_symbol" => self.execute_expand_symbol(arguments),
+            "find_references" => self.execute_find_references(arguments),
+            "rename_impact" => self.execute_rename_impact(arguments),
+            "find_dead_code" => self.execute_find_dead_code(arguments),
+            "read_symbols" => self.execute_read_symbols(arguments),
+            "find_similar_code" => self.execute_find_similar_code(arguments),
+            "ask_codebase" => self.execute_ask_codebase(arguments),
+            "warm_up" => self.execute_warm_up(arguments),
+            "get_file_context" => self.execute_get_file_context(arguments),
+            "get_index_status" => self.execute_get_index_status(arguments),
+            "clear_index" => self.execute_clear_index(arguments),
+            "list_indexed_files" => self.execute_list_indexed_files(arguments),
+            "result_context" => self.execute_result_context(arguments),
+            "file_tree" => self.execute_file_tree(arguments),
+            "find_cycles" => self.execute_find_cycles(arguments),
+            "module_summary" => self.execute_module_summary(arguments),
+            "get_file_symbols" => self.execute_get_file_symbols(arguments),
+            "find_orphans" => self.execute_find_orphans(),
+            "prune_orphans" => self.execute_prune_orphans(),
+            "symbol_metrics" => self.execute_symbol_metrics(arguments),
+            "debug_parse" if self.debug_tools => self.execute_debug_parse(arguments),
+            _ => ToolCallResult::error(format!("Unknown tool: {}", call.name)),
+        };
+
+        let elapsed_ms = start.elapsed().as_millis();
+        if result.is_error == Some(true) {
+            let message = result.content.first().map_or("", |c| c.text.as_str());
+            self.logger.error(format_args!(
+                "tool={} elapsed_ms={} failed: {}",
+                call.name, elapsed_ms, message
+            ));
+        } else {
+            self.logger
+                .info(format_args!("tool={} elapsed_ms={}", call.name, elapsed_ms));
+        }
+
+        if suppress_content {
+            result = result
+                .with_note("Note: file contents are suppressed by server policy (--no-content).");
+        }
+
+        JsonRpcResponse::success(id, serde_json::to_value(result).unwrap())
+    }
+
+    fn execute_semantic_search(&self, args: Option<Value>) -> ToolCallResult {
+        let args = match args {
+            Some(a) => a,
+            None => return ToolCallResult::error("Missing arguments".to_string()),
+        };
+
+        let query = match args.get("query").and_then(|v| v.as_str()) {
+            Some(q) => q.to_string(),
+            None => return ToolCallResult::error("Missing required 'query' argument".to_string()),
+        };
+
+        if let Err(e) = validate_query(&query) {
+            return ToolCallResult::error(e);
+        }
+
+        let max_results = args
+            .get("max_results")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(10)
+            .min(50) as usize;
+
+        let offset = args.get("offset").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+
+        let mode = args
+            .get("mode")
+            .and_then(|v| v.as_str())
+            .unwrap_or("balanced");
+
+        let include_content = args
+            .get("include_content")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+
+        let explain = args
+            .get("explain")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        let highlight = args
+            .get("highlight")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        let dedup = args.get("dedup").and_then(|v| v.as_bool()).unwrap_or(true);
+
+        let context_lines = args
+            .get("context_lines")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0)
+            .min(50) as usize;
+
+        let model = model_override(args.get("model").and_then(|v| v.as_str()));
+        let model = model.as_deref();
+        if let Some(Err(e)) = model.map(locate_model) {
+            return ToolCallResult::error(e.to_string());
+        }
+
+        let json_output = match args.get("output_format").and_then(|v| v.as_str()) {
+            None | Some("text") => false,
+            Some("json") => true,
+            Some(other) => {
+                return ToolCallResult::error(format!(
+                    "Invalid output_format '{}': expected 'text' or 'json'",
+                    other
+                ))
+            }
+        };
+
+        let min_score = args
+            .get("min_score")
+            .and_then(|v| v.as_f64())
+            .unwrap_or(0.0);
+        if !(0.0..=1.0).contains(&min_score) {
+            return ToolCallResult::error(format!(
+                "Invalid min_score {}: expected a value between 0.0 and 1.0",
+                min_score
+            ));
+        }
+
+        let diversity = args
+            .get("diversity")
+            .and_then(|v| v.as_f64())
+            .unwrap_or(0.0);
+        if !(0.0..=1.0).contains(&diversity) {
+            return ToolCallResult::error(format!(
+                "Invalid diversity {}: expected a value between 0.0 and 1.0",
+                diversity
+            ));
+        }
+
+        let semantic_weight = args
+            .get("semantic_weight")
+            .and_then(|v| v.as_f64())
+            .map(|w| w as f32)
+            .unwrap_or(DEFAULT_SEMANTIC_WEIGHT);
+        if !(0.0..=1.0).contains(&semantic_weight) {
+            return ToolCallResult::error(format!(
+                "Invalid semantic_weight {}: expected a value between 0.0 and 1.0",
+                semantic_weight
+            ));
+        }
+
+        let language = args
+            .get("language")
+            .and_then(|v| v.as_str())
+            .map(|l| l.trim_start_matches('.').to_lowercase());
+
+        let path_glob = match args.get("path_glob").and_then(|v| v.as_str()) {
+            Some(pattern) => match glob::Pattern::new(pattern) {
+                Ok(p) => Some(p),
+                Err(e) => {
+                    return ToolCallResult::error(format!("Invalid path_glob '{}': {}", pattern, e))
+                }
+            },
+            None => None,
+        };
+
+        let exclude_globs = match exclude_globs_arg(&args) {
+            Ok(patterns) => patterns,
+            Err(e) => return ToolCallResult::error(e),
+        };
+
+        let normalize = args
+            .get("normalize_query")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+
+        // Fences and stray whitespace skew the embedding; BM25 keeps the raw text
+        let embed_query = if normalize {
+            normalize_query(&query)
+        } else {
+            query.clone()
+        };
+
+        let auto_index = args
+            .get("auto_index")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        let index_name = match index_name_arg(&args) {
+            Ok(name) => name,
+            Err(e) => return ToolCallResult::error(e),
+        };
+        let path = args.get("path").and_then(|v| v.as_str());
+
+        // Read-only: map the embeddings rather than loading them all. This is
+        // the store auto-indexing writes to, so an indexed path isn't re-synced
+        let mut store = match self.cached_store(index_name) {
+            Ok(s) => s,
+            Err(e) => return ToolCallResult::error(format!("Failed to load index: {}", e)),
+        };
+
+        let mut auto_index_note = None;
+        self.logger.debug(format_args!(
+            "index loaded store={} files={} chunks={}",
+            index_name.unwrap_or("default"),
+            store.files.len(),
+            store.chunk_count()
+        ));
+
+        if store.chunk_count() == 0 {
+            let index_path = match path {
+                Some(p) if auto_index => p,
+                _ => {
+                    return ToolCallResult::error(
+                        "No files indexed. Run 'sgrep watch <path>' first to index your codebase."
+                            .to_string(),
+                    );
+                }
+            };
+
+            let speed_mode = match mode {
+                "code" => SpeedMode::Code,
+                _ => SpeedMode::Balanced,
+            };
+
+            let options = SyncOptions {
+                model: model.map(|m| m.to_string()),
+                ..Default::default()
+            };
+
+            let start = Instant::now();
+            if let Err(e) = self.sync_directory(index_path, index_name, speed_mode, options) {
+                return ToolCallResult::error(format!("Auto-indexing failed: {}", e));
+            }
+
+            store = match self.cached_store(index_name) {
+                Ok(s) => s,
+                Err(e) => return ToolCallResult::error(format!("Failed to load index: {}", e)),
+            };
+
+            if store.chunk_count() == 0 {
+                return ToolCallResult::error(format!(
+                    "Auto-indexed {} but no indexable files were found.",
+                    index_path
+                ));
+            }
+
+            auto_index_note = Some(format!(
+                "Auto-indexed {} in {:.1}s ({} files)\n\n",
+                index_path,
+                start.elapsed().as_secs_f32(),
+                store.file_count()
+            ));
+        }
+
+        // Query and index embeddings are only comparable from the same model
+        let query_model = match (model, mode) {
+            (_, "lexical") => None,
+            (Some(m), _) => Some(m),
+            (None, "code") => Some(SpeedMode::Code.model_id()),
+            (None, "hybrid") => None,
+            (None, _) => Some(SpeedMode::Balanced.model_id()),
+        };
+        if let (Some(indexed), Some(query_model)) = (store.model.as_deref(), query_model) {
+            if indexed != query_model {
+                return ToolCallResult::error(format!(
+                    "Index was built with model '{}' but the query would use '{}'. Pass model='{}' or re-index.",
+                    indexed, query_model, indexed
+                ));
+            }
+        }
+
+        // Generate query embedding based on mode (or an explicit model);
+        // an empty embedding makes the searcher rank by keywords alone
+        let embedded = match (model, mode) {
+            (_, "lexical") => Ok(Vec::new()),
+            (Some(model_id), _) => self.embed_query_with(Some(model_id), &embed_query),
+            (None, "hybrid") => self.embed_query_with(None, &embed_query),
+            (None, "code") => self.embed_query_with(Some(SpeedMode::Code.model_id()), &embed_query),
+            // balanced mode
+            _ => self.embed_query_with(Some(SpeedMode::Balanced.model_id()), &embed_query),
+        };
+        // Keep the agent unblocked (e.g. offline, or the model is still downloading)
+        let query_embedding = match embedded {
+            Ok(embedding) => embedding,
+            Err(e) => {
+                self.logger.error(format_args!(
+                    "query embedding failed, falling back to keyword search: {}",
+                    e
+                ));
+                let note = format!("Note: {}. Showing keyword (BM25) results instead.\n\n", e);
+                auto_index_note = Some(auto_index_note.unwrap_or_default() + &note);
+                Vec::new()
+            }
+        };
+        if let Err(e) = check_query_dim(&store, &query_embedding) {
+            return ToolCallResult::error(e.to_string());
+        }
+
+        // Search one past the page so we know whether another page follows;
+        // filters drop results afterwards, so they search a wider pool
+        let needed = offset.saturating_add(max_results + 1);
+        // Merging also shrinks the list, so it searches the wider pool too
+        let filtered =
+            language.is_some() || path_glob.is_some() || !exclude_globs.is_empty() || dedup;
+        let searcher = HybridSearcher::with_weights(semantic_weight, 1.0 - semantic_weight)
+            .with_diversity(diversity as f32);
+        let mut results = searcher.search(
             &store,
             &query_embedding,
             &query,
-            max_results,
+            if filtered {
+                needed.saturating_mul(FILTERED_SEARCH_FACTOR)
+            } else {
+                needed
+            },
             None,
             false,
             None,
         );
+        let candidates = results.len();
+        results.retain(|r| {
+            matches_result_filters(&r.chunk.file_path, language.as_deref(), path_glob.as_ref())
+                && !excluded_by_globs(&r.chunk.file_path, &exclude_globs)
+        });
+        drop_weak_results(&mut results, min_score as f32);
+        // Files deleted since the last index would be dead ends
+        results.retain(|r| Path::new(&r.chunk.file_path).exists());
+        if dedup {
+            merge_nearby_results(&mut results, MERGE_GAP_LINES);
+        }
+        results.truncate(needed);
+        let total = results.len();
+        self.logger.debug(format_args!(
+            "search mode={} candidates={} after_filters={}",
+            mode, candidates, total
+        ));
+        let has_more = total > offset.saturating_add(max_results);
+        let results: Vec<SearchResult> = results
+            .drain(offset.min(total)..)
+            .take(max_results)
+            .collect();
+
+        if json_output {
+            let result = render_search_json(&results, include_content, &query, auto_index_note);
+            return match page_note(offset, results.len(), has_more) {
+                Some(note) => result.with_note(&note),
+                None => result,
+            };
+        }
+
+        if results.is_empty() && offset > 0 {
+            return ToolCallResult::success(format!(
+                "No results at offset {} for query: '{}' ({} in total)",
+                offset, query, total
+            ));
+        }
 
         if results.is_empty() {
             return ToolCallResult::success(format!(
-                "No results found for query: '{}'\n\nTry:\n- Different search terms\n- Check if the directory is indexed",
-                query
+                "{}{}",
+                auto_index_note.unwrap_or_default(),
+                no_results_message(&query)
             ));
         }
 
+        // Highlight with the same terms the keyword scorer matched on
+        let terms = if highlight {
+            query_terms(&query)
+        } else {
+            Vec::new()
+        };
+
         // Format results
-        let mut output = format!("Found {} results for: '{}'\n\n", results.len(), query);
+        let mut output = auto_index_note.unwrap_or_default();
+        output.push_str(&format!(
+            "Found {} results for: '{}'\n\n",
+            results.len(),
+            query
+        ));
 
         for (i, result) in results.iter().enumerate() {
             let score_pct = (result.score * 100.0) as u32;
             output.push_str(&format!(
                 "{}. {} ({}% match)\n",
-                i + 1,
+                offset + i + 1,
                 result.chunk.file_path,
                 score_pct
             ));
@@ -434,10 +1692,39 @@ impl McpServer {
                 result.chunk.start_line, result.chunk.end_line
             ));
 
-            if include_content {
+            if explain {
+                let breakdown = searcher.explain(result);
+                let mut parts = vec![
+                    format!("embedding {:.3}", breakdown.embedding),
+                    format!("lexical {:.3}", breakdown.lexical),
+                ];
+                if let Some(colbert) = breakdown.colbert {
+                    parts.push(format!("colbert {:.3}", colbert));
+                }
+                output.push_str(&format!(
+                    "   Score: {:.3} = {}\n",
+                    breakdown.total,
+                    parts.join(" + ")
+                ));
+            }
+
+            let context = (include_content && context_lines > 0)
+                .then(|| chunk_with_context(&result.chunk, context_lines))
+                .flatten();
+            if let Some(window) = context {
+                output.push_str(&format!(
+                    "   Context: lines {}-{} ('>' marks the match)\n",
+                    window.first, window.last
+                ));
+                output.push_str("   ```\n");
+                for line in window.text.lines() {
+                    output.push_str(&format!("   {}\n", highlight_terms(line, &terms)));
+                }
+                output.push_str("   ```\n");
+            } else if include_content {
                 output.push_str("   ```\n");
                 for line in result.chunk.content.lines().take(15) {
-                    output.push_str(&format!("   {}\n", line));
+                    output.push_str(&format!("   {}\n", highlight_terms(line, &terms)));
                 }
                 if result.chunk.content.lines().count() > 15 {
                     output.push_str("   ...\n");
@@ -447,22 +1734,192 @@ impl McpServer {
             output.push('\n');
         }
 
+        if let Some(note) = page_note(offset, results.len(), has_more) {
+            output.push_str(&note);
+        }
+
         ToolCallResult::success(output)
     }
 
-    fn execute_index_directory(&self, args: Option<Value>) -> ToolCallResult {
+    fn execute_multi_mode_search(&self, args: Option<Value>) -> ToolCallResult {
         let args = match args {
             Some(a) => a,
             None => return ToolCallResult::error("Missing arguments".to_string()),
         };
 
-        let path = match args.get("path").and_then(|v| v.as_str()) {
-            Some(p) => p.to_string(),
-            None => return ToolCallResult::error("Missing required 'path' argument".to_string()),
+        let query = match args.get("query").and_then(|v| v.as_str()) {
+            Some(q) => q.to_string(),
+            None => return ToolCallResult::error("Missing required 'query' argument".to_string()),
         };
 
-        let mode = args
-            .get("mode")
+        if let Err(e) = validate_query(&query) {
+            return ToolCallResult::error(e);
+        }
+
+        let max_results = args
+            .get("max_results")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(5)
+            .clamp(1, 20) as usize;
+
+        let include_content = args
+            .get("include_content")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        let normalize = args
+            .get("normalize_query")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+
+        let embed_query = if normalize {
+            normalize_query(&query)
+        } else {
+            query.clone()
+        };
+
+        let index_name = match index_name_arg(&args) {
+            Ok(name) => name,
+            Err(e) => return ToolCallResult::error(e),
+        };
+        let store = match self.cached_store(index_name) {
+            Ok(s) => s,
+            Err(e) => return ToolCallResult::error(format!("Failed to load index: {}", e)),
+        };
+
+        if store.chunk_count() == 0 {
+            return ToolCallResult::error(
+                "No files indexed. Run 'sgrep watch <path>' first to index your codebase."
+                    .to_string(),
+            );
+        }
+
+        // Hybrid fuses the balanced and code embeddings, so one embedder covers all three modes
+        let hybrid = match self.hybrid_embedder() {
+            Ok(e) => e,
+            Err(e) => return ToolCallResult::error(format!("Model load failed: {}", e)),
+        };
+        let parts = hybrid
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .embed_query_parts(&embed_query);
+        let (balanced_emb, code_emb, hybrid_emb) = match parts {
+            Ok(parts) => parts,
+            Err(e) => return ToolCallResult::error(format!("Embedding failed: {}", e)),
+        };
+
+        // Same compatibility rule as semantic_search: a mode's model must match the index
+        let modes = [
+            (
+                "balanced",
+                Some(SpeedMode::Balanced.model_id()),
+                balanced_emb,
+            ),
+            ("code", Some(SpeedMode::Code.model_id()), code_emb),
+            ("hybrid", None, hybrid_emb),
+        ];
+
+        let searcher = HybridSearcher::default();
+        let mut per_mode = Vec::new();
+        let mut skipped = Vec::new();
+        for (mode, model, embedding) in modes {
+            if let (Some(indexed), Some(model)) = (store.model.as_deref(), model) {
+                if indexed != model {
+                    skipped.push(format!("{} (index built with '{}')", mode, indexed));
+                    continue;
+                }
+            }
+            if check_query_dim(&store, &embedding).is_err() {
+                skipped.push(format!(
+                    "{} ({}-dim embeddings, index has {})",
+                    mode,
+                    embedding.len(),
+                    store.embedding_dim()
+                ));
+                continue;
+            }
+            let results =
+                searcher.search(&store, &embedding, &query, max_results, None, false, None);
+            per_mode.push((mode, results));
+        }
+
+        let merged = merge_mode_results(&per_mode, MULTI_MODE_MAX_TOTAL);
+        if merged.is_empty() {
+            return ToolCallResult::success(format!(
+                "No results found in any mode for query: '{}'",
+                query
+            ));
+        }
+
+        let mut output = format!("Multi-mode results for: '{}'\n", query);
+        let counts: Vec<String> = per_mode
+            .iter()
+            .map(|(mode, results)| format!("{}: {} hits", mode, results.len()))
+            .collect();
+        output.push_str(&format!(
+            "{} ({} unique)\n",
+            counts.join(" | "),
+            merged.len()
+        ));
+        if !skipped.is_empty() {
+            output.push_str(&format!("Skipped: {}\n", skipped.join(", ")));
+        }
+        output.push('\n');
+
+        for (i, hit) in merged.iter().enumerate() {
+            let tags: Vec<String> = hit
+                .modes
+                .iter()
+                .map(|(mode, rank, score)| {
+                    format!("{} #{} {}%", mode, rank, (score * 100.0) as u32)
+                })
+                .collect();
+            output.push_str(&format!(
+                "{}. {}:{}-{} [{}]\n",
+                i + 1,
+                hit.chunk.file_path,
+                hit.chunk.start_line,
+                hit.chunk.end_line,
+                tags.join(", ")
+            ));
+
+            if include_content {
+                output.push_str("   ```\n");
+                for line in hit.chunk.content.lines().take(8) {
+                    output.push_str(&format!("   {}\n", line));
+                }
+                if hit.chunk.content.lines().count() > 8 {
+                    output.push_str("   ...\n");
+                }
+                output.push_str("   ```\n");
+            }
+        }
+
+        ToolCallResult::success(output)
+    }
+
+    fn execute_index_directory(
+        &self,
+        args: Option<Value>,
+        progress_token: Option<Value>,
+    ) -> ToolCallResult {
+        let args = match args {
+            Some(a) => a,
+            None => return ToolCallResult::error("Missing arguments".to_string()),
+        };
+
+        let path = match args.get("path").and_then(|v| v.as_str()) {
+            Some(p) => p.to_string(),
+            None => return ToolCallResult::error("Missing required 'path' argument".to_string()),
+        };
+
+        let index_name = match index_name_arg(&args) {
+            Ok(name) => name,
+            Err(e) => return ToolCallResult::error(e),
+        };
+
+        let mode = args
+            .get("mode")
             .and_then(|v| v.as_str())
             .unwrap_or("balanced");
 
@@ -472,18 +1929,185 @@ impl McpServer {
             _ => SpeedMode::Balanced,
         };
 
-        // Run indexing using the existing tokio runtime
+        let align_boundaries = args
+            .get("align_boundaries")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        let strategy = match args.get("chunking").and_then(|v| v.as_str()) {
+            None => ChunkStrategy::Window,
+            Some(name) => match ChunkStrategy::parse(name) {
+                Some(strategy) => strategy,
+                None => {
+                    return ToolCallResult::error(format!(
+                        "Invalid chunking '{}': expected \"window\" or \"symbol\"",
+                        name
+                    ))
+                }
+            },
+        };
+
+        let model = model_override(args.get("model").and_then(|v| v.as_str()));
+        if let Some(Err(e)) = model.as_deref().map(locate_model) {
+            return ToolCallResult::error(e.to_string());
+        }
+
+        let threads = match args.get("threads").map(|v| v.as_u64()) {
+            None => None,
+            Some(Some(n)) if n >= 1 => Some(n as usize),
+            Some(_) => {
+                return ToolCallResult::error("'threads' must be a positive integer".to_string())
+            }
+        };
+
+        let quantization = match args.get("quantization").map(|v| v.as_str()) {
+            None => None,
+            Some(Some("none")) => Some(Quantization::None),
+            Some(Some("int8")) => Some(Quantization::Int8),
+            Some(_) => {
+                return ToolCallResult::error(
+                    "'quantization' must be \"none\" or \"int8\"".to_string(),
+                )
+            }
+        };
+
+        let languages = match language_list_arg(&args, "languages") {
+            Ok(list) => list,
+            Err(e) => return ToolCallResult::error(e),
+        };
+        let exclude_languages = match language_list_arg(&args, "exclude_languages") {
+            Ok(list) => list,
+            Err(e) => return ToolCallResult::error(e),
+        };
+
+        let build_map = args
+            .get("build_map")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+
+        let options = SyncOptions {
+            chunker: CodeChunker::default()
+                .with_align_boundaries(align_boundaries)
+                .with_strategy(strategy),
+            model,
+            embed_path_context: args.get("embed_path_context").and_then(|v| v.as_bool()),
+            progress: progress_token.map(|token| progress_reporter(token, self.logger)),
+            threads,
+            quantization,
+            include_ignored: args
+                .get("include_ignored")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+            languages,
+            exclude_languages,
+        };
+
+        match self.sync_directory(&path, index_name, speed_mode, options) {
+            Ok(summary) => {
+                self.logger.info(format_args!(
+                    "indexed path={} added={} updated={} removed={} unchanged={} skipped={}",
+                    path,
+                    summary.added,
+                    summary.updated,
+                    summary.removed,
+                    summary.unchanged,
+                    summary.skipped.len()
+                ));
+                let mut output = match index_name {
+                    Some(name) => format!(
+                        "Successfully indexed directory: {} (index '{}')\n\nSearch it with semantic_search and index_name='{}'.",
+                        path, name, name
+                    ),
+                    None => format!(
+                        "Successfully indexed directory: {}\n\nYou can now use semantic_search to find code.",
+                        path
+                    ),
+                };
+                output.push_str(&format!(
+                    "\n\n{} added, {} updated, {} removed, {} unchanged, {} skipped",
+                    summary.added,
+                    summary.updated,
+                    summary.removed,
+                    summary.unchanged,
+                    summary.skipped.len()
+                ));
+                for file in summary.skipped.iter().take(MAX_LISTED_SKIPS) {
+                    output.push_str(&format!("\n  - {} ({})", file.path, file.reason));
+                }
+                if summary.skipped.len() > MAX_LISTED_SKIPS {
+                    output.push_str(&format!(
+                        "\n  ... and {} more",
+                        summary.skipped.len() - MAX_LISTED_SKIPS
+                    ));
+                }
+                if build_map {
+                    // The index is already saved, so a map failure only gets a note
+                    let compiled = Path::new(&path)
+                        .canonicalize()
+                        .map_err(anyhow::Error::from)
+                        .and_then(|root| compile_code_map(&root));
+                    match compiled {
+                        Ok(stats) => output.push_str(&format!(
+                            "\n\nCodebase map: {} files, {} symbols",
+                            stats.files, stats.symbols
+                        )),
+                        Err(e) => {
+                            self.logger
+                                .error(format_args!("map compile failed path={}: {}", path, e));
+                            output.push_str(&format!("\n\nCodebase map not built: {}", e));
+                        }
+                    }
+                }
+                ToolCallResult::success(output)
+            }
+            Err(e) => ToolCallResult::error(format!("Indexing failed: {}", e)),
+        }
+    }
+
+    /// Index a directory into `store_name`, or the default store (blocks on the existing tokio runtime)
+    fn sync_directory(
+        &self,
+        path: &str,
+        store_name: Option<&str>,
+        speed_mode: SpeedMode,
+        options: SyncOptions,
+    ) -> Result<SyncSummary> {
         let handle = tokio::runtime::Handle::current();
-        match tokio::task::block_in_place(|| {
+        tokio::task::block_in_place(|| {
             handle.block_on(async {
-                crate::commands::watch::sync_files(&path, None, speed_mode).await
+                sync_files_with_options(path, store_name, speed_mode, options).await
             })
-        }) {
-            Ok(_) => ToolCallResult::success(format!(
-                "Successfully indexed directory: {}\n\nYou can now use semantic_search to find code.",
-                path
+        })
+    }
+
+    fn execute_compile_map(&self, args: Option<Value>) -> ToolCallResult {
+        let args = args.unwrap_or(json!({}));
+
+        let path = args
+            .get("path")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| ".".to_string());
+
+        let root = match Path::new(&path).canonicalize() {
+            Ok(p) => p,
+            Err(e) => return ToolCallResult::error(format!("Invalid path: {}", e)),
+        };
+
+        let start = Instant::now();
+        match compile_code_map(&root) {
+            Ok(stats) => ToolCallResult::success(format!(
+                "Compiled codebase map for {} in {:.1}s\n\n{} files, {} symbols ({} functions, {} structs/classes, {} other)\nSaved: {}",
+                root.display(),
+                start.elapsed().as_secs_f32(),
+                stats.files,
+                stats.symbols,
+                stats.functions,
+                stats.structs,
+                stats.other,
+                CodeMap::map_path(&root).display()
             )),
-            Err(e) => ToolCallResult::error(format!("Indexing failed: {}", e)),
+            Err(e) => ToolCallResult::error(format!("Compiling map failed: {}", e)),
         }
     }
 
@@ -501,36 +2125,97 @@ impl McpServer {
             .and_then(|v| v.as_bool())
             .unwrap_or(false);
 
+        let include_unparsed = args
+            .get("include_unparsed")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        let public_only = args
+            .get("public_only")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        let full_signatures = args
+            .get("full_signatures")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        let format = match args.get("format").and_then(|v| v.as_str()) {
+            None => "text",
+            Some(f @ ("text" | "json" | "dot")) => f,
+            Some(other) => {
+                return ToolCallResult::error(format!(
+                    "Invalid format '{}': expected \"text\", \"json\" or \"dot\"",
+                    other
+                ))
+            }
+        };
+
+        let kinds = match kinds_arg(&args) {
+            Ok(kinds) => kinds,
+            Err(e) => return ToolCallResult::error(e),
+        };
+
+        let max_tokens = match args.get("max_tokens").map(|v| v.as_u64()) {
+            None => None,
+            Some(Some(n)) if n >= 1 => Some(n as usize),
+            Some(_) => {
+                return ToolCallResult::error("'max_tokens' must be a positive integer".to_string())
+            }
+        };
+
+        let by_centrality = match args.get("sort").and_then(|v| v.as_str()) {
+            None | Some("file") => false,
+            Some("centrality") => true,
+            Some(other) => {
+                return ToolCallResult::error(format!(
+                    "Invalid sort '{}': expected \"file\" or \"centrality\"",
+                    other
+                ))
+            }
+        };
+
         let root = match Path::new(&path).canonicalize() {
             Ok(p) => p,
             Err(e) => return ToolCallResult::error(format!("Invalid path: {}", e)),
         };
 
-        // Load the codebase map
-        match CodeMap::load(&root) {
+        // Load the codebase map, keeping only the requested kinds (and visibility)
+        let map = load_code_map(&root).map(|map| {
+            map.map(|mut map| {
+                map.retain_kinds(&kinds);
+                if public_only {
+                    map.retain_public();
+                }
+                map.with_full_signatures(full_signatures)
+            })
+        });
+        match map {
+            Ok(Some(map)) if format == "dot" => ToolCallResult::success(map.to_dot()),
+            Ok(Some(map)) if format == "json" => match serde_json::to_string_pretty(&map.to_json())
+            {
+                Ok(json) => ToolCallResult::success(json),
+                Err(e) => ToolCallResult::error(format!("Failed to serialize map: {}", e)),
+            },
             Ok(Some(map)) => {
-                let overview = if minimal {
-                    map.to_minimal_overview()
-                } else {
-                    map.to_compact_overview()
-                };
-
-                let stats = map.stats();
-                let token_estimate = overview.len() / 4;
+                let render = |map: &CodeMap| {
+                    let mut overview = if minimal {
+                        map.to_minimal_overview()
+                    } else if by_centrality {
+                        map.to_centrality_overview()
+                    } else {
+                        map.to_compact_overview()
+                    };
 
-                let mut output = format!(
-                    "# Codebase Map\n\n{} files, {} symbols (~{} tokens)\n\n",
-                    stats.files, stats.symbols, token_estimate
-                );
-                output.push_str(&overview);
+                    if include_unparsed {
+                        overview.push_str(&map.to_unparsed_overview());
+                    }
+                    overview
+                };
 
-                ToolCallResult::success(output)
-            }
-            Ok(None) => {
-                ToolCallResult::error(
-                    "No codebase map found. Run 'sgrep compile' first to generate a map of your codebase.".to_string()
-                )
+                ToolCallResult::success(render_code_map(&map, max_tokens, render))
             }
+            Ok(None) => ToolCallResult::error(NO_CODE_MAP.to_string()),
             Err(e) => ToolCallResult::error(format!("Failed to load map: {}", e)),
         }
     }
@@ -546,6 +2231,10 @@ impl McpServer {
             None => return ToolCallResult::error("Missing required 'query' argument".to_string()),
         };
 
+        if let Err(e) = validate_query(&query) {
+            return ToolCallResult::error(e);
+        }
+
         let path = args
             .get("path")
             .and_then(|v| v.as_str())
@@ -557,51 +2246,119 @@ impl McpServer {
             .and_then(|v| v.as_u64())
             .unwrap_or(20) as usize;
 
+        let offset = args.get("offset").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+
+        let full_signatures = args
+            .get("full_signatures")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        let kinds = match kinds_arg(&args) {
+            Ok(kinds) => kinds,
+            Err(e) => return ToolCallResult::error(e),
+        };
+
+        let regex = if args.get("regex").and_then(|v| v.as_bool()).unwrap_or(false) {
+            match Regex::new(&query) {
+                Ok(re) => Some(re),
+                Err(e) => {
+                    return ToolCallResult::error(format!("Invalid regex '{}': {}", query, e))
+                }
+            }
+        } else {
+            None
+        };
+
         let root = match Path::new(&path).canonicalize() {
             Ok(p) => p,
             Err(e) => return ToolCallResult::error(format!("Invalid path: {}", e)),
         };
 
-        match CodeMap::load(&root) {
-            Ok(Some(map)) => {
-                let results = map.search(&query);
+        match load_code_map(&root) {
+            Ok(Some(map)) => ToolCallResult::success(render_symbol_search(
+                &map.with_full_signatures(full_signatures),
+                &query,
+                regex.as_ref(),
+                &kinds,
+                max_results,
+                offset,
+            )),
+            Ok(None) => ToolCallResult::error(NO_CODE_MAP.to_string()),
+            Err(e) => ToolCallResult::error(format!("Failed to load map: {}", e)),
+        }
+    }
 
-                if results.is_empty() {
-                    return ToolCallResult::success(format!(
-                        "No symbols found matching '{}'\n\nTry:\n- Different search terms\n- Run 'sgrep compile' to update the map",
-                        query
-                    ));
-                }
+    fn execute_search_by_doc(&self, args: Option<Value>) -> ToolCallResult {
+        let args = match args {
+            Some(a) => a,
+            None => return ToolCallResult::error("Missing arguments".to_string()),
+        };
 
-                let mut output = format!(
-                    "Found {} symbols matching '{}':\n\n",
-                    results.len().min(max_results),
-                    query
-                );
+        let query = match args.get("query").and_then(|v| v.as_str()) {
+            Some(q) => q.to_string(),
+            None => return ToolCallResult::error("Missing required 'query' argument".to_string()),
+        };
 
-                for (i, sym) in results.iter().take(max_results).enumerate() {
-                    output.push_str(&format!(
-                        "{}. [{}] {}\n   File: {}:{}\n   ID: {}\n",
-                        i + 1,
-                        sym.kind.as_str(),
-                        sym.signature,
-                        sym.file,
-                        sym.line,
-                        sym.id
-                    ));
-                    if !sym.summary.is_empty() {
-                        output.push_str(&format!("   Summary: {}\n", sym.summary));
-                    }
-                    output.push('\n');
-                }
+        if let Err(e) = validate_query(&query) {
+            return ToolCallResult::error(e);
+        }
 
-                ToolCallResult::success(output)
-            }
-            Ok(None) => ToolCallResult::error(
-                "No codebase map found. Run 'sgrep compile' first.".to_string(),
-            ),
-            Err(e) => ToolCallResult::error(format!("Failed to load map: {}", e)),
+        let path = args
+            .get("path")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| ".".to_string());
+
+        let max_results = args
+            .get("max_results")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(10) as usize;
+
+        let root = match Path::new(&path).canonicalize() {
+            Ok(p) => p,
+            Err(e) => return ToolCallResult::error(format!("Invalid path: {}", e)),
+        };
+
+        let map = match load_code_map(&root) {
+            Ok(Some(m)) => m,
+            Ok(None) => return ToolCallResult::error(NO_CODE_MAP.to_string()),
+            Err(e) => return ToolCallResult::error(format!("Failed to load map: {}", e)),
+        };
+
+        if !map.has_symbol_summaries() {
+            return ToolCallResult::success(
+                "No symbol summaries were captured in the codebase map, so there is nothing to match against. Recompile with `sgrep compile` to extract doc comments, or use search_symbols to search by name, or semantic_search to search code content.".to_string(),
+            );
+        }
+
+        let results = map.search_summaries(&query);
+        if results.is_empty() {
+            return ToolCallResult::success(format!(
+                "No symbol documentation matches '{}'\n\nTry:\n- Describing the behavior with different words\n- search_symbols if you know part of the name",
+                query
+            ));
+        }
+
+        let mut output = format!(
+            "Found {} symbols documented as '{}':\n\n",
+            results.len().min(max_results),
+            query
+        );
+        for (i, (sym, score)) in results.iter().take(max_results).enumerate() {
+            output.push_str(&format!(
+                "{}. [{}] {} (score: {:.2})\n   File: {}:{}\n   ID: {}\n   Summary: {}\n\n",
+                i + 1,
+                map.kind_label(sym),
+                map.display_signature(sym),
+                score,
+                sym.file,
+                sym.line,
+                sym.id,
+                sym.summary
+            ));
         }
+
+        ToolCallResult::success(output)
     }
 
     fn execute_expand_symbol(&self, args: Option<Value>) -> ToolCallResult {
@@ -633,18 +2390,18 @@ impl McpServer {
             Err(e) => return ToolCallResult::error(format!("Invalid path: {}", e)),
         };
 
-        match CodeMap::load(&root) {
+        match load_code_map(&root) {
             Ok(Some(map)) => {
                 match map.expand(&symbol_id) {
                     Some(expanded) => {
                         let sym = expanded.symbol;
                         let mut output = format!(
                             "# {} {}\n\nFile: {}:{}\nKind: {}\nSignature: {}\n",
-                            sym.kind.as_str(),
+                            map.kind_label(sym),
                             sym.name,
                             sym.file,
                             sym.line,
-                            sym.kind.as_str(),
+                            map.kind_label(sym),
                             sym.signature
                         );
 
@@ -666,14 +2423,35 @@ impl McpServer {
                             }
                         }
 
+                        if !expanded.implements.is_empty() {
+                            output.push_str("\n## Implements:\n");
+                            for imp in &expanded.implements {
+                                output.push_str(&format!(
+                                    "  - {} ({}:{})\n",
+                                    imp.trait_name, imp.file, imp.line
+                                ));
+                            }
+                        }
+
+                        if !expanded.implementors.is_empty() {
+                            output.push_str("\n## Implementors:\n");
+                            for ty in &expanded.implementors {
+                                output.push_str(&format!("  - {} ({})\n", ty.name, ty.file));
+                            }
+                        }
+
                         // Include source code if requested
                         if include_code {
                             let file_path = root.join(&sym.file);
                             if file_path.exists() {
                                 if let Ok(content) = fs::read_to_string(&file_path) {
                                     let lines: Vec<&str> = content.lines().collect();
-                                    let start = sym.line.saturating_sub(1);
-                                    let end = (start + 30).min(lines.len()); // Max 30 lines
+                                    // The file may have shrunk since the map was built
+                                    let start = sym.line.saturating_sub(1).min(lines.len());
+                                    // Maps compiled before end_line existed get a 30-line window
+                                    let windowed = sym.end_line < sym.line;
+                                    let end = if windowed { start + 30 } else { sym.end_line };
+                                    let end = end.min(lines.len());
 
                                     output.push_str("\n## Source Code:\n```\n");
                                     for (i, line) in lines[start..end].iter().enumerate() {
@@ -683,7 +2461,7 @@ impl McpServer {
                                             line
                                         ));
                                     }
-                                    if end < lines.len() {
+                                    if windowed && end < lines.len() {
                                         output.push_str("     | ...\n");
                                     }
                                     output.push_str("```\n");
@@ -693,19 +2471,363 @@ impl McpServer {
 
                         ToolCallResult::success(output)
                     }
-                    None => ToolCallResult::error(format!(
-                        "Symbol '{}' not found. Use search_symbols to find valid symbol IDs.",
-                        symbol_id
-                    )),
+                    None => unknown_symbol(&map, &symbol_id),
                 }
             }
-            Ok(None) => ToolCallResult::error(
-                "No codebase map found. Run 'sgrep compile' first.".to_string(),
-            ),
+            Ok(None) => ToolCallResult::error(NO_CODE_MAP.to_string()),
             Err(e) => ToolCallResult::error(format!("Failed to load map: {}", e)),
         }
     }
 
+    fn execute_find_references(&self, args: Option<Value>) -> ToolCallResult {
+        let args = match args {
+            Some(a) => a,
+            None => return ToolCallResult::error("Missing arguments".to_string()),
+        };
+
+        let symbol_id = match args.get("symbol_id").and_then(|v| v.as_str()) {
+            Some(id) => id.to_string(),
+            None => {
+                return ToolCallResult::error("Missing required 'symbol_id' argument".to_string())
+            }
+        };
+
+        let path = args
+            .get("path")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| ".".to_string());
+
+        let root = match Path::new(&path).canonicalize() {
+            Ok(p) => p,
+            Err(e) => return ToolCallResult::error(format!("Invalid path: {}", e)),
+        };
+
+        match load_code_map(&root) {
+            Ok(Some(map)) => match render_references(&map, &root, &symbol_id) {
+                Some(output) => ToolCallResult::success(output),
+                None => unknown_symbol(&map, &symbol_id),
+            },
+            Ok(None) => ToolCallResult::error(NO_CODE_MAP.to_string()),
+            Err(e) => ToolCallResult::error(format!("Failed to load map: {}", e)),
+        }
+    }
+
+    /// A read-only (memory-mapped) index, loaded again only if it changed on disk
+    fn cached_store(&self, store_name: Option<&str>) -> Result<Arc<VectorStore>> {
+        // Stamp before loading: a save racing the load just costs one more reload
+        let modified = VectorStore::modified(store_name)?;
+        self.stores
+            .get_or_load(store_name.unwrap_or("default"), modified, || {
+                let start = Instant::now();
+                let store = VectorStore::load_mmap(store_name)?;
+                self.logger.debug(format_args!(
+                    "store loaded store={} elapsed_ms={}",
+                    store_name.unwrap_or("default"),
+                    start.elapsed().as_millis()
+                ));
+                Ok(store)
+            })
+    }
+
+    /// The embedder for `model_id`, loaded on first use
+    fn local_embedder(&self, model_id: &str) -> Result<Arc<Mutex<LocalEmbedder>>> {
+        self.embedders.get_or_load(model_id, || {
+            let start = Instant::now();
+            // The presets keep their tuned settings; anything else loads from its config
+            let embedder = match [SpeedMode::Balanced, SpeedMode::Code]
+                .into_iter()
+                .find(|m| m.model_id() == model_id)
+            {
+                Some(mode) => LocalEmbedder::with_speed_mode(mode)?,
+                None => LocalEmbedder::with_model(model_id)?,
+            };
+            self.logger.info(format_args!(
+                "loaded model={} elapsed_ms={}",
+                model_id,
+                start.elapsed().as_millis()
+            ));
+            Ok(embedder)
+        })
+    }
+
+    /// The hybrid embedder, sharing the cached Balanced and Code models
+    fn hybrid_embedder(&self) -> Result<Arc<Mutex<HybridEmbedder>>> {
+        self.hybrid.get_or_load(HYBRID_KEY, || {
+            Ok(HybridEmbedder::from_embedders(
+                self.local_embedder(SpeedMode::Balanced.model_id())?,
+                self.local_embedder(SpeedMode::Code.model_id())?,
+            ))
+        })
+    }
+
+    /// Embed a query with a cached model, or the hybrid embedder for `None`
+    fn embed_query_with(&self, model_id: Option<&str>, query: &str) -> Result<Vec<f32>, String> {
+        let load_failed = |e: anyhow::Error| format!("Model load failed: {}", e);
+        let embedded = match model_id {
+            Some(id) => self
+                .local_embedder(id)
+                .map_err(load_failed)?
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .embed_query(query),
+            None => self
+                .hybrid_embedder()
+                .map_err(load_failed)?
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .embed_query(query),
+        };
+        embedded.map_err(|e| format!("Embedding failed: {}", e))
+    }
+
+    fn execute_warm_up(&self, args: Option<Value>) -> ToolCallResult {
+        let args = args.unwrap_or(json!({}));
+
+        let model = model_override(args.get("model").and_then(|v| v.as_str()));
+        if let Some(Err(e)) = model.as_deref().map(locate_model) {
+            return ToolCallResult::error(e.to_string());
+        }
+
+        let mode = args
+            .get("mode")
+            .and_then(|v| v.as_str())
+            .unwrap_or("balanced");
+        // Same model choice as semantic_search; `None` is the hybrid embedder
+        let model_id = match (model.as_deref(), mode) {
+            (Some(m), _) => Some(m),
+            (None, "balanced") => Some(SpeedMode::Balanced.model_id()),
+            (None, "code") => Some(SpeedMode::Code.model_id()),
+            (None, "hybrid") => None,
+            (None, other) => {
+                return ToolCallResult::error(format!(
+                    "Invalid mode '{}': expected 'balanced', 'code' or 'hybrid'",
+                    other
+                ))
+            }
+        };
+
+        let (label, cached) = match model_id {
+            Some(id) => (id, self.embedders.contains(id)),
+            None => (
+                "hybrid (BGE + CodeRankEmbed)",
+                self.hybrid.contains(HYBRID_KEY),
+            ),
+        };
+        if cached {
+            return ToolCallResult::success(format!("{} is already loaded.", label));
+        }
+
+        let start = Instant::now();
+        let loaded = match model_id {
+            Some(id) => self.local_embedder(id).map(drop),
+            None => self.hybrid_embedder().map(drop),
+        };
+        match loaded {
+            Ok(()) => ToolCallResult::success(format!(
+                "Loaded {} in {:.1}s; later searches reuse it.",
+                label,
+                start.elapsed().as_secs_f32()
+            )),
+            Err(e) => ToolCallResult::error(format!("Model load failed: {}", e)),
+        }
+    }
+
+    fn execute_find_dead_code(&self, args: Option<Value>) -> ToolCallResult {
+        let args = args.unwrap_or(json!({}));
+
+        let path = args
+            .get("path")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| ".".to_string());
+
+        let max_results = args
+            .get("max_results")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(50) as usize;
+
+        let root = match Path::new(&path).canonicalize() {
+            Ok(p) => p,
+            Err(e) => return ToolCallResult::error(format!("Invalid path: {}", e)),
+        };
+
+        match load_code_map(&root) {
+            Ok(Some(map)) => ToolCallResult::success(render_dead_code(&map, max_results)),
+            Ok(None) => ToolCallResult::error(NO_CODE_MAP.to_string()),
+            Err(e) => ToolCallResult::error(format!("Failed to load map: {}", e)),
+        }
+    }
+
+    fn execute_rename_impact(&self, args: Option<Value>) -> ToolCallResult {
+        let args = match args {
+            Some(a) => a,
+            None => return ToolCallResult::error("Missing arguments".to_string()),
+        };
+
+        let symbol_id = match args.get("symbol_id").and_then(|v| v.as_str()) {
+            Some(id) => id.to_string(),
+            None => {
+                return ToolCallResult::error("Missing required 'symbol_id' argument".to_string())
+            }
+        };
+
+        let new_name = match args.get("new_name").and_then(|v| v.as_str()) {
+            Some(name) => name.trim().to_string(),
+            None => {
+                return ToolCallResult::error("Missing required 'new_name' argument".to_string())
+            }
+        };
+
+        if new_name.is_empty() || !new_name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return ToolCallResult::error(format!(
+                "Invalid new_name '{}': expected a plain identifier",
+                new_name
+            ));
+        }
+
+        let path = args
+            .get("path")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| ".".to_string());
+
+        let root = match Path::new(&path).canonicalize() {
+            Ok(p) => p,
+            Err(e) => return ToolCallResult::error(format!("Invalid path: {}", e)),
+        };
+
+        match load_code_map(&root) {
+            Ok(Some(map)) => match render_rename_impact(&map, &root, &symbol_id, &new_name) {
+                Some(output) => ToolCallResult::success(output),
+                None => unknown_symbol(&map, &symbol_id),
+            },
+            Ok(None) => ToolCallResult::error(NO_CODE_MAP.to_string()),
+            Err(e) => ToolCallResult::error(format!("Failed to load map: {}", e)),
+        }
+    }
+
+    fn execute_read_symbols(&self, args: Option<Value>) -> ToolCallResult {
+        let args = match args {
+            Some(a) => a,
+            None => return ToolCallResult::error("Missing arguments".to_string()),
+        };
+
+        let symbol_ids: Vec<&str> = match args.get("symbol_ids").and_then(|v| v.as_array()) {
+            Some(ids) => ids.iter().filter_map(|v| v.as_str()).collect(),
+            None => {
+                return ToolCallResult::error("Missing required 'symbol_ids' argument".to_string())
+            }
+        };
+
+        if symbol_ids.is_empty() {
+            return ToolCallResult::error("'symbol_ids' must not be empty".to_string());
+        }
+
+        let path = args
+            .get("path")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| ".".to_string());
+
+        let max_bytes = args
+            .get("max_bytes")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(40_000) as usize;
+
+        let include_code = args
+            .get("include_code")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+
+        let root = match Path::new(&path).canonicalize() {
+            Ok(p) => p,
+            Err(e) => return ToolCallResult::error(format!("Invalid path: {}", e)),
+        };
+
+        let map = match load_code_map(&root) {
+            Ok(Some(m)) => m,
+            Ok(None) => return ToolCallResult::error(NO_CODE_MAP.to_string()),
+            Err(e) => return ToolCallResult::error(format!("Failed to load map: {}", e)),
+        };
+
+        let mut sources: HashMap<String, Option<String>> = HashMap::new();
+        let mut output = String::new();
+        let mut not_found = Vec::new();
+        let mut omitted = Vec::new();
+        let mut bytes_used = 0;
+
+        for id in symbol_ids {
+            let sym = match map.symbols.get(id) {
+                Some(s) => s,
+                None => {
+                    not_found.push(id);
+                    continue;
+                }
+            };
+
+            let content = sources
+                .entry(sym.file.clone())
+                .or_insert_with(|| fs::read_to_string(root.join(&sym.file)).ok());
+            let content = match content {
+                Some(c) => c,
+                None => {
+                    not_found.push(id);
+                    continue;
+                }
+            };
+
+            let lines: Vec<&str> = content.lines().collect();
+            if lines.is_empty() {
+                not_found.push(id);
+                continue;
+            }
+            let start = sym.line.saturating_sub(1).min(lines.len() - 1);
+            // Maps saved before end lines were tracked have `end_line` 0
+            let end = sym.end_line.max(sym.line).min(lines.len()) - 1;
+
+            let mut section = format!(
+                "## {} {} ({}:{}-{})\n",
+                map.kind_label(sym),
+                sym.name,
+                sym.file,
+                start + 1,
+                end + 1
+            );
+            if include_code {
+                section.push_str("```\n");
+                for (i, line) in lines[start..=end].iter().enumerate() {
+                    section.push_str(&format!("{:4} | {}\n", start + i + 1, line));
+                }
+                section.push_str("```\n");
+            }
+            section.push('\n');
+
+            if bytes_used + section.len() > max_bytes {
+                omitted.push(id);
+                continue;
+            }
+            bytes_used += section.len();
+            output.push_str(&section);
+        }
+
+        if !omitted.is_empty() {
+            output.push_str(&format!(
+                "Omitted (over {} byte limit): {}\n",
+                max_bytes,
+                omitted.join(", ")
+            ));
+        }
+        if !not_found.is_empty() {
+            output.push_str(&format!(
+                "Not found: {}\nUse search_symbols to find valid symbol IDs.\n",
+                not_found.join(", ")
+            ));
+        }
+
+        ToolCallResult::success(output)
+    }
+
     fn execute_find_similar_code(&self, args: Option<Value>) -> ToolCallResult {
         let args = args.unwrap_or(json!({}));
 
@@ -717,6 +2839,11 @@ impl McpServer {
             .and_then(|v| v.as_u64())
             .unwrap_or(10) as usize;
 
+        let include_content = args
+            .get("include_content")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+
         let query_text = match (code, file) {
             (Some(c), _) => c.to_string(),
             (_, Some(f)) => match fs::read_to_string(f) {
@@ -731,7 +2858,7 @@ impl McpServer {
         };
 
         // Load the vector store
-        let store = match VectorStore::load(None) {
+        let store = match self.cached_store(None) {
             Ok(s) => s,
             Err(e) => return ToolCallResult::error(format!("Failed to load index: {}", e)),
         };
@@ -743,13 +2870,14 @@ impl McpServer {
         }
 
         // Generate embedding for the code
-        let query_embedding = match LocalEmbedder::with_speed_mode(SpeedMode::Code) {
-            Ok(mut embedder) => match embedder.embed_query(&query_text) {
+        let query_embedding =
+            match self.embed_query_with(Some(SpeedMode::Code.model_id()), &query_text) {
                 Ok(emb) => emb,
-                Err(e) => return ToolCallResult::error(format!("Embedding failed: {}", e)),
-            },
-            Err(e) => return ToolCallResult::error(format!("Model load failed: {}", e)),
-        };
+                Err(e) => return ToolCallResult::error(e),
+            };
+        if let Err(e) = check_query_dim(&store, &query_embedding) {
+            return ToolCallResult::error(e.to_string());
+        }
 
         // Search for similar code
         let searcher = HybridSearcher::default();
@@ -781,14 +2909,17 @@ impl McpServer {
                 "   Lines {}-{}\n",
                 result.chunk.start_line, result.chunk.end_line
             ));
-            output.push_str("   ```\n");
-            for line in result.chunk.content.lines().take(10) {
-                output.push_str(&format!("   {}\n", line));
-            }
-            if result.chunk.content.lines().count() > 10 {
-                output.push_str("   ...\n");
+            if include_content {
+                output.push_str("   ```\n");
+                for line in result.chunk.content.lines().take(10) {
+                    output.push_str(&format!("   {}\n", line));
+                }
+                if result.chunk.content.lines().count() > 10 {
+                    output.push_str("   ...\n");
+                }
+                output.push_str("   ```\n");
             }
-            output.push_str("   ```\n\n");
+            output.push('\n');
         }
 
         ToolCallResult::success(output)
@@ -812,8 +2943,13 @@ impl McpServer {
             .and_then(|v| v.as_u64())
             .unwrap_or(5) as usize;
 
+        let include_content = args
+            .get("include_content")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+
         // Load the vector store
-        let store = match VectorStore::load(None) {
+        let store = match self.cached_store(None) {
             Ok(s) => s,
             Err(e) => return ToolCallResult::error(format!("Failed to load index: {}", e)),
         };
@@ -825,13 +2961,14 @@ impl McpServer {
         }
 
         // Generate embedding for the question
-        let query_embedding = match LocalEmbedder::with_speed_mode(SpeedMode::Balanced) {
-            Ok(mut embedder) => match embedder.embed_query(&question) {
+        let query_embedding =
+            match self.embed_query_with(Some(SpeedMode::Balanced.model_id()), &question) {
                 Ok(emb) => emb,
-                Err(e) => return ToolCallResult::error(format!("Embedding failed: {}", e)),
-            },
-            Err(e) => return ToolCallResult::error(format!("Model load failed: {}", e)),
-        };
+                Err(e) => return ToolCallResult::error(e),
+            };
+        if let Err(e) = check_query_dim(&store, &query_embedding) {
+            return ToolCallResult::error(e.to_string());
+        }
 
         // Search for relevant context
         let searcher = HybridSearcher::default();
@@ -868,12 +3005,15 @@ impl McpServer {
                 "Lines {}-{}:\n",
                 result.chunk.start_line, result.chunk.end_line
             ));
-            output.push_str("```\n");
-            output.push_str(&result.chunk.content);
-            if !result.chunk.content.ends_with('\n') {
-                output.push('\n');
+            if include_content {
+                output.push_str("```\n");
+                output.push_str(&result.chunk.content);
+                if !result.chunk.content.ends_with('\n') {
+                    output.push('\n');
+                }
+                output.push_str("```\n");
             }
-            output.push_str("```\n\n");
+            output.push('\n');
         }
 
         output.push_str("## Summary:\n");
@@ -926,7 +3066,7 @@ impl McpServer {
         output.push_str(&format!("- **Extension**: .{}\n", ext));
 
         // Try to get symbol information from codemap
-        if let Ok(Some(map)) = CodeMap::load(Path::new(".")) {
+        if let Ok(Some(map)) = load_code_map(Path::new(".")) {
             let symbols: Vec<_> = map
                 .search(&file_path)
                 .into_iter()
@@ -939,8 +3079,8 @@ impl McpServer {
                 for sym in symbols.iter().take(20) {
                     output.push_str(&format!(
                         "- [{}] {} (line {})\n",
-                        sym.kind.as_str(),
-                        sym.signature,
+                        map.kind_label(sym),
+                        map.display_signature(sym),
                         sym.line
                     ));
                 }
@@ -963,7 +3103,8 @@ impl McpServer {
             .take(20)
             .collect();
 
-        if !imports.is_empty() {
+        // Import lines are source too
+        if !imports.is_empty() && !self.no_content {
             output.push_str("\n## Imports/Dependencies:\n\n");
             for imp in &imports {
                 output.push_str(&format!("- {}\n", imp.trim()));
@@ -981,50 +3122,149 @@ impl McpServer {
         ToolCallResult::success(output)
     }
 
-    fn execute_list_indexed_files(&self, args: Option<Value>) -> ToolCallResult {
+    fn execute_get_index_status(&self, args: Option<Value>) -> ToolCallResult {
         let args = args.unwrap_or(json!({}));
-        let pattern = args.get("pattern").and_then(|v| v.as_str());
+        let index_name = match index_name_arg(&args) {
+            Ok(name) => name,
+            Err(e) => return ToolCallResult::error(e),
+        };
+        let path = args.get("path").and_then(|v| v.as_str());
 
-        // Load the vector store
-        let store = match VectorStore::load(None) {
+        match VectorStore::exists(index_name) {
+            Ok(true) => {}
+            Ok(false) => {
+                return ToolCallResult::error(format!(
+                    "No index found for '{}'. Run index_directory first.",
+                    index_name.unwrap_or("default")
+                ))
+            }
+            Err(e) => return ToolCallResult::error(format!("Failed to locate index: {}", e)),
+        }
+
+        let store = match self.cached_store(index_name) {
             Ok(s) => s,
             Err(e) => return ToolCallResult::error(format!("Failed to load index: {}", e)),
         };
 
-        if store.chunk_count() == 0 {
-            return ToolCallResult::error(
-                "No files indexed. Run 'sgrep watch <path>' first.".to_string(),
-            );
-        }
+        // The codebase map lives in the directory itself
+        let has_map = Path::new(path.unwrap_or("."))
+            .canonicalize()
+            .ok()
+            .map(|root| CodeMap::map_path(&root).exists());
 
-        let files = store.list_files();
+        ToolCallResult::success(render_index_status(&store, has_map))
+    }
 
-        let filtered: Vec<&String> = if let Some(pat) = pattern {
-            files
-                .iter()
-                .filter(|f| {
-                    // Simple glob matching
-                    if pat.contains('*') {
-                        let parts: Vec<&str> = pat.split('*').collect();
-                        if parts.len() == 2 {
-                            let (prefix, suffix) = (parts[0], parts[1]);
-                            f.starts_with(prefix) && f.ends_with(suffix)
-                        } else {
-                            f.contains(&pat.replace('*', ""))
-                        }
-                    } else {
-                        f.contains(pat)
-                    }
-                })
-                .collect()
-        } else {
-            files.iter().collect()
+    fn execute_clear_index(&self, args: Option<Value>) -> ToolCallResult {
+        let args = match args {
+            Some(a) => a,
+            None => return ToolCallResult::error("Missing arguments".to_string()),
         };
 
-        let mut output = format!("# Indexed Files ({})\n\n", filtered.len());
+        let path = match args.get("path").and_then(|v| v.as_str()) {
+            Some(p) => p,
+            None => return ToolCallResult::error("Missing required 'path' argument".to_string()),
+        };
 
-        // Group by directory
-        let mut by_dir: std::collections::HashMap<String, Vec<&String>> =
+        let index_name = match index_name_arg(&args) {
+            Ok(name) => name,
+            Err(e) => return ToolCallResult::error(e),
+        };
+
+        if args.get("confirm").and_then(|v| v.as_bool()) != Some(true) {
+            return ToolCallResult::error(format!(
+                "Refusing to delete the index for '{}' without confirm: true",
+                path
+            ));
+        }
+
+        let has_store = match VectorStore::exists(index_name) {
+            Ok(exists) => exists,
+            Err(e) => return ToolCallResult::error(format!("Failed to locate index: {}", e)),
+        };
+        let root = Path::new(path).canonicalize().ok();
+        let has_map = root.as_ref().is_some_and(|r| CodeMap::map_path(r).exists());
+
+        if !has_store && !has_map {
+            return ToolCallResult::error(format!("No index or codebase map found for '{}'", path));
+        }
+
+        let chunks = if has_store {
+            match VectorStore::delete(index_name) {
+                Ok(n) => n,
+                Err(e) => return ToolCallResult::error(format!("Failed to delete index: {}", e)),
+            }
+        } else {
+            0
+        };
+
+        let map_removed = match root {
+            Some(r) if has_map => match CodeMap::delete(&r) {
+                Ok(removed) => removed,
+                Err(e) => {
+                    return ToolCallResult::error(format!("Failed to delete codebase map: {}", e))
+                }
+            },
+            _ => false,
+        };
+
+        ToolCallResult::success(format!(
+            "Cleared index for '{}': removed {} chunk{}{}.\n\nRun index_directory to rebuild it.",
+            path,
+            chunks,
+            if chunks == 1 { "" } else { "s" },
+            if map_removed {
+                " and the codebase map"
+            } else {
+                ""
+            }
+        ))
+    }
+
+    fn execute_list_indexed_files(&self, args: Option<Value>) -> ToolCallResult {
+        let args = args.unwrap_or(json!({}));
+        let pattern = args.get("pattern").and_then(|v| v.as_str());
+
+        // Load the vector store
+        let store = match self.cached_store(None) {
+            Ok(s) => s,
+            Err(e) => return ToolCallResult::error(format!("Failed to load index: {}", e)),
+        };
+
+        if store.chunk_count() == 0 {
+            return ToolCallResult::error(
+                "No files indexed. Run 'sgrep watch <path>' first.".to_string(),
+            );
+        }
+
+        let files = store.list_files();
+
+        let filtered: Vec<&String> = if let Some(pat) = pattern {
+            files
+                .iter()
+                .filter(|f| {
+                    // Simple glob matching
+                    if pat.contains('*') {
+                        let parts: Vec<&str> = pat.split('*').collect();
+                        if parts.len() == 2 {
+                            let (prefix, suffix) = (parts[0], parts[1]);
+                            f.starts_with(prefix) && f.ends_with(suffix)
+                        } else {
+                            f.contains(&pat.replace('*', ""))
+                        }
+                    } else {
+                        f.contains(pat)
+                    }
+                })
+                .collect()
+        } else {
+            files.iter().collect()
+        };
+
+        let mut output = format!("# Indexed Files ({})\n\n", filtered.len());
+
+        // Group by directory
+        let mut by_dir: std::collections::HashMap<String, Vec<&String>> =
             std::collections::HashMap::new();
         for file in &filtered {
             let dir = Path::new(file)
@@ -1053,10 +3293,2813 @@ impl McpServer {
 
         ToolCallResult::success(output)
     }
-}
 
-impl Default for McpServer {
-    fn default() -> Self {
-        Self::new()
+    fn execute_result_context(&self, args: Option<Value>) -> ToolCallResult {
+        let args = match args {
+            Some(a) => a,
+            None => return ToolCallResult::error("Missing arguments".to_string()),
+        };
+
+        let file_path = match args.get("file_path").and_then(|v| v.as_str()) {
+            Some(p) => p.to_string(),
+            None => {
+                return ToolCallResult::error("Missing required 'file_path' argument".to_string())
+            }
+        };
+
+        let start_line = match args.get("start_line").and_then(|v| v.as_u64()) {
+            Some(l) if l >= 1 => l as usize,
+            _ => {
+                return ToolCallResult::error(
+                    "Missing or invalid 'start_line' argument (1-based line number)".to_string(),
+                )
+            }
+        };
+
+        let end_line = args
+            .get("end_line")
+            .and_then(|v| v.as_u64())
+            .map(|l| l as usize)
+            .unwrap_or(start_line)
+            .max(start_line);
+
+        let radius = args
+            .get("radius")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(25)
+            .min(200) as usize;
+
+        let include_content = args
+            .get("include_content")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+
+        let content = match fs::read_to_string(&file_path) {
+            Ok(c) => c,
+            Err(e) => return ToolCallResult::error(format!("Failed to read file: {}", e)),
+        };
+
+        let lines: Vec<&str> = content.lines().collect();
+        if start_line > lines.len() {
+            return ToolCallResult::error(format!(
+                "start_line {} is past the end of {} ({} lines)",
+                start_line,
+                file_path,
+                lines.len()
+            ));
+        }
+
+        let window = line_window(&lines, start_line, end_line, radius);
+
+        let mut output = format!(
+            "# {} (lines {}-{}, match {}-{} of {})\n",
+            file_path,
+            window.first,
+            window.last,
+            start_line,
+            end_line.min(lines.len()),
+            lines.len()
+        );
+        if include_content {
+            output.push_str("```\n");
+            output.push_str(&window.text);
+            output.push_str("```\n");
+        }
+
+        ToolCallResult::success(output)
+    }
+
+    fn execute_file_tree(&self, args: Option<Value>) -> ToolCallResult {
+        let args = args.unwrap_or(json!({}));
+
+        let max_depth = args
+            .get("max_depth")
+            .and_then(|v| v.as_u64())
+            .map(|d| d as usize);
+
+        let format = args
+            .get("format")
+            .and_then(|v| v.as_str())
+            .unwrap_or("text");
+
+        let store = match self.cached_store(None) {
+            Ok(s) => s,
+            Err(e) => return ToolCallResult::error(format!("Failed to load index: {}", e)),
+        };
+
+        let files = store.list_files();
+        if files.is_empty() {
+            return ToolCallResult::error(
+                "No files indexed. Run 'sgrep watch <path>' first.".to_string(),
+            );
+        }
+
+        let root = match args.get("path").and_then(|v| v.as_str()) {
+            Some(p) => match Path::new(p).canonicalize() {
+                Ok(p) => p,
+                Err(e) => return ToolCallResult::error(format!("Invalid path: {}", e)),
+            },
+            None => common_root(&files).unwrap_or_default(),
+        };
+
+        let mut tree = build_file_tree(&files, &root);
+        if tree.file_count == 0 {
+            return ToolCallResult::error(format!("No indexed files under {}", root.display()));
+        }
+        if let Some(depth) = max_depth {
+            tree.limit_depth(depth);
+        }
+
+        if format == "json" {
+            return match serde_json::to_string_pretty(&tree) {
+                Ok(text) => ToolCallResult::success(text),
+                Err(e) => ToolCallResult::error(format!("Failed to serialize tree: {}", e)),
+            };
+        }
+
+        ToolCallResult::success(format!(
+            "# File Tree: {}\n\n{}",
+            root.display(),
+            tree.render()
+        ))
+    }
+
+    fn execute_find_cycles(&self, args: Option<Value>) -> ToolCallResult {
+        let args = args.unwrap_or(json!({}));
+
+        let path = args
+            .get("path")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| ".".to_string());
+
+        let level = match args.get("level").and_then(|v| v.as_str()).unwrap_or("file") {
+            "file" => CycleLevel::File,
+            "symbol" => CycleLevel::Symbol,
+            other => {
+                return ToolCallResult::error(format!(
+                    "Invalid level '{}': expected 'symbol' or 'file'",
+                    other
+                ))
+            }
+        };
+
+        let max_cycles = args
+            .get("max_cycles")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(20)
+            .clamp(1, 200) as usize;
+
+        let root = match Path::new(&path).canonicalize() {
+            Ok(p) => p,
+            Err(e) => return ToolCallResult::error(format!("Invalid path: {}", e)),
+        };
+
+        let map = match load_code_map(&root) {
+            Ok(Some(m)) => m,
+            Ok(None) => return ToolCallResult::error(NO_CODE_MAP.to_string()),
+            Err(e) => return ToolCallResult::error(format!("Failed to load map: {}", e)),
+        };
+
+        let graph = map.dependency_graph(level);
+        if graph.is_empty() {
+            return ToolCallResult::success(
+                "No dependency edges in the codebase map, so no cycles can be detected."
+                    .to_string(),
+            );
+        }
+
+        let (cycles, truncated) = map.find_cycles(level, max_cycles);
+        if cycles.is_empty() {
+            return ToolCallResult::success(format!(
+                "No circular dependencies found ({} nodes with dependencies checked).",
+                graph.len()
+            ));
+        }
+
+        let mut output = format!("# Dependency Cycles ({})\n\n", cycles.len());
+        for (i, cycle) in cycles.iter().enumerate() {
+            output.push_str(&format!(
+                "{}. {} nodes: {}\n",
+                i + 1,
+                cycle.len(),
+                cycle.join(", ")
+            ));
+        }
+        if truncated {
+            output.push_str(&format!(
+                "\n(truncated at {} cycles; raise max_cycles to see more)\n",
+                max_cycles
+            ));
+        }
+
+        ToolCallResult::success(output)
+    }
+
+    fn execute_module_summary(&self, args: Option<Value>) -> ToolCallResult {
+        let args = args.unwrap_or(json!({}));
+
+        let prefix = match args.get("prefix").and_then(|v| v.as_str()) {
+            Some(p) => p.to_string(),
+            None => return ToolCallResult::error("Missing 'prefix' argument".to_string()),
+        };
+
+        let path = args
+            .get("path")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| ".".to_string());
+
+        let markdown = match args
+            .get("format")
+            .and_then(|v| v.as_str())
+            .unwrap_or("text")
+        {
+            "text" => false,
+            "markdown" => true,
+            other => {
+                return ToolCallResult::error(format!(
+                    "Invalid format '{}': expected 'text' or 'markdown'",
+                    other
+                ))
+            }
+        };
+
+        let max_symbols = args
+            .get("max_symbols")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(15) as usize;
+
+        let root = match Path::new(&path).canonicalize() {
+            Ok(p) => p,
+            Err(e) => return ToolCallResult::error(format!("Invalid path: {}", e)),
+        };
+
+        let map = match load_code_map(&root) {
+            Ok(Some(m)) => m,
+            Ok(None) => return ToolCallResult::error(NO_CODE_MAP.to_string()),
+            Err(e) => return ToolCallResult::error(format!("Failed to load map: {}", e)),
+        };
+
+        let summary = map.module_summary(&prefix, max_symbols);
+        if summary.files == 0 {
+            return ToolCallResult::error(format!("No mapped files under '{}'", summary.prefix));
+        }
+
+        if markdown {
+            ToolCallResult::success(summary.to_markdown())
+        } else {
+            ToolCallResult::success(summary.to_text())
+        }
+    }
+
+    fn execute_get_file_symbols(&self, args: Option<Value>) -> ToolCallResult {
+        let args = args.unwrap_or(json!({}));
+
+        let file = match args.get("file").and_then(|v| v.as_str()) {
+            Some(f) => f.to_string(),
+            None => return ToolCallResult::error("Missing 'file' argument".to_string()),
+        };
+
+        let path = args
+            .get("path")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| ".".to_string());
+
+        let root = match Path::new(&path).canonicalize() {
+            Ok(p) => p,
+            Err(e) => return ToolCallResult::error(format!("Invalid path: {}", e)),
+        };
+
+        let map = match load_code_map(&root) {
+            Ok(Some(m)) => m,
+            Ok(None) => return ToolCallResult::error(NO_CODE_MAP.to_string()),
+            Err(e) => return ToolCallResult::error(format!("Failed to load map: {}", e)),
+        };
+
+        // Accept absolute paths under the root too
+        let file = Path::new(&file)
+            .strip_prefix(&root)
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or(file);
+
+        match render_file_symbols(&map, &file) {
+            Ok(output) => ToolCallResult::success(output),
+            Err(e) => ToolCallResult::error(e),
+        }
+    }
+
+    fn execute_find_orphans(&self) -> ToolCallResult {
+        let store = match self.cached_store(None) {
+            Ok(s) => s,
+            Err(e) => return ToolCallResult::error(format!("Failed to load index: {}", e)),
+        };
+
+        let orphans = store.orphaned_files();
+        if orphans.is_empty() {
+            return ToolCallResult::success(format!(
+                "No orphaned files ({} indexed files all exist on disk).",
+                store.file_count()
+            ));
+        }
+
+        let mut output = format!("# Orphaned Files ({})\n\n", orphans.len());
+        for path in &orphans {
+            let chunks = store.get_file(path).map(|f| f.chunks.len()).unwrap_or(0);
+            output.push_str(&format!("- {} ({} chunks)\n", path, chunks));
+        }
+        output.push_str("\nRun prune_orphans to remove them from the index.\n");
+
+        ToolCallResult::success(output)
+    }
+
+    fn execute_prune_orphans(&self) -> ToolCallResult {
+        let _lock = match VectorStore::lock(None) {
+            Ok(l) => l,
+            Err(e) => return ToolCallResult::error(e.to_string()),
+        };
+        let mut store = match VectorStore::load(None) {
+            Ok(s) => s,
+            Err(e) => return ToolCallResult::error(format!("Failed to load index: {}", e)),
+        };
+
+        let (files, chunks) = store.prune_orphans();
+        if files == 0 {
+            return ToolCallResult::success("No orphaned files to prune.".to_string());
+        }
+
+        store.update_bm25_stats();
+        if let Err(e) = store.save(None) {
+            return ToolCallResult::error(format!("Failed to save index: {}", e));
+        }
+
+        ToolCallResult::success(format!(
+            "Pruned {} orphaned files ({} chunks). {} files remain indexed.",
+            files,
+            chunks,
+            store.file_count()
+        ))
+    }
+
+    fn execute_symbol_metrics(&self, args: Option<Value>) -> ToolCallResult {
+        let args = args.unwrap_or(json!({}));
+
+        let path = args
+            .get("path")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| ".".to_string());
+
+        let max_results = args
+            .get("max_results")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(20)
+            .min(100) as usize;
+
+        let root = match Path::new(&path).canonicalize() {
+            Ok(p) => p,
+            Err(e) => return ToolCallResult::error(format!("Invalid path: {}", e)),
+        };
+
+        let map = match load_code_map(&root) {
+            Ok(Some(map)) => map,
+            Ok(None) => return ToolCallResult::error(NO_CODE_MAP.to_string()),
+            Err(e) => return ToolCallResult::error(format!("Failed to load map: {}", e)),
+        };
+
+        let metrics = compute_symbol_metrics(&map, &root);
+
+        if metrics.is_empty() {
+            return ToolCallResult::success(
+                "No functions found to measure. Run 'sgrep compile' to update the map.".to_string(),
+            );
+        }
+
+        let mut output = format!(
+            "# Symbol Metrics (approximate)\n\nComplexity = 1 + branch keywords/operators in the body. Heuristic only.\nShowing {} of {} functions, most complex first.\n\n",
+            metrics.len().min(max_results),
+            metrics.len()
+        );
+
+        for (i, m) in metrics.iter().take(max_results).enumerate() {
+            output.push_str(&format!(
+                "{}. [{}] {} ~complexity {}, {} lines\n   File: {}:{}-{}\n   ID: {}\n",
+                i + 1,
+                m.kind,
+                m.name,
+                m.complexity,
+                m.lines,
+                m.file,
+                m.start_line,
+                m.end_line,
+                m.symbol_id
+            ));
+        }
+
+        ToolCallResult::success(output)
+    }
+
+    fn execute_debug_parse(&self, args: Option<Value>) -> ToolCallResult {
+        let args = match args {
+            Some(a) => a,
+            None => return ToolCallResult::error("Missing arguments".to_string()),
+        };
+
+        let file_path = match args.get("file_path").and_then(|v| v.as_str()) {
+            Some(p) => p.to_string(),
+            None => {
+                return ToolCallResult::error("Missing required 'file_path' argument".to_string())
+            }
+        };
+
+        let path = Path::new(&file_path);
+        let content = match fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) => return ToolCallResult::error(format!("Failed to read file: {}", e)),
+        };
+
+        let parser = match SymbolParser::new() {
+            Ok(p) => p,
+            Err(e) => return ToolCallResult::error(format!("Failed to build parser: {}", e)),
+        };
+
+        let parsed = match parser.parse_file(path, &content) {
+            Ok(p) => p,
+            Err(e) => return ToolCallResult::error(format!("Parse failed: {}", e)),
+        };
+
+        let mut warnings = Vec::new();
+        if parsed.language == "unknown" {
+            warnings.push("Unrecognized file extension; no parser was run".to_string());
+        } else if parsed.symbols.is_empty() {
+            warnings.push(format!(
+                "No symbols extracted (the '{}' parser may not support this file yet)",
+                parsed.language
+            ));
+        }
+
+        let output = json!({
+            "parsed": parsed,
+            "warnings": warnings,
+        });
+
+        match serde_json::to_string_pretty(&output) {
+            Ok(text) => ToolCallResult::success(text),
+            Err(e) => ToolCallResult::error(format!("Failed to serialize parse output: {}", e)),
+        }
+    }
+}
+
+impl Default for McpServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Load the codebase map with kind display labels from the user config
+fn load_code_map(root: &Path) -> Result<Option<CodeMap>> {
+    let labels = Config::load().map(|c| c.kind_labels).unwrap_or_default();
+    Ok(CodeMap::load(root)?.map(|m| m.with_kind_labels(labels)))
+}
+
+/// Build and save the codebase map for `root`, holding the map lock throughout
+fn compile_code_map(root: &Path) -> Result<CodeMapStats> {
+    let _lock = CodeMap::lock(root)?;
+    let map = build_map(root, |_, _| {})?;
+    map.save(root)?;
+    Ok(map.stats())
+}
+
+/// Format `get_file_symbols` output; errors when the file has no mapped symbols
+fn render_file_symbols(map: &CodeMap, file: &str) -> Result<String, String> {
+    let symbols = map.symbols_in_file(file);
+    if symbols.is_empty() {
+        let file = file.trim_start_matches("./");
+        return Err(if map.unparsed.iter().any(|u| u.path == file) {
+            format!(
+                "'{}' is in the codebase map but has no parsed symbols",
+                file
+            )
+        } else {
+            format!(
+                "File '{}' not found in the codebase map. Check the path is relative to the \
+                 project root, or run 'sgrep compile' to update the map",
+                file
+            )
+        });
+    }
+
+    let mut output = format!("{} symbols in {}:\n\n", symbols.len(), symbols[0].file);
+    for sym in symbols {
+        let lines = if sym.end_line > sym.line {
+            format!("{}-{}", sym.line, sym.end_line)
+        } else {
+            sym.line.to_string()
+        };
+        output.push_str(&format!(
+            "L{} [{}] {}\n   ID: {}\n",
+            lines,
+            map.kind_label(sym),
+            map.display_signature(sym),
+            sym.id
+        ));
+        if !sym.summary.is_empty() {
+            output.push_str(&format!("   Summary: {}\n", sym.summary));
+        }
+    }
+
+    Ok(output)
+}
+
+/// Format `search_symbols` results
+fn render_symbol_search(
+    map: &CodeMap,
+    query: &str,
+    regex: Option<&Regex>,
+    kinds: &[SymbolKind],
+    max_results: usize,
+    offset: usize,
+) -> String {
+    let results = match regex {
+        Some(re) => map.search_regex(re, kinds),
+        None => map.search_by_kind(query, kinds),
+    };
+
+    if results.is_empty() {
+        let kind_note = if kinds.is_empty() {
+            String::new()
+        } else {
+            let names: Vec<&str> = kinds.iter().map(|k| k.as_str()).collect();
+            format!(" (kind: {})", names.join(", "))
+        };
+        return format!(
+            "No symbols found matching '{}'{}\n\nTry:\n- Different search terms\n- Run 'sgrep compile' to update the map",
+            query, kind_note
+        );
+    }
+
+    let page: Vec<_> = results
+        .iter()
+        .skip(offset)
+        .take(max_results)
+        .copied()
+        .collect();
+    if page.is_empty() {
+        return format!(
+            "No symbols at offset {} ({} match '{}')",
+            offset,
+            results.len(),
+            query
+        );
+    }
+    let has_more = results.len() > offset + page.len();
+
+    let mut output = broad_query_warning(query).unwrap_or_default();
+    output.push_str(&format!(
+        "Found {} symbols matching '{}':\n\n",
+        page.len(),
+        query
+    ));
+
+    for (i, sym) in page.iter().enumerate() {
+        output.push_str(&format!(
+            "{}. [{}] {}\n   File: {}:{}\n   ID: {}\n",
+            offset + i + 1,
+            map.kind_label(sym),
+            map.display_signature(sym),
+            sym.file,
+            sym.line,
+            sym.id
+        ));
+        if !sym.summary.is_empty() {
+            output.push_str(&format!("   Summary: {}\n", sym.summary));
+        }
+        output.push('\n');
+    }
+
+    if let Some(note) = page_note(offset, page.len(), has_more) {
+        output.push_str(&note);
+    }
+
+    output
+}
+
+/// Trailer telling the client whether another page follows
+fn page_note(offset: usize, shown: usize, has_more: bool) -> Option<String> {
+    has_more.then(|| {
+        format!(
+            "has_more: true (call again with offset={} for the next page)\n",
+            offset + shown
+        )
+    })
+}
+
+/// `get_index_status` report; `has_map` is `None` when the path isn't a directory
+fn render_index_status(store: &VectorStore, has_map: Option<bool>) -> String {
+    let mut output = String::from("# Index Status\n\n");
+    output.push_str(&format!("Files indexed: {}\n", store.file_count()));
+    output.push_str(&format!("Chunks: {}\n", store.chunk_count()));
+    output.push_str(&format!("Embedding dimension: {}\n", store.embedding_dim()));
+    output.push_str(&format!("Quantization: {}\n", store.quantization.as_str()));
+    output.push_str(&format!(
+        "Model: {}\n",
+        store.model.as_deref().unwrap_or("unknown")
+    ));
+    output.push_str(&format!(
+        "ANN index: {}\n",
+        if store.has_ann_index() { "yes" } else { "no" }
+    ));
+    output.push_str(&format!(
+        "Last indexed: {}\n",
+        store.last_indexed().unwrap_or("unknown")
+    ));
+    output.push_str(&format!(
+        "Codebase map: {}\n",
+        match has_map {
+            Some(true) => "compiled",
+            Some(false) => "not compiled (run 'sgrep compile')",
+            None => "unknown",
+        }
+    ));
+
+    if store.chunk_count() == 0 {
+        output.push_str("\nThe index is empty. Run index_directory before searching.\n");
+    }
+    output
+}
+
+/// `notifications/progress` for a client-supplied progress token
+fn progress_notification(token: &Value, progress: u64, total: u64) -> JsonRpcNotification {
+    JsonRpcNotification::new(
+        "notifications/progress",
+        json!({
+            "progressToken": token,
+            "progress": progress,
+            "total": total,
+        }),
+    )
+}
+
+/// Whether to notify at this step: roughly every 1% of files, plus the last
+fn progress_due(progress: u64, total: u64) -> bool {
+    progress == total || progress.is_multiple_of((total / 100).max(1))
+}
+
+/// `get_codebase_map` text output
+///
+/// With `max_tokens`, the least-central symbols are dropped (largest kept set
+/// found by binary search) until the whole response fits the estimate.
+fn render_code_map(
+    map: &CodeMap,
+    max_tokens: Option<usize>,
+    render: impl Fn(&CodeMap) -> String,
+) -> String {
+    let stats = map.stats();
+    let compose = |map: &CodeMap, omitted: usize| {
+        let mut overview = render(map);
+        if omitted > 0 {
+            overview.push_str(&format!(
+                "... {} more symbols omitted (raise max_tokens or filter by kind)\n",
+                omitted
+            ));
+        }
+        format!(
+            "# Codebase Map\n\n{} files, {} symbols (~{} tokens)\n\n{}",
+            stats.files,
+            stats.symbols,
+            overview.len() / 4,
+            overview
+        )
+    };
+
+    let output = compose(map, 0);
+    let budget = match max_tokens {
+        Some(budget) if output.len() / 4 > budget => budget,
+        _ => return output,
+    };
+
+    let total = map.symbols.len();
+    let truncated = |keep: usize| {
+        let mut map = map.clone();
+        map.retain_most_central(keep);
+        compose(&map, total - keep)
+    };
+
+    // Largest `keep` whose output fits; `total` is known not to
+    let (mut lo, mut hi) = (0, total);
+    let mut best = None;
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        let output = truncated(mid);
+        if output.len() / 4 <= budget {
+            best = Some(output);
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    best.unwrap_or_else(|| truncated(0))
+}
+
+/// Optional `kind` argument: a comma-separated list of symbol kinds
+fn kinds_arg(args: &Value) -> Result<Vec<SymbolKind>, String> {
+    match args.get("kind").map(|v| v.as_str()) {
+        None => Ok(Vec::new()),
+        Some(Some(list)) => parse_kinds(list).map_err(|e| e.to_string()),
+        Some(None) => Err("'kind' must be a string".to_string()),
+    }
+}
+
+/// Optional `index_name` argument, checked to be a safe store name
+fn index_name_arg(args: &Value) -> Result<Option<&str>, String> {
+    match args.get("index_name").map(|v| v.as_str()) {
+        None => Ok(None),
+        Some(Some(name)) => VectorStore::validate_name(name)
+            .map(|_| Some(name))
+            .map_err(|e| e.to_string()),
+        Some(None) => Err("'index_name' must be a string".to_string()),
+    }
+}
+
+/// Sync callback that writes progress notifications to stdout
+///
+/// Requests are handled one at a time, so nothing else writes to stdout
+/// while indexing runs.
+fn progress_reporter(token: Value, logger: &'static Logger) -> ProgressFn {
+    Box::new(move |progress, total| {
+        if progress_due(progress, total) {
+            let notification = progress_notification(&token, progress, total);
+            if let Err(e) = McpServer::write_response(&mut io::stdout(), &notification) {
+                logger.error(format_args!("progress send failed: {}", e));
+            }
+        }
+    })
+}
+
+fn no_results_message(query: &str) -> String {
+    format!(
+        "No results found for query: '{}'\n\nTry:\n- Different search terms\n- Check if the directory is indexed",
+        query
+    )
+}
+
+/// Apply `semantic_search`'s `min_score`
+fn drop_weak_results(results: &mut Vec<SearchResult>, min_score: f32) {
+    results.retain(|r| r.score >= min_score);
+}
+
+/// How many more candidates a filtered `semantic_search` ranks before filtering
+const FILTERED_SEARCH_FACTOR: usize = 10;
+
+/// Same-file results at most this many lines apart are merged by `dedup`
+const MERGE_GAP_LINES: usize = 3;
+
+/// Fold each result into a better-ranked one from the same file whose range
+/// overlaps or lies within `gap` lines, keeping its score and the union range
+///
+/// Expects `results` sorted by score; the order of what remains is kept.
+fn merge_nearby_results(results: &mut Vec<SearchResult>, gap: usize) {
+    let mut merged: Vec<SearchResult> = Vec::with_capacity(results.len());
+    for result in results.drain(..) {
+        let chunk = &result.chunk;
+        let target = merged.iter_mut().find(|kept| {
+            kept.chunk.file_path == chunk.file_path
+                && chunk.start_line <= kept.chunk.end_line + gap
+                && kept.chunk.start_line <= chunk.end_line + gap
+        });
+        match target {
+            Some(kept) => merge_chunk_lines(&mut kept.chunk, chunk),
+            None => merged.push(result),
+        }
+    }
+    *results = merged;
+}
+
+/// Widen `kept` to cover `other`, splicing in lines only `other` has
+///
+/// Lines neither chunk covers (a gap between them) become a single "...".
+fn merge_chunk_lines(kept: &mut FileChunk, other: &FileChunk) {
+    let mut lines: BTreeMap<usize, &str> = BTreeMap::new();
+    for chunk in [other, &*kept] {
+        for (i, line) in chunk.content.lines().enumerate() {
+            lines.insert(chunk.start_line + i, line);
+        }
+    }
+
+    let start = kept.start_line.min(other.start_line);
+    let end = kept.end_line.max(other.end_line);
+    let mut content = Vec::new();
+    let mut in_gap = false;
+    for n in start..=end {
+        match lines.get(&n) {
+            Some(line) => {
+                content.push(*line);
+                in_gap = false;
+            }
+            None if !in_gap => {
+                content.push("...");
+                in_gap = true;
+            }
+            None => {}
+        }
+    }
+
+    kept.content = content.join("\n");
+    kept.start_line = start;
+    kept.end_line = end;
+}
+
+/// Whether a result's file passes the `language` and `path_glob` filters
+///
+/// The language matches by name (`rust`) or extension (`rs`). A relative
+/// glob may match the path from any directory down, so `src/**` finds
+/// `/home/me/project/src/main.rs`.
+fn matches_result_filters(
+    file_path: &str,
+    language: Option<&str>,
+    path_glob: Option<&glob::Pattern>,
+) -> bool {
+    let path = Path::new(file_path);
+
+    if let Some(language) = language {
+        let name = detect_language(path);
+        let ext = path.extension().map(|e| e.to_string_lossy().to_lowercase());
+        if name.as_deref() != Some(language) && ext.as_deref() != Some(language) {
+            return false;
+        }
+    }
+
+    if let Some(pattern) = path_glob {
+        if !glob_matches_path(pattern, file_path) {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Whether `pattern` matches the path, or (if relative) any tail of it
+fn glob_matches_path(pattern: &glob::Pattern, file_path: &str) -> bool {
+    let options = glob::MatchOptions {
+        require_literal_separator: true,
+        ..Default::default()
+    };
+    let file_path = file_path.replace('\\', "/");
+    let mut suffixes = std::iter::once(file_path.as_str()).chain(
+        file_path
+            .match_indices('/')
+            .map(|(i, _)| &file_path[i + 1..]),
+    );
+    suffixes.any(|suffix| pattern.matches_with(suffix, options))
+}
+
+/// Whether a result's file matches any `exclude_globs` pattern
+fn excluded_by_globs(file_path: &str, patterns: &[glob::Pattern]) -> bool {
+    patterns
+        .iter()
+        .any(|pattern| glob_matches_path(pattern, file_path))
+}
+
+/// Optional string array of language names, e.g. `languages`
+fn language_list_arg(args: &Value, name: &str) -> Result<Vec<String>, String> {
+    let Some(value) = args.get(name) else {
+        return Ok(Vec::new());
+    };
+    let Some(items) = value.as_array() else {
+        return Err(format!(
+            "Invalid {}: expected an array of language names",
+            name
+        ));
+    };
+    items
+        .iter()
+        .map(|item| {
+            item.as_str()
+                .map(|lang| lang.trim().to_lowercase())
+                .ok_or_else(|| format!("Invalid {} entry {}: expected a string", name, item))
+        })
+        .collect()
+}
+
+/// Compile the optional `exclude_globs` string array
+fn exclude_globs_arg(args: &Value) -> Result<Vec<glob::Pattern>, String> {
+    let Some(value) = args.get("exclude_globs") else {
+        return Ok(Vec::new());
+    };
+    let Some(items) = value.as_array() else {
+        return Err("Invalid exclude_globs: expected an array of glob strings".to_string());
+    };
+    items
+        .iter()
+        .map(|item| {
+            let pattern = item.as_str().ok_or_else(|| {
+                format!("Invalid exclude_globs entry {}: expected a string", item)
+            })?;
+            glob::Pattern::new(pattern)
+                .map_err(|e| format!("Invalid exclude_globs pattern '{}': {}", pattern, e))
+        })
+        .collect()
+}
+
+/// One `semantic_search` result in `output_format: "json"`
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct JsonSearchResult {
+    file_path: String,
+    start_line: usize,
+    end_line: usize,
+    score: f32,
+    /// Empty when content is excluded
+    content: String,
+}
+
+/// `semantic_search` results as a JSON array in the first content block
+///
+/// A second, plain-text block carries the result count and any notes, so
+/// notes appended later (e.g. the --no-content policy) never corrupt the JSON.
+fn render_search_json(
+    results: &[SearchResult],
+    include_content: bool,
+    query: &str,
+    note: Option<String>,
+) -> ToolCallResult {
+    let hits: Vec<JsonSearchResult> = results
+        .iter()
+        .map(|r| JsonSearchResult {
+            file_path: r.chunk.file_path.clone(),
+            start_line: r.chunk.start_line,
+            end_line: r.chunk.end_line,
+            score: r.score,
+            content: if include_content {
+                r.chunk.content.clone()
+            } else {
+                String::new()
+            },
+        })
+        .collect();
+
+    let json = match serde_json::to_string_pretty(&hits) {
+        Ok(json) => json,
+        Err(e) => return ToolCallResult::error(format!("Failed to serialize results: {}", e)),
+    };
+
+    let mut result = ToolCallResult::success(json);
+    result.content.push(TextContent {
+        content_type: "text".to_string(),
+        text: format!(
+            "{}Found {} results for: '{}'",
+            note.unwrap_or_default(),
+            hits.len(),
+            query
+        ),
+    });
+    result
+}
+
+/// Error for a symbol id missing from the map, listing its overloads if any
+fn unknown_symbol(map: &CodeMap, symbol_id: &str) -> ToolCallResult {
+    let overloads = map.overloads(symbol_id);
+    if overloads.is_empty() {
+        return ToolCallResult::error(format!(
+            "Symbol '{}' not found. Use search_symbols to find valid symbol IDs.",
+            symbol_id
+        ));
+    }
+
+    let mut message = format!(
+        "Symbol '{}' is declared {} times in its file. Use one of:\n",
+        symbol_id,
+        overloads.len()
+    );
+    for sym in overloads {
+        message.push_str(&format!("  - {}  {}\n", sym.id, sym.signature));
+    }
+    ToolCallResult::error(message)
+}
+
+/// Every line in the symbol's dependents that mentions it, as `file:line`
+///
+/// Returns `None` when the symbol isn't in the map. Each dependent's body is
+/// read from disk to locate the mentions; if it can't be, the dependent's
+/// own declaration line stands in.
+fn render_references(map: &CodeMap, root: &Path, symbol_id: &str) -> Option<String> {
+    let expanded = map.expand(symbol_id)?;
+    let sym = expanded.symbol;
+
+    if map.symbols.values().all(|s| s.depended_by.is_empty()) {
+        return Some(format!(
+            "No references found for '{}', or dependency edges were not built for this map. Run 'sgrep compile' to rebuild them.",
+            sym.id
+        ));
+    }
+
+    if expanded.dependents.is_empty() {
+        return Some(format!("No references to '{}' found", sym.id));
+    }
+
+    let references: Vec<String> = reference_sites(root, sym, expanded.dependents)
+        .into_iter()
+        .map(|(caller, line)| format!("{}:{}  in {}", caller.file, line, caller.qualified_name()))
+        .collect();
+
+    Some(format!(
+        "Found {} references to {} ({}):\n\n{}\n",
+        references.len(),
+        sym.name,
+        sym.id,
+        references.join("\n")
+    ))
+}
+
+/// Lines in each caller's body that mention `sym` by name, in file order
+///
+/// A caller whose file can't be read (or whose body never spells the name
+/// out) contributes its own declaration line instead.
+fn reference_sites<'a>(
+    root: &Path,
+    sym: &Symbol,
+    mut callers: Vec<&'a Symbol>,
+) -> Vec<(&'a Symbol, usize)> {
+    callers.sort_by(|a, b| a.file.cmp(&b.file).then(a.line.cmp(&b.line)));
+
+    let mut sources: HashMap<&str, Option<String>> = HashMap::new();
+    let mut sites = Vec::new();
+    for caller in callers {
+        let content = sources
+            .entry(caller.file.as_str())
+            .or_insert_with(|| fs::read_to_string(root.join(&caller.file)).ok());
+
+        let mentions: Vec<usize> = content
+            .as_deref()
+            .map(|content| {
+                let start = caller.line.saturating_sub(1);
+                let end = caller.end_line.max(caller.line);
+                content
+                    .lines()
+                    .enumerate()
+                    .take(end)
+                    .skip(start)
+                    .filter(|(_, line)| {
+                        line.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                            .any(|word| word == sym.name)
+                    })
+                    .map(|(i, _)| i + 1)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        if mentions.is_empty() {
+            sites.push((caller, caller.line));
+        }
+        sites.extend(mentions.into_iter().map(|line| (caller, line)));
+    }
+    sites
+}
+
+/// Format `find_dead_code` output from `CodeMap::orphans`
+fn render_dead_code(map: &CodeMap, max_results: usize) -> String {
+    if map.symbols.values().all(|s| s.depended_by.is_empty()) {
+        return "Can't look for dead code: dependency edges were not built for this map. Run 'sgrep compile' to rebuild them.".to_string();
+    }
+
+    let orphans = map.orphans();
+    if orphans.is_empty() {
+        return "No uncalled functions found (entry points, exported symbols and tests are not checked).".to_string();
+    }
+
+    let mut output = format!(
+        "# Possibly dead code ({} functions with no callers in the map)\n\nHeuristic: calls through dynamic dispatch, reflection, macros or code outside the map aren't visible. Entry points, exported symbols and tests are skipped. Check each one with find_references before deleting.\n\n",
+        orphans.len()
+    );
+    for sym in orphans.iter().take(max_results) {
+        output.push_str(&format!(
+            "{}:{}  [{}] {}\n",
+            sym.file,
+            sym.line,
+            map.kind_label(sym),
+            map.display_signature(sym)
+        ));
+    }
+    if orphans.len() > max_results {
+        output.push_str(&format!(
+            "... and {} more (raise max_results to see them)\n",
+            orphans.len() - max_results
+        ));
+    }
+
+    output
+}
+
+/// Preview of renaming a symbol: its declaration plus every reference site
+///
+/// Returns `None` when the symbol isn't in the map. Nothing is written; the
+/// sites come from the dependency edges, like `render_references`.
+fn render_rename_impact(
+    map: &CodeMap,
+    root: &Path,
+    symbol_id: &str,
+    new_name: &str,
+) -> Option<String> {
+    let expanded = map.expand(symbol_id)?;
+    let sym = expanded.symbol;
+
+    if sym.name == new_name {
+        return Some(format!("'{}' is already named {}", sym.id, new_name));
+    }
+
+    if map.symbols.values().all(|s| s.depended_by.is_empty()) {
+        return Some(format!(
+            "Can't preview renaming '{}': dependency edges were not built for this map. Run 'sgrep compile' to rebuild them.",
+            sym.id
+        ));
+    }
+
+    let dependents = expanded.dependents.len();
+    let sites = reference_sites(root, sym, expanded.dependents);
+    let files: HashSet<&str> = sites
+        .iter()
+        .map(|(caller, _)| caller.file.as_str())
+        .chain([sym.file.as_str()])
+        .collect();
+
+    let mut output = format!(
+        "Renaming {} ({}) to {} would touch {} references in {} dependents across {} files:\n\n",
+        sym.name,
+        sym.id,
+        new_name,
+        sites.len(),
+        dependents,
+        files.len()
+    );
+    output.push_str(&format!("{}:{}  declaration\n", sym.file, sym.line));
+    for (caller, line) in &sites {
+        output.push_str(&format!(
+            "{}:{}  in {}\n",
+            caller.file,
+            line,
+            caller.qualified_name()
+        ));
+    }
+
+    // Same scope already using the new name
+    let clashes: Vec<&Symbol> = map
+        .symbols
+        .values()
+        .filter(|s| s.name == new_name && s.file == sym.file && s.container == sym.container)
+        .collect();
+    for clash in clashes {
+        output.push_str(&format!(
+            "\nWarning: {} already declares {} at line {}\n",
+            clash.file, new_name, clash.line
+        ));
+    }
+
+    Some(output)
+}
+
+/// A search hit merged across modes
+struct MultiModeHit<'a> {
+    chunk: &'a FileChunk,
+    /// (mode, 1-based rank, score) for each mode that returned the chunk
+    modes: Vec<(&'static str, usize, f32)>,
+}
+
+/// Merge per-mode rankings into one list, collapsing identical chunks
+///
+/// Hits are ordered by their best rank in any mode; ties go to the hit more
+/// modes agree on, then to the earlier mode.
+fn merge_mode_results<'a>(
+    per_mode: &'a [(&'static str, Vec<SearchResult>)],
+    limit: usize,
+) -> Vec<MultiModeHit<'a>> {
+    let mut hits: Vec<MultiModeHit> = Vec::new();
+    let mut index: HashMap<&str, usize> = HashMap::new();
+
+    for (mode, results) in per_mode {
+        for (rank, result) in results.iter().enumerate() {
+            let entry = (*mode, rank + 1, result.score);
+            match index.get(result.chunk.id.as_str()) {
+                Some(&i) => hits[i].modes.push(entry),
+                None => {
+                    index.insert(&result.chunk.id, hits.len());
+                    hits.push(MultiModeHit {
+                        chunk: &result.chunk,
+                        modes: vec![entry],
+                    });
+                }
+            }
+        }
+    }
+
+    let best_rank = |hit: &MultiModeHit| hit.modes.iter().map(|m| m.1).min().unwrap_or(0);
+    hits.sort_by(|a, b| {
+        best_rank(a)
+            .cmp(&best_rank(b))
+            .then(b.modes.len().cmp(&a.modes.len()))
+    });
+    hits.truncate(limit);
+    hits
+}
+
+/// A numbered excerpt of a file
+struct LineWindow {
+    /// First and last line shown (1-based, inclusive)
+    first: usize,
+    last: usize,
+    text: String,
+}
+
+/// Number lines `start..=end` (1-based) plus `radius` lines either side
+///
+/// Lines inside the range are prefixed with '>' so they stand out.
+fn line_window(lines: &[&str], start: usize, end: usize, radius: usize) -> LineWindow {
+    let first = start.saturating_sub(radius).max(1);
+    let last = (end + radius).min(lines.len());
+
+    let mut text = String::new();
+    for n in first..=last {
+        let marker = if (start..=end).contains(&n) { '>' } else { ' ' };
+        text.push_str(&format!("{} {:4} | {}\n", marker, n, lines[n - 1]));
+    }
+
+    LineWindow { first, last, text }
+}
+
+/// A result chunk re-read from its file with `context` lines either side
+///
+/// `None` when the file is unreadable or no longer reaches the chunk, so the
+/// caller can fall back to the indexed content.
+fn chunk_with_context(chunk: &FileChunk, context: usize) -> Option<LineWindow> {
+    let content = fs::read_to_string(&chunk.file_path).ok()?;
+    let lines: Vec<&str> = content.lines().collect();
+    if chunk.start_line == 0 || chunk.start_line > lines.len() {
+        return None;
+    }
+    let end = chunk.end_line.clamp(chunk.start_line, lines.len());
+    Some(line_window(&lines, chunk.start_line, end, context))
+}
+
+/// Reject empty or whitespace-only queries before any embedding or search work
+fn validate_query(query: &str) -> std::result::Result<(), String> {
+    if query.trim().is_empty() {
+        return Err(
+            "Query is empty. Provide search terms, e.g. 'error handling' or 'parse_config'."
+                .to_string(),
+        );
+    }
+    Ok(())
+}
+
+/// Warn when a symbol query is too short to narrow anything down
+fn broad_query_warning(query: &str) -> Option<String> {
+    let trimmed = query.trim();
+    if trimmed.chars().count() == 1 {
+        Some(format!(
+            "Note: single-character query '{}' matches very broadly; use a longer name fragment for more useful results.\n\n",
+            trimmed
+        ))
+    } else {
+        None
+    }
+}
+
+/// File stems that usually hold a program's entry point
+const ENTRY_FILE_STEMS: &[&str] = &["main", "index", "__main__", "app", "cli", "server"];
+
+/// Fill in a prompt from `prompts/list` with codebase map data
+fn render_prompt(
+    map: &CodeMap,
+    name: &str,
+    args: &HashMap<String, String>,
+) -> Result<PromptGetResult, String> {
+    match name {
+        "explain_module" => {
+            let module = args
+                .get("module")
+                .ok_or_else(|| "Missing 'module' argument".to_string())?;
+            let summary = map.module_summary(module, 15);
+            if summary.files == 0 {
+                return Err(format!("No mapped files under '{}'", summary.prefix));
+            }
+
+            Ok(PromptGetResult::user(
+                "Explain a module",
+                format!(
+                    "Explain what the `{}` module does: its responsibility, main types and \
+                     functions, and how the rest of the codebase uses it. Here is its summary \
+                     from the codebase map:\n\n{}\n\
+                     Use expand_symbol or read_symbols on the key symbols before answering.",
+                    module,
+                    summary.to_text()
+                ),
+            ))
+        }
+        "find_entry_point" => {
+            let is_entry_file = |file: &str| {
+                Path::new(file)
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .is_some_and(|stem| ENTRY_FILE_STEMS.contains(&stem))
+            };
+
+            let mut candidates: Vec<&Symbol> = map
+                .symbols
+                .values()
+                .filter(|s| s.container.is_none())
+                .filter(|s| s.name == "main" || is_entry_file(&s.file))
+                .collect();
+            candidates.sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+
+            let line = |s: &Symbol| {
+                format!(
+                    "  {} {} [{}:{}]\n",
+                    map.kind_label(s),
+                    map.display_signature(s),
+                    s.file,
+                    s.line
+                )
+            };
+
+            let mut context = String::from("Entry point candidates:\n");
+            if candidates.is_empty() {
+                context.push_str("  (no main functions or entry files found)\n");
+            }
+            for sym in candidates.iter().take(20) {
+                context.push_str(&line(sym));
+            }
+            context.push_str("\nMost referenced symbols:\n");
+            for sym in map.symbols_by_centrality().into_iter().take(10) {
+                context.push_str(&line(sym));
+            }
+
+            Ok(PromptGetResult::user(
+                "Find the entry point",
+                format!(
+                    "Find where execution starts in this codebase ({}, {} files) and trace the \
+                     first few calls from there. From the codebase map:\n\n{}\n\
+                     Confirm the entry point with expand_symbol before describing the flow.",
+                    map.root,
+                    map.files.len(),
+                    context
+                ),
+            ))
+        }
+        other => Err(format!("Unknown prompt: {}", other)),
+    }
+}
+
+/// Indexed files as `file://` resources, named by their path under the indexed root
+fn list_resources(files: &[String]) -> ResourcesListResult {
+    let root = common_root(files).unwrap_or_default();
+    let mut files: Vec<&String> = files.iter().collect();
+    files.sort();
+
+    let resources = files
+        .into_iter()
+        .map(|file| {
+            let path = Path::new(file);
+            ResourceDefinition {
+                uri: format!("file://{}", file),
+                name: path
+                    .strip_prefix(&root)
+                    .unwrap_or(path)
+                    .display()
+                    .to_string(),
+                mime_type: "text/plain".to_string(),
+            }
+        })
+        .collect();
+
+    ResourcesListResult { resources }
+}
+
+/// Read an indexed file by its `file://` URI
+///
+/// Only files in the index are served, and the resolved path must stay under
+/// the indexed root, so `..` segments and symlinks can't reach other files.
+fn read_resource(files: &[String], uri: &str) -> Result<ResourceReadResult> {
+    let Some(requested) = uri.strip_prefix("file://") else {
+        anyhow::bail!("Unsupported resource URI: {}", uri);
+    };
+
+    let not_found = || anyhow::anyhow!("Resource not found: {}", uri);
+    let root = common_root(files)
+        .and_then(|r| r.canonicalize().ok())
+        .ok_or_else(not_found)?;
+    let path = Path::new(requested)
+        .canonicalize()
+        .map_err(|_| not_found())?;
+
+    let indexed = files
+        .iter()
+        .filter_map(|f| Path::new(f).canonicalize().ok())
+        .any(|f| f == path);
+    if !indexed || !path.starts_with(&root) {
+        return Err(not_found());
+    }
+
+    let text = fs::read_to_string(&path)?;
+    Ok(ResourceReadResult {
+        contents: vec![ResourceContents {
+            uri: uri.to_string(),
+            mime_type: "text/plain".to_string(),
+            text,
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::codemap::{FileSummary, Symbol, SymbolKind, Visibility};
+    use crate::core::store::IndexedFile;
+
+    fn request(value: Value) -> JsonRpcRequest {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn call_tool(server: &mut McpServer, name: &str, arguments: Value) -> Value {
+        let response = server.dispatch(request(json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "tools/call",
+            "params": { "name": name, "arguments": arguments }
+        })));
+        serde_json::to_value(&response).unwrap()["result"].clone()
+    }
+
+    #[test]
+    fn test_response_echoes_request_id() {
+        let mut server = McpServer::new();
+
+        for id in [json!(1), json!(42), json!("abc-123"), json!("7")] {
+            let response = server.dispatch(request(json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": "ping"
+            })));
+            assert_eq!(response.id, Some(id));
+        }
+
+        assert!(server.in_flight.is_empty());
+    }
+
+    fn serve_lines(server: &mut McpServer, lines: &[Value]) -> Vec<Value> {
+        let input: String = lines.iter().map(|l| format!("{}\n", l)).collect();
+        let mut output = Vec::new();
+        server.serve(input.as_bytes(), &mut output).unwrap();
+        output
+            .split(|&b| b == b'\n')
+            .filter(|l| !l.is_empty())
+            .map(|l| serde_json::from_slice(l).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_notifications_get_no_response() {
+        let mut server = McpServer::new();
+
+        let output = serve_lines(
+            &mut server,
+            &[
+                json!({"jsonrpc": "2.0", "method": "notifications/initialized"}),
+                json!({"jsonrpc": "2.0", "method": "initialized"}),
+                json!({"jsonrpc": "2.0", "method": "no/such/method"}),
+            ],
+        );
+        assert!(output.is_empty(), "{:?}", output);
+    }
+
+    #[test]
+    fn test_batch_requests() {
+        let mut server = McpServer::new();
+
+        let output = serve_lines(
+            &mut server,
+            &[json!([
+                {"jsonrpc": "2.0", "id": "a", "method": "ping"},
+                {"jsonrpc": "2.0", "method": "notifications/initialized"},
+                {"jsonrpc": "2.0", "id": 2, "method": "no/such/method"}
+            ])],
+        );
+        assert_eq!(output.len(), 1);
+        let batch = output[0].as_array().unwrap();
+        assert_eq!(batch.len(), 2);
+        assert_eq!(batch[0]["id"], json!("a"));
+        assert!(batch[0]["error"].is_null());
+        assert_eq!(batch[1]["id"], json!(2));
+        assert_eq!(batch[1]["error"]["code"], json!(-32601));
+
+        let output = serve_lines(&mut server, &[json!([])]);
+        assert_eq!(output[0]["error"]["code"], json!(-32600));
+
+        // Only notifications: nothing to send back
+        let output = serve_lines(
+            &mut server,
+            &[json!([{"jsonrpc": "2.0", "method": "initialized"}])],
+        );
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn test_shutdown_and_exit() {
+        let mut server = McpServer::new();
+
+        let output = serve_lines(
+            &mut server,
+            &[
+                json!({"jsonrpc": "2.0", "id": 1, "method": "shutdown"}),
+                json!({"jsonrpc": "2.0", "method": "exit"}),
+                json!({"jsonrpc": "2.0", "id": 2, "method": "ping"}),
+            ],
+        );
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0]["id"], json!(1));
+        assert!(output[0]["error"].is_null());
+    }
+
+    #[test]
+    fn test_string_and_numeric_ids_stay_distinct() {
+        let mut server = McpServer::new();
+
+        let numeric = server.dispatch(request(
+            json!({"jsonrpc": "2.0", "id": 7, "method": "ping"}),
+        ));
+        let string = server.dispatch(request(
+            json!({"jsonrpc": "2.0", "id": "7", "method": "ping"}),
+        ));
+
+        let numeric = serde_json::to_value(&numeric).unwrap();
+        let string = serde_json::to_value(&string).unwrap();
+        assert!(numeric["id"].is_number());
+        assert!(string["id"].is_string());
+    }
+
+    #[test]
+    fn test_empty_and_whitespace_queries_rejected() {
+        let mut server = McpServer::new();
+
+        for tool in ["semantic_search", "search_symbols"] {
+            for query in ["", "   ", "\n\t "] {
+                let result = call_tool(&mut server, tool, json!({ "query": query }));
+                assert_eq!(result["isError"], json!(true), "{} {:?}", tool, query);
+                let text = result["content"][0]["text"].as_str().unwrap();
+                assert!(text.contains("Query is empty"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_single_character_query_warns() {
+        assert!(validate_query("x").is_ok());
+        assert!(broad_query_warning(" x ").unwrap().contains("'x'"));
+        assert!(broad_query_warning("xy").is_none());
+        assert!(broad_query_warning("parse").is_none());
+    }
+
+    fn search_hit(id: &str, score: f32) -> SearchResult {
+        SearchResult {
+            chunk: FileChunk {
+                id: id.to_string(),
+                file_path: format!("src/{}.rs", id),
+                content: String::new(),
+                start_line: 1,
+                end_line: 10,
+                chunk_type: "block".to_string(),
+                language: Some("rust".to_string()),
+                embedding: vec![],
+                token_embeddings: None,
+                symbol_name: None,
+                parent_name: None,
+                hierarchy_path: None,
+            },
+            score,
+            bm25_score: 0.0,
+            vector_score: score,
+            colbert_score: None,
+        }
+    }
+
+    #[test]
+    fn test_search_symbols_pagination() {
+        let mut map = CodeMap::new("/repo");
+        for i in 1..=5 {
+            let id = format!("handlers.rs:handle_{}", i);
+            map.symbols.insert(
+                id.clone(),
+                Symbol {
+                    id,
+                    name: format!("handle_{}", i),
+                    file: "handlers.rs".to_string(),
+                    line: i * 10,
+                    end_line: i * 10,
+                    kind: SymbolKind::Function,
+                    signature: format!("handle_{}() -> ()", i),
+                    summary: String::new(),
+                    depends_on: vec![],
+                    depended_by: vec![],
+                    embedding: vec![],
+                    container: None,
+                    visibility: Visibility::Unknown,
+                },
+            );
+        }
+
+        let first = render_symbol_search(&map, "handle", None, &[], 2, 0);
+        assert!(first.contains("1. [fn] handle_1()"));
+        assert!(first.contains("2. [fn] handle_2()"));
+        assert!(first.contains("has_more: true (call again with offset=2"));
+
+        let last = render_symbol_search(&map, "handle", None, &[], 2, 4);
+        assert!(last.contains("5. [fn] handle_5()"));
+        assert!(!last.contains("has_more"));
+
+        let beyond = render_symbol_search(&map, "handle", None, &[], 2, 99);
+        assert_eq!(beyond, "No symbols at offset 99 (5 match 'handle')");
+    }
+
+    #[test]
+    fn test_progress_notifications() {
+        let notification = progress_notification(&json!("idx-1"), 40, 200);
+        let mut out = Vec::new();
+        McpServer::write_response(&mut out, &notification).unwrap();
+        let line: Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(
+            line,
+            json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/progress",
+                "params": { "progressToken": "idx-1", "progress": 40, "total": 200 }
+            })
+        );
+
+        // Small repos report every file; large ones about 100 times
+        assert!((1..=5).all(|n| progress_due(n, 5)));
+        let due = (1..=10_000).filter(|&n| progress_due(n, 10_000)).count();
+        assert_eq!(due, 100);
+        assert!(progress_due(10_001, 10_001));
+    }
+
+    #[test]
+    fn test_clear_index_requires_confirm() {
+        let mut server = McpServer::new();
+        let root = std::env::temp_dir().join(format!("sgrep-clear-{}", std::process::id()));
+        let map = CodeMap::new(&root.to_string_lossy());
+        map.save(&root).unwrap();
+        let path = root.to_string_lossy().to_string();
+
+        // A store name nothing else writes, so only the map is found
+        let index_name = "clear-map-only";
+        for args in [
+            json!({ "path": path, "index_name": index_name }),
+            json!({ "path": path, "index_name": index_name, "confirm": false }),
+        ] {
+            let result = call_tool(&mut server, "clear_index", args);
+            assert_eq!(result["isError"], json!(true));
+            assert!(CodeMap::map_path(&root).exists());
+        }
+
+        let args = json!({ "path": path, "index_name": index_name, "confirm": true });
+        let result = call_tool(&mut server, "clear_index", args.clone());
+        assert_ne!(result["isError"], json!(true));
+        let text = result["content"][0]["text"].as_str().unwrap();
+        assert!(text.contains("and the codebase map"));
+        assert!(!CodeMap::map_path(&root).exists());
+
+        // Nothing left to clear
+        let result = call_tool(&mut server, "clear_index", args);
+        assert_eq!(result["isError"], json!(true));
+
+        let result = call_tool(
+            &mut server,
+            "clear_index",
+            json!({ "path": path, "index_name": "../escape", "confirm": true }),
+        );
+        assert_eq!(result["isError"], json!(true));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_get_index_status_reads_the_named_store() {
+        let root = std::env::temp_dir().join(format!("sgrep-status-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let mut store = VectorStore::default();
+        store.add_chunk(search_hit("store", 0.0).chunk);
+        store.save_with_compression(Some("status-test"), 0).unwrap();
+        CodeMap::new(&root.to_string_lossy()).save(&root).unwrap();
+
+        let mut server = McpServer::new();
+        let path = root.to_string_lossy().to_string();
+        let result = call_tool(
+            &mut server,
+            "get_index_status",
+            json!({ "path": path, "index_name": "status-test" }),
+        );
+        assert!(result.get("isError").is_none(), "{}", result);
+        let text = result["content"][0]["text"].as_str().unwrap();
+        assert!(text.contains("Chunks: 1\n"), "{}", text);
+        assert!(text.contains("Codebase map: compiled"), "{}", text);
+
+        let result = call_tool(
+            &mut server,
+            "get_index_status",
+            json!({ "path": path, "index_name": "status-missing" }),
+        );
+        assert_eq!(result["isError"], json!(true));
+        let text = result["content"][0]["text"].as_str().unwrap();
+        assert!(
+            text.contains("No index found for 'status-missing'"),
+            "{}",
+            text
+        );
+
+        VectorStore::delete(Some("status-test")).unwrap();
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_multi_mode_search_resolves_index_name() {
+        let mut server = McpServer::new();
+        let result = call_tool(
+            &mut server,
+            "multi_mode_search",
+            json!({ "query": "parse config", "index_name": "multi-never-indexed" }),
+        );
+        let text = result["content"][0]["text"].as_str().unwrap();
+        assert!(text.contains("No files indexed"), "{}", text);
+
+        let result = call_tool(
+            &mut server,
+            "multi_mode_search",
+            json!({ "query": "parse config", "index_name": "/tmp/project" }),
+        );
+        assert_eq!(result["isError"], json!(true));
+        let text = result["content"][0]["text"].as_str().unwrap();
+        assert!(!text.contains("No files indexed"), "{}", text);
+    }
+
+    #[test]
+    fn test_clear_index_deletes_the_named_store() {
+        let root = std::env::temp_dir().join(format!("sgrep-clear-store-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let index_name = Some("clear-store");
+        let mut store = VectorStore::default();
+        store.add_chunk(search_hit("store", 0.0).chunk);
+        store.save_with_compression(index_name, 0).unwrap();
+        CodeMap::new(&root.to_string_lossy()).save(&root).unwrap();
+
+        let mut server = McpServer::new();
+        let result = call_tool(
+            &mut server,
+            "clear_index",
+            json!({ "path": root.to_string_lossy(), "index_name": "clear-store", "confirm": true }),
+        );
+        assert!(result.get("isError").is_none(), "{}", result);
+        let text = result["content"][0]["text"].as_str().unwrap();
+        assert!(
+            text.contains("removed 1 chunk and the codebase map"),
+            "{}",
+            text
+        );
+
+        assert!(!VectorStore::exists(index_name).unwrap());
+        for path in [
+            VectorStore::store_path_bin(index_name).unwrap(),
+            VectorStore::store_path(index_name).unwrap(),
+            VectorStore::vectors_path(index_name).unwrap(),
+            VectorStore::meta_path(index_name).unwrap(),
+        ] {
+            assert!(!path.exists(), "{} left behind", path.display());
+        }
+        assert!(!CodeMap::map_path(&root).exists());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_index_status() {
+        let mut store = VectorStore::default();
+        let output = render_index_status(&store, Some(false));
+        assert!(output.contains("Chunks: 0\n"));
+        assert!(output.contains("Last indexed: unknown\n"));
+        assert!(output.contains("Codebase map: not compiled"));
+        assert!(output.contains("The index is empty"));
+
+        let mut chunk = search_hit("store", 0.0).chunk;
+        chunk.embedding = vec![0.0; 384];
+        store.add_chunk(chunk);
+        for (path, indexed_at) in [
+            ("src/store.rs", "2026-03-01T10:00:00+00:00"),
+            ("src/search.rs", "2026-03-02T09:30:00+00:00"),
+        ] {
+            store.add_file(IndexedFile {
+                path: path.to_string(),
+                hash: String::new(),
+                chunks: vec![],
+                indexed_at: indexed_at.to_string(),
+            });
+        }
+        store.model = Some("bge-small".to_string());
+
+        let output = render_index_status(&store, Some(true));
+        assert!(output.contains("Files indexed: 2\n"));
+        assert!(output.contains("Chunks: 1\n"));
+        assert!(output.contains("Embedding dimension: 384\n"));
+        assert!(output.contains("Model: bge-small\n"));
+        assert!(output.contains("Last indexed: 2026-03-02T09:30:00+00:00\n"));
+        assert!(output.contains("Codebase map: compiled\n"));
+        assert!(!output.contains("The index is empty"));
+    }
+
+    #[test]
+    fn test_min_score_threshold() {
+        let hits = || vec![search_hit("a", 0.62), search_hit("b", 0.41)];
+
+        let mut results = hits();
+        drop_weak_results(&mut results, 0.0);
+        assert_eq!(results.len(), 2);
+
+        let mut results = hits();
+        drop_weak_results(&mut results, 0.5);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].chunk.id, "a");
+
+        let mut results = hits();
+        drop_weak_results(&mut results, 0.9);
+        assert!(results.is_empty());
+        assert!(no_results_message("auth").starts_with("No results found for query: 'auth'"));
+    }
+
+    #[test]
+    fn test_result_filters() {
+        let glob = |p: &str| glob::Pattern::new(p).unwrap();
+        let file = "/home/me/project/src/core/store.rs";
+
+        assert!(matches_result_filters(file, Some("rust"), None));
+        assert!(matches_result_filters(file, Some("rs"), None));
+        assert!(!matches_result_filters(file, Some("python"), None));
+
+        assert!(matches_result_filters(file, None, Some(&glob("src/**"))));
+        assert!(matches_result_filters(file, None, Some(&glob("*.rs"))));
+        assert!(matches_result_filters(
+            file,
+            None,
+            Some(&glob("/home/me/**/store.rs"))
+        ));
+        assert!(!matches_result_filters(file, None, Some(&glob("src/*.rs"))));
+        assert!(!matches_result_filters(file, None, Some(&glob("tests/**"))));
+
+        assert!(matches_result_filters(
+            file,
+            Some("rust"),
+            Some(&glob("src/core/*"))
+        ));
+        assert!(!matches_result_filters(
+            file,
+            Some("go"),
+            Some(&glob("src/core/*"))
+        ));
+
+        assert!(glob::Pattern::new("src/[").is_err());
+    }
+
+    #[test]
+    fn test_exclude_globs() {
+        let excludes =
+            exclude_globs_arg(&json!({ "exclude_globs": ["tests/**", "*.snap"] })).unwrap();
+        let mut results = vec![
+            search_hit("core/store", 0.9),
+            search_hit("fixtures", 0.8),
+            search_hit("parser", 0.7),
+        ];
+        results[1].chunk.file_path = "/home/me/project/tests/fixtures/store.rs".to_string();
+        results[2].chunk.file_path = "src/parser/snapshots/parse.snap".to_string();
+
+        let include = glob::Pattern::new("**/*.rs").unwrap();
+        results.retain(|r| {
+            matches_result_filters(&r.chunk.file_path, None, Some(&include))
+                && !excluded_by_globs(&r.chunk.file_path, &excludes)
+        });
+        // The fixture matches the include glob too, but exclusion wins
+        let files: Vec<&str> = results.iter().map(|r| r.chunk.file_path.as_str()).collect();
+        assert_eq!(files, ["src/core/store.rs"]);
+
+        assert!(exclude_globs_arg(&json!({})).unwrap().is_empty());
+        let err = exclude_globs_arg(&json!({ "exclude_globs": ["src/["] })).unwrap_err();
+        assert!(
+            err.contains("Invalid exclude_globs pattern 'src/['"),
+            "{}",
+            err
+        );
+        assert!(exclude_globs_arg(&json!({ "exclude_globs": "tests/**" })).is_err());
+        assert!(exclude_globs_arg(&json!({ "exclude_globs": [1] })).is_err());
+    }
+
+    #[test]
+    fn test_search_json_output() {
+        let mut hit = search_hit("store", 0.75);
+        hit.chunk.content = "fn save() {}".to_string();
+        let results = vec![hit, search_hit("search", 0.5)];
+
+        let result = render_search_json(&results, true, "save index", None)
+            .with_note("Note: appended later");
+        let parsed: Vec<JsonSearchResult> = serde_json::from_str(&result.content[0].text).unwrap();
+        assert_eq!(
+            parsed[0],
+            JsonSearchResult {
+                file_path: "src/store.rs".to_string(),
+                start_line: 1,
+                end_line: 10,
+                score: 0.75,
+                content: "fn save() {}".to_string(),
+            }
+        );
+        assert_eq!(parsed[1].file_path, "src/search.rs");
+        assert!(result.content[1].text.starts_with("Found 2 results"));
+        assert!(result.content[1].text.ends_with("Note: appended later"));
+
+        let result = render_search_json(&results, false, "save index", None);
+        let parsed: Vec<JsonSearchResult> = serde_json::from_str(&result.content[0].text).unwrap();
+        assert!(parsed.iter().all(|r| r.content.is_empty()));
+
+        let result = render_search_json(&[], true, "nothing", None);
+        assert_eq!(result.content[0].text, "[]");
+    }
+
+    #[test]
+    fn test_merge_mode_results_dedupes_and_tags() {
+        let per_mode = vec![
+            ("balanced", vec![search_hit("a", 0.9), search_hit("b", 0.8)]),
+            ("code", vec![search_hit("c", 0.7), search_hit("a", 0.6)]),
+            ("hybrid", vec![search_hit("a", 0.85), search_hit("c", 0.75)]),
+        ];
+
+        let merged = merge_mode_results(&per_mode, 10);
+        let ids: Vec<&str> = merged.iter().map(|h| h.chunk.id.as_str()).collect();
+        // "a" and "c" both rank first somewhere; "a" is found by more modes
+        assert_eq!(ids, vec!["a", "c", "b"]);
+
+        let modes: Vec<(&str, usize)> = merged[0].modes.iter().map(|m| (m.0, m.1)).collect();
+        assert_eq!(modes, vec![("balanced", 1), ("code", 2), ("hybrid", 1)]);
+
+        assert_eq!(merge_mode_results(&per_mode, 2).len(), 2);
+    }
+
+    #[test]
+    fn test_line_window_marks_core_range() {
+        let lines: Vec<&str> = (1..=20).map(|_| "x").collect();
+
+        let window = line_window(&lines, 10, 11, 2);
+        assert_eq!((window.first, window.last), (8, 13));
+        let marked: Vec<&str> = window.text.lines().filter(|l| l.starts_with('>')).collect();
+        assert_eq!(marked, vec![">   10 | x", ">   11 | x"]);
+
+        // Clamped at both ends of the file
+        let window = line_window(&lines, 1, 20, 5);
+        assert_eq!((window.first, window.last), (1, 20));
+    }
+
+    #[test]
+    fn test_merge_nearby_results() {
+        let hit = |file: &str, start: usize, end: usize, score: f32| {
+            let mut result = search_hit(file, score);
+            result.chunk.start_line = start;
+            result.chunk.end_line = end;
+            result.chunk.content = (start..=end)
+                .map(|n| format!("line {}", n))
+                .collect::<Vec<_>>()
+                .join("\n");
+            result
+        };
+
+        let mut results = vec![
+            hit("a", 10, 20, 0.9),
+            hit("b", 1, 5, 0.8),
+            hit("a", 15, 25, 0.7),
+            hit("a", 28, 30, 0.6),
+            hit("a", 100, 110, 0.5),
+        ];
+        merge_nearby_results(&mut results, 3);
+
+        let summary: Vec<(&str, usize, usize, f32)> = results
+            .iter()
+            .map(|r| {
+                let c = &r.chunk;
+                (c.file_path.as_str(), c.start_line, c.end_line, r.score)
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("src/a.rs", 10, 30, 0.9),
+                ("src/b.rs", 1, 5, 0.8),
+                ("src/a.rs", 100, 110, 0.5),
+            ]
+        );
+
+        // Overlapping lines appear once; the 26-27 gap is elided
+        let content: Vec<&str> = results[0].chunk.content.lines().collect();
+        assert_eq!(content.first(), Some(&"line 10"));
+        assert_eq!(content.iter().filter(|l| **l == "line 18").count(), 1);
+        assert!(content.contains(&"line 25") && content.contains(&"line 30"));
+        assert_eq!(content.iter().filter(|l| **l == "...").count(), 1);
+    }
+
+    #[test]
+    fn test_chunk_with_context() {
+        let path = std::env::temp_dir().join(format!("sgrep-context-{}.rs", std::process::id()));
+        let source: String = (1..=10).map(|n| format!("line {}\n", n)).collect();
+        fs::write(&path, source).unwrap();
+
+        let mut chunk = search_hit("context", 1.0).chunk;
+        chunk.file_path = path.to_string_lossy().to_string();
+
+        (chunk.start_line, chunk.end_line) = (5, 6);
+        let window = chunk_with_context(&chunk, 2).unwrap();
+        assert_eq!((window.first, window.last), (3, 8));
+        let marked: Vec<&str> = window.text.lines().filter(|l| l.starts_with('>')).collect();
+        assert_eq!(marked.len(), 2);
+        assert!(window.text.contains("line 3") && window.text.contains("line 8"));
+        assert!(!window.text.contains("line 2\n") && !window.text.contains("line 9"));
+
+        // Clamped at both ends of the file
+        (chunk.start_line, chunk.end_line) = (1, 2);
+        let window = chunk_with_context(&chunk, 5).unwrap();
+        assert_eq!((window.first, window.last), (1, 7));
+        (chunk.start_line, chunk.end_line) = (9, 12);
+        let window = chunk_with_context(&chunk, 5).unwrap();
+        assert_eq!((window.first, window.last), (4, 10));
+
+        // The file shrank past the chunk: fall back to indexed content
+        (chunk.start_line, chunk.end_line) = (20, 25);
+        assert!(chunk_with_context(&chunk, 3).is_none());
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_code_map_token_budget() {
+        let mut map = CodeMap::new("/repo");
+        let ids: Vec<String> = (0..200).map(|i| format!("lib.rs:func_{}", i)).collect();
+        for (i, id) in ids.iter().enumerate() {
+            map.symbols.insert(
+                id.clone(),
+                Symbol {
+                    id: id.clone(),
+                    name: format!("func_{}", i),
+                    file: "lib.rs".to_string(),
+                    line: i + 1,
+                    end_line: i + 1,
+                    kind: SymbolKind::Function,
+                    signature: format!("pub fn func_{}(input: &str) -> Result<Output>", i),
+                    summary: "Does one step of the pipeline".to_string(),
+                    depends_on: vec![],
+                    // func_199 is the hub
+                    depended_by: if i == 199 { ids.clone() } else { vec![] },
+                    embedding: vec![],
+                    container: None,
+                    visibility: Visibility::Unknown,
+                },
+            );
+        }
+        map.files.insert(
+            "lib.rs".to_string(),
+            FileSummary {
+                path: "lib.rs".to_string(),
+                language: "rust".to_string(),
+                symbols: ids.clone(),
+                imports: vec![],
+                exports: vec![],
+                summary: String::new(),
+                lines: 200,
+            },
+        );
+
+        let full = render_code_map(&map, None, CodeMap::to_compact_overview);
+        assert!(full.len() / 4 > 500);
+        assert_eq!(
+            render_code_map(&map, Some(100_000), CodeMap::to_compact_overview),
+            full
+        );
+
+        let output = render_code_map(&map, Some(500), CodeMap::to_compact_overview);
+        assert!(output.len() / 4 <= 500, "{} tokens", output.len() / 4);
+        assert!(output.contains("more symbols omitted"));
+        assert!(output.contains("fn func_199("));
+
+        let estimate: usize = output
+            .split("(~")
+            .nth(1)
+            .and_then(|s| s.split(' ').next())
+            .and_then(|n| n.parse().ok())
+            .unwrap();
+        assert!(estimate <= 500);
+    }
+
+    #[test]
+    fn test_kind_labels_in_search_symbols() {
+        let mut map = CodeMap::new("/repo");
+        map.files.insert(
+            "server.go".to_string(),
+            FileSummary {
+                path: "server.go".to_string(),
+                language: "go".to_string(),
+                symbols: vec!["server.go:Server".to_string()],
+                imports: vec![],
+                exports: vec![],
+                summary: String::new(),
+                lines: 10,
+            },
+        );
+        map.symbols.insert(
+            "server.go:Server".to_string(),
+            Symbol {
+                id: "server.go:Server".to_string(),
+                name: "Server".to_string(),
+                file: "server.go".to_string(),
+                line: 3,
+                end_line: 3,
+                kind: SymbolKind::Struct,
+                signature: "type Server struct".to_string(),
+                summary: String::new(),
+                depends_on: vec![],
+                depended_by: vec![],
+                embedding: vec![],
+                container: None,
+                visibility: Visibility::Unknown,
+            },
+        );
+
+        let output = render_symbol_search(&map, "Server", None, &[], 10, 0);
+        assert!(output.contains("[struct] type Server struct"));
+
+        let labels = HashMap::from([(
+            "go".to_string(),
+            HashMap::from([("struct".to_string(), "type".to_string())]),
+        )]);
+        let map = map.with_kind_labels(labels);
+        let output = render_symbol_search(&map, "Server", None, &[], 10, 0);
+        assert!(output.contains("[type] type Server struct"));
+        assert_eq!(map.symbols["server.go:Server"].kind, SymbolKind::Struct);
+    }
+
+    #[test]
+    fn test_full_signatures_in_search_symbols() {
+        let signature =
+            "def connect(host: str, port: int, timeout: float = DEFAULT_TIMEOUT_SECONDS, \
+                         retries: int = 3, pool: Pool = ConnectionPool(max_size=32))";
+        let mut map = CodeMap::new("/repo");
+        map.symbols.insert(
+            "net.py:connect".to_string(),
+            Symbol {
+                id: "net.py:connect".to_string(),
+                name: "connect".to_string(),
+                file: "net.py".to_string(),
+                line: 1,
+                end_line: 2,
+                kind: SymbolKind::Function,
+                signature: signature.to_string(),
+                summary: String::new(),
+                depends_on: vec![],
+                depended_by: vec![],
+                embedding: vec![],
+                container: None,
+                visibility: Visibility::Public,
+            },
+        );
+
+        // Compact by default: every parameter name survives, long defaults don't
+        let output = render_symbol_search(&map, "connect", None, &[], 10, 0);
+        for name in [
+            "host: str",
+            "port: int",
+            "timeout: float = ...",
+            "retries: int = 3",
+            "pool: Pool = ...",
+        ] {
+            assert!(output.contains(name), "missing {} in {}", name, output);
+        }
+        assert!(!output.contains("DEFAULT_TIMEOUT_SECONDS"));
+
+        let map = map.with_full_signatures(true);
+        let output = render_symbol_search(&map, "connect", None, &[], 10, 0);
+        assert!(output.contains(signature));
+    }
+
+    #[test]
+    fn test_find_references() {
+        let root = std::env::temp_dir().join(format!("sgrep-refs-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(
+            root.join("lib.rs"),
+            "fn load() {}\n\nfn run() {\n    load();\n    let x = 1;\n    load();\n}\n",
+        )
+        .unwrap();
+
+        let mut map = CodeMap::new(&root.to_string_lossy());
+        for (name, line, end_line) in [("load", 1, 1), ("run", 3, 7), ("idle", 9, 9)] {
+            let id = format!("lib.rs:{}", name);
+            map.symbols.insert(
+                id.clone(),
+                Symbol {
+                    id,
+                    name: name.to_string(),
+                    file: "lib.rs".to_string(),
+                    line,
+                    end_line,
+                    kind: SymbolKind::Function,
+                    signature: format!("{}() -> ()", name),
+                    summary: String::new(),
+                    depends_on: vec![],
+                    depended_by: vec![],
+                    embedding: vec![],
+                    container: None,
+                    visibility: Visibility::Unknown,
+                },
+            );
+        }
+
+        // Before edges exist
+        let output = render_references(&map, &root, "lib.rs:load").unwrap();
+        assert!(output.contains("dependency edges were not built"));
+
+        map.symbols.get_mut("lib.rs:run").unwrap().depends_on = vec!["lib.rs:load".to_string()];
+        map.symbols.get_mut("lib.rs:load").unwrap().depended_by = vec!["lib.rs:run".to_string()];
+
+        let output = render_references(&map, &root, "lib.rs:load").unwrap();
+        assert!(output.starts_with("Found 2 references to load (lib.rs:load)"));
+        assert!(output.contains("lib.rs:4  in run\nlib.rs:6  in run"));
+
+        assert!(render_references(&map, &root, "lib.rs:idle")
+            .unwrap()
+            .starts_with("No references to 'lib.rs:idle'"));
+        assert!(render_references(&map, &root, "lib.rs:missing").is_none());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_rename_impact() {
+        let root = std::env::temp_dir().join(format!("sgrep-rename-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(
+            root.join("lib.rs"),
+            "fn load() {}\n\nfn run() {\n    load();\n    load();\n}\n\nfn fetch() {}\n",
+        )
+        .unwrap();
+        fs::write(root.join("api.rs"), "fn serve() {\n    crate::load();\n}\n").unwrap();
+
+        let mut map = CodeMap::new(&root.to_string_lossy());
+        for (file, name, line, end_line) in [
+            ("lib.rs", "load", 1, 1),
+            ("lib.rs", "run", 3, 6),
+            ("lib.rs", "fetch", 8, 8),
+            ("api.rs", "serve", 1, 3),
+        ] {
+            let id = format!("{}:{}", file, name);
+            map.symbols.insert(
+                id.clone(),
+                Symbol {
+                    id,
+                    name: name.to_string(),
+                    file: file.to_string(),
+                    line,
+                    end_line,
+                    kind: SymbolKind::Function,
+                    signature: format!("{}() -> ()", name),
+                    summary: String::new(),
+                    depends_on: vec![],
+                    depended_by: vec![],
+                    embedding: vec![],
+                    container: None,
+                    visibility: Visibility::Unknown,
+                },
+            );
+        }
+
+        let output = render_rename_impact(&map, &root, "lib.rs:load", "read").unwrap();
+        assert!(output.contains("dependency edges were not built"));
+
+        for caller in ["lib.rs:run", "api.rs:serve"] {
+            let caller_sym = map.symbols.get_mut(caller).unwrap();
+            caller_sym.depends_on = vec!["lib.rs:load".to_string()];
+        }
+        map.symbols.get_mut("lib.rs:load").unwrap().depended_by =
+            vec!["lib.rs:run".to_string(), "api.rs:serve".to_string()];
+
+        let output = render_rename_impact(&map, &root, "lib.rs:load", "read").unwrap();
+        assert!(output.starts_with(
+            "Renaming load (lib.rs:load) to read would touch 3 references in 2 dependents across 2 files"
+        ));
+        assert!(output.contains(
+            "lib.rs:1  declaration\napi.rs:2  in serve\nlib.rs:4  in run\nlib.rs:5  in run\n"
+        ));
+        assert!(!output.contains("Warning"));
+
+        let output = render_rename_impact(&map, &root, "lib.rs:load", "fetch").unwrap();
+        assert!(output.contains("Warning: lib.rs already declares fetch at line 8"));
+
+        assert!(render_rename_impact(&map, &root, "lib.rs:missing", "read").is_none());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_resources_list_and_read() {
+        let root = std::env::temp_dir().join(format!("sgrep-resources-{}", std::process::id()));
+        let project = root.join("project");
+        fs::create_dir_all(project.join("src")).unwrap();
+        fs::write(project.join("src/lib.rs"), "pub fn load() {}\n").unwrap();
+        fs::write(project.join("main.rs"), "fn main() {}\n").unwrap();
+        fs::write(project.join("secret.env"), "TOKEN=1\n").unwrap();
+        fs::write(root.join("outside.rs"), "fn outside() {}\n").unwrap();
+
+        let project = project.canonicalize().unwrap();
+        let files: Vec<String> = ["src/lib.rs", "main.rs"]
+            .iter()
+            .map(|f| project.join(f).display().to_string())
+            .collect();
+
+        let listed = list_resources(&files);
+        let names: Vec<&str> = listed.resources.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["main.rs", "src/lib.rs"]);
+        let lib_uri = format!("file://{}", files[0]);
+        assert!(listed.resources.iter().any(|r| r.uri == lib_uri));
+
+        let read = read_resource(&files, &lib_uri).unwrap();
+        assert_eq!(read.contents[0].uri, lib_uri);
+        assert_eq!(read.contents[0].text, "pub fn load() {}\n");
+
+        // Unindexed files, traversal out of the root and other schemes are refused
+        for uri in [
+            format!("file://{}", project.join("secret.env").display()),
+            format!("file://{}", project.join("src/../../outside.rs").display()),
+            format!("file://{}", project.join("missing.rs").display()),
+            "https://example.com/lib.rs".to_string(),
+        ] {
+            assert!(read_resource(&files, &uri).is_err(), "{}", uri);
+        }
+
+        let mut server = McpServer::new();
+        let response = server.dispatch(request(json!({
+            "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}
+        })));
+        let response = serde_json::to_value(&response).unwrap();
+        assert!(response["result"]["capabilities"]["resources"].is_object());
+
+        let response = server.dispatch(request(json!({
+            "jsonrpc": "2.0", "id": 2, "method": "resources/read"
+        })));
+        assert_eq!(response.error.unwrap().code, -32602);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_prompts_list_and_get() {
+        let mut server = McpServer::new();
+        let response = server.dispatch(request(json!({
+            "jsonrpc": "2.0", "id": 1, "method": "prompts/list"
+        })));
+        let response = serde_json::to_value(&response).unwrap();
+        let names: Vec<&str> = response["result"]["prompts"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["explain_module", "find_entry_point"]);
+
+        let mut map = CodeMap::new("/repo");
+        for (file, name, signature, depended_by) in [
+            ("src/main.rs", "main", "fn main()", vec![]),
+            (
+                "src/core/store.rs",
+                "load_store",
+                "pub fn load_store(path: &Path) -> Result<Store>",
+                vec!["src/main.rs:main".to_string()],
+            ),
+        ] {
+            let id = format!("{}:{}", file, name);
+            map.symbols.insert(
+                id.clone(),
+                Symbol {
+                    id: id.clone(),
+                    name: name.to_string(),
+                    file: file.to_string(),
+                    line: 1,
+                    end_line: 3,
+                    kind: SymbolKind::Function,
+                    signature: signature.to_string(),
+                    summary: String::new(),
+                    depends_on: vec![],
+                    depended_by,
+                    embedding: vec![],
+                    container: None,
+                    visibility: Visibility::Public,
+                },
+            );
+            map.files.insert(
+                file.to_string(),
+                FileSummary {
+                    path: file.to_string(),
+                    language: "rust".to_string(),
+                    symbols: vec![id],
+                    imports: vec![],
+                    exports: vec![name.to_string()],
+                    summary: String::new(),
+                    lines: 3,
+                },
+            );
+        }
+
+        let args: HashMap<String, String> = [("module".to_string(), "src/core".to_string())].into();
+        let prompt = render_prompt(&map, "explain_module", &args).unwrap();
+        assert_eq!(prompt.messages.len(), 1);
+        assert_eq!(prompt.messages[0].role, "user");
+        let text = &prompt.messages[0].content.text;
+        assert!(text.contains("Explain what the `src/core` module does"));
+        assert!(text.contains("pub fn load_store(path: &Path) -> Result<Store>"));
+        assert!(!text.contains("fn main()"));
+
+        let prompt = render_prompt(&map, "find_entry_point", &HashMap::new()).unwrap();
+        let text = &prompt.messages[0].content.text;
+        assert!(text.contains("Entry point candidates:\n  fn fn main() [src/main.rs:1]\n"));
+        assert!(text.contains("Most referenced symbols:\n  fn pub fn load_store"));
+
+        assert!(render_prompt(&map, "explain_module", &HashMap::new()).is_err());
+        let args: HashMap<String, String> = [("module".to_string(), "docs".to_string())].into();
+        assert!(render_prompt(&map, "explain_module", &args).is_err());
+        assert!(render_prompt(&map, "no_such_prompt", &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_debug_logging_records_requests() {
+        use crate::core::log::{CapturedLog, LogLevel};
+
+        let captured = CapturedLog::default();
+        let mut server = McpServer::new();
+        server.logger = captured.logger(LogLevel::Debug);
+        server.dispatch(request(
+            json!({"jsonrpc": "2.0", "id": 3, "method": "ping"}),
+        ));
+        call_tool(&mut server, "no_such_tool", json!({}));
+
+        let output = captured.contents();
+        assert!(output.contains(" DEBUG request method=ping id=3 elapsed_ms="));
+        assert!(output.contains(" ERROR tool=no_such_tool elapsed_ms="));
+        assert!(output.contains("failed: Unknown tool: no_such_tool"));
+
+        server.in_flight.insert("5".to_string());
+        server.dispatch(request(
+            json!({"jsonrpc": "2.0", "id": 5, "method": "ping"}),
+        ));
+        assert!(captured
+            .contents()
+            .contains(" ERROR duplicate in-flight request id=5"));
+
+        let captured = CapturedLog::default();
+        server.logger = captured.logger(LogLevel::Error);
+        server.dispatch(request(
+            json!({"jsonrpc": "2.0", "id": 4, "method": "ping"}),
+        ));
+        assert!(captured.contents().is_empty());
+    }
+
+    #[test]
+    fn test_search_symbols_regex() {
+        let mut map = CodeMap::new("/repo");
+        for (line, name, kind, signature) in [
+            (
+                1,
+                "handle_request",
+                SymbolKind::Function,
+                "pub fn handle_request(req: Request) -> Response",
+            ),
+            (
+                9,
+                "handle_ping",
+                SymbolKind::Function,
+                "fn handle_ping() -> Response",
+            ),
+            (
+                14,
+                "rehandle_cache",
+                SymbolKind::Function,
+                "fn rehandle_cache(cache: &mut Cache)",
+            ),
+            (
+                20,
+                "RequestHandler",
+                SymbolKind::Struct,
+                "pub struct RequestHandler",
+            ),
+        ] {
+            let id = format!("server.rs:{}", name);
+            map.symbols.insert(
+                id.clone(),
+                Symbol {
+                    id,
+                    name: name.to_string(),
+                    file: "server.rs".to_string(),
+                    line,
+                    end_line: line,
+                    kind,
+                    signature: signature.to_string(),
+                    summary: String::new(),
+                    depends_on: vec![],
+                    depended_by: vec![],
+                    embedding: vec![],
+                    container: None,
+                    visibility: Visibility::Unknown,
+                },
+            );
+        }
+
+        let re = Regex::new("^handle_").unwrap();
+        let output = render_symbol_search(&map, "^handle_", Some(&re), &[], 10, 0);
+        assert!(output.starts_with("Found 2 symbols matching '^handle_'"));
+        assert!(output.contains("1. [fn] pub fn handle_request(req: Request) -> Response"));
+        assert!(output.contains("2. [fn] fn handle_ping() -> Response"));
+        assert!(!output.contains("rehandle_cache"));
+
+        // Signatures are matched too
+        let re = Regex::new(r"-> Response$").unwrap();
+        let output = render_symbol_search(&map, "-> Response$", Some(&re), &[], 10, 0);
+        assert!(output.starts_with("Found 2 symbols"));
+
+        let re = Regex::new("Handler$").unwrap();
+        let output =
+            render_symbol_search(&map, "Handler$", Some(&re), &[SymbolKind::Function], 10, 0);
+        assert!(output.starts_with("No symbols found matching 'Handler$' (kind: fn)"));
+
+        let mut server = McpServer::new();
+        let result = call_tool(
+            &mut server,
+            "search_symbols",
+            json!({"query": "handle_(", "regex": true}),
+        );
+        assert_eq!(result["isError"], json!(true));
+        assert!(result["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .starts_with("Invalid regex 'handle_(':"));
+    }
+
+    #[test]
+    fn test_embedder_cache_loads_each_model_once() {
+        use std::cell::Cell;
+
+        let cache: EmbedderCache<String> = EmbedderCache::new();
+        let loads = Cell::new(0);
+        let load = |name: &str| {
+            loads.set(loads.get() + 1);
+            Ok(name.to_string())
+        };
+
+        let first = cache.get_or_load("bge", || load("bge")).unwrap();
+        let second = cache.get_or_load("bge", || load("bge")).unwrap();
+        assert_eq!(loads.get(), 1);
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(cache.contains("bge"));
+
+        cache.get_or_load("code", || load("code")).unwrap();
+        assert_eq!(loads.get(), 2);
+
+        // A failed load leaves nothing behind, so the next call retries
+        assert!(cache
+            .get_or_load("broken", || Err(anyhow::anyhow!("offline")))
+            .is_err());
+        assert!(!cache.contains("broken"));
+        cache.get_or_load("broken", || load("broken")).unwrap();
+        assert_eq!(loads.get(), 3);
+
+        let mut server = McpServer::new();
+        let result = call_tool(&mut server, "warm_up", json!({"mode": "lexical"}));
+        assert_eq!(result["isError"], json!(true));
+        assert!(result["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .starts_with("Invalid mode 'lexical'"));
+    }
+
+    #[test]
+    fn test_store_cache_reloads_only_when_modified() {
+        use std::cell::Cell;
+        use std::time::Duration;
+
+        let cache = StoreCache::new();
+        let loads = Cell::new(0);
+        let load = || {
+            loads.set(loads.get() + 1);
+            Ok(VectorStore::default())
+        };
+        let saved = Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000));
+
+        let first = cache.get_or_load("default", saved, load).unwrap();
+        let second = cache.get_or_load("default", saved, load).unwrap();
+        assert_eq!(loads.get(), 1);
+        assert!(Arc::ptr_eq(&first, &second));
+
+        // A re-index moves the modification time; clearing removes the files
+        let resaved = saved.map(|t| t + Duration::from_secs(5));
+        let third = cache.get_or_load("default", resaved, load).unwrap();
+        assert_eq!(loads.get(), 2);
+        assert!(!Arc::ptr_eq(&second, &third));
+        cache.get_or_load("default", None, load).unwrap();
+        assert_eq!(loads.get(), 3);
+
+        // Stores are cached per name
+        cache.get_or_load("other", saved, load).unwrap();
+        assert_eq!(loads.get(), 4);
+    }
+
+    #[test]
+    fn test_compile_map_then_get_codebase_map() {
+        let root = std::env::temp_dir().join(format!("sgrep-compile-map-{}", std::process::id()));
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(
+            root.join("src/lib.rs"),
+            "pub fn parse_config(text: &str) -> usize {\n    count_lines(text)\n}\n\nfn count_lines(text: &str) -> usize {\n    text.lines().count()\n}\n",
+        )
+        .unwrap();
+        let path = root.display().to_string();
+
+        let mut server = McpServer::new();
+        let before = call_tool(&mut server, "get_codebase_map", json!({"path": path}));
+        assert_eq!(before["isError"], json!(true));
+        assert!(before["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .contains("compile_map"));
+
+        let compiled = call_tool(&mut server, "compile_map", json!({"path": path}));
+        assert!(compiled.get("isError").is_none(), "{}", compiled);
+        let text = compiled["content"][0]["text"].as_str().unwrap();
+        assert!(
+            text.contains("1 files, 2 symbols (2 functions, 0 structs/classes, 0 other)"),
+            "{}",
+            text
+        );
+
+        let map = call_tool(&mut server, "get_codebase_map", json!({"path": path}));
+        assert!(map.get("isError").is_none(), "{}", map);
+        let text = map["content"][0]["text"].as_str().unwrap();
+        assert!(text.contains("parse_config"));
+        assert!(text.contains("count_lines"));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_auto_index_reuses_an_indexed_store() {
+        // What a first auto-indexing call leaves behind: the default store
+        let project = std::env::temp_dir().join(format!("sgrep-auto-index-{}", std::process::id()));
+        fs::create_dir_all(&project).unwrap();
+        let source =
+            "// Read settings from disk\nfn parse_config(text: &str) -> Config {\n    todo!()\n}";
+        fs::write(project.join("config.rs"), source).unwrap();
+        let file = project.join("config.rs").display().to_string();
+        let mut store = VectorStore::default();
+        store.add_chunk(FileChunk {
+            id: format!("{}:1-4", file),
+            file_path: file.clone(),
+            content: source.to_string(),
+            start_line: 1,
+            end_line: 4,
+            chunk_type: "function".to_string(),
+            language: Some("rust".to_string()),
+            embedding: vec![],
+            token_embeddings: None,
+            symbol_name: Some("parse_config".to_string()),
+            parent_name: None,
+            hierarchy_path: None,
+        });
+        store.update_bm25_stats();
+        store.save_with_compression(None, 0).unwrap();
+
+        // A later call with the same path searches that store instead of syncing again
+        let mut server = McpServer::new();
+        let args = json!({
+            "query": "settings",
+            "path": project.display().to_string(),
+            "auto_index": true,
+            "mode": "lexical",
+            "include_content": false
+        });
+        for _ in 0..2 {
+            let result = call_tool(&mut server, "semantic_search", args.clone());
+            assert!(result.get("isError").is_none(), "{}", result);
+            let text = result["content"][0]["text"].as_str().unwrap();
+            assert!(!text.contains("Auto-indexed"), "{}", text);
+            assert!(text.contains("config.rs"), "{}", text);
+        }
+
+        VectorStore::delete(None).unwrap();
+        let _ = fs::remove_dir_all(&project);
+    }
+
+    #[test]
+    fn test_no_content_keeps_source_out_of_tool_output() {
+        let root = std::env::temp_dir().join(format!("sgrep-no-content-{}", std::process::id()));
+        fs::create_dir_all(root.join("src")).unwrap();
+        let source = "use std::fmt;\n\n// Count the lines in text\nfn count_lines(text: &str) -> usize {\n    text.lines().count()\n}\n";
+        let file = root.join("src/lib.rs");
+        fs::write(&file, source).unwrap();
+        let path = root.display().to_string();
+        let file_path = file.display().to_string();
+
+        let mut store = VectorStore::default();
+        store.add_chunk(FileChunk {
+            id: format!("{}:1-6", file_path),
+            file_path: file_path.clone(),
+            content: source.to_string(),
+            start_line: 1,
+            end_line: 6,
+            chunk_type: "function".to_string(),
+            language: Some("rust".to_string()),
+            embedding: vec![],
+            token_embeddings: None,
+            symbol_name: Some("count_lines".to_string()),
+            parent_name: None,
+            hierarchy_path: None,
+        });
+        store.update_bm25_stats();
+        store.save_with_compression(Some("no-content"), 0).unwrap();
+
+        let mut server = McpServer::new().with_no_content(true);
+        let compiled = call_tool(&mut server, "compile_map", json!({ "path": path }));
+        assert!(compiled.get("isError").is_none(), "{}", compiled);
+
+        for (tool, args) in [
+            (
+                "semantic_search",
+                json!({
+                    "query": "lines",
+                    "index_name": "no-content",
+                    "mode": "lexical",
+                    "include_content": true
+                }),
+            ),
+            (
+                "read_symbols",
+                json!({
+                    "path": path,
+                    "symbol_ids": ["src/lib.rs:count_lines"],
+                    "include_code": true
+                }),
+            ),
+            (
+                "get_file_context",
+                json!({ "file_path": file_path, "include_content": true }),
+            ),
+        ] {
+            let result = call_tool(&mut server, tool, args);
+            assert!(result.get("isError").is_none(), "{}: {}", tool, result);
+            let text = result["content"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter_map(|c| c["text"].as_str())
+                .collect::<String>();
+            assert!(text.contains("lib.rs"), "{}: {}", tool, text);
+            assert!(text.contains("suppressed"), "{}: {}", tool, text);
+            assert!(!text.contains("text.lines().count()"), "{}: {}", tool, text);
+            assert!(!text.contains("use std::fmt"), "{}: {}", tool, text);
+        }
+
+        VectorStore::delete(Some("no-content")).unwrap();
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_expand_symbol_survives_a_shrunk_file() {
+        let root = std::env::temp_dir().join(format!("sgrep-expand-shrunk-{}", std::process::id()));
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/lib.rs"), "fn kept() {}\n").unwrap();
+        let mut map = CodeMap::new(&root.to_string_lossy());
+        map.add_symbol(
+            Symbol::new(
+                "src/lib.rs",
+                "removed",
+                40,
+                SymbolKind::Function,
+                "fn removed()".to_string(),
+            )
+            .with_end_line(45),
+        );
+        map.save(&root).unwrap();
+
+        let mut server = McpServer::new();
+        let result = call_tool(
+            &mut server,
+            "expand_symbol",
+            json!({
+                "path": root.display().to_string(),
+                "symbol_id": "src/lib.rs:removed",
+                "include_code": true
+            }),
+        );
+        assert!(result.get("isError").is_none(), "{}", result);
+        let _ = fs::remove_dir_all(&root);
     }
 }